@@ -47,6 +47,13 @@ else
     fail "--version shows version"
 fi
 
+# Test: version as JSON
+if $KV --version -j | grep -q '"features":\['; then
+    pass "--version -j produces JSON"
+else
+    fail "--version -j produces JSON"
+fi
+
 # Test: help flag
 if $KV --help | grep -q "USAGE"; then
     pass "--help shows usage"
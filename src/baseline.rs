@@ -0,0 +1,190 @@
+//! One-shot deltas against output saved by an earlier run (--baseline).
+//!
+//! Save `kv net -jv > prev.json` (or a `kv snapshot -v`), then later run
+//! `kv net --baseline prev.json` to see how far each counter moved since.
+//! Like a watch mode, but across separate runs - handy from cron.
+//!
+//! Entries are matched by their `name` field. Counters that are missing
+//! on either side, or went backwards (reboot, driver reload), get no delta.
+
+#![allow(dead_code)]
+
+use crate::cli::ExtraArgs;
+use crate::io;
+use crate::json::JsonRef;
+use crate::limits::MAX_BASELINE_SIZE;
+use crate::print;
+use crate::stack::StackString;
+
+/// Suffix appended to counter names for their deltas.
+const DELTA_SUFFIX: &str = "_delta";
+
+/// Find `--baseline <file>` (or `--baseline=<file>`) in subcommand args.
+pub fn parse_path(args: &ExtraArgs) -> Option<&str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--baseline" {
+            return iter.next();
+        }
+        if let Some(path) = arg.strip_prefix("--baseline=") {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// A saved entry and how long ago it was saved, for turning deltas into rates.
+#[derive(Clone, Copy)]
+pub struct Since<'a> {
+    pub entry: JsonRef<'a>,
+    /// Seconds between the baseline and now, if known
+    pub interval_s: Option<u64>,
+}
+
+/// A previously saved kv JSON document.
+pub struct Baseline {
+    /// One byte more than a baseline may have, to tell a file of exactly
+    /// MAX_BASELINE_SIZE from a longer one
+    buf: [u8; MAX_BASELINE_SIZE + 1],
+    len: usize,
+}
+
+impl Baseline {
+    /// Read a baseline file. Returns None if unreadable, too big or not JSON.
+    pub fn load(path: &str) -> Option<Self> {
+        let mut baseline = Baseline { buf: [0u8; MAX_BASELINE_SIZE + 1], len: 0 };
+        baseline.len = io::read_file_bytes(path, &mut baseline.buf)?;
+        // Anything past the limit means the JSON would be cut off
+        if baseline.len > MAX_BASELINE_SIZE {
+            print::eprint("Warning: baseline file is too big (limit ");
+            print::eprint(itoa::Buffer::new().format(MAX_BASELINE_SIZE));
+            print::eprintln(" bytes)");
+            return None;
+        }
+        baseline.root()?;
+        Some(baseline)
+    }
+
+    fn root(&self) -> Option<JsonRef<'_>> {
+        let text = core::str::from_utf8(&self.buf[..self.len]).ok()?;
+        JsonRef::parse(text)
+    }
+
+    /// When the baseline was captured (from the output envelope).
+    pub fn timestamp(&self) -> Option<u64> {
+        self.root()?.get("timestamp_unix")?.as_u64()
+    }
+
+    /// Seconds between the baseline and now, if both are known.
+    pub fn interval(&self) -> Option<u64> {
+        delta(io::unix_time(), self.timestamp())
+    }
+
+    /// The saved entry called `name` in `section`.
+    ///
+    /// Works with subcommand output (`data` is the list itself) and with
+    /// snapshots (`data.<section>` is the list).
+    pub fn entry(&self, section: &str, name: &str) -> Option<JsonRef<'_>> {
+        let data = self.root()?.get("data")?;
+        let list = data.get(section).unwrap_or(data);
+        list.items()
+            .find(|item| item.get("name").and_then(|n| n.as_str()) == Some(name))
+    }
+
+    /// `entry`, along with the baseline's age.
+    pub fn entry_since(&self, section: &str, name: &str) -> Option<Since<'_>> {
+        Some(Since {
+            entry: self.entry(section, name)?,
+            interval_s: self.interval(),
+        })
+    }
+}
+
+/// How far a counter moved. None if unknown or if it went backwards.
+pub fn delta(now: Option<u64>, before: Option<u64>) -> Option<u64> {
+    now?.checked_sub(before?)
+}
+
+/// Call `f(name_delta, delta)` for each counter that has a delta against `prev`.
+pub fn for_each_delta(prev: &JsonRef<'_>, counters: &[(&str, Option<u64>)], mut f: impl FnMut(&str, u64)) {
+    for &(name, now) in counters {
+        let before = prev.get(name).and_then(|v| v.as_u64());
+        if let Some(d) = delta(now, before) {
+            let mut key: StackString<48> = StackString::from_str(name);
+            key.push_str(DELTA_SUFFIX);
+            f(key.as_str(), d);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::testing::TempDir;
+
+    #[test]
+    fn test_delta() {
+        assert_eq!(delta(Some(150), Some(100)), Some(50));
+        assert_eq!(delta(Some(100), Some(100)), Some(0));
+        // Counter reset
+        assert_eq!(delta(Some(10), Some(100)), None);
+        assert_eq!(delta(None, Some(1)), None);
+        assert_eq!(delta(Some(1), None), None);
+    }
+
+    fn baseline_from(text: &str) -> Baseline {
+        let mut b = Baseline { buf: [0u8; MAX_BASELINE_SIZE + 1], len: text.len() };
+        b.buf[..text.len()].copy_from_slice(text.as_bytes());
+        b
+    }
+
+    #[test]
+    fn test_oversized_baseline_rejected() {
+        // Never ends, so the buffer fills up
+        assert!(Baseline::load("/dev/zero").is_none());
+    }
+
+    #[test]
+    fn test_baseline_size_limit() {
+        // "{}" padded with spaces to exactly the limit loads; a byte more doesn't
+        let dir = TempDir::new("baseline");
+        let mut doc = [b' '; MAX_BASELINE_SIZE + 1];
+        doc[..2].copy_from_slice(b"{}");
+        let exact = dir.write("exact.json", &doc[..MAX_BASELINE_SIZE]);
+        let over = dir.write("over.json", &doc);
+        assert!(Baseline::load(exact.as_str()).is_some());
+        assert!(Baseline::load(over.as_str()).is_none());
+    }
+
+    #[test]
+    fn test_entry_subcommand_and_snapshot() {
+        let net = baseline_from(r#"{"timestamp_unix":1700000000,"data":[{"name":"lo","rx_bytes":1},{"name":"eth0","rx_bytes":2}]}"#);
+        assert_eq!(net.timestamp(), Some(1700000000));
+        let eth0 = net.entry("net", "eth0").unwrap();
+        assert_eq!(eth0.get("rx_bytes").and_then(|v| v.as_u64()), Some(2));
+        assert!(net.entry("net", "wlan0").is_none());
+
+        let snap = baseline_from(r#"{"data":{"mem":{},"net":[{"name":"eth0","rx_bytes":3}]}}"#);
+        assert_eq!(snap.entry("net", "eth0").unwrap().get("rx_bytes").and_then(|v| v.as_u64()), Some(3));
+        assert!(snap.entry("block", "sda").is_none());
+    }
+
+    #[test]
+    fn test_for_each_delta() {
+        let prev = JsonRef::parse(r#"{"name":"eth0","rx_bytes":1000,"tx_bytes":500}"#).unwrap();
+        let mut seen: [(StackString<48>, u64); 4] = Default::default();
+        let mut n = 0;
+        for_each_delta(
+            &prev,
+            &[("rx_bytes", Some(1500)), ("tx_bytes", Some(100)), ("rx_packets", Some(9))],
+            |k, d| {
+                seen[n] = (StackString::from_str(k), d);
+                n += 1;
+            },
+        );
+        // tx went backwards, rx_packets wasn't saved
+        assert_eq!(n, 1);
+        assert_eq!(seen[0].0.as_str(), "rx_bytes_delta");
+        assert_eq!(seen[0].1, 500);
+    }
+}
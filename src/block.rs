@@ -9,20 +9,38 @@
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
-use crate::fields::block as f;
-use crate::filter::{matches_any, opt_str};
+use crate::baseline::{self, Baseline};
+use crate::cli::{self, ExtraArgs, GlobalOptions};
+use crate::fields::{block as f, kind};
+use crate::filter::{bool_str, opt_str, Filterable};
 use crate::io;
-use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::json::{begin_kv_output_streaming, JsonRef, StreamingJsonWriter};
+use crate::page::Page;
 use crate::print::{self, TextWriter};
 use crate::stack::StackString;
+use crate::sum::Totals;
+use crate::template::Template;
 
 const BLOCK_SYSFS_PATH: &str = "/sys/block";
 const MOUNTS_PATH: &str = "/proc/self/mounts";
 
+/// Sector unit of the size and stat files, independent of the device's
+/// sector size: a 4Kn disk still counts 512-byte sectors there.
+const STAT_SECTOR_SIZE: u32 = 512;
+
 /// Maximum number of mount entries we track.
 const MAX_MOUNT_ENTRIES: usize = 128;
 
+/// Most holders or slaves we keep per device.
+const MAX_LINKS: usize = 8;
+
+/// Most devices `--topology` arranges into a tree.
+const MAX_TOPOLOGY_NODES: usize = 128;
+
+/// Deepest `--topology` nesting; real stacks (disk, partition, crypt,
+/// LVM, ...) stay well below this.
+const MAX_TOPOLOGY_DEPTH: usize = 8;
+
 /// Type of block device.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockType {
@@ -30,19 +48,121 @@ pub enum BlockType {
     Part,
     Loop,
     Ram,
+    /// Device-mapper: LVM logical volume
+    Lvm,
+    /// Device-mapper: dm-crypt/LUKS mapping
+    Crypt,
+    /// Device-mapper: multipath device
+    Multipath,
+    /// Device-mapper, some other target
+    Dm,
+    /// MD software RAID array
+    Md,
     Other,
 }
 
 impl BlockType {
+    /// Every type, for checking names given on the command line.
+    const ALL: [BlockType; 10] = [
+        BlockType::Disk,
+        BlockType::Part,
+        BlockType::Loop,
+        BlockType::Ram,
+        BlockType::Lvm,
+        BlockType::Crypt,
+        BlockType::Multipath,
+        BlockType::Dm,
+        BlockType::Md,
+        BlockType::Other,
+    ];
+
+    /// The type shown as `name` (TYPE=loop is "loop").
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == name)
+    }
+
+    /// This type's bit in a `TypeSelection` mask.
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
     fn as_str(&self) -> &'static str {
         match self {
             BlockType::Disk => "disk",
             BlockType::Part => "part",
             BlockType::Loop => "loop",
             BlockType::Ram => "ram",
+            BlockType::Lvm => "lvm",
+            BlockType::Crypt => "crypt",
+            BlockType::Multipath => "multipath",
+            BlockType::Dm => "dm",
+            BlockType::Md => "md",
             BlockType::Other => "other",
         }
     }
+
+    /// Classify a device-mapper device by the prefix its target puts in
+    /// dm/uuid ("LVM-...", "CRYPT-LUKS2-...", "mpath-...").
+    fn from_dm_uuid(uuid: &str) -> Self {
+        if uuid.starts_with("LVM-") {
+            BlockType::Lvm
+        } else if uuid.starts_with("CRYPT-") {
+            BlockType::Crypt
+        } else if uuid.starts_with("mpath-") {
+            BlockType::Multipath
+        } else {
+            BlockType::Dm
+        }
+    }
+}
+
+/// I/O counters from /sys/block/<dev>/stat.
+///
+/// The file holds 11 or more whitespace-separated fields (newer kernels add
+/// discard and flush counters). We only keep the ones worth showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockStat {
+    /// Reads completed
+    pub reads: u64,
+    /// Sectors read (always 512-byte units)
+    pub read_sectors: u64,
+    /// Writes completed
+    pub writes: u64,
+    /// Sectors written (always 512-byte units)
+    pub write_sectors: u64,
+    /// I/Os currently in flight
+    pub io_in_progress: u64,
+}
+
+impl BlockStat {
+    /// Did any counter go backwards since `prev`? In-flight I/Os come and
+    /// go, so they don't count.
+    pub fn is_behind(&self, prev: &BlockStat) -> bool {
+        self.reads < prev.reads
+            || self.read_sectors < prev.read_sectors
+            || self.writes < prev.writes
+            || self.write_sectors < prev.write_sectors
+    }
+}
+
+/// Parse the contents of a block device stat file.
+///
+/// Field order: reads, reads merged, sectors read, ms reading, writes,
+/// writes merged, sectors written, ms writing, in flight, ms doing I/O,
+/// weighted ms doing I/O, then optional discard/flush fields.
+pub fn parse_block_stat(line: &str) -> Option<BlockStat> {
+    let mut fields = [0u64; 11];
+    let mut parts = line.split_whitespace();
+    for field in fields.iter_mut() {
+        *field = parts.next()?.parse().ok()?;
+    }
+    Some(BlockStat {
+        reads: fields[0],
+        read_sectors: fields[2],
+        writes: fields[4],
+        write_sectors: fields[6],
+        io_in_progress: fields[8],
+    })
 }
 
 /// Stack-based mountpoint lookup table.
@@ -83,14 +203,18 @@ impl MountpointMap {
 
     /// Read mount points from /proc/self/mounts.
     fn from_mounts() -> Self {
-        let mut map = Self::new();
-
         let contents: Option<StackString<8192>> = io::read_file_stack(MOUNTS_PATH);
-        let Some(contents) = contents else {
-            return map;
-        };
+        match contents {
+            Some(contents) => Self::from_contents(contents.as_str()),
+            None => Self::new(),
+        }
+    }
+
+    /// Build the map from /proc/self/mounts contents read by someone else.
+    fn from_contents(contents: &str) -> Self {
+        let mut map = Self::new();
 
-        for line in contents.as_str().lines() {
+        for line in contents.lines() {
             let mut parts = line.split_whitespace();
             if let (Some(device), Some(mountpoint)) = (parts.next(), parts.next()) {
                 // Only track /dev/* devices
@@ -104,6 +228,61 @@ impl MountpointMap {
     }
 }
 
+/// Device names listed in a holders/ or slaves/ directory.
+#[derive(Default, Clone)]
+pub struct NameList {
+    names: [StackString<32>; MAX_LINKS],
+    count: usize,
+}
+
+impl NameList {
+    /// Read the entry names of `dir` (missing directory = empty list).
+    fn read(dir: &str) -> Self {
+        let mut list = Self::default();
+        io::for_each_dir_entry(dir, |name| list.push(name));
+        list
+    }
+
+    fn push(&mut self, name: &str) {
+        if self.count < MAX_LINKS {
+            self.names[self.count] = StackString::from_str(name);
+            self.count += 1;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names[..self.count].iter().map(|s| s.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.iter().any(|n| n == name)
+    }
+
+    /// Comma-separated, for text output.
+    fn join(&self) -> StackString<288> {
+        let mut s = StackString::new();
+        for (i, name) in self.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            s.push_str(name);
+        }
+        s
+    }
+
+    fn write_json(&self, w: &mut StreamingJsonWriter, key: &str) {
+        w.field_array(key);
+        for name in self.iter() {
+            w.array_string(name);
+        }
+        w.end_field_array();
+    }
+}
+
 /// Information about a block device or partition.
 pub struct BlockDevice {
     /// Device name (e.g., "sda", "sda1", "nvme0n1p1")
@@ -131,16 +310,51 @@ pub struct BlockDevice {
     /// Rotational device (HDD) or not (SSD)?
     pub rotational: Option<bool>,
     /// Scheduler in use
-    pub scheduler: Option<StackString<32>>,
+    pub scheduler: Option<StackString<64>>,
+    /// Zone model of a zoned disk: "host-managed" or "host-aware"
+    pub zoned: Option<StackString<16>>,
+    /// Number of zones on a zoned disk
+    pub nr_zones: Option<u64>,
+    /// Reads completed (from stat)
+    pub reads: Option<u64>,
+    /// Sectors read, in 512-byte units (from stat)
+    pub read_sectors: Option<u64>,
+    /// Writes completed (from stat)
+    pub writes: Option<u64>,
+    /// Sectors written, in 512-byte units (from stat)
+    pub write_sectors: Option<u64>,
+    /// I/Os currently in flight (from stat)
+    pub io_in_progress: Option<u64>,
+    /// Device-mapper name, e.g. "vg0-root" (from dm/name)
+    pub dm_name: Option<StackString<128>>,
+    /// MD RAID level, e.g. "raid1" (from md/level)
+    pub md_level: Option<StackString<16>>,
+    /// Member disks of an MD array (from md/raid_disks)
+    pub raid_disks: Option<u32>,
+    /// Devices built on top of this one, e.g. the dm-crypt mapping of a partition
+    pub holders: NameList,
+    /// Devices this one is built on, e.g. the members of an MD array
+    pub slaves: NameList,
 }
 
 impl BlockDevice {
-    /// Read a block device from sysfs.
-    ///
+    /// Read a block device from sysfs, retrying once if we raced a hot-plug.
+    /// With `stable_read`, the stat counters are re-read until they agree.
+    fn read(name: &str, parent: Option<&str>, mountpoints: &MountpointMap, stable_read: bool) -> Option<Self> {
+        let base: StackString<128> = match parent {
+            Some(p) => {
+                let parent_path: StackString<64> = io::join_path(BLOCK_SYSFS_PATH, p);
+                io::join_path(parent_path.as_str(), name)
+            }
+            None => io::join_path(BLOCK_SYSFS_PATH, name),
+        };
+        io::retry_if_present(base.as_str(), || Self::read_once(name, parent, mountpoints, stable_read))
+    }
+
     /// For partitions, most attributes (removable, queue/*, model) don't exist -
     /// they inherit physical characteristics from the parent disk. We skip reading
     /// them to avoid noisy debug output.
-    fn read(name: &str, parent: Option<&str>, mountpoints: &MountpointMap) -> Option<Self> {
+    fn read_once(name: &str, parent: Option<&str>, mountpoints: &MountpointMap, stable_read: bool) -> Option<Self> {
         let base: StackString<128> = if let Some(p) = parent {
             let parent_path: StackString<64> = io::join_path(BLOCK_SYSFS_PATH, p);
             io::join_path(parent_path.as_str(), name)
@@ -154,14 +368,14 @@ impl BlockDevice {
             return None;
         }
 
-        let size_sectors: u64 = io::read_file_parse(size_path.as_str()).unwrap_or(0);
+        let size_sectors: u64 = io::read_required_parse(size_path.as_str()).unwrap_or(0);
         let dev_path: StackString<256> = io::join_path(base.as_str(), "dev");
         let dev_str: Option<StackString<16>> = io::read_file_stack(dev_path.as_str());
         let (major, minor) = parse_dev(dev_str.as_ref()?.as_str())?;
 
         // Determine device type
         let is_partition = parent.is_some();
-        let dev_type = if is_partition {
+        let mut dev_type = if is_partition {
             BlockType::Part
         } else if name.starts_with("loop") {
             BlockType::Loop
@@ -201,25 +415,76 @@ impl BlockDevice {
 
             // Scheduler (e.g., "[mq-deadline] none" - extract the active one)
             let sched_path: StackString<256> = io::join_path(base.as_str(), "queue/scheduler");
-            let scheduler: Option<StackString<32>> = io::read_file_stack::<64>(sched_path.as_str())
-                .and_then(|s| extract_active_scheduler(s.as_str()));
+            let scheduler = io::read_file_value::<64>(sched_path.as_str())
+                .and_then(|s| scheduler_value(s.as_str(), io::is_raw()));
 
             (removable, sector_size, model, rotational, scheduler)
         };
 
+        let (zoned, nr_zones) = if is_partition { (None, None) } else { read_zoned(base.as_str()) };
+
+        // Device-mapper (LVM, LUKS, multipath): a friendly name, and the
+        // target type from the uuid prefix
+        let mut dm_name: Option<StackString<128>> = None;
+        if !is_partition && name.starts_with("dm-") {
+            let dm_name_path: StackString<256> = io::join_path(base.as_str(), "dm/name");
+            let dm_uuid_path: StackString<256> = io::join_path(base.as_str(), "dm/uuid");
+            dm_name = io::read_file_stack(dm_name_path.as_str());
+            let uuid: Option<StackString<160>> = io::read_file_stack(dm_uuid_path.as_str());
+            dev_type = BlockType::from_dm_uuid(uuid.as_ref().map_or("", |u| u.as_str()));
+        }
+
+        // MD software RAID
+        let mut md_level: Option<StackString<16>> = None;
+        let mut raid_disks: Option<u32> = None;
+        if !is_partition && name.starts_with("md") {
+            let level_path: StackString<256> = io::join_path(base.as_str(), "md/level");
+            let disks_path: StackString<256> = io::join_path(base.as_str(), "md/raid_disks");
+            md_level = io::read_file_stack(level_path.as_str());
+            raid_disks = io::read_file_parse(disks_path.as_str());
+            if md_level.is_some() {
+                dev_type = BlockType::Md;
+            }
+        }
+
+        // Stacking: partitions can have holders, only whole devices have slaves
+        let holders_path: StackString<256> = io::join_path(base.as_str(), "holders");
+        let holders = NameList::read(holders_path.as_str());
+        let slaves = if is_partition {
+            NameList::default()
+        } else {
+            let slaves_path: StackString<256> = io::join_path(base.as_str(), "slaves");
+            NameList::read(slaves_path.as_str())
+        };
+
         // ro is valid for both disks and partitions
         let ro_path: StackString<256> = io::join_path(base.as_str(), "ro");
         let ro = io::read_file_parse::<u8>(ro_path.as_str())
             .map(|v| v != 0)
             .unwrap_or(false);
 
+        // I/O statistics - partitions have their own stat file too.
+        // They can be caught mid-update; --stable-read re-reads them
+        let stat_path: StackString<256> = io::join_path(base.as_str(), "stat");
+        let stat = if stable_read {
+            io::read_file_stable(stat_path.as_str(), parse_block_stat, BlockStat::is_behind)
+        } else {
+            io::read_file_stack::<256>(stat_path.as_str()).and_then(|s| parse_block_stat(s.as_str()))
+        };
+
         // Look up mount point by device path
         let mut dev_path_buf: StackString<64> = StackString::new();
         dev_path_buf.push_str("/dev/");
         dev_path_buf.push_str(name);
-        let mountpoint = mountpoints.get(dev_path_buf.as_str())
+        let mut mountpoint = mountpoints.get(dev_path_buf.as_str())
             .map(StackString::from_str);
 
+        // Device-mapper devices are usually mounted as /dev/mapper/<name>
+        if let (None, Some(dm)) = (&mountpoint, &dm_name) {
+            let mapper: StackString<160> = io::join_path("/dev/mapper", dm.as_str());
+            mountpoint = mountpoints.get(mapper.as_str()).map(StackString::from_str);
+        }
+
         Some(BlockDevice {
             name: StackString::from_str(name),
             dev_type,
@@ -234,22 +499,45 @@ impl BlockDevice {
             model,
             rotational,
             scheduler,
+            zoned,
+            nr_zones,
+            reads: stat.map(|s| s.reads),
+            read_sectors: stat.map(|s| s.read_sectors),
+            writes: stat.map(|s| s.writes),
+            write_sectors: stat.map(|s| s.write_sectors),
+            io_in_progress: stat.map(|s| s.io_in_progress),
+            dm_name,
+            md_level,
+            raid_disks,
+            holders,
+            slaves,
         })
     }
 
-    /// Check if this device matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let fields = [
-            self.name.as_str(),
-            opt_str(&self.model),
-            opt_str(&self.mountpoint),
-            self.dev_type.as_str(),
-        ];
-        matches_any(&fields, pattern, case_insensitive)
+    /// Size in bytes.
+    pub fn size_bytes(&self) -> u64 {
+        self.size_sectors.saturating_mul(STAT_SECTOR_SIZE as u64)
+    }
+
+    /// Counters that --baseline reports deltas for.
+    fn counters(&self) -> [(&'static str, Option<u64>); 4] {
+        [
+            (f::READS, self.reads),
+            (f::READ_SECTORS, self.read_sectors),
+            (f::WRITES, self.writes),
+            (f::WRITE_SECTORS, self.write_sectors),
+        ]
+    }
+
+    /// Output as a --template line. `size` (as with -h) and `parent` can
+    /// be used on top of the filter fields.
+    fn print_template(&self, template: &mut Template) {
+        let size = io::format_human_size(self.size_bytes());
+        template.print(self, &[(f::SIZE, size.as_str()), (f::PARENT, opt_str(&self.parent))]);
     }
 
     /// Output as text.
-    fn print_text(&self, verbose: bool, human: bool) {
+    fn print_text(&self, verbose: bool, human: bool, prev: Option<&JsonRef>) {
         let mut w = TextWriter::new();
 
         w.field_str(f::NAME, self.name.as_str());
@@ -264,7 +552,7 @@ impl BlockDevice {
         w.field_str(f::MAJMIN, majmin.as_str());
 
         if human {
-            let size = io::format_sectors_human(self.size_sectors, self.sector_size);
+            let size = io::format_sectors_human(self.size_sectors, STAT_SECTOR_SIZE);
             w.field_str(f::SIZE, size.as_str());
         } else {
             w.field_u64(f::SIZE_SECTORS, self.size_sectors);
@@ -294,14 +582,57 @@ impl BlockDevice {
             if let Some(ref sched) = self.scheduler {
                 w.field_str(f::SCHEDULER, sched.as_str());
             }
+            w.field_str_opt(f::ZONED, self.zoned.as_ref().map(|s| s.as_str()));
+            w.field_u64_opt(f::ZONES, self.nr_zones);
+
+            if let Some(reads) = self.reads {
+                w.field_u64(f::READS, reads);
+            }
+            if let Some(sectors) = self.read_sectors {
+                if human {
+                    let size = io::format_sectors_human(sectors, STAT_SECTOR_SIZE);
+                    w.field_str(f::READ_SIZE, size.as_str());
+                } else {
+                    w.field_u64(f::READ_SECTORS, sectors);
+                }
+            }
+            if let Some(writes) = self.writes {
+                w.field_u64(f::WRITES, writes);
+            }
+            if let Some(sectors) = self.write_sectors {
+                if human {
+                    let size = io::format_sectors_human(sectors, STAT_SECTOR_SIZE);
+                    w.field_str(f::WRITE_SIZE, size.as_str());
+                } else {
+                    w.field_u64(f::WRITE_SECTORS, sectors);
+                }
+            }
+            if let Some(inflight) = self.io_in_progress {
+                w.field_u64(f::IO_IN_PROGRESS, inflight);
+            }
+
+            w.field_str_opt(f::DM_NAME, self.dm_name.as_ref().map(|s| s.as_str()));
+            w.field_str_opt(f::MD_LEVEL, self.md_level.as_ref().map(|s| s.as_str()));
+            w.field_u64_opt(f::RAID_DISKS, self.raid_disks.map(|v| v as u64));
+            if !self.holders.is_empty() {
+                w.field_str(f::HOLDERS, self.holders.join().as_str());
+            }
+            if !self.slaves.is_empty() {
+                w.field_str(f::SLAVES, self.slaves.join().as_str());
+            }
         }
 
-        w.finish();
+        if let Some(prev) = prev {
+            baseline::for_each_delta(prev, &self.counters(), |key, d| w.field_u64(key, d));
+        }
+
+        w.finish_record();
     }
 
     /// Write as JSON object.
-    fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool, human: bool) {
-        w.array_object_begin();
+    fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool, human: bool, prev: Option<&JsonRef>) {
+        w.record_begin();
+        w.field_str(kind::KIND, kind::BLOCK_DEVICE);
 
         w.field_str(f::NAME, self.name.as_str());
         w.field_str(f::TYPE, self.dev_type.as_str());
@@ -309,7 +640,7 @@ impl BlockDevice {
         w.field_u64(f::MINOR, self.minor as u64);
 
         if human {
-            let size = io::format_sectors_human(self.size_sectors, self.sector_size);
+            let size = io::format_sectors_human(self.size_sectors, STAT_SECTOR_SIZE);
             w.field_str(f::SIZE, size.as_str());
         } else {
             w.field_u64(f::SIZE_SECTORS, self.size_sectors);
@@ -329,21 +660,474 @@ impl BlockDevice {
                 w.field_bool(f::ROTATIONAL, rot);
             }
             w.field_str_opt(f::SCHEDULER, self.scheduler.as_ref().map(|s| s.as_str()));
+            w.field_str_opt(f::ZONED, self.zoned.as_ref().map(|s| s.as_str()));
+            w.field_u64_opt(f::NR_ZONES, self.nr_zones);
+
+            if let Some(reads) = self.reads {
+                w.field_u64(f::READS, reads);
+            }
+            if let Some(sectors) = self.read_sectors {
+                if human {
+                    let size = io::format_sectors_human(sectors, STAT_SECTOR_SIZE);
+                    w.field_str(f::READ_SIZE, size.as_str());
+                } else {
+                    w.field_u64(f::READ_SECTORS, sectors);
+                }
+            }
+            if let Some(writes) = self.writes {
+                w.field_u64(f::WRITES, writes);
+            }
+            if let Some(sectors) = self.write_sectors {
+                if human {
+                    let size = io::format_sectors_human(sectors, STAT_SECTOR_SIZE);
+                    w.field_str(f::WRITE_SIZE, size.as_str());
+                } else {
+                    w.field_u64(f::WRITE_SECTORS, sectors);
+                }
+            }
+            if let Some(inflight) = self.io_in_progress {
+                w.field_u64(f::IO_IN_PROGRESS, inflight);
+            }
+
+            w.field_str_opt(f::DM_NAME, self.dm_name.as_ref().map(|s| s.as_str()));
+            w.field_str_opt(f::MD_LEVEL, self.md_level.as_ref().map(|s| s.as_str()));
+            w.field_u64_opt(f::RAID_DISKS, self.raid_disks.map(|v| v as u64));
+            if !self.holders.is_empty() {
+                self.holders.write_json(w, f::HOLDERS);
+            }
+            if !self.slaves.is_empty() {
+                self.slaves.write_json(w, f::SLAVES);
+            }
+        }
+
+        if let Some(prev) = prev {
+            baseline::for_each_delta(prev, &self.counters(), |key, d| w.field_u64(key, d));
         }
 
         w.array_object_end();
     }
 }
 
+impl Filterable for BlockDevice {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::NAME, self.name.as_str());
+        visit(f::MODEL, opt_str(&self.model));
+        visit(f::MOUNTPOINT, opt_str(&self.mountpoint));
+        visit(f::TYPE, self.dev_type.as_str());
+        visit(f::DM_NAME, opt_str(&self.dm_name));
+    }
+
+    fn filter_keyed_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::REMOVABLE, bool_str(self.removable));
+        visit(f::RO, bool_str(self.ro));
+    }
+
+    fn filter_numeric_fields(&self, visit: &mut dyn FnMut(&str, i64)) {
+        visit(f::SIZE_BYTES, self.size_bytes() as i64);
+        visit(f::SIZE_SECTORS, self.size_sectors as i64);
+        let counters = [
+            (f::READS, self.reads),
+            (f::READ_SECTORS, self.read_sectors),
+            (f::WRITES, self.writes),
+            (f::WRITE_SECTORS, self.write_sectors),
+            (f::IO_IN_PROGRESS, self.io_in_progress),
+        ];
+        for (name, value) in counters {
+            if let Some(v) = value {
+                visit(name, v as i64);
+            }
+        }
+    }
+}
+
+/// Every `--template` field, whether or not a record has it.
+const TEMPLATE_FIELDS: &[&str] = &[
+    f::NAME,
+    f::MODEL,
+    f::MOUNTPOINT,
+    f::TYPE,
+    f::DM_NAME,
+    f::REMOVABLE,
+    f::RO,
+    f::SIZE_BYTES,
+    f::SIZE_SECTORS,
+    f::READS,
+    f::READ_SECTORS,
+    f::WRITES,
+    f::WRITE_SECTORS,
+    f::IO_IN_PROGRESS,
+    f::SIZE,
+    f::PARENT,
+];
+
+/// Disk count and total capacity for `--summary`.
+///
+/// Only whole disks count: partitions would count their space twice, and
+/// loop, ram and device-mapper devices sit on top of other storage.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSummary {
+    pub disks: u32,
+    pub capacity_bytes: u64,
+}
+
+impl DiskSummary {
+    /// Count `dev` if it is a whole disk.
+    pub fn add(&mut self, dev: &BlockDevice) {
+        if dev.dev_type == BlockType::Disk {
+            self.disks += 1;
+            self.capacity_bytes = self.capacity_bytes.saturating_add(dev.size_bytes());
+        }
+    }
+
+    /// Summarize a list of devices.
+    pub fn from_devices(devices: &[BlockDevice]) -> Self {
+        let mut summary = DiskSummary::default();
+        for dev in devices {
+            summary.add(dev);
+        }
+        summary
+    }
+
+    /// The footer line, e.g. "TOTAL_DISKS=3 TOTAL_CAPACITY=2T".
+    fn print_text(&self, human: bool) {
+        let mut w = TextWriter::new();
+        w.field_u64(f::TOTAL_DISKS, self.disks as u64);
+        if human {
+            w.field_str(f::TOTAL_CAPACITY, io::format_human_size(self.capacity_bytes).as_str());
+        } else {
+            w.field_u64(f::TOTAL_CAPACITY_BYTES, self.capacity_bytes);
+        }
+        w.finish();
+    }
+
+    /// The "summary" object next to "data".
+    fn write_json(&self, w: &mut StreamingJsonWriter, human: bool) {
+        w.field_object("summary");
+        w.field_u64(f::TOTAL_DISKS, self.disks as u64);
+        if human {
+            w.field_str(f::TOTAL_CAPACITY, io::format_human_size(self.capacity_bytes).as_str());
+        } else {
+            w.field_u64(f::TOTAL_CAPACITY_BYTES, self.capacity_bytes);
+        }
+        w.end_field_object();
+    }
+}
+
+/// One device in the `--topology` tree: just what the tree and its
+/// rows need, so a full table of them fits on the stack.
+#[derive(Default, Clone)]
+pub struct TopoNode {
+    pub name: StackString<32>,
+    pub dev_type: &'static str,
+    pub size_bytes: u64,
+    /// The disk, for a partition
+    pub parent: Option<StackString<32>>,
+    /// What the device is built on (dm, md)
+    pub slaves: NameList,
+    pub dm_name: Option<StackString<128>>,
+    pub mountpoint: Option<StackString<256>>,
+    /// Failed `-f`: only shown on the way to, or under, a device that didn't
+    pub filtered_out: bool,
+}
+
+impl TopoNode {
+    fn from_device(dev: &BlockDevice) -> Self {
+        TopoNode {
+            name: dev.name.clone(),
+            dev_type: dev.dev_type.as_str(),
+            size_bytes: dev.size_bytes(),
+            parent: dev.parent.clone(),
+            slaves: dev.slaves.clone(),
+            dm_name: dev.dm_name.clone(),
+            mountpoint: dev.mountpoint.clone(),
+            filtered_out: false,
+        }
+    }
+
+    /// Does this device sit directly on `name`?
+    fn is_child_of(&self, name: &str) -> bool {
+        self.parent.as_ref().is_some_and(|p| p.as_str() == name) || self.slaves.contains(name)
+    }
+
+    /// A tree row: `sda2 part 475.9G /` (dm devices add their name).
+    fn format_row(&self) -> StackString<512> {
+        let mut s = StackString::from_str(self.name.as_str());
+        if let Some(ref dm) = self.dm_name {
+            s.push_str(" (");
+            s.push_str(dm.as_str());
+            s.push(')');
+        }
+        s.push(' ');
+        s.push_str(self.dev_type);
+        s.push(' ');
+        s.push_str(io::format_human_size(self.size_bytes).as_str());
+        if let Some(ref mp) = self.mountpoint {
+            s.push(' ');
+            s.push_str(mp.as_str());
+        }
+        s
+    }
+}
+
+/// Walk the storage stack as a tree, depth first.
+///
+/// Roots are the devices built on nothing else in `nodes` (disks, loop
+/// and ram devices, or a device whose disk was left out). A device's
+/// children are its partitions and everything that lists it as a slave,
+/// so a partition under LUKS under LVM nests three deep. A device on
+/// several slaves (RAID, an LVM volume spanning disks) shows up under
+/// each of them, as in lsblk. Devices `filtered_out` only show on the
+/// path to one that isn't, or under one. `f` gets the depth, the node
+/// and whether it's being entered (true) or left (false), which lets JSON
+/// close each node's children.
+pub fn walk_topology(nodes: &[TopoNode], f: &mut impl FnMut(usize, &TopoNode, bool)) {
+    let is_root = |n: &TopoNode| !nodes.iter().any(|other| n.is_child_of(other.name.as_str()));
+    for node in nodes.iter().filter(|n| is_root(n)) {
+        walk_from(nodes, node, 0, false, f);
+    }
+}
+
+fn walk_from(nodes: &[TopoNode], node: &TopoNode, depth: usize, under_match: bool, f: &mut impl FnMut(usize, &TopoNode, bool)) {
+    if !under_match && !leads_to_match(nodes, node, depth) {
+        return;
+    }
+    f(depth, node, true);
+    if depth < MAX_TOPOLOGY_DEPTH {
+        for child in nodes.iter().filter(|n| n.is_child_of(node.name.as_str())) {
+            walk_from(nodes, child, depth + 1, under_match || !node.filtered_out, f);
+        }
+    }
+    f(depth, node, false);
+}
+
+/// Does `node`, or anything built on it, pass the filter?
+fn leads_to_match(nodes: &[TopoNode], node: &TopoNode, depth: usize) -> bool {
+    !node.filtered_out
+        || (depth < MAX_TOPOLOGY_DEPTH
+            && nodes
+                .iter()
+                .filter(|n| n.is_child_of(node.name.as_str()))
+                .any(|child| leads_to_match(nodes, child, depth + 1)))
+}
+
+/// `kv block --topology`: the storage stack as an indented tree.
+///
+/// Devices of a type `types` leaves out aren't in the tree at all, so
+/// what was built on them starts a tree of its own.
+fn print_topology(opts: &GlobalOptions, types: TypeSelection, mountpoints: &MountpointMap) {
+    let mut nodes: [TopoNode; MAX_TOPOLOGY_NODES] = core::array::from_fn(|_| TopoNode::default());
+    let mut count = 0;
+    let mut dropped = 0;
+    let mut add = |dev: &BlockDevice| {
+        if count < MAX_TOPOLOGY_NODES {
+            nodes[count] = TopoNode::from_device(dev);
+            nodes[count].filtered_out = !dev.matches_filter(&opts.filter);
+            count += 1;
+        } else {
+            dropped += 1;
+        }
+    };
+    io::for_each_dir_entry(BLOCK_SYSFS_PATH, |disk_name| {
+        let Some(disk) = BlockDevice::read(disk_name, None, mountpoints, opts.stable_read) else { return };
+        // Skip loop devices with size 0 (unbound)
+        if disk.dev_type == BlockType::Loop && disk.size_sectors == 0 {
+            return;
+        }
+        if types.allows(disk.dev_type) {
+            add(&disk);
+        }
+        if !types.visits_partitions_of(disk.dev_type) {
+            return;
+        }
+        for_each_partition(BLOCK_SYSFS_PATH, disk_name, |entry_name| {
+            let part = BlockDevice::read(entry_name, Some(disk_name), mountpoints, opts.stable_read);
+            if let Some(part) = part.filter(|p| types.allows(p.dev_type)) {
+                add(&part);
+            }
+        });
+    });
+    if dropped > 0 {
+        let mut buf = itoa::Buffer::new();
+        print::eprint("Warning: topology holds ");
+        print::eprint(buf.format(MAX_TOPOLOGY_NODES));
+        print::eprint(" devices; ");
+        print::eprint(buf.format(dropped));
+        print::eprintln(" more left out");
+    }
+    let nodes = &nodes[..count];
+
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "block");
+        w.field_array("data");
+        walk_topology(nodes, &mut |_, node, entering| {
+            if entering {
+                w.record_begin();
+                w.field_str(kind::KIND, kind::BLOCK_NODE);
+                w.field_str(f::NAME, node.name.as_str());
+                w.field_str(f::TYPE, node.dev_type);
+                w.field_u64(f::SIZE_BYTES, node.size_bytes);
+                w.field_str_opt(f::DM_NAME, node.dm_name.as_ref().map(|s| s.as_str()));
+                w.field_str_opt(f::MOUNTPOINT, node.mountpoint.as_ref().map(|s| s.as_str()));
+                w.field_array(f::CHILDREN);
+            } else {
+                w.end_field_array();
+                w.array_object_end();
+            }
+        });
+        w.end_field_array();
+        w.end_object();
+        w.finish();
+    } else if nodes.is_empty() {
+        print::println("block: no block devices found");
+    } else if nodes.iter().all(|n| n.filtered_out) {
+        print::println("block: no matching devices");
+    } else {
+        walk_topology(nodes, &mut |depth, node, entering| {
+            if entering {
+                for _ in 0..depth {
+                    print::print("  ");
+                }
+                print::println_record(node.format_row().as_str());
+            }
+        });
+    }
+}
+
+/// Options specific to the block subcommand.
+#[derive(Default)]
+pub struct BlockOptions {
+    /// Print a disk count and total capacity after the list
+    pub summary: bool,
+    /// Print the holder/slave stack as a tree instead of the list
+    pub topology: bool,
+    /// Device types to leave out, or to keep only
+    pub types: TypeSelection,
+}
+
+impl BlockOptions {
+    /// Parse block-specific options from remaining arguments.
+    pub fn parse(args: &ExtraArgs) -> Self {
+        let mut opts = BlockOptions::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg {
+                "--summary" => opts.summary = true,
+                "--topology" => opts.topology = true,
+                "--exclude-types" => {
+                    if let Some(list) = iter.next() {
+                        opts.types.exclude |= parse_type_list(list);
+                    }
+                }
+                "--only-types" => {
+                    if let Some(list) = iter.next() {
+                        opts.types.only |= parse_type_list(list);
+                    }
+                }
+                _ => {}
+            }
+        }
+        opts
+    }
+}
+
+/// Which device types to list (`--exclude-types`, `--only-types`).
+/// Masks of `BlockType::bit`; an empty `only` means every type.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TypeSelection {
+    pub exclude: u16,
+    pub only: u16,
+}
+
+impl TypeSelection {
+    /// Should devices of this type be listed?
+    pub fn allows(&self, dev_type: BlockType) -> bool {
+        self.exclude & dev_type.bit() == 0 && (self.only == 0 || self.only & dev_type.bit() != 0)
+    }
+
+    /// Should the partitions of a disk of this type be looked at? Not
+    /// when its type was excluded: `--exclude-types loop` drops loop0p1
+    /// along with loop0. Otherwise each partition goes by the "part" type.
+    pub fn visits_partitions_of(&self, disk_type: BlockType) -> bool {
+        self.exclude & disk_type.bit() == 0
+    }
+}
+
+/// Parse a comma list of type names like "loop,ram" into a mask, warning
+/// about (and skipping) names that aren't a type.
+fn parse_type_list(list: &str) -> u16 {
+    let mut mask = 0;
+    for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match BlockType::parse(name) {
+            Some(t) => mask |= t.bit(),
+            None => {
+                print::eprint("Warning: unknown block type '");
+                print::eprint(name);
+                print::eprintln("' (disk, part, loop, ram, lvm, crypt, multipath, dm, md, other)");
+            }
+        }
+    }
+    mask
+}
+
+/// Call `f` with the name of each partition directory of a disk, in
+/// natural order (sda2 before sda10).
+///
+/// Partition directories start with the disk name. Like the devicetree
+/// walker, this skips symlinks, and also anything that resolves outside
+/// the disk's own directory, so a crafted or mirrored sysfs (e.g. in a
+/// container) can't send us off reading other files. Without /proc to
+/// resolve paths, only the symlink check applies.
+fn for_each_partition(block_path: &str, disk_name: &str, mut f: impl FnMut(&str)) {
+    let disk_path: StackString<64> = io::join_path(block_path, disk_name);
+    let disk_real: Option<StackString<512>> = io::canonical_path(disk_path.as_str());
+
+    io::for_each_dir_entry_sorted(disk_path.as_str(), io::natural_cmp, |entry_name| {
+        if !entry_name.starts_with(disk_name) {
+            return;
+        }
+        let entry_path: StackString<128> = io::join_path(disk_path.as_str(), entry_name);
+        if io::is_symlink(entry_path.as_str()) || !io::is_dir(entry_path.as_str()) {
+            return;
+        }
+        if disk_real.as_ref().is_some_and(|real| !io::resolves_under(entry_path.as_str(), real.as_str())) {
+            return;
+        }
+        f(entry_name);
+    });
+}
+
 /// Parse major:minor string.
 fn parse_dev(s: &str) -> Option<(u32, u32)> {
     let (maj, min) = s.split_once(':')?;
     Some((maj.trim().parse().ok()?, min.trim().parse().ok()?))
 }
 
+/// Zone model and zone count of the disk at `dev_dir` (a /sys/block entry).
+///
+/// Conventional devices say "none" in queue/zoned (older kernels have no
+/// such file); both come back as None then.
+fn read_zoned(dev_dir: &str) -> (Option<StackString<16>>, Option<u64>) {
+    let zoned_path: StackString<256> = io::join_path(dev_dir, "queue/zoned");
+    let zoned = io::read_file_stack::<16>(zoned_path.as_str()).filter(|z| z.as_str() != "none");
+    if zoned.is_none() {
+        return (None, None);
+    }
+    let zones_path: StackString<256> = io::join_path(dev_dir, "queue/nr_zones");
+    (zoned, io::read_file_parse(zones_path.as_str()))
+}
+
+/// The SCHEDULER value: the active one, or the whole list with --raw.
+fn scheduler_value(s: &str, raw: bool) -> Option<StackString<64>> {
+    if raw {
+        Some(StackString::from_str(s))
+    } else {
+        extract_active_scheduler(s)
+    }
+}
+
 /// Extract active scheduler from scheduler file content.
 /// Format: "mq-deadline kyber [none]" -> "none"
-fn extract_active_scheduler(s: &str) -> Option<StackString<32>> {
+fn extract_active_scheduler(s: &str) -> Option<StackString<64>> {
     // Active scheduler is in brackets
     let start = s.find('[')?;
     let end = s.find(']')?;
@@ -355,159 +1139,562 @@ fn extract_active_scheduler(s: &str) -> Option<StackString<32>> {
 }
 
 /// Entry point for `kv block` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let block_opts = BlockOptions::parse(args);
+
+    // The tree has no list to page, total or template, and no summary line
+    if block_opts.topology {
+        let ignored = opts
+            .list_option()
+            .or(block_opts.summary.then_some("--summary"))
+            .or(baseline::parse_path(args).map(|_| "--baseline"));
+        if let Some(option) = ignored {
+            cli::reject_combination(option, "--topology");
+            return 1;
+        }
+    }
+
+    // --sum prints only the totals, so there's no list for a summary line
+    if block_opts.summary && opts.sum.is_some() {
+        cli::reject_combination("--summary", "--sum");
+        return 1;
+    }
+
+    let baseline = match baseline::parse_path(args) {
+        Some(path) => match Baseline::load(path) {
+            Some(b) => Some(b),
+            None => {
+                print::eprint("Error: cannot read baseline ");
+                print::eprintln(path);
+                return 1;
+            }
+        },
+        None => None,
+    };
+    let prev = |name: &str| baseline.as_ref().and_then(|b| b.entry("block", name));
+
     if !io::path_exists(BLOCK_SYSFS_PATH) {
         if opts.json {
-            let mut w = begin_kv_output_streaming(opts.pretty, "block");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "block");
             w.field_array("data");
             w.end_field_array();
+            if block_opts.summary {
+                DiskSummary::default().write_json(&mut w, opts.human);
+            }
             w.end_object();
             w.finish();
         } else {
             print::println("block: no block devices found");
+            if block_opts.summary {
+                DiskSummary::default().print_text(opts.human);
+            }
         }
         return 0;
     }
 
     let mountpoints = MountpointMap::from_mounts();
-    let filter = opts.filter.as_ref().map(|s| s.as_str());
-    let case_insensitive = opts.filter_case_insensitive;
+    if block_opts.topology {
+        print_topology(opts, block_opts.types, &mountpoints);
+        return 0;
+    }
+
+    let filter = &opts.filter;
+    let types = block_opts.types;
+
+    if let Some(fields) = &opts.sum {
+        // A partition's bytes are already in its disk's, so partitions
+        // only count when their disk didn't (`-f sda1`, `--only-types part`)
+        let mut totals = Totals::new(fields);
+        io::for_each_dir_entry(BLOCK_SYSFS_PATH, |disk_name| {
+            let Some(disk) = BlockDevice::read(disk_name, None, &mountpoints, opts.stable_read) else {
+                return;
+            };
+            if disk.dev_type == BlockType::Loop && disk.size_sectors == 0 {
+                return;
+            }
+            let counted = types.allows(disk.dev_type) && disk.matches_filter(filter);
+            if counted {
+                totals.add(&disk);
+            }
+            if counted || !types.visits_partitions_of(disk.dev_type) {
+                return;
+            }
+            for_each_partition(BLOCK_SYSFS_PATH, disk_name, |entry_name| {
+                let part = BlockDevice::read(entry_name, Some(disk_name), &mountpoints, opts.stable_read);
+                if let Some(part) = part.filter(|p| types.allows(p.dev_type) && p.matches_filter(filter)) {
+                    totals.add(&part);
+                }
+            });
+        });
+        if opts.json {
+            totals.print_json(opts.json_layout(), opts.human, "block");
+        } else {
+            totals.print_text(opts.human);
+        }
+        return 0;
+    }
+
+    // Totals cover the disks that were listed, so a filter narrows them too
+    let mut summary = DiskSummary::default();
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "block");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "block");
+        if let Some(interval) = baseline.as_ref().and_then(Baseline::interval) {
+            w.field_u64("baseline_interval_s", interval);
+        }
         w.field_array("data");
 
         let mut count = 0;
+        let mut page = Page::from_options(opts);
         io::for_each_dir_entry(BLOCK_SYSFS_PATH, |disk_name| {
-            if let Some(disk) = BlockDevice::read(disk_name, None, &mountpoints) {
+            if let Some(disk) = BlockDevice::read(disk_name, None, &mountpoints, opts.stable_read) {
                 // Skip loop devices with size 0 (unbound)
                 if disk.dev_type == BlockType::Loop && disk.size_sectors == 0 {
                     return;
                 }
 
                 // Output disk if it matches filter (or no filter)
-                if let Some(pattern) = filter {
-                    if disk.matches_filter(pattern, case_insensitive) {
-                        disk.write_json(&mut w, opts.verbose, opts.human);
-                        count += 1;
+                if types.allows(disk.dev_type) && disk.matches_filter(filter) {
+                    if page.take() {
+                        disk.write_json(&mut w, opts.verbose, opts.human, prev(disk_name).as_ref());
+                        summary.add(&disk);
                     }
-                } else {
-                    disk.write_json(&mut w, opts.verbose, opts.human);
                     count += 1;
                 }
 
                 // Look for partitions as subdirectories
-                let disk_path: StackString<64> = io::join_path(BLOCK_SYSFS_PATH, disk_name);
-                io::for_each_dir_entry(disk_path.as_str(), |entry_name| {
-                    // Partition directories start with the disk name
-                    if entry_name.starts_with(disk_name) {
-                        if let Some(part) = BlockDevice::read(entry_name, Some(disk_name), &mountpoints) {
-                            if let Some(pattern) = filter {
-                                if part.matches_filter(pattern, case_insensitive) {
-                                    part.write_json(&mut w, opts.verbose, opts.human);
-                                    count += 1;
-                                }
-                            } else {
-                                part.write_json(&mut w, opts.verbose, opts.human);
-                                count += 1;
-                            }
+                if !types.visits_partitions_of(disk.dev_type) {
+                    return;
+                }
+                for_each_partition(BLOCK_SYSFS_PATH, disk_name, |entry_name| {
+                    let part = BlockDevice::read(entry_name, Some(disk_name), &mountpoints, opts.stable_read);
+                    if let Some(part) = part.filter(|p| types.allows(p.dev_type) && p.matches_filter(filter)) {
+                        if page.take() {
+                            part.write_json(&mut w, opts.verbose, opts.human, prev(entry_name).as_ref());
                         }
+                        count += 1;
                     }
                 });
             }
         });
 
         w.end_field_array();
+        page.write_json(&mut w);
+        if block_opts.summary {
+            summary.write_json(&mut w, opts.human);
+        }
         w.end_object();
         w.finish();
 
-        if count == 0 && filter.is_some() {
+        if count == 0 && filter.is_active() {
             // Empty filtered result is fine
         }
     } else {
         let mut count = 0;
+        let mut template = Template::from_options(opts, TEMPLATE_FIELDS);
+        let mut page = Page::from_options(opts);
         io::for_each_dir_entry(BLOCK_SYSFS_PATH, |disk_name| {
-            if let Some(disk) = BlockDevice::read(disk_name, None, &mountpoints) {
+            if let Some(disk) = BlockDevice::read(disk_name, None, &mountpoints, opts.stable_read) {
                 // Skip loop devices with size 0 (unbound)
                 if disk.dev_type == BlockType::Loop && disk.size_sectors == 0 {
                     return;
                 }
 
                 // Output disk if it matches filter (or no filter)
-                if let Some(pattern) = filter {
-                    if disk.matches_filter(pattern, case_insensitive) {
-                        disk.print_text(opts.verbose, opts.human);
-                        count += 1;
+                if types.allows(disk.dev_type) && disk.matches_filter(filter) {
+                    if page.take() {
+                        match template.as_mut() {
+                            Some(t) => disk.print_template(t),
+                            None => disk.print_text(opts.verbose, opts.human, prev(disk_name).as_ref()),
+                        }
+                        summary.add(&disk);
                     }
-                } else {
-                    disk.print_text(opts.verbose, opts.human);
                     count += 1;
                 }
 
                 // Look for partitions as subdirectories
-                let disk_path: StackString<64> = io::join_path(BLOCK_SYSFS_PATH, disk_name);
-                io::for_each_dir_entry(disk_path.as_str(), |entry_name| {
-                    // Partition directories start with the disk name
-                    if entry_name.starts_with(disk_name) {
-                        if let Some(part) = BlockDevice::read(entry_name, Some(disk_name), &mountpoints) {
-                            if let Some(pattern) = filter {
-                                if part.matches_filter(pattern, case_insensitive) {
-                                    part.print_text(opts.verbose, opts.human);
-                                    count += 1;
-                                }
-                            } else {
-                                part.print_text(opts.verbose, opts.human);
-                                count += 1;
+                if !types.visits_partitions_of(disk.dev_type) {
+                    return;
+                }
+                for_each_partition(BLOCK_SYSFS_PATH, disk_name, |entry_name| {
+                    let part = BlockDevice::read(entry_name, Some(disk_name), &mountpoints, opts.stable_read);
+                    if let Some(part) = part.filter(|p| types.allows(p.dev_type) && p.matches_filter(filter)) {
+                        if page.take() {
+                            match template.as_mut() {
+                                Some(t) => part.print_template(t),
+                                None => part.print_text(opts.verbose, opts.human, prev(entry_name).as_ref()),
                             }
                         }
+                        count += 1;
                     }
                 });
             }
         });
+        if let Some(t) = &template {
+            t.finish();
+        }
 
         if count == 0 {
-            if filter.is_some() {
+            if filter.is_active() {
                 print::println("block: no matching devices");
             } else {
                 print::println("block: no block devices found");
             }
         }
+        if block_opts.summary {
+            summary.print_text(opts.human);
+        }
     }
 
     0
 }
 
-/// Write block devices to JSON writer (for snapshot).
+/// Write block devices to JSON writer (for snapshot), with /proc/self/mounts
+/// already read, so a snapshot that also lists mounts reads the file once
+/// and both sections agree.
+///
+/// With `include_empty`, a missing /sys/block still gives `"block": []`.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
-    if !io::path_exists(BLOCK_SYSFS_PATH) {
+pub fn write_snapshot_with_mounts(w: &mut StreamingJsonWriter, verbose: bool, mounts: Option<&str>, include_empty: bool) {
+    if !include_empty && !io::path_exists(BLOCK_SYSFS_PATH) {
         return;
     }
+    let mountpoints = match mounts {
+        Some(contents) => MountpointMap::from_contents(contents),
+        None => MountpointMap::new(),
+    };
+    write_snapshot_devices(w, verbose, &mountpoints);
+}
 
-    let mountpoints = MountpointMap::from_mounts();
-
+#[cfg(feature = "snapshot")]
+fn write_snapshot_devices(w: &mut StreamingJsonWriter, verbose: bool, mountpoints: &MountpointMap) {
     w.key("block");
     w.begin_array();
-    io::for_each_dir_entry(BLOCK_SYSFS_PATH, |disk_name| {
-        if let Some(disk) = BlockDevice::read(disk_name, None, &mountpoints) {
+    io::for_each_dir_entry_sorted(BLOCK_SYSFS_PATH, io::natural_cmp, |disk_name| {
+        if let Some(disk) = BlockDevice::read(disk_name, None, mountpoints, false) {
             // Skip loop devices with size 0 (unbound)
             if disk.dev_type == BlockType::Loop && disk.size_sectors == 0 {
                 return;
             }
 
-            disk.write_json(w, verbose, false);
+            disk.write_json(w, verbose, false, None);
 
             // Look for partitions as subdirectories
-            let disk_path: StackString<64> = io::join_path(BLOCK_SYSFS_PATH, disk_name);
-            io::for_each_dir_entry(disk_path.as_str(), |entry_name| {
-                // Partition directories start with the disk name
-                if entry_name.starts_with(disk_name) {
-                    if let Some(part) = BlockDevice::read(entry_name, Some(disk_name), &mountpoints) {
-                        part.write_json(w, verbose, false);
-                    }
+            for_each_partition(BLOCK_SYSFS_PATH, disk_name, |entry_name| {
+                if let Some(part) = BlockDevice::read(entry_name, Some(disk_name), mountpoints, false) {
+                    part.write_json(w, verbose, false, None);
                 }
             });
         }
     });
     w.end_array();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::testing::TempDir;
+
+    fn device(name: &str, dev_type: BlockType, size_sectors: u64, sector_size: u32) -> BlockDevice {
+        BlockDevice {
+            name: StackString::from_str(name),
+            dev_type,
+            major: 8,
+            minor: 0,
+            size_sectors,
+            sector_size,
+            removable: false,
+            ro: false,
+            parent: None,
+            mountpoint: None,
+            model: None,
+            rotational: None,
+            scheduler: None,
+            zoned: None,
+            nr_zones: None,
+            reads: None,
+            read_sectors: None,
+            writes: None,
+            write_sectors: None,
+            io_in_progress: None,
+            dm_name: None,
+            md_level: None,
+            raid_disks: None,
+            holders: NameList::default(),
+            slaves: NameList::default(),
+        }
+    }
+
+    #[test]
+    fn test_partition_symlink_skipped() {
+        // A fake /sys/block: sda/sda1 is a real partition, sda/sda2 a
+        // symlink out to a directory elsewhere
+        let base = TempDir::new("block");
+        let sda = base.mkdir("sda");
+        let sda1 = base.mkdir("sda/sda1");
+        let outside = base.mkdir("outside");
+        let sda2 = base.symlink(outside.as_str(), "sda/sda2");
+
+        let mut seen: [StackString<16>; 4] = Default::default();
+        let mut count = 0;
+        for_each_partition(base.path(), "sda", |name| {
+            seen[count] = StackString::from_str(name);
+            count += 1;
+        });
+
+        let real: StackString<512> = io::canonical_path(sda.as_str()).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(seen[0].as_str(), "sda1");
+        assert!(io::resolves_under(sda1.as_str(), real.as_str()));
+        assert!(!io::resolves_under(sda2.as_str(), real.as_str()));
+    }
+
+    #[test]
+    fn test_read_zoned() {
+        // A fake /sys/block entry for an SMR drive, then a conventional one
+        let dev = TempDir::new("zoned");
+        dev.mkdir("queue");
+
+        // Nothing there yet: an older kernel
+        let missing = read_zoned(dev.path());
+        assert!(missing.0.is_none() && missing.1.is_none());
+
+        dev.write("queue/zoned", b"host-managed\n");
+        dev.write("queue/nr_zones", b"1024\n");
+        let (model, zones) = read_zoned(dev.path());
+        assert_eq!(model.as_ref().map(|s| s.as_str()), Some("host-managed"));
+        assert_eq!(zones, Some(1024));
+
+        dev.write("queue/zoned", b"none\n");
+        let conventional = read_zoned(dev.path());
+        assert!(conventional.0.is_none() && conventional.1.is_none());
+    }
+
+    #[test]
+    fn test_mountpoints_from_contents() {
+        let contents = "/dev/sda1 / ext4 rw,relatime 0 0\n\
+                        tmpfs /tmp tmpfs rw 0 0\n\
+                        /dev/mapper/vg0-home /home xfs rw 0 0\n";
+        let map = MountpointMap::from_contents(contents);
+        assert_eq!(map.get("/dev/sda1"), Some("/"));
+        assert_eq!(map.get("/dev/mapper/vg0-home"), Some("/home"));
+        // Only /dev/* sources are kept
+        assert_eq!(map.get("tmpfs"), None);
+        assert_eq!(map.get("/dev/sdb1"), None);
+    }
+
+    fn node(name: &str, parent: Option<&str>, slaves: &[&str]) -> TopoNode {
+        let mut list = NameList::default();
+        for slave in slaves {
+            list.push(slave);
+        }
+        TopoNode {
+            name: StackString::from_str(name),
+            parent: parent.map(StackString::from_str),
+            slaves: list,
+            ..TopoNode::default()
+        }
+    }
+
+    #[test]
+    fn test_walk_topology() {
+        // sda2 -> LUKS (dm-0) -> LVM (dm-1), plus a RAID1 over sdb1 and sdc1
+        let nodes = [
+            node("dm-1", None, &["dm-0"]),
+            node("sda", None, &[]),
+            node("sda1", Some("sda"), &[]),
+            node("sda2", Some("sda"), &[]),
+            node("dm-0", None, &["sda2"]),
+            node("sdb", None, &[]),
+            node("sdb1", Some("sdb"), &[]),
+            node("sdc", None, &[]),
+            node("sdc1", Some("sdc"), &[]),
+            node("md0", None, &["sdb1", "sdc1"]),
+        ];
+        let mut rows: [(usize, StackString<32>); 16] = Default::default();
+        let mut count = 0;
+        let mut open = 0;
+        walk_topology(&nodes, &mut |depth, node, entering| {
+            if entering {
+                rows[count] = (depth, node.name.clone());
+                count += 1;
+                open += 1;
+            } else {
+                open -= 1;
+            }
+        });
+        assert_eq!(open, 0);
+        let rows = rows.each_ref().map(|(depth, name)| (*depth, name.as_str()));
+        assert_eq!(
+            rows[..count],
+            [
+                (0, "sda"),
+                (1, "sda1"),
+                (1, "sda2"),
+                (2, "dm-0"),
+                (3, "dm-1"),
+                (0, "sdb"),
+                (1, "sdb1"),
+                (2, "md0"),
+                (0, "sdc"),
+                (1, "sdc1"),
+                (2, "md0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_topology_filtered() {
+        // -f mountpoint=/ matched dm-1 only: its stack shows, sdb doesn't
+        let mut nodes = [
+            node("sda", None, &[]),
+            node("sda2", Some("sda"), &[]),
+            node("dm-0", None, &["sda2"]),
+            node("dm-1", None, &["dm-0"]),
+            node("sdb", None, &[]),
+            // Its disk was past the node cap, so this is a root
+            node("sdc1", Some("sdc"), &[]),
+        ];
+        for n in nodes.iter_mut() {
+            n.filtered_out = n.name.as_str() != "dm-1";
+        }
+        let mut names: [StackString<32>; 8] = Default::default();
+        let mut count = 0;
+        walk_topology(&nodes, &mut |_, node, entering| {
+            if entering {
+                names[count] = node.name.clone();
+                count += 1;
+            }
+        });
+        let names = names.each_ref().map(|n| n.as_str());
+        assert_eq!(names[..count], ["sda", "sda2", "dm-0", "dm-1"]);
+
+        // Everything under a match shows, and a root without its disk too
+        nodes[0].filtered_out = false;
+        nodes[5].filtered_out = false;
+        count = 0;
+        walk_topology(&nodes, &mut |_, _, entering| count += entering as usize);
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn test_topology_row() {
+        let mut n = node("dm-0", None, &["sda2"]);
+        n.dev_type = "crypt";
+        n.size_bytes = 512 << 30;
+        n.dm_name = Some(StackString::from_str("luks-root"));
+        n.mountpoint = Some(StackString::from_str("/"));
+        assert_eq!(n.format_row().as_str(), "dm-0 (luks-root) crypt 512G /");
+    }
+
+    #[test]
+    fn test_scheduler_value() {
+        assert_eq!(scheduler_value("[none] mq-deadline\n", false).unwrap().as_str(), "none");
+        assert_eq!(scheduler_value("[none] mq-deadline\n", true).unwrap().as_str(), "[none] mq-deadline\n");
+        assert!(scheduler_value("none", false).is_none());
+    }
+
+    #[test]
+    fn test_disk_summary() {
+        let devices = [
+            device("sda", BlockType::Disk, 1_953_525_168, 512),
+            device("sda1", BlockType::Part, 1_953_523_712, 512),
+            // A 4Kn drive: size is still in 512-byte sectors
+            device("nvme0n1", BlockType::Disk, 1_000_215_216, 4096),
+            device("loop0", BlockType::Loop, 131_072, 512),
+            device("zram0", BlockType::Ram, 16_777_216, 512),
+            device("dm-0", BlockType::Lvm, 976_224_256, 512),
+        ];
+        let summary = DiskSummary::from_devices(&devices);
+        assert_eq!(summary.disks, 2);
+        assert_eq!(summary.capacity_bytes, (1_953_525_168 + 1_000_215_216) * 512);
+        assert_eq!(io::format_human_size(summary.capacity_bytes).as_str(), "1T");
+
+        assert_eq!(DiskSummary::from_devices(&[]), DiskSummary::default());
+    }
+
+    #[test]
+    fn test_parse_block_stat() {
+        let line = "  151237    41512  9728114    61829   114356   131547  6807370   127386        2    97112   194112        0        0        0        0";
+        let stat = parse_block_stat(line).unwrap();
+        assert_eq!(stat.reads, 151237);
+        assert_eq!(stat.read_sectors, 9728114);
+        assert_eq!(stat.writes, 114356);
+        assert_eq!(stat.write_sectors, 6807370);
+        assert_eq!(stat.io_in_progress, 2);
+    }
+
+    #[test]
+    fn test_parse_block_stat_minimal() {
+        // Older kernels only have the original 11 fields
+        let stat = parse_block_stat("1 0 8 0 2 0 16 0 0 0 0\n").unwrap();
+        assert_eq!(stat.reads, 1);
+        assert_eq!(stat.write_sectors, 16);
+    }
+
+    #[test]
+    fn test_parse_block_stat_invalid() {
+        assert!(parse_block_stat("").is_none());
+        assert!(parse_block_stat("1 2 3").is_none());
+        assert!(parse_block_stat("1 0 x 0 2 0 16 0 0 0 0").is_none());
+    }
+
+    #[test]
+    fn test_block_stat_is_behind() {
+        let prev = parse_block_stat("10 0 80 0 5 0 40 0 3 0 0").unwrap();
+        // Fewer I/Os in flight is fine; fewer sectors written isn't
+        assert!(!parse_block_stat("10 0 80 0 5 0 40 0 0 0 0").unwrap().is_behind(&prev));
+        assert!(!parse_block_stat("11 0 88 0 5 0 40 0 1 0 0").unwrap().is_behind(&prev));
+        assert!(parse_block_stat("10 0 80 0 5 0 32 0 3 0 0").unwrap().is_behind(&prev));
+    }
+
+    #[test]
+    fn test_block_type_from_dm_uuid() {
+        assert_eq!(BlockType::from_dm_uuid("LVM-x8kQ3nN2aB4cD5eF6gH7iJ8kL9mN0oP1qR2sT3uV4wX5yZ6"), BlockType::Lvm);
+        assert_eq!(BlockType::from_dm_uuid("CRYPT-LUKS2-0a1b2c3d4e5f-luks-0a1b"), BlockType::Crypt);
+        assert_eq!(BlockType::from_dm_uuid("mpath-3600508b400105e210000900000490000"), BlockType::Multipath);
+        // No uuid (e.g. a plain dmsetup create) or an unknown target
+        assert_eq!(BlockType::from_dm_uuid(""), BlockType::Dm);
+        assert_eq!(BlockType::from_dm_uuid("part1-mpath-3600"), BlockType::Dm);
+        assert_eq!(BlockType::Lvm.as_str(), "lvm");
+    }
+
+    fn block_options(args: &[&str]) -> BlockOptions {
+        let mut extra = ExtraArgs::new();
+        for arg in args {
+            extra.push(arg);
+        }
+        BlockOptions::parse(&extra)
+    }
+
+    #[test]
+    fn test_exclude_types() {
+        let types = block_options(&["--exclude-types", "loop,ram"]).types;
+        assert!(!types.allows(BlockType::Loop) && !types.allows(BlockType::Ram));
+        assert!(types.allows(BlockType::Disk) && types.allows(BlockType::Part));
+        // loop0p1 goes with loop0, sda1 stays
+        assert!(!types.visits_partitions_of(BlockType::Loop));
+        assert!(types.visits_partitions_of(BlockType::Disk));
+    }
+
+    #[test]
+    fn test_only_types() {
+        let types = block_options(&["--only-types", "disk"]).types;
+        assert!(types.allows(BlockType::Disk));
+        assert!(!types.allows(BlockType::Part) && !types.allows(BlockType::Loop));
+        // Partitions are still looked at, so "disk,part" lists both
+        let types = block_options(&["--only-types", "disk,part"]).types;
+        assert!(types.visits_partitions_of(BlockType::Disk) && types.allows(BlockType::Part));
+        // No options: everything, unknown names are skipped
+        assert!(BlockType::ALL.iter().all(|&t| TypeSelection::default().allows(t)));
+        assert_eq!(block_options(&["--exclude-types", "floppy"]).types, TypeSelection::default());
+        assert_eq!(parse_type_list(" lvm, crypt "), BlockType::Lvm.bit() | BlockType::Crypt.bit());
+    }
+
+    #[test]
+    fn test_parse_dev() {
+        assert_eq!(parse_dev("8:0"), Some((8, 0)));
+        assert_eq!(parse_dev("259:1\n"), Some((259, 1)));
+        assert_eq!(parse_dev("garbage"), None);
+    }
+}
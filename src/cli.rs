@@ -6,6 +6,7 @@
 //! The basic pattern:
 //! - First positional arg (after program name) is the subcommand
 //! - Everything else is flags/options for that subcommand
+//! - With --merge, further subcommand names run after the first
 //! - Global flags like --json, --pretty, --verbose apply to all subcommands
 //!
 //! This version uses stack-based storage to avoid heap allocation.
@@ -13,28 +14,13 @@
 #![allow(dead_code)]
 
 use core::ffi::{c_char, CStr};
-use crate::print;
+use crate::filter::{push_lowercase, Filter, FilterMode, FilterStr, Pattern, PatternList};
+use crate::json::{begin_kv_output_streaming, IndentStyle, Layout, Section};
+use crate::io;
+use crate::limits::{MAX_ARG_LEN, MAX_EXTRA_ARGS, MAX_FILTERS, MAX_FILTER_LEN, MAX_PATTERN_LIST_SIZE, MAX_SUBCMD_LEN};
+use crate::print::{self, KeyCase, TextStyle};
 use crate::stack::StackString;
-
-// =============================================================================
-// Input Safety Limits
-// =============================================================================
-
-/// Maximum length for filter patterns (defense against memory exhaustion).
-/// 1024 chars is plenty for any reasonable substring match.
-const MAX_FILTER_LEN: usize = 1024;
-
-/// Maximum length for subcommand name.
-const MAX_SUBCMD_LEN: usize = 32;
-
-/// Maximum number of extra arguments to store.
-const MAX_EXTRA_ARGS: usize = 8;
-
-/// Maximum length for each extra argument.
-const MAX_ARG_LEN: usize = 256;
-
-/// Type alias for filter string.
-pub type FilterStr = StackString<MAX_FILTER_LEN>;
+use crate::sum::SumFields;
 
 /// Type alias for subcommand string.
 pub type SubcmdStr = StackString<MAX_SUBCMD_LEN>;
@@ -49,20 +35,159 @@ pub struct GlobalOptions {
     pub json: bool,
     /// Pretty-print JSON (only meaningful with json=true)
     pub pretty: bool,
+    /// Set by `kv --merge -j` for each subcommand it runs: where in the
+    /// merged document its envelope goes
+    pub section: Option<Section>,
+    /// Indentation per level for pretty JSON
+    pub indent: IndentStyle,
     /// Verbose output - show extra fields
     pub verbose: bool,
     /// Human-readable output (e.g., "1.5G" instead of bytes)
     pub human: bool,
     /// Show help
     pub help: bool,
-    /// Filter pattern (case-sensitive if lowercase, insensitive if uppercase)
-    pub filter: Option<FilterStr>,
-    /// Whether filter is case-insensitive (-F vs -f)
-    pub filter_case_insensitive: bool,
+    /// Show every subcommand's detailed help (--help-all)
+    pub help_all: bool,
+    /// Filter patterns from -f/-F (may be given several times)
+    pub filter: Filter,
     /// Debug mode - show file access and parse errors
     pub debug: bool,
+    /// Key case and separator for text output
+    pub text_style: TextStyle,
+    /// Re-read counters until two reads agree (avoids torn values)
+    pub stable_read: bool,
+    /// Flatten JSON into a single object with dotted keys
+    pub flatten: bool,
+    /// Print the paths a subcommand reads instead of its output
+    pub list_paths: bool,
+    /// Write data output to this file instead of stdout
+    pub output: Option<ArgStr>,
+    /// Append to the --output file instead of truncating it
+    pub append: bool,
+    /// Exit 3 if a file the subcommand needs couldn't be read
+    pub strict: bool,
+    /// Exit 4 if the subcommand printed no data
+    pub probe: bool,
+    /// Run every subcommand named, one after another
+    pub merge: bool,
+    /// Decimal places for temperatures, volts, amps and watts (None = per-field default)
+    pub precision: Option<u8>,
+    /// Show temperatures in Fahrenheit in text output (JSON stays millicelsius)
+    pub fahrenheit: bool,
+    /// Follow enumerated values with a short description in text output
+    pub explain: bool,
+    /// Show values as the kernel wrote them, without kv's cleanup
+    pub raw: bool,
+    /// Print time spent per phase to stderr after the output
+    pub profile_timings: bool,
+    /// Human-readable sizes in powers of 1000 (KB, MB, ...) instead of 1024
+    pub si: bool,
+    /// Print totals of these numeric fields instead of one row per item
+    pub sum: Option<SumFields>,
+    /// Print each item as this `{field}` template instead of KEY=value
+    pub template: Option<ArgStr>,
+    /// Print at most this many items (after filtering)
+    pub limit: Option<usize>,
+    /// Skip this many items first (after filtering)
+    pub offset: usize,
+    /// IEEE OUI list for MAC_VENDOR= in `kv net` (--oui-file or KV_OUI_FILE)
+    pub oui_file: Option<ArgStr>,
+    /// pci.ids for VENDOR_NAME=/DEVICE_NAME= in `kv pci` (--pci-ids or KV_PCI_IDS)
+    pub pci_ids: Option<ArgStr>,
+}
+
+impl GlobalOptions {
+    /// How this run's JSON envelope is written.
+    pub fn json_layout(&self) -> Layout {
+        Layout { pretty: self.pretty, section: self.section }
+    }
+
+    /// The first of `--sum`, `--template`, `--limit` and `--offset` given.
+    /// Tree views print nested records rather than a list, so they can't
+    /// honour any of them.
+    pub fn list_option(&self) -> Option<&'static str> {
+        if self.sum.is_some() {
+            Some("--sum")
+        } else if self.template.is_some() {
+            Some("--template")
+        } else if self.limit.is_some() {
+            Some("--limit")
+        } else if self.offset > 0 {
+            Some("--offset")
+        } else {
+            None
+        }
+    }
+}
+
+/// "Error: --limit can't be used with --topology"
+pub fn reject_combination(option: &str, mode: &str) {
+    print::eprint("Error: ");
+    print::eprint(option);
+    print::eprint(" can't be used with ");
+    print::eprintln(mode);
+}
+
+/// A global flag, as listed in shell completions.
+pub struct FlagSpec {
+    /// Short form without the dash, if any
+    pub short: Option<char>,
+    /// Long form without the dashes
+    pub long: &'static str,
+    /// Takes a value in the next argument
+    pub takes_value: bool,
 }
 
+const fn flag(short: Option<char>, long: &'static str, takes_value: bool) -> FlagSpec {
+    FlagSpec { short, long, takes_value }
+}
+
+/// Global flags understood by `Invocation::parse_from_raw`.
+/// Keep in sync with the match there and with `print_help`.
+pub const GLOBAL_FLAGS: &[FlagSpec] = &[
+    flag(Some('j'), "json", false),
+    flag(Some('p'), "pretty", false),
+    flag(None, "indent", true),
+    flag(Some('v'), "verbose", false),
+    flag(Some('h'), "human", false),
+    flag(Some('f'), "filter", true),
+    flag(Some('F'), "ifilter", true),
+    flag(None, "filter-mode", true),
+    flag(None, "sum", true),
+    flag(None, "template", true),
+    flag(None, "limit", true),
+    flag(None, "first", true),
+    flag(None, "offset", true),
+    flag(None, "oui-file", true),
+    flag(None, "pci-ids", true),
+    flag(None, "text-key-case", true),
+    flag(None, "text-sep", true),
+    flag(None, "precision", true),
+    flag(None, "fahrenheit", false),
+    flag(None, "si", false),
+    flag(None, "explain", false),
+    flag(None, "raw", false),
+    flag(None, "stable-read", false),
+    flag(None, "flatten", false),
+    flag(None, "list-paths", false),
+    flag(None, "profile-timings", false),
+    flag(None, "strict", false),
+    flag(None, "probe", false),
+    flag(None, "merge", false),
+    flag(None, "output", true),
+    flag(None, "append", false),
+    flag(None, "completions", true),
+    flag(None, "has-feature", true),
+    flag(None, "no-json", false),
+    flag(None, "no-pretty", false),
+    flag(None, "no-verbose", false),
+    flag(None, "no-human", false),
+    flag(Some('D'), "debug", false),
+    flag(Some('H'), "help", false),
+    flag(None, "help-all", false),
+    flag(Some('V'), "version", false),
+];
+
 /// Arguments storage - fixed-size array of stack strings.
 pub struct ExtraArgs {
     args: [ArgStr; MAX_EXTRA_ARGS],
@@ -130,54 +255,212 @@ pub struct Invocation {
     pub options: GlobalOptions,
     /// Remaining arguments for the subcommand
     pub args: ExtraArgs,
+    /// Subcommands to run after `subcommand` (--merge), in order
+    pub merged: ExtraArgs,
+    /// A flag couldn't be parsed (already reported on stderr)
+    pub parse_error: bool,
+}
+
+/// Apply a run of combined short flags like `jpv` (without the dash).
+///
+/// All letters are checked before any is applied, so on an unknown
+/// letter `opts` is left untouched and that letter is returned.
+fn apply_short_flags(letters: &str, opts: &mut GlobalOptions) -> Result<(), char> {
+    if let Some(bad) = letters.chars().find(|c| !matches!(c, 'j' | 'p' | 'v' | 'h' | 'H' | 'D')) {
+        return Err(bad);
+    }
+    for c in letters.chars() {
+        match c {
+            'j' => opts.json = true,
+            'p' => opts.pretty = true,
+            'v' => opts.verbose = true,
+            'h' => opts.human = true,
+            'H' => opts.help = true,
+            _ => opts.debug = true,
+        }
+    }
+    Ok(())
+}
+
+/// A run of short flags like "-jpv": letters only, and no -f/-F, which
+/// take an argument.
+fn is_flag_cluster(arg: &str) -> bool {
+    let letters = &arg[1..];
+    letters.len() > 1 && !letters.contains(['f', 'F']) && letters.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+/// How to write a combined run that mixes in subcommand flags: global
+/// letters stay together and the rest are split off, "jpn" -> "-jp -n".
+fn split_short_flags(letters: &str) -> StackString<64> {
+    let is_global = |c: &char| matches!(c, 'j' | 'p' | 'v' | 'h' | 'H' | 'D');
+    let mut out: StackString<64> = StackString::new();
+    if letters.chars().any(|c| is_global(&c)) {
+        out.push('-');
+        for c in letters.chars().filter(is_global) {
+            out.push(c);
+        }
+    }
+    for c in letters.chars().filter(|c| !is_global(c)) {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push('-');
+        out.push(c);
+    }
+    out
 }
 
 impl Invocation {
     /// Parse command-line arguments into an Invocation from raw argc/argv.
     ///
+    /// `envp` supplies KV_OPTS, KV_DEBUG, KV_OUI_FILE and KV_PCI_IDS (see `parse_from`); it may be null.
+    ///
     /// # Safety
     /// `argv` must be a valid pointer to an array of at least `argc` valid C strings.
-    pub unsafe fn parse_from_raw(argc: i32, argv: *const *const u8) -> Self {
-        // Process arguments directly without intermediate Vec allocation
-        let mut opts = GlobalOptions::default();
-        let mut subcommand: Option<SubcmdStr> = None;
-        let mut extra_args = ExtraArgs::new();
-        let mut skip_next = false;
-        let mut found_subcommand = false;
-
-        // Skip program name (i=0), start from i=1
-        for i in 1..argc as isize {
-            if skip_next {
-                skip_next = false;
-                continue;
-            }
-
+    /// `envp` must be null or a null-terminated array of valid C strings.
+    pub unsafe fn parse_from_raw(argc: i32, argv: *const *const u8, envp: *const *const u8) -> Self {
+        // Skip program name (i=0), start from i=1; non-UTF-8 args are dropped
+        let args = (1..argc as isize).filter_map(|i| {
             // SAFETY: caller guarantees argv is valid array of C strings
             let arg_ptr = unsafe { *argv.offset(i) };
-            let cstr = unsafe { CStr::from_ptr(arg_ptr as *const c_char) };
-            let arg = match cstr.to_str() {
-                Ok(s) => s,
-                Err(_) => continue,
-            };
+            unsafe { CStr::from_ptr(arg_ptr as *const c_char) }.to_str().ok()
+        });
+        // SAFETY: caller guarantees envp is null or a valid environment block
+        Self::parse_from(args, |name| unsafe { env_var(envp, name) })
+    }
+
+    /// Parse arguments (without the program name), looking up environment
+    /// variables through `env`.
+    ///
+    /// KV_OPTS is split on whitespace and parsed as global flags ahead of
+    /// `args`, so argv wins: `KV_OPTS=-j kv mem --no-json` prints text.
+    /// Subcommands and other positional words in KV_OPTS are ignored.
+    /// KV_DEBUG set to anything but "" or "0" is the same as -D, and a
+    /// non-empty KV_OUI_FILE is the same as --oui-file (KV_PCI_IDS likewise
+    /// for --pci-ids).
+    pub fn parse_from<'a>(
+        args: impl IntoIterator<Item = &'a str>,
+        env: impl Fn(&str) -> Option<&'a str>,
+    ) -> Self {
+        let mut inv = Invocation {
+            subcommand: None,
+            options: GlobalOptions::default(),
+            args: ExtraArgs::new(),
+            merged: ExtraArgs::new(),
+            parse_error: false,
+        };
+
+        if env("KV_DEBUG").is_some_and(|v| !v.is_empty() && v != "0") {
+            inv.options.debug = true;
+        }
+        if let Some(path) = env("KV_OUI_FILE").filter(|v| !v.is_empty()) {
+            inv.options.oui_file = whole_arg("KV_OUI_FILE", path);
+            inv.parse_error |= inv.options.oui_file.is_none();
+        }
+        if let Some(path) = env("KV_PCI_IDS").filter(|v| !v.is_empty()) {
+            inv.options.pci_ids = whole_arg("KV_PCI_IDS", path);
+            inv.parse_error |= inv.options.pci_ids.is_none();
+        }
+        if let Some(kv_opts) = env("KV_OPTS") {
+            inv.parse_args(kv_opts.split_whitespace(), true);
+        }
+        inv.parse_args(args.into_iter(), false);
+        if inv.options.merge {
+            inv.split_merged();
+        }
+        inv
+    }
+
+    /// Subcommand options that take over the whole run, so the other
+    /// `--merge` sections would never run (`--watch`) or couldn't share
+    /// one document with them (`--dts` prints devicetree source).
+    const MERGE_CONFLICTS: &'static [&'static str] = &["--watch", "--dts"];
+
+    /// Subcommands whose arguments aren't flags (`kv read mem` reads a
+    /// file called mem), so --merge can't tell them from section names.
+    const MERGE_POSITIONAL: &'static [&'static str] = &["read"];
+
+    /// Move the subcommand names among the extra arguments to `merged`.
+    ///
+    /// Done after parsing, as `--merge` may come last: `kv cpu mem --merge`.
+    ///
+    /// Every section gets the same arguments, so one that only makes sense
+    /// for a single subcommand run (see MERGE_CONFLICTS) is a parse error,
+    /// and so is a subcommand that takes a path (MERGE_POSITIONAL).
+    fn split_merged(&mut self) {
+        // `kv --merge read mem` reads a file called mem; it isn't a section
+        let first = self.subcommand.as_ref().map(|s| s.as_str());
+        if let Some(name) = first.filter(|s| Self::MERGE_POSITIONAL.contains(s)) {
+            Self::reject_merge(name);
+            self.parse_error = true;
+            return;
+        }
+        let args = core::mem::take(&mut self.args);
+        for arg in args.iter() {
+            let name = resolve_alias(arg);
+            if is_subcommand(name) {
+                self.merged.push(name);
+            } else {
+                self.args.push(arg);
+            }
+        }
+        if self.merged.is_empty() {
+            return;
+        }
+        if let Some(arg) = self.args.iter().find(|a| Self::MERGE_CONFLICTS.contains(a)) {
+            Self::reject_merge(arg);
+            self.parse_error = true;
+        } else if let Some(name) = self.merged.iter().find(|n| Self::MERGE_POSITIONAL.contains(n)) {
+            Self::reject_merge(name);
+            self.parse_error = true;
+        }
+    }
+
+    /// "Error: --watch can't be used with --merge; run that subcommand on its own"
+    fn reject_merge(what: &str) {
+        print::eprint("Error: ");
+        print::eprint(what);
+        print::eprintln(" can't be used with --merge; run that subcommand on its own");
+    }
 
+    /// Parse one run of arguments into `self`.
+    ///
+    /// With `from_env`, only global flags are taken; anything that would
+    /// pick a subcommand or reach the subcommand's own options is ignored.
+    fn parse_args<'a>(&mut self, mut args: impl Iterator<Item = &'a str>, from_env: bool) {
+        let opts = &mut self.options;
+
+        while let Some(arg) = args.next() {
             // Handle special top-level flags first
-            if !found_subcommand {
+            if self.subcommand.is_none() && !from_env {
                 match arg {
                     "--help" | "-H" => {
                         opts.help = true;
-                        subcommand = Some(StackString::from_str(arg));
-                        found_subcommand = true;
+                        self.subcommand = Some(StackString::from_str(arg));
                         continue;
                     }
                     "--version" | "-V" => {
-                        subcommand = Some(StackString::from_str(arg));
-                        found_subcommand = true;
+                        self.subcommand = Some(StackString::from_str(arg));
                         continue;
                     }
                     "help" => {
                         opts.help = true;
-                        subcommand = Some(StackString::from_str("help"));
-                        found_subcommand = true;
+                        self.subcommand = Some(StackString::from_str("help"));
+                        continue;
+                    }
+                    "-1" => {
+                        // Shorthand for the one-line summary
+                        self.subcommand = Some(StackString::from_str("summary"));
+                        continue;
+                    }
+                    "--completions" => {
+                        // The shell name (if any) lands in args
+                        self.subcommand = Some(StackString::from_str(arg));
+                        continue;
+                    }
+                    "--has-feature" => {
+                        // The feature list (if any) lands in args
+                        self.subcommand = Some(StackString::from_str(arg));
                         continue;
                     }
                     _ => {}
@@ -192,98 +475,194 @@ impl Invocation {
                     "-v" | "--verbose" => opts.verbose = true,
                     "-h" | "--human" => opts.human = true,
                     "-H" | "--help" => opts.help = true,
+                    "--help-all" => opts.help_all = true,
                     "-D" | "--debug" => opts.debug = true,
-                    "-f" | "--filter" => {
-                        // Next arg is the filter pattern
-                        if i + 1 < argc as isize {
-                            let next_ptr = unsafe { *argv.offset(i + 1) };
-                            let next_cstr = unsafe { CStr::from_ptr(next_ptr as *const c_char) };
-                            if let Ok(pattern) = next_cstr.to_str() {
-                                let mut filter = FilterStr::new();
-                                // Truncate if needed
-                                for (idx, c) in pattern.chars().enumerate() {
-                                    if idx >= MAX_FILTER_LEN {
-                                        print::eprint("Warning: filter truncated to ");
-                                        let mut buf = itoa::Buffer::new();
-                                        print::eprint(buf.format(MAX_FILTER_LEN));
-                                        print::eprintln(" chars");
-                                        break;
-                                    }
-                                    filter.push(c);
+                    // Undo a flag from an alias or wrapper; the last one given wins
+                    "--no-json" => opts.json = false,
+                    "--no-pretty" => opts.pretty = false,
+                    "--no-verbose" => opts.verbose = false,
+                    "--no-human" => opts.human = false,
+                    "--stable-read" => opts.stable_read = true,
+                    "--list-paths" => opts.list_paths = true,
+                    "--profile-timings" => opts.profile_timings = true,
+                    "--strict" => opts.strict = true,
+                    "--probe" => opts.probe = true,
+                    "--merge" => opts.merge = true,
+                    "--fahrenheit" => opts.fahrenheit = true,
+                    "--si" => opts.si = true,
+                    "--explain" => opts.explain = true,
+                    "--raw" => opts.raw = true,
+                    "--flatten" => {
+                        opts.flatten = true;
+                        opts.json = true;
+                    }
+                    "-f" | "--filter" | "-F" | "--ifilter" => {
+                        // Next arg is the filter pattern (-F = case-insensitive)
+                        if let Some(pattern) = args.next() {
+                            let case_insensitive = arg == "-F" || arg == "--ifilter";
+                            if pattern == "-" {
+                                load_filter_list(&mut opts.filter, case_insensitive);
+                            } else {
+                                push_filter(&mut opts.filter, pattern, case_insensitive);
+                            }
+                        }
+                    }
+                    "--filter-mode" => {
+                        if let Some(next) = args.next() {
+                            match FilterMode::parse(next) {
+                                Some(mode) => opts.filter.mode = mode,
+                                None => {
+                                    print::eprintln("Error: --filter-mode expects 'and' or 'or'");
+                                    self.parse_error = true;
+                                }
+                            }
+                        }
+                    }
+                    "--sum" => {
+                        if let Some(next) = args.next() {
+                            let fields = SumFields::parse(next);
+                            if fields.is_empty() {
+                                print::eprintln("Error: --sum expects field names, e.g. rx_bytes,tx_bytes");
+                                self.parse_error = true;
+                            } else {
+                                opts.sum = Some(fields);
+                            }
+                        }
+                    }
+                    "--template" => {
+                        if let Some(next) = args.next() {
+                            match whole_arg(arg, next) {
+                                Some(text) => opts.template = Some(text),
+                                None => self.parse_error = true,
+                            }
+                        }
+                    }
+                    "--limit" | "--first" => {
+                        if let Some(next) = args.next() {
+                            match next.parse() {
+                                Ok(n) => opts.limit = Some(n),
+                                Err(_) => {
+                                    print::eprintln("Error: --limit expects a number of items");
+                                    self.parse_error = true;
+                                }
+                            }
+                        }
+                    }
+                    "--offset" => {
+                        if let Some(next) = args.next() {
+                            match next.parse() {
+                                Ok(n) => opts.offset = n,
+                                Err(_) => {
+                                    print::eprintln("Error: --offset expects a number of items");
+                                    self.parse_error = true;
+                                }
+                            }
+                        }
+                    }
+                    "--oui-file" => {
+                        if let Some(next) = args.next() {
+                            match whole_arg(arg, next) {
+                                Some(path) => opts.oui_file = Some(path),
+                                None => self.parse_error = true,
+                            }
+                        }
+                    }
+                    "--pci-ids" => {
+                        if let Some(next) = args.next() {
+                            match whole_arg(arg, next) {
+                                Some(path) => opts.pci_ids = Some(path),
+                                None => self.parse_error = true,
+                            }
+                        }
+                    }
+                    "--indent" => {
+                        if let Some(next) = args.next() {
+                            match IndentStyle::parse(next) {
+                                Some(style) => opts.indent = style,
+                                None => {
+                                    print::eprintln("Error: --indent expects a number of spaces (0-8) or 'tab'");
+                                    self.parse_error = true;
                                 }
-                                opts.filter = Some(filter);
-                                opts.filter_case_insensitive = false;
-                                skip_next = true;
                             }
                         }
                     }
-                    "-F" | "--ifilter" => {
-                        // Next arg is the filter pattern (case-insensitive)
-                        if i + 1 < argc as isize {
-                            let next_ptr = unsafe { *argv.offset(i + 1) };
-                            let next_cstr = unsafe { CStr::from_ptr(next_ptr as *const c_char) };
-                            if let Ok(pattern) = next_cstr.to_str() {
-                                let mut filter = FilterStr::new();
-                                // Lowercase and truncate if needed
-                                for (idx, c) in pattern.chars().enumerate() {
-                                    if idx >= MAX_FILTER_LEN {
-                                        print::eprint("Warning: filter truncated to ");
-                                        let mut buf = itoa::Buffer::new();
-                                        print::eprint(buf.format(MAX_FILTER_LEN));
-                                        print::eprintln(" chars");
-                                        break;
-                                    }
-                                    // Lowercase for case-insensitive matching
-                                    for lc in c.to_lowercase() {
-                                        filter.push(lc);
-                                    }
+                    "--text-key-case" => {
+                        if let Some(next) = args.next() {
+                            match KeyCase::parse(next) {
+                                Some(case) => opts.text_style.key_case = case,
+                                None => {
+                                    print::eprintln("Error: --text-key-case expects 'upper', 'lower' or 'asis'");
+                                    self.parse_error = true;
                                 }
-                                opts.filter = Some(filter);
-                                opts.filter_case_insensitive = true;
-                                skip_next = true;
                             }
                         }
                     }
-                    // Combined short flags like -jpv
-                    s if !s.starts_with("--") && s.len() > 2 => {
-                        let has_filter = s.contains('f') || s.contains('F');
-                        if !has_filter {
-                            for c in s[1..].chars() {
-                                match c {
-                                    'j' => opts.json = true,
-                                    'p' => opts.pretty = true,
-                                    'v' => opts.verbose = true,
-                                    'h' => opts.human = true,
-                                    'H' => opts.help = true,
-                                    'D' => opts.debug = true,
-                                    _ => {
-                                        // Unknown flag - treat as extra arg
-                                        extra_args.push(arg);
-                                        break;
-                                    }
+                    "--precision" => {
+                        if let Some(next) = args.next() {
+                            // Anything above the maximum is clamped rather than rejected
+                            match next.parse::<u32>() {
+                                Ok(n) => opts.precision = Some(n.min(io::MAX_PRECISION as u32) as u8),
+                                Err(_) => {
+                                    print::eprintln("Error: --precision expects a number of decimal places (0-6)");
+                                    self.parse_error = true;
                                 }
                             }
-                        } else {
-                            extra_args.push(arg);
                         }
                     }
-                    _ => extra_args.push(arg),
+                    "--append" => opts.append = true,
+                    "--output" => {
+                        if let Some(path) = args.next() {
+                            if path.is_empty() {
+                                print::eprintln("Error: --output expects a file path");
+                                self.parse_error = true;
+                            } else {
+                                match whole_arg(arg, path) {
+                                    Some(path) => opts.output = Some(path),
+                                    None => self.parse_error = true,
+                                }
+                            }
+                        }
+                    }
+                    "--text-sep" => {
+                        if let Some(next) = args.next() {
+                            // Exactly one character, e.g. ':' or '='
+                            let mut chars = next.chars();
+                            match (chars.next(), chars.next()) {
+                                (Some(sep), None) if !sep.is_whitespace() => opts.text_style.sep = sep,
+                                _ => {
+                                    print::eprintln("Error: --text-sep expects a single non-space character");
+                                    self.parse_error = true;
+                                }
+                            }
+                        }
+                    }
+                    // Combined short flags like -jpv; negative numbers like -10 are args
+                    s if is_flag_cluster(s) => {
+                        if let Err(bad) = apply_short_flags(&s[1..], opts) {
+                            // "unknown flag: -n in -jn", then how to split it up
+                            let mut letter = [0u8; 4];
+                            print::eprint("unknown flag: -");
+                            print::eprint(bad.encode_utf8(&mut letter));
+                            print::eprint(" in ");
+                            print::eprintln(s);
+                            print::eprint("Only global flags combine; use ");
+                            print::eprintln(split_short_flags(&s[1..]).as_str());
+                            self.parse_error = true;
+                        }
+                    }
+                    _ if from_env => warn_env_ignored(arg),
+                    _ => self.args.push(arg),
                 }
-            } else if !found_subcommand {
-                // First non-flag is subcommand
-                subcommand = Some(StackString::from_str(arg));
-                found_subcommand = true;
+            } else if from_env {
+                warn_env_ignored(arg);
+            } else if self.subcommand.is_none() {
+                // First non-flag is subcommand (`lspci` and friends are aliases)
+                self.subcommand = Some(StackString::from_str(resolve_alias(arg)));
             } else {
                 // Extra argument
-                extra_args.push(arg);
+                self.args.push(arg);
             }
         }
-
-        Invocation {
-            subcommand,
-            options: opts,
-            args: extra_args,
-        }
     }
 
     /// Check if help was requested (either via flag or "help" subcommand).
@@ -294,11 +673,31 @@ impl Invocation {
     /// Check if version was requested.
     pub fn wants_version(&self) -> bool {
         match self.subcommand.as_ref().map(|s| s.as_str()) {
-            Some("--version") | Some("-V") => true,
+            Some("--version") | Some("-V") | Some("version") => true,
             _ => false,
         }
     }
 
+    /// The shell to print completions for, if `--completions` was given.
+    ///
+    /// Empty when the shell name is missing.
+    pub fn completions_shell(&self) -> Option<&str> {
+        match self.subcommand.as_ref().map(|s| s.as_str()) {
+            Some("--completions") => Some(self.args.first().unwrap_or("")),
+            _ => None,
+        }
+    }
+
+    /// The comma-separated feature list, if `--has-feature` was given.
+    ///
+    /// Empty when the list is missing.
+    pub fn has_feature_list(&self) -> Option<&str> {
+        match self.subcommand.as_ref().map(|s| s.as_str()) {
+            Some("--has-feature") => Some(self.args.first().unwrap_or("")),
+            _ => None,
+        }
+    }
+
     /// Get the subcommand to show help for, if any.
     pub fn help_subject(&self) -> Option<&str> {
         // "kv help pci" - subject is in args
@@ -316,6 +715,111 @@ impl Invocation {
     }
 }
 
+/// Look up `name` in a raw "NAME=value" environment block.
+///
+/// # Safety
+/// `envp` must be null or a null-terminated array of valid C strings.
+unsafe fn env_var<'a>(envp: *const *const u8, name: &str) -> Option<&'a str> {
+    if envp.is_null() {
+        return None;
+    }
+    let mut i = 0;
+    loop {
+        // SAFETY: caller guarantees the array is null-terminated
+        let entry_ptr = unsafe { *envp.offset(i) };
+        if entry_ptr.is_null() {
+            return None;
+        }
+        let entry = unsafe { CStr::from_ptr(entry_ptr as *const c_char) };
+        let pair = entry.to_str().ok().and_then(|e| e.split_once('='));
+        if let Some((_, value)) = pair.filter(|&(key, _)| key == name) {
+            return Some(value);
+        }
+        i += 1;
+    }
+}
+
+/// Keep an option's value whole, or report it and give None if it won't fit
+/// in an ArgStr. A path cut short would name some other file, and a
+/// template would quietly lose its tail.
+fn whole_arg(option: &str, value: &str) -> Option<ArgStr> {
+    if value.len() > MAX_ARG_LEN {
+        print::eprint("Error: ");
+        print::eprint(option);
+        print::eprint(" is longer than ");
+        let mut buf = itoa::Buffer::new();
+        print::eprint(buf.format(MAX_ARG_LEN));
+        print::eprintln(" bytes");
+        return None;
+    }
+    Some(StackString::from_str(value))
+}
+
+/// KV_OPTS only carries global flags; say what was dropped.
+fn warn_env_ignored(token: &str) {
+    print::eprint("Warning: KV_OPTS: ignoring ");
+    print::eprintln(token);
+}
+
+/// Add a -f/-F pattern to the filter list, truncating overly long patterns.
+///
+/// Case-insensitive patterns are lowercased here so matching only has to
+/// fold the field side.
+fn push_filter(filter: &mut Filter, pattern: &str, case_insensitive: bool) {
+    let pattern = match pattern.char_indices().nth(MAX_FILTER_LEN) {
+        Some((end, _)) => {
+            print::eprint("Warning: filter truncated to ");
+            let mut buf = itoa::Buffer::new();
+            print::eprint(buf.format(MAX_FILTER_LEN));
+            print::eprintln(" chars");
+            &pattern[..end]
+        }
+        None => pattern,
+    };
+    // Fold the same way the fields will be
+    let mut text = FilterStr::new();
+    if case_insensitive {
+        push_lowercase(&mut text, pattern);
+    } else {
+        text.push_str(pattern);
+    }
+
+    // "mtu>big" is still usable as a substring, but probably a typo
+    let pattern = Pattern { text, case_insensitive };
+    if pattern.comparison().is_some() && pattern.numeric_comparison().is_none() {
+        print::eprint("Warning: ");
+        print::eprint(pattern.text.as_str());
+        print::eprintln(" compares against a non-number, matching it as text");
+    }
+
+    if !filter.push(pattern.text, case_insensitive) {
+        print::eprint("Warning: only the first ");
+        let mut buf = itoa::Buffer::new();
+        print::eprint(buf.format(MAX_FILTERS));
+        print::eprintln(" filter patterns are used");
+    }
+}
+
+/// Read `-f -` patterns from stdin into the filter.
+fn load_filter_list(filter: &mut Filter, case_insensitive: bool) {
+    // stdin is already used up
+    if filter.has_list() {
+        print::eprintln("Warning: '-f -' can only be given once");
+        return;
+    }
+    let list = PatternList::load(io::read_stdin, case_insensitive);
+    if list.is_empty() {
+        print::eprintln("Warning: no filter patterns on stdin, nothing will match");
+    }
+    if list.is_truncated() {
+        print::eprint("Warning: only the first ");
+        let mut buf = itoa::Buffer::new();
+        print::eprint(buf.format(MAX_PATTERN_LIST_SIZE));
+        print::eprintln(" bytes of filter patterns on stdin are used");
+    }
+    filter.set_list(list);
+}
+
 /// Print the main help text.
 pub fn print_help() {
     print::println(env!("CARGO_PKG_DESCRIPTION"));
@@ -327,130 +831,288 @@ pub fn print_help() {
         "OPTIONS:\n",
         "    -j, --json        Output as JSON\n",
         "    -p, --pretty      Pretty-print JSON (use with -j)\n",
+        "    --indent <n|tab>  Pretty JSON indent: n spaces (default 2) or a tab\n",
         "    -v, --verbose     Show additional fields (most commands, see -H)\n",
         "    -h, --human       Human-readable sizes (1K, 2.5M, 3G)\n",
-        "    -f <pattern>      Filter output (case-sensitive)\n",
-        "    -F <pattern>      Filter output (case-insensitive)\n",
+        "    -f <pattern>      Filter output (case-sensitive, repeatable)\n",
+        "    -F <pattern>      Filter output (case-insensitive, repeatable)\n",
+        "    -f -, -F -        Read patterns from stdin, one per line (any may match)\n",
+        "    --filter-mode <m> Combine multiple filters with 'and' (default) or 'or'\n",
+        "    --sum <fields>    Print totals of numeric fields, e.g. rx_bytes,tx_bytes\n",
+        "                      (net, block, power)\n",
+        "    --template <t>    Text as '{name}\\t{state}', one line per item (net, block,\n",
+        "                      pci, usb, mounts, power); fields as in -f field=value\n",
+        "    --limit <n>       Show only the first n items that pass the filters; JSON\n",
+        "                      adds total and shown (also --first <n>)\n",
+        "    --offset <n>      Skip the first n items that pass the filters\n",
+        "    --oui-file <path> IEEE oui.txt to name MAC vendors from (net, MAC_VENDOR=)\n",
+        "    --pci-ids <path>  pci.ids to name PCI vendors and devices from (pci,\n",
+        "                      VENDOR_NAME=, DEVICE_NAME=)\n",
+        "    --text-key-case   Text key case: 'upper' (default), 'lower' or 'asis'\n",
+        "    --text-sep <c>    Text key/value separator (default '=')\n",
+        "    --precision <n>   Decimal places for temperatures, volts, amps, watts (0-6)\n",
+        "    --fahrenheit      Temperatures in Fahrenheit (text only, JSON unchanged)\n",
+        "    --si              -h sizes in powers of 1000 (1MB) instead of 1024 (976K)\n",
+        "    --explain         Describe cryptic values: POWER_STATE=D3hot (off, can wake)\n",
+        "    --raw             Values exactly as the kernel wrote them (may hold odd whitespace)\n",
+        "    --stable-read     Re-read counters until two reads agree (net, block stats)\n",
+        "    --flatten         JSON as one flat object with dotted keys (implies -j)\n",
+        "    --list-paths      List the sysfs/procfs paths read instead of the output\n",
+        "    --profile-timings Print time spent per phase (file reads, dir scans) to stderr\n",
+        "    --strict          Exit 3 if a file kv can't do without was unreadable\n",
+        "    --probe           Exit 4 if there was no data to show (for health checks)\n",
+        "    --merge           Run several subcommands in one go: kv --merge cpu mem\n",
+        "    --output <path>   Write output to a file instead of stdout\n",
+        "    --append          Append to the --output file instead of truncating it\n",
+        "    --completions <s> Print a shell completion script (bash, zsh, fish)\n",
+        "    --has-feature <l> Exit 0 if all features in l (comma-separated) are compiled in\n",
+        "    --no-json, --no-pretty, --no-verbose, --no-human\n",
+        "                      Turn the flag back off (the last one given wins)\n",
         "    -D, --debug       Show debug info (file access, parse errors)\n",
         "    -H, --help        Show help (use 'kv <cmd> -H' for subcommand details)\n",
-        "    -V, --version     Show version and compiled features\n",
+        "    --help-all        Show the detailed help of every subcommand\n",
+        "    -V, --version     Show version and compiled features (-j for JSON)\n",
         "\n",
         "SUBCOMMANDS:\n",
     ));
 
-    #[cfg(feature = "pci")]
-    print::print("    pci        Show PCI devices\n");
-    #[cfg(feature = "usb")]
-    print::print("    usb        Show USB devices\n");
-    #[cfg(feature = "block")]
-    print::print("    block      Show block devices and partitions\n");
-    #[cfg(feature = "net")]
-    print::print("    net        Show network interfaces\n");
-    #[cfg(feature = "cpu")]
-    print::print("    cpu        Show CPU information\n");
-    #[cfg(feature = "mem")]
-    print::print("    mem        Show memory information\n");
-    #[cfg(feature = "mounts")]
-    print::print("    mounts     Show mounted filesystems\n");
-    #[cfg(feature = "thermal")]
-    print::print("    thermal    Show temperature sensors\n");
-    #[cfg(feature = "power")]
-    print::print("    power      Show power supplies/batteries\n");
-    #[cfg(feature = "dt")]
-    print::print("    dt         Show devicetree nodes (use -H for dt-specific options)\n");
-    #[cfg(feature = "snapshot")]
-    print::print("    snapshot   Combined JSON dump of all info\n");
+    for_each_subcommand(|name, summary| {
+        print::print("    ");
+        print::print(name);
+        for _ in name.len()..11 {
+            print::print(" ");
+        }
+        print::println(summary);
+    });
+
+    // Only the aliases of compiled-in subcommands work
+    let mut aliases = SUBCOMMAND_ALIASES.iter().filter(|(_, target)| has_features(target)).peekable();
+    if aliases.peek().is_some() {
+        print::print("\nALIASES:\n   ");
+        for (alias, target) in aliases {
+            print::print(" ");
+            print::print(alias);
+            print::print(" (");
+            print::print(target);
+            print::print(")");
+        }
+        print::println_empty();
+    }
 
     print::print(concat!(
         "\n",
         "ENVIRONMENT:\n",
         "    KV_DEBUG=1    Enable debug mode (same as -D)\n",
+        "    KV_OPTS=...   Global flags applied before the command line, e.g. '-j -p'\n",
+        "                  (flags given on the command line win)\n",
+        "    KV_OUI_FILE=  OUI list for kv net, like --oui-file\n",
+        "    KV_PCI_IDS=   PCI ID list for kv pci, like --pci-ids\n",
         "\n",
         "EXIT CODES:\n",
         "    0    Success (even if some data unavailable)\n",
         "    1    Error (bad arguments, severe I/O failure)\n",
+        "    3    Required file unreadable (only with --strict)\n",
+        "    4    Nothing to show: no sensors, no devices, no matches (only with --probe)\n",
         "\n",
         "EXAMPLES:\n",
         "    kv pci                # List PCI devices\n",
         "    kv pci -jph           # As pretty JSON with human-readable sizes\n",
         "    kv net -f wlP         # Network interfaces containing exactly 'wlP'\n",
         "    kv net -F up          # Same, case-insensitive\n",
+        "    kv block -f removable=1 -f media   # Removable AND mounted under /media\n",
+        "    kv net -f 'mtu>1500'  # Numeric comparison (also <, >=, <=)\n",
         "    kv snapshot           # Everything, as JSON\n",
         "    KV_DEBUG=1 kv mem     # With debug output\n",
     ));
 }
 
-/// Print version information including compiled features.
-pub fn print_version() {
-    print::print("kv ");
-    print::println(env!("CARGO_PKG_VERSION"));
-
-    // Print features without Vec
-    print::print("features:");
-    let mut first = true;
-
-    macro_rules! print_feature {
-        ($name:expr) => {
-            if first {
-                print::print(" ");
-                first = false;
-            } else {
-                print::print(", ");
-            }
-            print::print($name);
-        };
-    }
-
+/// Call `f` with the name and help summary of each compiled-in subcommand.
+///
+/// Feature names match subcommand names, so this is also the feature list.
+#[allow(unused_mut, unused_variables)] // with --no-default-features, f is never called
+pub fn for_each_subcommand(mut f: impl FnMut(&'static str, &'static str)) {
     #[cfg(feature = "pci")]
-    print_feature!("pci");
+    f("pci", "Show PCI devices");
     #[cfg(feature = "usb")]
-    print_feature!("usb");
+    f("usb", "Show USB devices");
     #[cfg(feature = "block")]
-    print_feature!("block");
+    f("block", "Show block devices and partitions");
     #[cfg(feature = "net")]
-    print_feature!("net");
+    f("net", "Show network interfaces");
     #[cfg(feature = "cpu")]
-    print_feature!("cpu");
+    f("cpu", "Show CPU information");
     #[cfg(feature = "mem")]
-    print_feature!("mem");
+    f("mem", "Show memory information");
     #[cfg(feature = "mounts")]
-    print_feature!("mounts");
+    f("mounts", "Show mounted filesystems");
     #[cfg(feature = "thermal")]
-    print_feature!("thermal");
+    f("thermal", "Show temperature sensors (use -H for thermal-specific options)");
     #[cfg(feature = "power")]
-    print_feature!("power");
+    f("power", "Show power supplies/batteries");
     #[cfg(feature = "dt")]
-    print_feature!("dt");
+    f("dt", "Show devicetree nodes (use -H for dt-specific options)");
     #[cfg(feature = "snapshot")]
-    print_feature!("snapshot");
+    f("snapshot", "Combined JSON dump of all info");
+    #[cfg(feature = "summary")]
+    f("summary", "One-line host/cpu/mem/uptime/load/temp summary (also -1)");
+    #[cfg(feature = "read")]
+    f("read", "Read one /sys or /proc attribute (or list a directory)");
+    #[cfg(feature = "kernel")]
+    f("kernel", "Kernel release, compiler, build date and command line");
+    #[cfg(feature = "tasks")]
+    f("tasks", "Process and thread counts");
+    #[cfg(feature = "dmi")]
+    f("dmi", "BIOS, board and chassis identity (vendor, product, serial)");
+}
 
-    if first {
-        print::print(" (none)");
-    }
-    print::println_empty();
+/// Names from other tools that work as subcommands: `kv lspci` is `kv pci`.
+pub const SUBCOMMAND_ALIASES: &[(&str, &str)] = &[
+    ("lspci", "pci"),
+    ("lsusb", "usb"),
+    ("lsblk", "block"),
+    ("free", "mem"),
+    ("df", "mounts"),
+];
+
+/// The subcommand `name` stands for: the one it's an alias of, if that's
+/// compiled in, otherwise `name` itself.
+pub fn resolve_alias(name: &str) -> &str {
+    SUBCOMMAND_ALIASES
+        .iter()
+        .find(|&&(alias, _)| alias == name)
+        .map(|&(_, target)| target)
+        .filter(|target| has_features(target))
+        .unwrap_or(name)
+}
 
+/// Whether `name` is a compiled-in subcommand (aliases aren't).
+pub fn is_subcommand(name: &str) -> bool {
+    let mut found = false;
+    for_each_subcommand(|n, _| found |= n == name);
+    found
+}
+
+/// Call `f` with the name of each compiled-in feature.
+fn for_each_feature(mut f: impl FnMut(&'static str)) {
+    for_each_subcommand(|name, _| f(name));
+}
+
+/// Are all features in the comma-separated `list` compiled in?
+///
+/// An empty list (or an empty name, as in `pci,,usb`) never matches.
+pub fn has_features(list: &str) -> bool {
+    !list.is_empty()
+        && list.split(',').all(|want| {
+            let mut found = false;
+            for_each_feature(|name| found |= name == want);
+            found
+        })
+}
+
+/// Target architecture name, for the architectures we build release binaries for.
+fn target_arch() -> Option<&'static str> {
     #[cfg(target_arch = "x86_64")]
-    print::println("arch: x86_64");
+    return Some("x86_64");
     #[cfg(target_arch = "x86")]
-    print::println("arch: x86");
+    return Some("x86");
     #[cfg(target_arch = "aarch64")]
-    print::println("arch: aarch64");
+    return Some("aarch64");
     #[cfg(target_arch = "arm")]
-    print::println("arch: arm");
+    return Some("arm");
     #[cfg(target_arch = "riscv64")]
-    print::println("arch: riscv64");
+    return Some("riscv64");
     #[cfg(target_arch = "powerpc64")]
-    print::println("arch: powerpc64");
+    return Some("powerpc64");
     #[cfg(target_arch = "mips")]
-    print::println("arch: mips");
+    return Some("mips");
+    #[allow(unreachable_code)]
+    None
+}
+
+/// Print version information including compiled features.
+///
+/// With `json`, prints the usual envelope with `{"version":...,
+/// "features":[...],"arch":...}` as its data, so scripts don't have to
+/// scrape the text form.
+pub fn print_version(json: bool, pretty: bool) {
+    if json {
+        let mut w = begin_kv_output_streaming(Layout::pretty(pretty), "version");
+        w.field_object("data");
+        w.field_str("version", env!("CARGO_PKG_VERSION"));
+        w.field_array("features");
+        for_each_feature(|name| w.array_string(name));
+        w.end_field_array();
+        w.field_str_opt("arch", target_arch());
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+        return;
+    }
+
+    print::print("kv ");
+    print::println(env!("CARGO_PKG_VERSION"));
+
+    // Print features without Vec
+    print::print("features:");
+    let mut first = true;
+    for_each_feature(|name| {
+        print::print(if first { " " } else { ", " });
+        print::print(name);
+        first = false;
+    });
+    if first {
+        print::print(" (none)");
+    }
+    print::println_empty();
+
+    if let Some(arch) = target_arch() {
+        print::print("arch: ");
+        print::println(arch);
+    }
 }
 
-/// Print help for a specific subcommand.
+/// Print detailed help for a subcommand (or an alias of one).
 pub fn print_subcommand_help(subcommand: &str) {
+    match subcommand_help(resolve_alias(subcommand)) {
+        Some(text) => print::print(text),
+        None => {
+            print::eprint("Unknown subcommand: ");
+            print::eprintln(subcommand);
+            print::eprintln("Run 'kv --help' for a list of subcommands.");
+        }
+    }
+}
+
+/// Print the detailed help of every compiled-in subcommand (`--help-all`).
+pub fn print_help_all() {
+    let mut first = true;
+    for_each_subcommand(|name, _| {
+        let Some(text) = subcommand_help(name) else { return };
+        if !first {
+            print::println_empty();
+        }
+        first = false;
+        print::println(HELP_RULE);
+        print::print(text);
+    });
+}
+
+/// Separator between sections of `--help-all`.
+const HELP_RULE: &str = "--------------------------------------------------------------------------------";
+
+/// Detailed help text for a subcommand, if it's compiled in.
+fn subcommand_help(subcommand: &str) -> Option<&'static str> {
     match subcommand {
         #[cfg(feature = "pci")]
-        "pci" => print::print(concat!(
+        "pci" => Some(concat!(
             "kv pci - Show PCI devices\n\n",
+            "PCI-SPECIFIC OPTIONS:\n",
+            "    --tree         Nest devices under the bridge they sit behind, and\n",
+            "                   SR-IOV virtual functions under their physical function\n",
+            "                   (indented text, or \"children\" arrays in JSON). Not\n",
+            "                   with --template, --limit or --offset\n",
+            "    -n, --numeric  Just the address and vendor/device IDs, like lspci -n:\n",
+            "                   \"0000:01:00.0 10de 2204\" (IDs in hex without 0x)\n\n",
             "Reads PCI device information from /sys/bus/pci/devices/\n\n",
             "FIELDS (default):\n",
             "    bdf            Bus:Device.Function address\n",
@@ -461,37 +1123,110 @@ pub fn print_subcommand_help(subcommand: &str) {
             "FIELDS (verbose):\n",
             "    subsystem_vendor_id, subsystem_device_id\n",
             "    numa_node, iommu_group\n",
+            "    parent         Upstream bridge (absent on a root bus)\n",
+            "    num_vfs, total_vfs  SR-IOV virtual functions enabled/supported (PFs)\n",
+            "    physical_function   BDF of the parent PF (SR-IOV VFs)\n",
+            "    bar0..bar5     Memory regions, e.g. BAR0=16M(mem,pf): size, mem or io,\n",
+            "                   pf if prefetchable. JSON: a \"bars\" array with index,\n",
+            "                   size_bytes, type and prefetchable. Unused BARs are left out\n",
         )),
 
         #[cfg(feature = "usb")]
-        "usb" => print::print(concat!(
+        "usb" => Some(concat!(
             "kv usb - Show USB devices\n\n",
+            "USB-SPECIFIC OPTIONS:\n",
+            "    -n, --numeric  Just the device name and vendor/product IDs:\n",
+            "                   \"1-1.4 046d c52b\" (IDs in hex without 0x)\n",
+            "    --interfaces   Each device's interfaces, indented below it (JSON:\n",
+            "                   an interfaces array): class, protocol and driver.\n",
+            "                   Not with -n or --template\n\n",
             "Reads USB device information from /sys/bus/usb/devices/\n",
             "Filters out root hub entries for cleaner output.\n",
         )),
 
         #[cfg(feature = "block")]
-        "block" => print::print(concat!(
+        "block" => Some(concat!(
             "kv block - Show block devices and partitions\n\n",
+            "BLOCK-SPECIFIC OPTIONS:\n",
+            "    --baseline <file>  Add *_delta fields for I/O counters vs saved -jv output\n",
+            "    --summary          End with TOTAL_DISKS and TOTAL_CAPACITY_BYTES (TOTAL_CAPACITY\n",
+            "                       with -h); JSON adds a summary object. Whole disks only:\n",
+            "                       no partitions, loop, ram or device-mapper devices.\n",
+            "                       Not with --sum, which prints only the totals\n",
+            "    --topology         The storage stack as an indented tree, e.g. disk >\n",
+            "                       partition > crypt > lvm (from holders/ and slaves/).\n",
+            "                       JSON: nested block_node records with children. Not\n",
+            "                       with --summary, --baseline, --sum, --template, --limit\n",
+            "                       or --offset\n",
+            "    --exclude-types <list>\n",
+            "                       Leave out these types, e.g. loop,ram (a disk's\n",
+            "                       partitions go with it)\n",
+            "    --only-types <list>\n",
+            "                       List only these types, e.g. disk or disk,part. Types:\n",
+            "                       disk part loop ram lvm crypt multipath dm md other\n\n",
             "Reads block device information from /sys/block/\n",
-            "Associates partitions with their parent disks.\n",
+            "Associates partitions with their parent disks.\n\n",
+            "Device-mapper devices get TYPE=lvm, crypt, multipath or dm, and\n",
+            "DM_NAME=vg0-root with -v. MD arrays are TYPE=md with MD_LEVEL and\n",
+            "RAID_DISKS under -v. HOLDERS and SLAVES (-v) list the devices\n",
+            "stacked on top of and underneath a device. Zoned disks (ZNS SSDs,\n",
+            "SMR drives) show ZONED=host-managed or host-aware and ZONES under -v.\n",
         )),
 
         #[cfg(feature = "net")]
-        "net" => print::print(concat!(
+        "net" => Some(concat!(
             "kv net - Show network interfaces\n\n",
-            "Reads network interface information from /sys/class/net/\n",
+            "NET-SPECIFIC OPTIONS:\n",
+            "    -a, --addresses    One line per address ('eth0 inet 192.168.1.5/24'),\n",
+            "                       like 'ip -br addr'; JSON has iface/family/address/prefix.\n",
+            "                       Not with --baseline, --sum, --template, --limit or --offset\n",
+            "    --baseline <file>  Add *_delta fields for rx/tx counters vs saved -jv output,\n",
+            "                       and RX_UTIL/TX_UTIL (% of link speed) where speed is known\n",
+            "    --stats-only       Just the counters, one interface per line: iface rx_bytes\n",
+            "                       tx_bytes rx_packets tx_packets rx_errors tx_errors\n",
+            "                       rx_dropped tx_dropped ('-' if unreadable). The columns\n",
+            "                       won't change. JSON: net_counters records. Not with\n",
+            "                       --baseline, --sum or --template\n",
+            "    --header           Start --stats-only output with the column names\n\n",
+            "Reads network interface information from /sys/class/net/\n\n",
+            "STATUS tells apart why a link is down: down(admin) if it was never\n",
+            "brought up (flags has no IFF_UP), down(no-carrier) if it was but\n",
+            "there's no link. Otherwise up, dormant or down. JSON also has\n",
+            "admin_up.\n\n",
+            "With -v, hardware interfaces also show DRIVER and BUS_INFO\n",
+            "(the device address, as in 'ethtool -i'), and wireless ones\n",
+            "WIFI_PHY (the cfg80211 phy, e.g. phy0). The SSID and channel\n",
+            "need nl80211 (netlink), so kv doesn't show them.\n",
         )),
 
         #[cfg(feature = "cpu")]
-        "cpu" => print::print(concat!(
+        "cpu" => Some(concat!(
             "kv cpu - Show CPU information\n\n",
-            "Reads CPU information from /proc/cpuinfo and /sys/devices/system/cpu/\n",
+            "CPU-SPECIFIC OPTIONS:\n",
+            "    --usage        Utilization: sample /proc/stat twice, print CPU_USAGE=23.4%\n",
+            "                   (busy = user+nice+system; per-core with -v, per_cpu in JSON)\n",
+            "    -n <seconds>   Time between the two samples (default 0.5)\n",
+            "    --topology     Cores and threads per package: Package 0: 6 cores, 12 threads\n",
+            "                   (where each CPU sits with -v: package_id, core_id, thread_siblings)\n\n",
+            "Reads CPU information from /proc/cpuinfo and /sys/devices/system/cpu/\n\n",
+            "With -v, FLAGS lists the CPU feature flags (\"Features\" on ARM). In JSON\n",
+            "they're a sorted array, e.g. jq '.data.flags | index(\"avx2\")'.\n",
+            "SMT=yes when a core runs more than one thread; JSON adds smt and a\n",
+            "topology array (cpu, package_id, core_id, thread_siblings per CPU).\n",
+            "THROTTLES counts thermal throttling events over all cores (Intel's\n",
+            "cpuN/thermal_throttle), PACKAGE_THROTTLES over packages; JSON has\n",
+            "throttles, package_throttles and throttle_count per CPU.\n",
         )),
 
         #[cfg(feature = "mem")]
-        "mem" => print::print(concat!(
+        "mem" => Some(concat!(
             "kv mem - Show memory information\n\n",
+            "MEM-SPECIFIC OPTIONS:\n",
+            "    --watch <secs>    A sample every <secs> seconds until interrupted, each\n",
+            "                      with the change in free, available, dirty and writeback\n",
+            "                      memory since the last (mem_free_delta_kb, ...)\n",
+            "    --only-changes    With --watch, print only the fields that changed since\n",
+            "                      the last sample, and skip samples where none did\n\n",
             "Reads memory information from /proc/meminfo\n\n",
             "FIELDS:\n",
             "    mem_total_kb      Total physical memory\n",
@@ -499,31 +1234,40 @@ pub fn print_subcommand_help(subcommand: &str) {
             "    mem_available_kb  Available memory (free + reclaimable)\n",
             "    swap_total_kb     Total swap space\n",
             "    swap_free_kb      Free swap space\n",
+            "    hugepages_total   Hugepages in the pool (-v, only if configured)\n",
+            "    hugepagesize_kb   Size of one hugepage (-v)\n",
         )),
 
         #[cfg(feature = "mounts")]
-        "mounts" => print::print(concat!(
+        "mounts" => Some(concat!(
             "kv mounts - Show mounted filesystems\n\n",
+            "MOUNTS-SPECIFIC OPTIONS:\n",
+            "    --real, --no-pseudo  Hide pseudo filesystems (proc, sysfs, tmpfs, cgroup...)\n",
+            "    --pseudo-only        Show only pseudo filesystems\n\n",
             "Reads mount information from /proc/self/mounts\n",
         )),
 
         #[cfg(feature = "thermal")]
-        "thermal" => print::print(concat!(
+        "thermal" => Some(concat!(
             "kv thermal - Show temperature sensors\n\n",
             "Reads thermal data from /sys/class/thermal/ (thermal zones)\n",
             "or /sys/class/hwmon/ (hardware monitors) as fallback.\n\n",
             "FIELDS:\n",
             "    sensor     Sensor type (cpu-thermal, coretemp, etc.)\n",
             "    label      Sensor label (Core 0, Package, etc.) - hwmon only\n",
-            "    temp_c     Current temperature in Celsius\n\n",
+            "    temp_c     Current temperature in Celsius\n",
+            "    name       Raw zone name (thermal_zone0, hwmon1) - JSON only\n\n",
             "FIELDS (verbose):\n",
             "    crit_c     Critical temperature threshold\n",
             "    policy     Thermal policy (step_wise, etc.)\n",
             "    source     Data source (thermal or hwmon)\n",
+            "    trips      Trip points as type:temp, with (±hyst) if the zone has it\n\n",
+            "THERMAL-SPECIFIC OPTIONS:\n",
+            "    --hot      Sort sensors by temperature, hottest first\n",
         )),
 
         #[cfg(feature = "power")]
-        "power" => print::print(concat!(
+        "power" => Some(concat!(
             "kv power - Show power supplies and batteries\n\n",
             "Reads power supply info from /sys/class/power_supply/\n\n",
             "TYPES:\n",
@@ -533,41 +1277,359 @@ pub fn print_subcommand_help(subcommand: &str) {
             "    UPS        Uninterruptible power supplies\n\n",
             "FIELDS (verbose):\n",
             "    voltage_v, current_a, power_w\n",
+            "    voltage_is_avg, current_is_avg, power_is_avg  (only when set:\n",
+            "                   the reading is the driver's *_avg, no *_now)\n",
+            "    charge_limit   Battery charge thresholds, e.g. 40-80 (JSON:\n",
+            "                   charge_start_threshold, charge_end_threshold)\n\n",
+            "TOTAL_POWER_W sums the draw of all batteries (power_now, or\n",
+            "voltage x current). It's negative while charging and only shown\n",
+            "when some battery reports it.\n\n",
+            "The first line answers \"am I on battery?\": ON_AC=yes when a Mains or\n",
+            "USB supply is online, ON_AC=no when a battery is discharging, and\n",
+            "BATTERY=87% averaged over the batteries (JSON: on_ac, battery_percent\n",
+            "after the data array). Filters don't change it.\n",
         )),
 
         #[cfg(feature = "dt")]
-        "dt" => print::print(concat!(
+        "dt" => Some(concat!(
             "kv dt - Show devicetree nodes\n\n",
             "USAGE:\n",
-            "    kv dt                  Show board model/compatible + node count\n",
+            "    kv dt                  Show board model/compatible, serial, RAM size + node count\n",
             "    kv dt -v               List all nodes\n",
             "    kv dt /soc/uart@1000   Show specific node with all properties\n",
+            "    kv dt /cpus /memory    Several nodes in turn (JSON: an array)\n",
             "    kv dt -f <pattern>     Filter nodes by path or compatible\n",
-            "    kv dt -d               Show only disabled nodes\n\n",
+            "    kv dt -d               Show only disabled nodes\n",
+            "    kv dt --dts [/path]    Dump the tree (or subtrees) as .dts-style text\n\n",
             "DT-SPECIFIC OPTIONS:\n",
-            "    -d, --disabled      Show only nodes with status != okay\n\n",
+            "    -d, --disabled      Show only nodes with status != okay\n",
+            "    --dts               Render nodes hierarchically, like a .dts file\n",
+            "    --max-depth <n>     Don't descend below level n (root is 0; default/max 64)\n",
+            "    --max-nodes <n>     Stop after n nodes (default 4096, max 65536)\n\n",
+            "When a limit cuts the tree short, a note goes to stderr and JSON\n",
+            "output gets \"truncated\": true.\n\n",
+            "Node paths may use %XX escapes (%2C for ','). Paths that don't\n",
+            "exist are reported on stderr.\n\n",
+            "The summary includes the root serial-number property (SERIAL=, JSON\n",
+            "serial_number) and the text properties of /chosen, when present.\n\n",
             "Reads devicetree from /sys/firmware/devicetree/base/\n",
             "NOTE: Only available on systems with devicetree (ARM, RISC-V)\n",
         )),
 
         #[cfg(feature = "snapshot")]
-        "snapshot" => print::print(concat!(
+        "snapshot" => Some(concat!(
             "kv snapshot - Combined JSON dump\n\n",
             "Outputs all available system information as a single JSON object.\n",
             "Always outputs JSON (--json is implied).\n\n",
+            "SNAPSHOT-SPECIFIC OPTIONS:\n",
+            "    --profile <name>  Curated subset of sections:\n",
+            "                        full      everything (default)\n",
+            "                        inventory cpu, pci, usb, block, net, dt\n",
+            "                        health    thermal, power, mem (verbose)\n",
+            "    --include-empty   Write every section the profile has, even with nothing\n",
+            "                      in it: [] for lists, {} for cpu/mem. By default\n",
+            "                      sections with nothing to report are left out\n\n",
             "Use --pretty for human-readable formatting.\n",
         )),
 
-        _ => {
-            print::eprint("Unknown subcommand: ");
-            print::eprintln(subcommand);
-            print::eprintln("Run 'kv --help' for a list of subcommands.");
-        }
+        #[cfg(feature = "summary")]
+        "summary" => Some(concat!(
+            "kv summary - One-line system summary (shorthand: kv -1)\n\n",
+            "Prints hostname, CPU model and count, total memory, uptime,\n",
+            "load average and the hottest sensor on a single line.\n",
+            "HOT=1 means a sensor is within 10C of its critical trip point\n",
+            "(or above 85C if it has none).\n\n",
+            "Use -h for MEM_TOTAL=16G UPTIME=3d4h, -j for a flat JSON object.\n",
+        )),
+
+        #[cfg(feature = "read")]
+        "read" => Some(concat!(
+            "kv read - Read one sysfs/procfs attribute\n\n",
+            "USAGE:\n",
+            "    kv read /sys/class/net/eth0/mtu    Print the value\n",
+            "    kv read /sys/class/net             List a directory's entries\n",
+            "    kv read -j /proc/sys/kernel/osrelease\n\n",
+            "Only paths under /sys and /proc are accepted (no '..').\n",
+            "Text values are printed as-is, binary ones as hex bytes.\n",
+            "JSON is {path, value} for a file, {path, entries} for a directory.\n",
+            "A path that can't be read exits 1, with the error on stderr\n",
+            "(in the JSON \"error\" field with -j).\n",
+        )),
+
+        #[cfg(feature = "kernel")]
+        "kernel" => Some(concat!(
+            "kv kernel - Show kernel release, build info and command line\n\n",
+            "Reads /proc/sys/kernel/osrelease, /proc/version and /proc/cmdline.\n\n",
+            "FIELDS:\n",
+            "    release        Kernel release (uname -r)\n",
+            "    compiler       Toolchain the kernel was built with\n",
+            "    build_date     When it was built\n",
+            "    cmdline        Boot parameters (JSON: an array, one per parameter)\n\n",
+            "FIELDS (verbose):\n",
+            "    builder        user@host that built it\n",
+            "    version_raw    /proc/version as-is (always in JSON)\n",
+        )),
+
+        #[cfg(feature = "tasks")]
+        "tasks" => Some(concat!(
+            "kv tasks - Show process and thread counts\n\n",
+            "Counts the numeric directories in /proc and reads /proc/loadavg.\n\n",
+            "FIELDS:\n",
+            "    processes      Processes kv can see in /proc\n",
+            "    threads        Threads (scheduling entities) from /proc/loadavg;\n",
+            "                   with -v counted from /proc/<pid>/task instead\n",
+            "    running        Runnable right now, from /proc/loadavg\n\n",
+            "Counting stops after 1048576 /proc entries (JSON: truncated=true).\n",
+        )),
+
+        #[cfg(feature = "dmi")]
+        "dmi" => Some(concat!(
+            "kv dmi - Show BIOS, board and chassis identity\n\n",
+            "Reads /sys/class/dmi/id (PCs and servers; ARM boards have none).\n",
+            "Serials are readable by root only and left out otherwise.\n\n",
+            "FIELDS:\n",
+            "    sys_vendor, product_name, product_serial\n",
+            "                   The machine\n",
+            "    board_vendor, board_name\n",
+            "                   The mainboard\n",
+            "    bios_vendor, bios_version, bios_date\n",
+            "                   The firmware\n",
+            "    chassis_type   SMBIOS chassis code, with chassis_name: 10 = Notebook\n",
+        )),
+
+        _ => None,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // Tests removed for no_std build
-    // They require alloc for Vec<String> in test harness
+    use super::*;
+
+    /// Parse `kv <args>` as if from the command line.
+    fn parse(args: &[&CStr]) -> Invocation {
+        let mut argv = [c"kv".as_ptr() as *const u8; 8];
+        for (slot, arg) in argv[1..].iter_mut().zip(args) {
+            *slot = arg.as_ptr() as *const u8;
+        }
+        // SAFETY: every pointer up to argc comes from a C string literal
+        unsafe { Invocation::parse_from_raw(args.len() as i32 + 1, argv.as_ptr(), core::ptr::null()) }
+    }
+
+    #[test]
+    fn test_apply_short_flags() {
+        let mut opts = GlobalOptions::default();
+        assert_eq!(apply_short_flags("jp", &mut opts), Ok(()));
+        assert!(opts.json && opts.pretty && !opts.verbose);
+
+        let mut opts = GlobalOptions::default();
+        assert_eq!(apply_short_flags("jpx", &mut opts), Err('x'));
+        assert!(!opts.json && !opts.pretty);
+    }
+
+    #[test]
+    fn test_subcommand_flag_in_combined_run() {
+        // -n is pci's own flag, not a global one
+        let inv = parse(&[c"pci", c"-jn"]);
+        assert!(inv.parse_error);
+        assert_eq!(split_short_flags("jn").as_str(), "-j -n");
+        assert_eq!(split_short_flags("jpa").as_str(), "-jp -a");
+        assert_eq!(split_short_flags("d").as_str(), "-d");
+        let inv = parse(&[c"pci", c"-j", c"-n"]);
+        assert!(!inv.parse_error && inv.options.json);
+        assert_eq!(inv.args.first(), Some("-n"));
+    }
+
+    #[test]
+    fn test_unknown_combined_flag() {
+        let inv = parse(&[c"mem", c"-jpx"]);
+        assert!(inv.parse_error);
+        let inv = parse(&[c"mem", c"-jp"]);
+        assert!(!inv.parse_error);
+        assert!(inv.options.json && inv.options.pretty);
+    }
+
+    #[test]
+    fn test_negative_number_is_an_arg() {
+        let inv = parse(&[c"read", c"-10"]);
+        assert!(!inv.parse_error);
+        assert_eq!(inv.args.first(), Some("-10"));
+        assert!(!is_flag_cluster("-j1"));
+        assert!(is_flag_cluster("-jp"));
+    }
+
+    #[test]
+    fn test_every_subcommand_has_help() {
+        // --help-all silently skips subcommands without a help entry
+        for_each_subcommand(|name, _| assert!(subcommand_help(name).is_some(), "{name}"));
+        assert!(subcommand_help("nope").is_none());
+    }
+
+    #[test]
+    fn test_ifilter_lowercases_unicode() {
+        let inv = Invocation::parse_from(["usb", "-F", "ÜRÜN", "-f", "ÜRÜN"], |_| None);
+        let mut patterns = inv.options.filter.patterns();
+        assert_eq!(patterns.next().map(|p| p.text.as_str()), Some("ürün"));
+        assert_eq!(patterns.next().map(|p| p.text.as_str()), Some("ÜRÜN"));
+    }
+
+    #[test]
+    fn test_negated_flags() {
+        let inv = parse(&[c"-jvh", c"mem", c"--no-json", c"--no-verbose"]);
+        assert!(!inv.options.json && !inv.options.verbose);
+        assert!(inv.options.human);
+
+        // Later flags win
+        let inv = parse(&[c"mem", c"--no-json", c"-j", c"-p", c"--no-pretty"]);
+        assert!(inv.options.json && !inv.options.pretty);
+        let inv = parse(&[c"mem", c"-h", c"--no-human", c"--human"]);
+        assert!(inv.options.human);
+    }
+
+    #[test]
+    fn test_kv_opts() {
+        let env = |name: &str| (name == "KV_OPTS").then_some("-j  -v");
+        let inv = Invocation::parse_from(["mem"], env);
+        assert_eq!(inv.subcommand.as_ref().map(|s| s.as_str()), Some("mem"));
+        assert!(inv.options.json && inv.options.verbose);
+
+        // The command line comes second, so it wins
+        let inv = Invocation::parse_from(["mem", "--no-json", "-p"], env);
+        assert!(!inv.options.json && inv.options.verbose && inv.options.pretty);
+
+        // Words that aren't global flags stay out of the subcommand's args
+        let env = |name: &str| (name == "KV_OPTS").then_some("pci --flatten -n");
+        let inv = Invocation::parse_from(["mem"], env);
+        assert_eq!(inv.subcommand.as_ref().map(|s| s.as_str()), Some("mem"));
+        assert!(inv.options.flatten && inv.args.is_empty());
+
+        let inv = Invocation::parse_from(["mem"], |_| None);
+        assert!(!inv.options.json && !inv.options.debug);
+    }
+
+    #[test]
+    fn test_kv_debug_env() {
+        let inv = Invocation::parse_from(["mem"], |name| (name == "KV_DEBUG").then_some("1"));
+        assert!(inv.options.debug);
+        let inv = Invocation::parse_from(["mem"], |name| (name == "KV_DEBUG").then_some("0"));
+        assert!(!inv.options.debug);
+    }
+
+    #[test]
+    #[cfg(feature = "pci")]
+    fn test_alias_parses_as_subcommand() {
+        let inv = Invocation::parse_from(["lspci", "-v"], |_| None);
+        assert_eq!(inv.subcommand.as_ref().map(|s| s.as_str()), Some("pci"));
+        assert!(inv.options.verbose);
+    }
+
+    #[test]
+    fn test_subcommand_aliases() {
+        // Only the first word is a subcommand
+        let inv = Invocation::parse_from(["read", "df"], |_| None);
+        assert_eq!(inv.args.first(), Some("df"));
+        assert_eq!(resolve_alias("pci"), "pci");
+        assert_eq!(resolve_alias("lshw"), "lshw");
+        for &(alias, target) in SUBCOMMAND_ALIASES {
+            // An alias of a subcommand that isn't compiled in stays unknown
+            let expected = if has_features(target) { target } else { alias };
+            assert_eq!(resolve_alias(alias), expected);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "cpu", feature = "mem", feature = "read"))]
+    fn test_merge_subcommands() {
+        let inv = Invocation::parse_from(["cpu", "free", "-v", "--merge", "--topology"], |_| None);
+        assert_eq!(inv.subcommand.as_ref().map(|s| s.as_str()), Some("cpu"));
+        assert!(inv.merged.iter().eq(["mem"]));
+        assert!(inv.args.iter().eq(["--topology"]));
+        assert!(inv.options.verbose);
+
+        // Options that take over the run can't be shared between sections
+        let inv = Invocation::parse_from(["--merge", "mem", "cpu", "--watch", "1"], |_| None);
+        assert!(inv.parse_error);
+        let inv = Invocation::parse_from(["dt", "mem", "-j", "--dts", "--merge"], |_| None);
+        assert!(inv.parse_error);
+        // ... but are fine without a second subcommand
+        let inv = Invocation::parse_from(["mem", "--watch", "1", "--merge"], |_| None);
+        assert!(!inv.parse_error);
+
+        // Without --merge, the second name is the first one's argument
+        let inv = Invocation::parse_from(["read", "mem"], |_| None);
+        assert!(inv.merged.is_empty());
+        assert_eq!(inv.args.first(), Some("mem"));
+        assert!(!is_subcommand("lspci") && !is_subcommand("help"));
+    }
+
+    #[test]
+    fn test_kv_oui_file_env() {
+        let env = |name: &str| (name == "KV_OUI_FILE").then_some("/usr/share/ieee-data/oui.txt");
+        let inv = Invocation::parse_from(["net"], env);
+        assert_eq!(inv.options.oui_file.as_ref().map(|s| s.as_str()), Some("/usr/share/ieee-data/oui.txt"));
+        // The flag wins over the environment
+        let inv = Invocation::parse_from(["net", "--oui-file", "oui.txt"], env);
+        assert_eq!(inv.options.oui_file.as_ref().map(|s| s.as_str()), Some("oui.txt"));
+    }
+
+    #[test]
+    fn test_oui_file_too_long() {
+        let long = "o".repeat(MAX_ARG_LEN + 1);
+        let inv = Invocation::parse_from(["net", "--oui-file", long.as_str()], |_| None);
+        assert!(inv.parse_error && inv.options.oui_file.is_none());
+        let inv = Invocation::parse_from(["net"], |name| (name == "KV_OUI_FILE").then_some(long.as_str()));
+        assert!(inv.parse_error && inv.options.oui_file.is_none());
+        let fits = "o".repeat(MAX_ARG_LEN);
+        let inv = Invocation::parse_from(["net", "--oui-file", fits.as_str()], |_| None);
+        assert!(!inv.parse_error);
+        assert_eq!(inv.options.oui_file.as_ref().map(|s| s.len()), Some(MAX_ARG_LEN));
+    }
+
+    #[test]
+    fn test_kv_pci_ids_env() {
+        let env = |name: &str| (name == "KV_PCI_IDS").then_some("/usr/share/hwdata/pci.ids");
+        let inv = Invocation::parse_from(["pci"], env);
+        assert_eq!(inv.options.pci_ids.as_ref().map(|s| s.as_str()), Some("/usr/share/hwdata/pci.ids"));
+        let inv = Invocation::parse_from(["pci", "--pci-ids", "pci.ids"], env);
+        assert_eq!(inv.options.pci_ids.as_ref().map(|s| s.as_str()), Some("pci.ids"));
+        assert!(inv.options.oui_file.is_none());
+    }
+
+    #[test]
+    fn test_pci_ids_too_long() {
+        let long = "p".repeat(MAX_ARG_LEN + 1);
+        let inv = Invocation::parse_from(["pci", "--pci-ids", long.as_str()], |_| None);
+        assert!(inv.parse_error && inv.options.pci_ids.is_none());
+        let inv = Invocation::parse_from(["pci"], |name| (name == "KV_PCI_IDS").then_some(long.as_str()));
+        assert!(inv.parse_error && inv.options.pci_ids.is_none());
+    }
+
+    #[test]
+    fn test_env_var() {
+        let envp = [c"HOME=/root".as_ptr() as *const u8, c"KV_OPTS=-j -p".as_ptr() as *const u8, core::ptr::null()];
+        // SAFETY: null-terminated array of C string literals
+        unsafe {
+            assert_eq!(env_var(envp.as_ptr(), "KV_OPTS"), Some("-j -p"));
+            assert_eq!(env_var(envp.as_ptr(), "KV_DEBUG"), None);
+            assert_eq!(env_var(core::ptr::null(), "KV_OPTS"), None);
+        }
+    }
+
+    #[test]
+    fn test_has_feature() {
+        let inv = parse(&[c"--has-feature", c"mem,pci"]);
+        assert_eq!(inv.has_feature_list(), Some("mem,pci"));
+        assert_eq!(parse(&[c"--has-feature"]).has_feature_list(), Some(""));
+        assert_eq!(parse(&[c"mem"]).has_feature_list(), None);
+
+        let mut first = None;
+        for_each_feature(|name| {
+            first.get_or_insert(name);
+        });
+        if let Some(name) = first {
+            assert!(has_features(name));
+        }
+        assert!(!has_features("no-such-feature"));
+        assert!(!has_features(""));
+        assert!(!has_features(","));
+    }
 }
@@ -0,0 +1,219 @@
+//! Shell completion scripts (`kv --completions <shell>`).
+//!
+//! The scripts are generated from the compiled-in subcommand list and the
+//! global flag table in cli.rs, so a minimal build only completes the
+//! subcommands it actually has.
+//!
+//! Usage:
+//!   kv --completions bash > /etc/bash_completion.d/kv
+//!   source <(kv --completions zsh)
+//!   kv --completions fish > ~/.config/fish/completions/kv.fish
+
+#![allow(dead_code)]
+
+use crate::cli::{for_each_subcommand, FlagSpec, GLOBAL_FLAGS};
+use crate::print;
+use crate::stack::StackString;
+
+/// Shells we can generate completions for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Names accepted by `--completions`, for the error message.
+const SUPPORTED: &str = "bash, zsh, fish";
+
+impl Shell {
+    /// Parse a shell name.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// Print `items` separated by spaces.
+fn print_words(items: impl Fn(&mut dyn FnMut(&str))) {
+    let mut first = true;
+    items(&mut |word| {
+        if !first {
+            print::print(" ");
+        }
+        print::print(word);
+        first = false;
+    });
+}
+
+/// Every subcommand name.
+fn subcommand_words(emit: &mut dyn FnMut(&str)) {
+    for_each_subcommand(|name, _| emit(name));
+}
+
+/// Every global flag, short and long forms.
+fn flag_words(emit: &mut dyn FnMut(&str)) {
+    for spec in GLOBAL_FLAGS {
+        if let Some(c) = spec.short {
+            let mut short: StackString<8> = StackString::from_str("-");
+            short.push(c);
+            emit(short.as_str());
+        }
+        let mut long: StackString<32> = StackString::from_str("--");
+        long.push_str(spec.long);
+        emit(long.as_str());
+    }
+}
+
+/// Values to offer after a flag, if it has a fixed set.
+fn flag_values(spec: &FlagSpec) -> Option<&'static str> {
+    match spec.long {
+        "filter-mode" => Some("and or"),
+        "text-key-case" => Some("upper lower asis"),
+        "indent" => Some("2 4 tab"),
+        "completions" => Some("bash zsh fish"),
+        _ => None,
+    }
+}
+
+fn print_bash() {
+    print::println("# bash completion for kv");
+    print::println("_kv() {");
+    print::println("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"");
+    print::println("    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"");
+    print::println("    case \"$prev\" in");
+    for spec in GLOBAL_FLAGS {
+        if let Some(values) = flag_values(spec) {
+            print::print("        --");
+            print::print(spec.long);
+            print::print(") COMPREPLY=($(compgen -W \"");
+            print::print(values);
+            print::println("\" -- \"$cur\")); return ;;");
+        }
+    }
+    print::println("        --output) COMPREPLY=($(compgen -f -- \"$cur\")); return ;;");
+    print::println("    esac");
+    print::print("    local flags=\"");
+    print_words(flag_words);
+    print::println("\"");
+    print::print("    local subcommands=\"");
+    print_words(subcommand_words);
+    print::println("\"");
+    print::println("    if [[ \"$cur\" == -* ]]; then");
+    print::println("        COMPREPLY=($(compgen -W \"$flags\" -- \"$cur\"))");
+    print::println("    elif [[ $COMP_CWORD -eq 1 ]]; then");
+    print::println("        COMPREPLY=($(compgen -W \"$subcommands\" -- \"$cur\"))");
+    print::println("    fi");
+    print::println("}");
+    print::println("complete -F _kv kv");
+}
+
+fn print_zsh() {
+    print::println("#compdef kv");
+    print::println("# zsh completion for kv");
+    print::println("_kv() {");
+    print::println("    case \"$words[CURRENT-1]\" in");
+    for spec in GLOBAL_FLAGS {
+        if let Some(values) = flag_values(spec) {
+            print::print("        --");
+            print::print(spec.long);
+            print::print(") compadd -- ");
+            print::print(values);
+            print::println("; return ;;");
+        }
+    }
+    print::println("        --output) _files; return ;;");
+    print::println("    esac");
+    print::println("    if [[ \"$words[CURRENT]\" == -* ]]; then");
+    print::print("        compadd -- ");
+    print_words(flag_words);
+    print::println_empty();
+    print::println("    elif (( CURRENT == 2 )); then");
+    print::print("        compadd -- ");
+    print_words(subcommand_words);
+    print::println_empty();
+    print::println("    fi");
+    print::println("}");
+    print::println("compdef _kv kv");
+}
+
+fn print_fish() {
+    print::println("# fish completion for kv");
+    print::println("complete -c kv -f");
+    for_each_subcommand(|name, summary| {
+        print::print("complete -c kv -n __fish_use_subcommand -a ");
+        print::print(name);
+        print::print(" -d '");
+        print::print(summary);
+        print::println("'");
+    });
+    for spec in GLOBAL_FLAGS {
+        print::print("complete -c kv");
+        if let Some(c) = spec.short {
+            let mut buf = [0u8; 4];
+            print::print(" -s ");
+            print::print(c.encode_utf8(&mut buf));
+        }
+        print::print(" -l ");
+        print::print(spec.long);
+        if spec.long == "output" {
+            print::print(" -r -F");
+        } else if let Some(values) = flag_values(spec) {
+            print::print(" -x -a '");
+            print::print(values);
+            print::print("'");
+        } else if spec.takes_value {
+            print::print(" -x");
+        }
+        print::println_empty();
+    }
+}
+
+/// Entry point for `kv --completions <shell>`.
+pub fn run(shell: &str) -> i32 {
+    match Shell::parse(shell) {
+        Some(Shell::Bash) => print_bash(),
+        Some(Shell::Zsh) => print_zsh(),
+        Some(Shell::Fish) => print_fish(),
+        None => {
+            if shell.is_empty() {
+                print::eprintln("Error: --completions expects a shell name");
+            } else {
+                print::eprint("Error: unknown shell '");
+                print::eprint(shell);
+                print::eprintln("'");
+            }
+            print::eprint("Supported shells: ");
+            print::eprintln(SUPPORTED);
+            return 1;
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_parse() {
+        assert_eq!(Shell::parse("bash"), Some(Shell::Bash));
+        assert_eq!(Shell::parse("zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::parse("fish"), Some(Shell::Fish));
+        assert_eq!(Shell::parse("Bash"), None);
+        assert_eq!(Shell::parse(""), None);
+    }
+
+    #[test]
+    fn test_flag_values_only_for_value_flags() {
+        for spec in GLOBAL_FLAGS {
+            if flag_values(spec).is_some() {
+                assert!(spec.takes_value, "{}", spec.long);
+            }
+        }
+    }
+}
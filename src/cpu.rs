@@ -11,18 +11,46 @@
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
+use crate::cli::{ExtraArgs, GlobalOptions};
 use crate::fields::cpu as f;
 use crate::io;
-use crate::json::StreamingJsonWriter;
+use crate::json::{begin_kv_output_streaming, Layout, StreamingJsonWriter};
 use crate::print;
 use crate::stack::StackString;
 
 const CPUINFO_PATH: &str = "/proc/cpuinfo";
 
+/// Cache descriptions for the first CPU (index0, index1, ...).
+const CPU0_CACHE_PATH: &str = "/sys/devices/system/cpu/cpu0/cache";
+
+/// Maximum cache levels/types we list (L1d, L1i, L2, L3 plus some slack).
+const MAX_CACHES: usize = 8;
+
 /// Maximum unique physical/core IDs we track for topology detection.
 const MAX_IDS: usize = 64;
 
+/// One directory per logical CPU (cpu0, cpu1, ...), each with a topology/.
+const CPU_SYSFS_PATH: &str = "/sys/devices/system/cpu";
+
+/// Maximum logical CPUs whose topology we list.
+const MAX_TOPOLOGY_CPUS: usize = 256;
+
+/// Maximum CPU flags listed in JSON (x86 has a couple of hundred).
+const MAX_FLAGS: usize = 512;
+
+/// Per-CPU time counters, for --usage.
+const STAT_PATH: &str = "/proc/stat";
+
+/// How much of /proc/stat we read. The cpu lines come first; the
+/// interrupt counts after them can be far bigger and aren't needed.
+const STAT_READ_SIZE: usize = 32 * 1024;
+
+/// Maximum per-CPU lines we take from /proc/stat.
+const MAX_STAT_CPUS: usize = 256;
+
+/// Time between the two /proc/stat samples unless -n says otherwise.
+const DEFAULT_USAGE_INTERVAL_MS: u32 = 500;
+
 /// Simple set for tracking unique u32 values (replaces HashSet).
 struct IdSet {
     ids: [u32; MAX_IDS],
@@ -60,6 +88,415 @@ impl IdSet {
     }
 }
 
+/// Kind of cache, from the sysfs `type` file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheType {
+    Data,
+    Instruction,
+    Unified,
+}
+
+impl CacheType {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Data" => Some(CacheType::Data),
+            "Instruction" => Some(CacheType::Instruction),
+            "Unified" => Some(CacheType::Unified),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            CacheType::Data => "Data",
+            CacheType::Instruction => "Instruction",
+            CacheType::Unified => "Unified",
+        }
+    }
+}
+
+/// One CPU cache (a sysfs cache/indexN directory).
+#[derive(Clone, Default)]
+pub struct CacheInfo {
+    /// Cache level (1, 2, 3...)
+    pub level: u8,
+    /// Data, instruction or unified
+    pub cache_type: Option<CacheType>,
+    /// Size in KiB
+    pub size_kb: Option<u64>,
+    /// CPUs sharing this cache (e.g., "0-7" or "0,4")
+    pub shared_cpu_list: Option<StackString<64>>,
+}
+
+impl CacheInfo {
+    /// Build from the files of one indexN directory, as `io::dir_files`
+    /// reads them.
+    pub fn from_files(read: impl Fn(&str) -> Option<StackString<64>>) -> Option<Self> {
+        let level = read("level")?.as_str().parse().ok()?;
+        Some(CacheInfo {
+            level,
+            cache_type: read("type").and_then(|t| CacheType::parse(t.as_str())),
+            size_kb: read("size").and_then(|s| parse_cache_size(s.as_str())),
+            shared_cpu_list: read("shared_cpu_list"),
+        })
+    }
+
+    /// Short label like "L1d", "L1i" or "L2".
+    pub fn label(&self) -> StackString<8> {
+        let mut s: StackString<8> = StackString::new();
+        let mut buf = itoa::Buffer::new();
+        s.push('L');
+        s.push_str(buf.format(self.level));
+        let suffix = match self.cache_type {
+            Some(CacheType::Data) => "d",
+            Some(CacheType::Instruction) => "i",
+            _ => "",
+        };
+        s.push_str(suffix);
+        s
+    }
+}
+
+/// The caches of cpu0, in index order.
+#[derive(Default)]
+pub struct CacheList {
+    caches: [CacheInfo; MAX_CACHES],
+    count: usize,
+}
+
+impl CacheList {
+    /// Read cpu0's caches from sysfs. Empty if the cache directory is missing.
+    pub fn read() -> Self {
+        let mut list = CacheList::default();
+        let mut index: StackString<16> = StackString::new();
+        let mut buf = itoa::Buffer::new();
+
+        for i in 0..MAX_CACHES {
+            index.clear();
+            index.push_str("index");
+            index.push_str(buf.format(i));
+            let dir: StackString<96> = io::join_path(CPU0_CACHE_PATH, index.as_str());
+            if !io::is_dir(dir.as_str()) {
+                break;
+            }
+            let cache = CacheInfo::from_files(io::dir_files(dir.as_str()));
+            if let Some(cache) = cache {
+                list.push(cache);
+            }
+        }
+
+        list
+    }
+
+    pub fn push(&mut self, cache: CacheInfo) {
+        if self.count < MAX_CACHES {
+            self.caches[self.count] = cache;
+            self.count += 1;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CacheInfo> {
+        self.caches[..self.count].iter()
+    }
+
+    /// Compact summary: "L1d:32K L1i:32K L2:256K L3:12M".
+    pub fn summary(&self) -> StackString<128> {
+        let mut s: StackString<128> = StackString::new();
+        for (i, cache) in self.iter().enumerate() {
+            if i > 0 {
+                s.push(' ');
+            }
+            s.push_str(cache.label().as_str());
+            if let Some(kb) = cache.size_kb {
+                s.push(':');
+                s.push_str(format_cache_size(kb).as_str());
+            }
+        }
+        s
+    }
+
+    /// Write as a JSON array value (key already written).
+    pub fn write_json(&self, w: &mut StreamingJsonWriter) {
+        w.begin_array();
+        for cache in self.iter() {
+            w.array_object_begin();
+            w.field_u64(f::LEVEL, cache.level as u64);
+            w.field_str_opt(f::TYPE, cache.cache_type.map(|t| t.as_str()));
+            w.field_u64_opt(f::SIZE_KB, cache.size_kb);
+            w.field_str_opt(f::SHARED_CPU_LIST, cache.shared_cpu_list.as_ref().map(|s| s.as_str()));
+            w.array_object_end();
+        }
+        w.end_array();
+    }
+}
+
+/// Parse a sysfs cache size like "32K", "12288K" or "1M" into KiB.
+pub fn parse_cache_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (num, mult) = match s.as_bytes().last()? {
+        b'K' => (&s[..s.len() - 1], 1),
+        b'M' => (&s[..s.len() - 1], 1024),
+        b'G' => (&s[..s.len() - 1], 1024 * 1024),
+        _ => (s, 1),
+    };
+    num.parse::<u64>().ok()?.checked_mul(mult)
+}
+
+/// Format KiB compactly: whole MiB as "12M", otherwise "32K".
+fn format_cache_size(kb: u64) -> StackString<16> {
+    let mut s: StackString<16> = StackString::new();
+    let mut buf = itoa::Buffer::new();
+    if kb >= 1024 && kb.is_multiple_of(1024) {
+        s.push_str(buf.format(kb / 1024));
+        s.push('M');
+    } else {
+        s.push_str(buf.format(kb));
+        s.push('K');
+    }
+    s
+}
+
+/// Where one logical CPU sits: a cpuN/topology directory.
+#[derive(Clone, Default)]
+pub struct CpuPlace {
+    /// N in cpuN
+    pub cpu: u32,
+    /// Socket (None if the kernel says -1, as on some ARM boards)
+    pub package_id: Option<u32>,
+    /// Core within the package
+    pub core_id: Option<u32>,
+    /// CPUs sharing this core, including this one (e.g., "0,6" or "0-1")
+    pub thread_siblings: Option<StackString<32>>,
+    /// Times this CPU's core was thermally throttled (Intel's thermal_throttle/)
+    pub core_throttles: Option<u64>,
+    /// Times its package was throttled; every CPU in the package reports the same
+    pub package_throttles: Option<u64>,
+}
+
+impl CpuPlace {
+    /// Build from the files of one topology directory.
+    pub fn from_files(cpu: u32, read: impl Fn(&str) -> Option<StackString<64>>) -> Self {
+        let id = |name| read(name).and_then(|s| s.as_str().parse().ok());
+        CpuPlace {
+            cpu,
+            package_id: id("physical_package_id"),
+            core_id: id("core_id"),
+            thread_siblings: read("thread_siblings_list").map(|s| StackString::from_str(s.as_str())),
+            core_throttles: None,
+            package_throttles: None,
+        }
+    }
+
+    /// Add the throttle counts from the CPU's thermal_throttle directory,
+    /// read the same way as `from_files`. Most non-Intel systems have none.
+    pub fn with_throttles(mut self, read: impl Fn(&str) -> Option<StackString<64>>) -> Self {
+        let count = |name| read(name).and_then(|s| s.as_str().parse().ok());
+        self.core_throttles = count("core_throttle_count");
+        self.package_throttles = count("package_throttle_count");
+        self
+    }
+}
+
+/// Thermal throttling events summed over all cores and packages.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ThrottleTotals {
+    pub core: u64,
+    pub package: Option<u64>,
+}
+
+/// Cores and threads of one package, for the `--topology` summary.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PackageCounts {
+    pub package_id: Option<u32>,
+    pub cores: u32,
+    pub threads: u32,
+}
+
+/// The topology of every logical CPU, sorted by CPU number.
+pub struct CpuTopology {
+    cpus: [CpuPlace; MAX_TOPOLOGY_CPUS],
+    count: usize,
+    /// CPUs past MAX_TOPOLOGY_CPUS, left out
+    dropped: usize,
+}
+
+impl Default for CpuTopology {
+    fn default() -> Self {
+        CpuTopology {
+            cpus: core::array::from_fn(|_| CpuPlace::default()),
+            count: 0,
+            dropped: 0,
+        }
+    }
+}
+
+impl CpuTopology {
+    /// Read cpuN/topology for each CPU in sysfs. Empty if there's none
+    /// (old kernels, some containers).
+    pub fn read() -> Self {
+        let mut topology = CpuTopology::default();
+        io::for_each_dir_entry(CPU_SYSFS_PATH, |name| {
+            // cpu0, cpu1, ... but not cpufreq or cpuidle
+            let Some(cpu) = name.strip_prefix("cpu").and_then(|n| n.parse().ok()) else {
+                return;
+            };
+            let base: StackString<64> = io::join_path(CPU_SYSFS_PATH, name);
+            let dir: StackString<96> = io::join_path(base.as_str(), "topology");
+            if !io::is_dir(dir.as_str()) {
+                return;
+            }
+            let throttle_dir: StackString<96> = io::join_path(base.as_str(), "thermal_throttle");
+            let place = CpuPlace::from_files(cpu, io::dir_files(dir.as_str()));
+            topology.push(place.with_throttles(io::dir_files(throttle_dir.as_str())));
+        });
+        topology.sort();
+        topology.warn_dropped();
+        topology
+    }
+
+    /// Add a CPU; past MAX_TOPOLOGY_CPUS it's only counted as dropped.
+    pub fn push(&mut self, place: CpuPlace) {
+        if self.count < MAX_TOPOLOGY_CPUS {
+            self.cpus[self.count] = place;
+            self.count += 1;
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    /// Say on stderr how many CPUs didn't fit, if any.
+    fn warn_dropped(&self) {
+        if self.dropped > 0 {
+            let mut buf = itoa::Buffer::new();
+            print::eprint("Warning: topology lists ");
+            print::eprint(buf.format(MAX_TOPOLOGY_CPUS));
+            print::eprint(" CPUs; ");
+            print::eprint(buf.format(self.dropped));
+            print::eprintln(" more left out");
+        }
+    }
+
+    /// Put the CPUs in numeric order (directory order is arbitrary).
+    pub fn sort(&mut self) {
+        self.cpus[..self.count].sort_unstable_by_key(|p| p.cpu);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CpuPlace> {
+        self.cpus[..self.count].iter()
+    }
+
+    /// Cores and threads of each package, in order of its first CPU.
+    ///
+    /// A core is a distinct core_id within the package; a CPU without a
+    /// core_id counts as a core of its own.
+    pub fn packages(&self, mut f: impl FnMut(PackageCounts)) {
+        let cpus = &self.cpus[..self.count];
+        for (i, place) in cpus.iter().enumerate() {
+            let package_id = place.package_id;
+            if cpus[..i].iter().any(|p| p.package_id == package_id) {
+                continue;
+            }
+            let mut counts = PackageCounts { package_id, cores: 0, threads: 0 };
+            for (j, p) in cpus.iter().enumerate().filter(|(_, p)| p.package_id == package_id) {
+                counts.threads += 1;
+                let seen = p.core_id.is_some() && cpus[..j].iter().any(|q| q.package_id == package_id && q.core_id == p.core_id);
+                if !seen {
+                    counts.cores += 1;
+                }
+            }
+            f(counts);
+        }
+    }
+
+    /// Throttle events of all cores, and of all packages if reported.
+    /// None when no CPU has thermal_throttle counts.
+    ///
+    /// SMT siblings share their core's counter, so each core counts once;
+    /// likewise each package.
+    pub fn throttles(&self) -> Option<ThrottleTotals> {
+        let cpus = &self.cpus[..self.count];
+        let mut totals: Option<ThrottleTotals> = None;
+        for (i, place) in cpus.iter().enumerate() {
+            let Some(core) = place.core_throttles else { continue };
+            let totals = totals.get_or_insert(ThrottleTotals { core: 0, package: None });
+            let earlier = &cpus[..i];
+            let core_seen = place.core_id.is_some()
+                && earlier.iter().any(|p| p.core_throttles.is_some() && p.package_id == place.package_id && p.core_id == place.core_id);
+            if !core_seen {
+                totals.core += core;
+            }
+            let package_seen = earlier.iter().any(|p| p.package_throttles.is_some() && p.package_id == place.package_id);
+            if let Some(package) = place.package_throttles.filter(|_| !package_seen) {
+                *totals.package.get_or_insert(0) += package;
+            }
+        }
+        totals
+    }
+
+    /// Whether any core runs more than one thread (SMT, Hyper-Threading).
+    pub fn smt(&self) -> bool {
+        let mut smt = false;
+        self.packages(|p| smt |= p.threads > p.cores);
+        smt
+    }
+
+    /// Write as a JSON array value (key already written).
+    pub fn write_json(&self, w: &mut StreamingJsonWriter) {
+        w.begin_array();
+        for place in self.iter() {
+            w.array_object_begin();
+            w.field_u64(f::CPU, place.cpu as u64);
+            w.field_u64_opt(f::PACKAGE_ID, place.package_id.map(|v| v as u64));
+            w.field_u64_opt(f::CORE_ID, place.core_id.map(|v| v as u64));
+            w.field_str_opt(f::THREAD_SIBLINGS, place.thread_siblings.as_ref().map(|s| s.as_str()));
+            w.field_u64_opt(f::THROTTLE_COUNT, place.core_throttles);
+            w.array_object_end();
+        }
+        w.end_array();
+    }
+
+    /// Write the per-package counts as a JSON array value (key already written).
+    pub fn write_packages_json(&self, w: &mut StreamingJsonWriter) {
+        w.begin_array();
+        self.packages(|p| {
+            w.array_object_begin();
+            w.field_u64_opt(f::PACKAGE_ID, p.package_id.map(|v| v as u64));
+            w.field_u64(f::CORES, p.cores as u64);
+            w.field_u64(f::THREADS, p.threads as u64);
+            w.array_object_end();
+        });
+        w.end_array();
+    }
+}
+
+/// "Package 0: 6 cores, 12 threads" ("Package ?" when the id is unknown).
+pub fn format_package_line(p: &PackageCounts) -> StackString<64> {
+    let mut s: StackString<64> = StackString::from_str("Package ");
+    let mut buf = itoa::Buffer::new();
+    s.push_str(p.package_id.map_or("?", |id| buf.format(id)));
+    s.push_str(": ");
+    for (i, (n, what)) in [(p.cores, "core"), (p.threads, "thread")].into_iter().enumerate() {
+        if i > 0 {
+            s.push_str(", ");
+        }
+        s.push_str(buf.format(n));
+        s.push(' ');
+        s.push_str(what);
+        if n != 1 {
+            s.push('s');
+        }
+    }
+    s
+}
+
 /// CPU information structure.
 #[derive(Default)]
 pub struct CpuInfo {
@@ -89,13 +526,27 @@ pub struct CpuInfo {
     pub isa: Option<StackString<64>>,
     /// RISC-V MMU type (e.g., "sv39")
     pub mmu: Option<StackString<16>>,
+    /// Feature flags, space-separated ("flags" on x86, "Features" on ARM)
+    pub flags: Option<StackString<4096>>,
+    /// Cache hierarchy of cpu0 from sysfs (empty if unavailable)
+    pub caches: CacheList,
+    /// Package/core/thread of each logical CPU from sysfs (empty if unavailable)
+    pub topology: CpuTopology,
 }
 
 impl CpuInfo {
     /// Read CPU information from /proc/cpuinfo.
-    pub fn read() -> Option<Self> {
-        let contents: StackString<8192> = io::read_file_stack(CPUINFO_PATH)?;
-        Some(Self::parse(contents.as_str()))
+    ///
+    /// The per-CPU topology takes a few files per CPU and only shows with
+    /// -v, so it's read only `with_topology`.
+    pub fn read(with_topology: bool) -> Option<Self> {
+        let contents: StackString<8192> = io::read_required_stack(CPUINFO_PATH)?;
+        let mut info = Self::parse(contents.as_str());
+        info.caches = CacheList::read();
+        if with_topology {
+            info.topology = CpuTopology::read();
+        }
+        Some(info)
     }
 
     /// Parse /proc/cpuinfo content.
@@ -119,6 +570,7 @@ impl CpuInfo {
             }
 
             let Some((key, value)) = parse_cpuinfo_line(line) else {
+                crate::dbg_parse!(CPUINFO_PATH, line);
                 continue;
             };
 
@@ -157,6 +609,7 @@ impl CpuInfo {
                         }
                     }
                     "cache size" => info.cache_size = Some(StackString::from_str(value)),
+                    "flags" | "Features" => info.flags = Some(StackString::from_str(value)),
                     // ARM-specific
                     "CPU implementer" => {
                         if info.vendor_id.is_none() {
@@ -220,20 +673,53 @@ impl CpuInfo {
                 w.field_mhz(f::CPU_MHZ, mhz_x100);
             }
             w.field_quoted_opt(f::CACHE_SIZE, self.cache_size.as_ref().map(|s| s.as_str()));
+            if !self.caches.is_empty() {
+                w.field_quoted(f::CACHE, self.caches.summary().as_str());
+            }
+            if !self.topology.is_empty() {
+                w.field_str(f::SMT, if self.topology.smt() { "yes" } else { "no" });
+            }
+            if let Some(totals) = self.topology.throttles() {
+                w.field_u64(f::THROTTLES, totals.core);
+                w.field_u64_opt(f::PACKAGE_THROTTLES, totals.package);
+            }
             w.field_str_opt(f::ARCHITECTURE, self.architecture.as_ref().map(|s| s.as_str()));
+            w.field_quoted_opt(f::FLAGS, self.flags.as_ref().map(|s| s.as_str()));
         }
 
-        w.finish();
+        w.finish_record();
     }
 
-    /// Output as JSON.
-    pub fn print_json(&self, pretty: bool, verbose: bool) {
-        let mut w = StreamingJsonWriter::new(pretty);
+    /// Write `smt`, the throttle totals and the per-CPU `topology` array,
+    /// if sysfs had them.
+    pub fn write_topology_json(&self, w: &mut StreamingJsonWriter) {
+        if !self.topology.is_empty() {
+            w.field_bool(f::SMT, self.topology.smt());
+            if let Some(totals) = self.topology.throttles() {
+                w.field_u64(f::THROTTLES, totals.core);
+                w.field_u64_opt(f::PACKAGE_THROTTLES, totals.package);
+            }
+            w.key(f::TOPOLOGY);
+            self.topology.write_json(w);
+        }
+    }
 
-        w.begin_object();
-        w.field_str("kv_version", env!("CARGO_PKG_VERSION"));
-        w.field_str("subcommand", "cpu");
+    /// Write `flags` as a sorted array, which is easier to query than one long string.
+    pub fn write_flags_json(&self, w: &mut StreamingJsonWriter) {
+        if let Some(ref flags) = self.flags {
+            w.field_array(f::FLAGS);
+            for flag in FlagList::split(flags.as_str()).as_slice() {
+                w.array_string(flag);
+            }
+            w.end_field_array();
+        }
+    }
 
+    /// Output as JSON.
+    pub fn print_json(&self, layout: Layout, verbose: bool) {
+        let mut w = begin_kv_output_streaming(layout, "cpu");
+
+        print::note_data();
         w.field_object("data");
         w.field_u64(f::LOGICAL_CPUS, self.logical_cpus as u64);
         w.field_str_opt(f::MODEL_NAME, self.model_name.as_ref().map(|s| s.as_str()));
@@ -254,6 +740,12 @@ impl CpuInfo {
             }
             w.field_str_opt(f::CACHE_SIZE, self.cache_size.as_ref().map(|s| s.as_str()));
             w.field_str_opt(f::ARCHITECTURE, self.architecture.as_ref().map(|s| s.as_str()));
+            if !self.caches.is_empty() {
+                w.key(f::CACHES);
+                self.caches.write_json(&mut w);
+            }
+            self.write_topology_json(&mut w);
+            self.write_flags_json(&mut w);
         }
 
         w.end_field_object();
@@ -262,6 +754,40 @@ impl CpuInfo {
     }
 }
 
+/// CPU flags split on whitespace, sorted and without duplicates.
+pub struct FlagList<'a> {
+    flags: [&'a str; MAX_FLAGS],
+    count: usize,
+}
+
+impl<'a> FlagList<'a> {
+    /// Split a flags line like "fpu vme de pse". Flags past MAX_FLAGS are dropped.
+    pub fn split(line: &'a str) -> Self {
+        let mut list = FlagList { flags: [""; MAX_FLAGS], count: 0 };
+        for flag in line.split_whitespace().take(MAX_FLAGS) {
+            list.flags[list.count] = flag;
+            list.count += 1;
+        }
+        let flags = &mut list.flags[..list.count];
+        flags.sort_unstable();
+
+        // Drop repeats in place
+        let mut kept = 0;
+        for i in 0..list.count {
+            if kept == 0 || list.flags[i] != list.flags[kept - 1] {
+                list.flags[kept] = list.flags[i];
+                kept += 1;
+            }
+        }
+        list.count = kept;
+        list
+    }
+
+    pub fn as_slice(&self) -> &[&'a str] {
+        &self.flags[..self.count]
+    }
+}
+
 /// Parse a single line from /proc/cpuinfo.
 fn parse_cpuinfo_line(line: &str) -> Option<(&str, &str)> {
     let (key, value) = line.split_once(':')?;
@@ -348,14 +874,335 @@ fn detect_architecture() -> Option<StackString<16>> {
     None
 }
 
+/// Jiffies one CPU (or all of them) spent busy, out of the total.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CpuTimes {
+    /// user + nice + system
+    pub busy: u64,
+    /// busy + idle + iowait + irq + softirq + steal
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Parse the counters after the "cpu"/"cpuN" label of a /proc/stat line.
+    /// Older kernels stop after idle, so missing trailing fields count as 0.
+    fn parse(counters: &str) -> Option<Self> {
+        let mut values = [0u64; 8];
+        let mut n = 0;
+        for (slot, field) in values.iter_mut().zip(counters.split_whitespace()) {
+            *slot = field.parse().ok()?;
+            n += 1;
+        }
+        if n < 4 {
+            return None;
+        }
+        let busy = values[0] + values[1] + values[2];
+        Some(CpuTimes { busy, total: values.iter().sum() })
+    }
+}
+
+/// The cpu lines of one /proc/stat read.
+pub struct StatSample {
+    /// The aggregate "cpu" line
+    all: Option<CpuTimes>,
+    /// (N, times) for each "cpuN" line
+    cpus: [(u32, CpuTimes); MAX_STAT_CPUS],
+    count: usize,
+}
+
+impl StatSample {
+    /// Parse /proc/stat content. Only the leading cpu lines are looked at.
+    pub fn parse(content: &str) -> Self {
+        let mut sample = StatSample {
+            all: None,
+            cpus: [(0, CpuTimes::default()); MAX_STAT_CPUS],
+            count: 0,
+        };
+        for line in content.lines() {
+            let Some(rest) = line.strip_prefix("cpu") else {
+                break;
+            };
+            let Some((label, counters)) = rest.split_once(' ') else {
+                continue;
+            };
+            let Some(times) = CpuTimes::parse(counters) else {
+                continue;
+            };
+            if label.is_empty() {
+                sample.all = Some(times);
+                continue;
+            }
+            let Ok(index) = label.parse() else {
+                continue;
+            };
+            if sample.count < MAX_STAT_CPUS {
+                sample.cpus[sample.count] = (index, times);
+                sample.count += 1;
+            }
+        }
+        sample
+    }
+
+    /// Read /proc/stat now.
+    pub fn read() -> Option<Self> {
+        let mut buf = [0u8; STAT_READ_SIZE];
+        let n = io::read_required_bytes(STAT_PATH, &mut buf)?;
+        // A cut-off last line just fails to parse and is skipped
+        let text = match core::str::from_utf8(&buf[..n]) {
+            Ok(text) => text,
+            Err(e) => core::str::from_utf8(&buf[..e.valid_up_to()]).ok()?,
+        };
+        Some(Self::parse(text))
+    }
+
+    fn cpu(&self, index: u32) -> Option<CpuTimes> {
+        self.cpus[..self.count].iter().find(|(i, _)| *i == index).map(|(_, t)| *t)
+    }
+}
+
+/// Busy share between two samples, in hundredths of a percent (2345 = 23.45%).
+/// None if no time passed, or a counter went backwards (CPU hotplug).
+pub fn usage_x100(before: CpuTimes, after: CpuTimes) -> Option<u32> {
+    let total = after.total.checked_sub(before.total)?;
+    let busy = after.busy.checked_sub(before.busy)?;
+    if total == 0 || busy > total {
+        return None;
+    }
+    Some((busy * 10_000 / total) as u32)
+}
+
+/// Utilization computed from two /proc/stat samples.
+pub struct CpuUsage {
+    /// All CPUs together
+    pub all: Option<u32>,
+    /// (N, usage) for each CPU present in both samples
+    per_cpu: [(u32, u32); MAX_STAT_CPUS],
+    count: usize,
+}
+
+impl CpuUsage {
+    /// Diff two samples, taken `before` and `after` some interval.
+    pub fn between(before: &StatSample, after: &StatSample) -> Self {
+        let mut usage = CpuUsage {
+            all: before.all.zip(after.all).and_then(|(b, a)| usage_x100(b, a)),
+            per_cpu: [(0, 0); MAX_STAT_CPUS],
+            count: 0,
+        };
+        for &(index, now) in &after.cpus[..after.count] {
+            let Some(pct) = before.cpu(index).and_then(|then| usage_x100(then, now)) else {
+                continue;
+            };
+            usage.per_cpu[usage.count] = (index, pct);
+            usage.count += 1;
+        }
+        usage
+    }
+
+    pub fn per_cpu(&self) -> &[(u32, u32)] {
+        &self.per_cpu[..self.count]
+    }
+
+    fn print_text(&self, verbose: bool) {
+        let mut w = print::TextWriter::new();
+        if let Some(pct) = self.all {
+            w.field_str(f::CPU_USAGE, format_percent(pct, true).as_str());
+        }
+        if verbose {
+            let mut buf = itoa::Buffer::new();
+            for &(index, pct) in self.per_cpu() {
+                let mut key: StackString<32> = StackString::from_str("cpu");
+                key.push_str(buf.format(index));
+                key.push_str("_usage");
+                w.field_str(key.as_str(), format_percent(pct, true).as_str());
+            }
+        }
+        w.finish_record();
+    }
+
+    fn print_json(&self, layout: Layout, verbose: bool) {
+        let mut w = begin_kv_output_streaming(layout, "cpu");
+        print::note_data();
+        w.field_object("data");
+        if let Some(pct) = self.all {
+            w.field_number(f::CPU_USAGE, format_percent(pct, false).as_str());
+        }
+        if verbose {
+            w.field_array(f::PER_CPU);
+            for &(index, pct) in self.per_cpu() {
+                w.array_object_begin();
+                w.field_u64(f::CPU, index as u64);
+                w.field_number(f::USAGE, format_percent(pct, false).as_str());
+                w.array_object_end();
+            }
+            w.end_field_array();
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    }
+}
+
+/// Format hundredths of a percent with one decimal (or --precision), "23.4" or "23.4%".
+fn format_percent(pct_x100: u32, with_sign: bool) -> StackString<16> {
+    let mut s = StackString::new();
+    io::push_fixed(&mut s, pct_x100 as i64, 2, io::precision_or(1));
+    if with_sign {
+        s.push('%');
+    }
+    s
+}
+
+/// Parse a -n interval in seconds ("2", "0.5") into milliseconds.
+pub fn parse_interval_ms(s: &str) -> Option<u32> {
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    if (whole.is_empty() && frac.is_empty()) || frac.len() > 3 {
+        return None;
+    }
+    let whole: u32 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let mut frac_ms: u32 = 0;
+    for (i, b) in frac.bytes().enumerate() {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        frac_ms += (b - b'0') as u32 * [100, 10, 1][i];
+    }
+    let ms = whole.checked_mul(1000)?.checked_add(frac_ms)?;
+    (ms > 0).then_some(ms)
+}
+
+/// Options specific to the cpu subcommand.
+pub struct CpuOptions {
+    /// Sample /proc/stat twice and report utilization
+    pub usage: bool,
+    /// Time between the two samples
+    pub interval_ms: u32,
+    /// Show packages, cores and threads instead of the usual fields
+    pub topology: bool,
+}
+
+impl Default for CpuOptions {
+    fn default() -> Self {
+        CpuOptions { usage: false, interval_ms: DEFAULT_USAGE_INTERVAL_MS, topology: false }
+    }
+}
+
+impl CpuOptions {
+    /// Parse cpu-specific options from remaining arguments. None (after an
+    /// error) if `-n` isn't given a number of seconds.
+    pub fn parse(args: &ExtraArgs) -> Option<Self> {
+        let mut opts = CpuOptions::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg {
+                "--usage" => opts.usage = true,
+                "--topology" => opts.topology = true,
+                "-n" => match iter.next().and_then(parse_interval_ms) {
+                    Some(ms) => opts.interval_ms = ms,
+                    None => {
+                        print::eprintln("Error: -n expects seconds (e.g. 1 or 0.5)");
+                        return None;
+                    }
+                },
+                _ => {}
+            }
+        }
+        Some(opts)
+    }
+}
+
+/// Report that /proc/stat couldn't be read: the JSON envelope carries
+/// the error, text goes to stderr. The caller exits 1.
+fn print_stat_error(opts: &GlobalOptions) {
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "cpu");
+        w.key("data");
+        w.value_null();
+        w.field_str("error", "cannot read /proc/stat");
+        w.end_object();
+        w.finish();
+    } else {
+        print::eprintln("Error: cannot read /proc/stat");
+    }
+}
+
+/// `kv cpu --usage`: sample /proc/stat twice, `interval_ms` apart.
+fn run_usage(opts: &GlobalOptions, interval_ms: u32) -> i32 {
+    let Some(before) = StatSample::read() else {
+        print_stat_error(opts);
+        return 1;
+    };
+    io::sleep_ms(interval_ms);
+    let Some(after) = StatSample::read() else {
+        print_stat_error(opts);
+        return 1;
+    };
+
+    let usage = CpuUsage::between(&before, &after);
+    if opts.json {
+        usage.print_json(opts.json_layout(), opts.verbose);
+    } else {
+        usage.print_text(opts.verbose);
+    }
+    0
+}
+
+/// `kv cpu --topology`: cores and threads per package, and with -v
+/// where each logical CPU sits.
+fn run_topology(opts: &GlobalOptions) -> i32 {
+    let topology = CpuTopology::read();
+
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "cpu");
+        w.field_object("data");
+        if !topology.is_empty() {
+            print::note_data();
+            w.field_bool(f::SMT, topology.smt());
+        }
+        w.key(f::PACKAGES);
+        topology.write_packages_json(&mut w);
+        if opts.verbose {
+            w.key(f::TOPOLOGY);
+            topology.write_json(&mut w);
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    } else if topology.is_empty() {
+        print::eprintln("cpu: no topology in /sys/devices/system/cpu");
+    } else {
+        topology.packages(|p| print::println_record(format_package_line(&p).as_str()));
+        let mut w = print::TextWriter::new();
+        w.field_str(f::SMT, if topology.smt() { "yes" } else { "no" });
+        w.finish();
+        if opts.verbose {
+            for place in topology.iter() {
+                let mut w = print::TextWriter::new();
+                w.field_u64(f::CPU, place.cpu as u64);
+                w.field_u64_opt(f::PACKAGE_ID, place.package_id.map(|v| v as u64));
+                w.field_u64_opt(f::CORE_ID, place.core_id.map(|v| v as u64));
+                w.field_str_opt(f::THREAD_SIBLINGS, place.thread_siblings.as_ref().map(|s| s.as_str()));
+                w.finish_record();
+            }
+        }
+    }
+    0
+}
+
 /// Entry point for `kv cpu` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
-    let Some(info) = CpuInfo::read() else {
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let Some(cpu_opts) = CpuOptions::parse(args) else {
+        return 1;
+    };
+    if cpu_opts.usage {
+        return run_usage(opts, cpu_opts.interval_ms);
+    }
+    if cpu_opts.topology {
+        return run_topology(opts);
+    }
+
+    let Some(info) = CpuInfo::read(opts.verbose) else {
         if opts.json {
-            let mut w = StreamingJsonWriter::new(opts.pretty);
-            w.begin_object();
-            w.field_str("kv_version", env!("CARGO_PKG_VERSION"));
-            w.field_str("subcommand", "cpu");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "cpu");
             w.key("data");
             w.value_null();
             w.field_str("error", "cannot read /proc/cpuinfo");
@@ -368,10 +1215,234 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     };
 
     if opts.json {
-        info.print_json(opts.pretty, opts.verbose);
+        info.print_json(opts.json_layout(), opts.verbose);
     } else {
         info.print_text(opts.verbose);
     }
 
     0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::testing::fixture;
+
+    const SAMPLE_X86: &str = "processor\t: 0
+vendor_id\t: GenuineIntel
+cpu family\t: 6
+model\t\t: 142
+model name\t: Intel(R) Core(TM) i7-8550U CPU @ 1.80GHz
+flags\t\t: fpu vme sse2 avx2 ht sse aes avx
+physical id\t: 0
+core id\t\t: 0
+
+processor\t: 1
+vendor_id\t: GenuineIntel
+flags\t\t: fpu vme sse2 avx2 ht sse aes avx
+physical id\t: 0
+core id\t\t: 1
+";
+
+    #[test]
+    fn test_parse_x86_flags() {
+        let info = CpuInfo::parse(SAMPLE_X86);
+        assert_eq!(info.logical_cpus, 2);
+        let flags = info.flags.as_ref().unwrap();
+        assert_eq!(flags.as_str(), "fpu vme sse2 avx2 ht sse aes avx");
+
+        let list = FlagList::split(flags.as_str());
+        assert_eq!(list.as_slice(), ["aes", "avx", "avx2", "fpu", "ht", "sse", "sse2", "vme"]);
+    }
+
+    #[test]
+    fn test_flag_list_dedups() {
+        assert_eq!(FlagList::split(" b  a\tb c ").as_slice(), ["a", "b", "c"]);
+        assert!(FlagList::split("").as_slice().is_empty());
+    }
+
+    const STAT_BEFORE: &str = "cpu  1000 0 500 8000 100 0 0 0 0 0
+cpu0 600 0 300 3900 50 0 0 0 0 0
+cpu1 400 0 200 4100 50 0 0 0 0 0
+intr 12345 0 0
+";
+
+    const STAT_AFTER: &str = "cpu  1300 50 650 8900 100 0 0 0 0 0
+cpu0 850 50 400 4000 50 0 0 0 0 0
+cpu1 450 0 250 4900 50 0 0 0 0 0
+intr 23456 0 0
+";
+
+    #[test]
+    fn test_stat_sample_parse() {
+        let sample = StatSample::parse(STAT_BEFORE);
+        assert_eq!(sample.all, Some(CpuTimes { busy: 1500, total: 9600 }));
+        assert_eq!(sample.cpu(1), Some(CpuTimes { busy: 600, total: 4750 }));
+        assert_eq!(sample.cpu(2), None);
+        // Pre-2.6 kernels only have user, nice, system and idle
+        assert_eq!(CpuTimes::parse("10 0 5 85"), Some(CpuTimes { busy: 15, total: 100 }));
+        assert_eq!(CpuTimes::parse("10 0"), None);
+    }
+
+    #[test]
+    fn test_usage_between_samples() {
+        let usage = CpuUsage::between(&StatSample::parse(STAT_BEFORE), &StatSample::parse(STAT_AFTER));
+        // 500 busy out of 1400
+        assert_eq!(usage.all, Some(3571));
+        // cpu0: 400 of 500, cpu1: 100 of 900
+        assert_eq!(usage.per_cpu(), [(0, 8000), (1, 1111)]);
+
+        // No time passed, or counters went backwards
+        let t = CpuTimes { busy: 10, total: 100 };
+        assert_eq!(usage_x100(t, t), None);
+        assert_eq!(usage_x100(t, CpuTimes { busy: 5, total: 50 }), None);
+        assert_eq!(format_percent(3571, true).as_str(), "35.7%");
+    }
+
+    #[test]
+    fn test_parse_interval_ms() {
+        assert_eq!(parse_interval_ms("2"), Some(2000));
+        assert_eq!(parse_interval_ms("0.5"), Some(500));
+        assert_eq!(parse_interval_ms(".25"), Some(250));
+        assert_eq!(parse_interval_ms("0"), None);
+        assert_eq!(parse_interval_ms("1.2345"), None);
+        assert_eq!(parse_interval_ms("x"), None);
+        assert_eq!(parse_interval_ms("."), None);
+    }
+
+    #[test]
+    fn test_parse_cache_size() {
+        assert_eq!(parse_cache_size("32K"), Some(32));
+        assert_eq!(parse_cache_size("12288K"), Some(12288));
+        assert_eq!(parse_cache_size("1M"), Some(1024));
+        assert_eq!(parse_cache_size("512"), Some(512));
+        assert_eq!(parse_cache_size(""), None);
+        assert_eq!(parse_cache_size("bigK"), None);
+    }
+
+    #[test]
+    fn test_cache_from_files() {
+        let l1d = CacheInfo::from_files(fixture(&[
+            ("level", "1"),
+            ("type", "Data"),
+            ("size", "48K"),
+            ("shared_cpu_list", "0,8"),
+        ]))
+        .unwrap();
+        assert_eq!(l1d.level, 1);
+        assert_eq!(l1d.cache_type, Some(CacheType::Data));
+        assert_eq!(l1d.size_kb, Some(48));
+        assert_eq!(l1d.shared_cpu_list.as_ref().map(|s| s.as_str()), Some("0,8"));
+        assert_eq!(l1d.label().as_str(), "L1d");
+
+        // No level file, not a usable cache entry
+        assert!(CacheInfo::from_files(fixture(&[("type", "Unified")])).is_none());
+    }
+
+    /// Two packages of two cores with two threads each, numbered like
+    /// x86 does it: the second threads come after all the first ones.
+    const TOPOLOGY_2P: [(&str, &str, &str); 8] = [
+        ("0", "0", "0,4"),
+        ("0", "1", "1,5"),
+        ("1", "0", "2,6"),
+        ("1", "1", "3,7"),
+        ("0", "0", "0,4"),
+        ("0", "1", "1,5"),
+        ("1", "0", "2,6"),
+        ("1", "1", "3,7"),
+    ];
+
+    fn topology_fixture(places: &[(&'static str, &'static str, &'static str)]) -> CpuTopology {
+        let mut topology = CpuTopology::default();
+        // Directory order, not CPU order
+        for (cpu, &(package, core, siblings)) in places.iter().enumerate().rev() {
+            let files: [(&str, &str); 3] = [("physical_package_id", package), ("core_id", core), ("thread_siblings_list", siblings)];
+            topology.push(CpuPlace::from_files(cpu as u32, fixture(&files)));
+        }
+        topology.sort();
+        topology
+    }
+
+    fn package_counts(topology: &CpuTopology) -> [Option<PackageCounts>; 2] {
+        let mut counts = [None, None];
+        let mut i = 0;
+        topology.packages(|p| {
+            counts[i] = Some(p);
+            i += 1;
+        });
+        counts
+    }
+
+    #[test]
+    fn test_topology_counts_dropped() {
+        let mut topology = CpuTopology::default();
+        for cpu in 0..MAX_TOPOLOGY_CPUS + 2 {
+            topology.push(CpuPlace { cpu: cpu as u32, ..CpuPlace::default() });
+        }
+        assert_eq!(topology.iter().count(), MAX_TOPOLOGY_CPUS);
+        assert_eq!(topology.dropped, 2);
+    }
+
+    #[test]
+    fn test_topology_packages() {
+        let topology = topology_fixture(&TOPOLOGY_2P);
+        assert!(topology.iter().map(|p| p.cpu).eq(0..8));
+        assert_eq!(
+            package_counts(&topology),
+            [
+                Some(PackageCounts { package_id: Some(0), cores: 2, threads: 4 }),
+                Some(PackageCounts { package_id: Some(1), cores: 2, threads: 4 }),
+            ]
+        );
+        assert!(topology.smt());
+        let p = PackageCounts { package_id: Some(0), cores: 6, threads: 12 };
+        assert_eq!(format_package_line(&p).as_str(), "Package 0: 6 cores, 12 threads");
+    }
+
+    #[test]
+    fn test_topology_without_smt() {
+        // Four single-thread cores; package -1 is "unknown" on some ARM boards
+        let topology = topology_fixture(&[("-1", "0", "0"), ("-1", "1", "1"), ("-1", "2", "2"), ("-1", "3", "3")]);
+        assert!(!topology.smt());
+        let [p, none] = package_counts(&topology);
+        assert_eq!(p, Some(PackageCounts { package_id: None, cores: 4, threads: 4 }));
+        assert!(none.is_none());
+        assert_eq!(format_package_line(&p.unwrap()).as_str(), "Package ?: 4 cores, 4 threads");
+        assert_eq!(
+            format_package_line(&PackageCounts { package_id: Some(0), cores: 1, threads: 1 }).as_str(),
+            "Package 0: 1 core, 1 thread"
+        );
+    }
+
+    #[test]
+    fn test_throttle_totals() {
+        // Two packages of two SMT cores; siblings repeat their core's count
+        let core_counts = ["5", "7", "0", "2", "5", "7", "0", "2"];
+        let package_counts = ["30", "30", "4", "4", "30", "30", "4", "4"];
+        let mut topology = CpuTopology::default();
+        for (cpu, &(package, core, siblings)) in TOPOLOGY_2P.iter().enumerate() {
+            let files = [("physical_package_id", package), ("core_id", core), ("thread_siblings_list", siblings)];
+            let throttle_files = [("core_throttle_count", core_counts[cpu]), ("package_throttle_count", package_counts[cpu])];
+            let place = CpuPlace::from_files(cpu as u32, fixture(&files));
+            topology.push(place.with_throttles(fixture(&throttle_files)));
+        }
+        assert_eq!(topology.throttles(), Some(ThrottleTotals { core: 14, package: Some(34) }));
+
+        // No thermal_throttle directories at all
+        assert_eq!(topology_fixture(&TOPOLOGY_2P).throttles(), None);
+    }
+
+    #[test]
+    fn test_cache_summary() {
+        let mut list = CacheList::default();
+        for files in [
+            &[("level", "1"), ("type", "Data"), ("size", "32K")][..],
+            &[("level", "1"), ("type", "Instruction"), ("size", "32K")][..],
+            &[("level", "2"), ("type", "Unified"), ("size", "256K")][..],
+            &[("level", "3"), ("type", "Unified"), ("size", "12288K")][..],
+        ] {
+            list.push(CacheInfo::from_files(fixture(files)).unwrap());
+        }
+        assert_eq!(list.summary().as_str(), "L1d:32K L1i:32K L2:256K L3:12M");
+    }
+}
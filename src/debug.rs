@@ -8,11 +8,16 @@
 
 #![allow(dead_code)]
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::print;
 
 /// Global debug mode flag, set once at startup.
 static DEBUG_ENABLED: AtomicBool = AtomicBool::new(false);
 
+/// How many parse errors have been reported so far.
+static PARSE_ERRORS: AtomicU64 = AtomicU64::new(0);
+
 /// Enable debug mode globally. Called once from main after parsing args.
 pub fn set_enabled(enabled: bool) {
     DEBUG_ENABLED.store(enabled, Ordering::Relaxed);
@@ -24,6 +29,40 @@ pub fn is_enabled() -> bool {
     DEBUG_ENABLED.load(Ordering::Relaxed)
 }
 
+/// Report a line a parser couldn't make sense of:
+/// `debug: /proc/meminfo: can't parse: "MemTotal: lots"`.
+/// Silent (and not counted) unless debug mode is on.
+pub fn parse_error(context: &str, line: &str) {
+    if !is_enabled() {
+        return;
+    }
+    PARSE_ERRORS.fetch_add(1, Ordering::Relaxed);
+    print::eprint("debug: ");
+    print::eprint(context);
+    print::eprint(": can't parse: \"");
+    print::eprint(line);
+    print::eprintln("\"");
+}
+
+/// Report a file that was longer than the buffer it was read into:
+/// `debug: /proc/cpuinfo: truncated at 8192 bytes`.
+pub fn truncated(path: &str, len: usize) {
+    if !is_enabled() {
+        return;
+    }
+    let mut buf = itoa::Buffer::new();
+    print::eprint("debug: ");
+    print::eprint(path);
+    print::eprint(": truncated at ");
+    print::eprint(buf.format(len));
+    print::eprintln(" bytes");
+}
+
+/// Number of parse errors reported while debug mode was on.
+pub fn parse_error_count() -> u64 {
+    PARSE_ERRORS.load(Ordering::Relaxed)
+}
+
 /// Print a debug message to stderr if debug mode is enabled.
 /// In no_std, this is simplified to avoid format! overhead.
 #[macro_export]
@@ -50,11 +89,13 @@ macro_rules! dbg_fail {
     };
 }
 
-/// Print a debug message about parse errors.
+/// Print a debug message about a line that failed to parse.
+/// Unlike the others this one is live: it takes plain strings, so no
+/// format! is needed.
 #[macro_export]
 macro_rules! dbg_parse {
-    ($context:expr, $err:expr) => {
-        // Disabled in no_std
+    ($context:expr, $line:expr) => {
+        $crate::debug::parse_error($context, $line)
     };
 }
 
@@ -0,0 +1,211 @@
+//! Firmware and board identity from DMI (`kv dmi`).
+//!
+//! What asset tracking wants to know about a PC or server: who made it,
+//! what it's called, its serial number, the board and BIOS, and what kind
+//! of box it is. The kernel copies these out of the SMBIOS tables into
+//! /sys/class/dmi/id, one file per field.
+//!
+//! Serial numbers and a few other files are readable by root only; as a
+//! normal user they're simply left out. ARM boards and most embedded
+//! systems have no DMI at all (the devicetree model is in `kv dt`).
+
+#![allow(dead_code)]
+
+use crate::cli::GlobalOptions;
+use crate::fields::dmi as f;
+use crate::io;
+use crate::json::{begin_kv_output_streaming, Layout};
+use crate::print::{self, TextWriter};
+use crate::stack::StackString;
+
+const DMI_PATH: &str = "/sys/class/dmi/id";
+
+/// A DMI string; vendors keep these short.
+type DmiStr = StackString<64>;
+
+/// Name of an SMBIOS chassis type (the "chassis_type" file).
+///
+/// The top bit of the byte says whether the chassis has a lock; the kernel
+/// masks it off, and so do we in case a firmware table slips it through.
+pub fn chassis_name(chassis_type: u8) -> &'static str {
+    match chassis_type & 0x7f {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "Desktop",
+        0x04 => "Low Profile Desktop",
+        0x05 => "Pizza Box",
+        0x06 => "Mini Tower",
+        0x07 => "Tower",
+        0x08 => "Portable",
+        0x09 => "Laptop",
+        0x0a => "Notebook",
+        0x0b => "Hand Held",
+        0x0c => "Docking Station",
+        0x0d => "All in One",
+        0x0e => "Sub Notebook",
+        0x0f => "Space-saving",
+        0x10 => "Lunch Box",
+        0x11 => "Main Server Chassis",
+        0x12 => "Expansion Chassis",
+        0x13 => "Sub Chassis",
+        0x14 => "Bus Expansion Chassis",
+        0x15 => "Peripheral Chassis",
+        0x16 => "RAID Chassis",
+        0x17 => "Rack Mount Chassis",
+        0x18 => "Sealed-case PC",
+        0x19 => "Multi-system",
+        0x1a => "CompactPCI",
+        0x1b => "AdvancedTCA",
+        0x1c => "Blade",
+        0x1d => "Blade Enclosure",
+        0x1e => "Tablet",
+        0x1f => "Convertible",
+        0x20 => "Detachable",
+        0x21 => "IoT Gateway",
+        0x22 => "Embedded PC",
+        0x23 => "Mini PC",
+        0x24 => "Stick PC",
+        _ => "Unknown",
+    }
+}
+
+/// A field's text, for the writers.
+fn text(value: &Option<DmiStr>) -> Option<&str> {
+    value.as_ref().map(|s| s.as_str())
+}
+
+/// The DMI fields we show. Any of them may be missing or unreadable.
+struct DmiInfo {
+    sys_vendor: Option<DmiStr>,
+    product_name: Option<DmiStr>,
+    product_serial: Option<DmiStr>,
+    board_vendor: Option<DmiStr>,
+    board_name: Option<DmiStr>,
+    bios_vendor: Option<DmiStr>,
+    bios_version: Option<DmiStr>,
+    bios_date: Option<DmiStr>,
+    chassis_type: Option<u8>,
+}
+
+impl DmiInfo {
+    fn read() -> Self {
+        Self::from_files(io::dir_files(DMI_PATH))
+    }
+
+    /// Build from the files of /sys/class/dmi/id, as `io::dir_files` reads them.
+    fn from_files(read: impl Fn(&str) -> Option<DmiStr>) -> Self {
+        // An empty file means the firmware left the string out
+        let text = |name| read(name).filter(|s| !s.as_str().is_empty());
+        DmiInfo {
+            sys_vendor: text("sys_vendor"),
+            product_name: text("product_name"),
+            product_serial: text("product_serial"),
+            board_vendor: text("board_vendor"),
+            board_name: text("board_name"),
+            bios_vendor: text("bios_vendor"),
+            bios_version: text("bios_version"),
+            bios_date: text("bios_date"),
+            chassis_type: read("chassis_type").and_then(|s| s.as_str().parse().ok()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        [&self.sys_vendor, &self.product_name, &self.board_name, &self.bios_version].iter().all(|s| s.is_none())
+            && self.chassis_type.is_none()
+    }
+
+    fn print_text(&self) {
+        let mut w = TextWriter::new();
+        w.field_quoted_opt(f::SYS_VENDOR, text(&self.sys_vendor));
+        w.field_quoted_opt(f::PRODUCT_NAME, text(&self.product_name));
+        w.field_quoted_opt(f::PRODUCT_SERIAL, text(&self.product_serial));
+        w.field_quoted_opt(f::BOARD_VENDOR, text(&self.board_vendor));
+        w.field_quoted_opt(f::BOARD_NAME, text(&self.board_name));
+        w.field_quoted_opt(f::BIOS_VENDOR, text(&self.bios_vendor));
+        w.field_quoted_opt(f::BIOS_VERSION, text(&self.bios_version));
+        w.field_str_opt(f::BIOS_DATE, text(&self.bios_date));
+        if let Some(chassis) = self.chassis_type {
+            w.field_u64(f::CHASSIS_TYPE, chassis as u64);
+            w.field_quoted(f::CHASSIS_NAME, chassis_name(chassis));
+        }
+        w.finish_record();
+    }
+
+    fn print_json(&self, layout: Layout) {
+        let mut w = begin_kv_output_streaming(layout, "dmi");
+        print::note_data();
+        w.field_object("data");
+        w.field_str_opt(f::SYS_VENDOR, text(&self.sys_vendor));
+        w.field_str_opt(f::PRODUCT_NAME, text(&self.product_name));
+        w.field_str_opt(f::PRODUCT_SERIAL, text(&self.product_serial));
+        w.field_str_opt(f::BOARD_VENDOR, text(&self.board_vendor));
+        w.field_str_opt(f::BOARD_NAME, text(&self.board_name));
+        w.field_str_opt(f::BIOS_VENDOR, text(&self.bios_vendor));
+        w.field_str_opt(f::BIOS_VERSION, text(&self.bios_version));
+        w.field_str_opt(f::BIOS_DATE, text(&self.bios_date));
+        if let Some(chassis) = self.chassis_type {
+            w.field_u64(f::CHASSIS_TYPE, chassis as u64);
+            w.field_str(f::CHASSIS_NAME, chassis_name(chassis));
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    }
+}
+
+/// Entry point for `kv dmi`.
+pub fn run(opts: &GlobalOptions) -> i32 {
+    let info = DmiInfo::read();
+    if info.is_empty() {
+        if opts.json {
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "dmi");
+            w.key("data");
+            w.value_null();
+            w.field_str("error", "no DMI information");
+            w.end_object();
+            w.finish();
+        } else {
+            print::print("dmi: no DMI information in ");
+            print::println(DMI_PATH);
+        }
+        return 0;
+    }
+
+    if opts.json {
+        info.print_json(opts.json_layout());
+    } else {
+        info.print_text();
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::testing::fixture;
+
+    #[test]
+    fn test_chassis_name() {
+        assert_eq!(chassis_name(3), "Desktop");
+        assert_eq!(chassis_name(10), "Notebook");
+        assert_eq!(chassis_name(23), "Rack Mount Chassis");
+        assert_eq!(chassis_name(36), "Stick PC");
+        // Lock bit set, and values past the spec
+        assert_eq!(chassis_name(0x80 | 9), "Laptop");
+        assert_eq!(chassis_name(0), "Unknown");
+        assert_eq!(chassis_name(0x42), "Unknown");
+    }
+
+    #[test]
+    fn test_from_files() {
+        // As a normal user: no serial
+        let files = [("sys_vendor", "LENOVO"), ("product_name", "20XW0055US"), ("bios_version", ""), ("chassis_type", "10")];
+        let info = DmiInfo::from_files(fixture(&files));
+        assert_eq!(info.sys_vendor.as_ref().map(|s| s.as_str()), Some("LENOVO"));
+        assert!(info.product_serial.is_none() && info.bios_version.is_none());
+        assert_eq!(info.chassis_type, Some(10));
+        assert!(!info.is_empty());
+
+        assert!(DmiInfo::from_files(|_| None).is_empty());
+    }
+}
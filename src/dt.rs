@@ -15,10 +15,11 @@
 #![allow(dead_code)]
 
 use crate::cli::{ExtraArgs, GlobalOptions};
-use crate::fields::dt as f;
-use crate::filter::matches_any;
+use crate::fields::{dt as f, kind};
+use crate::filter::Filterable;
 use crate::io;
 use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::limits::MAX_PROPERTY_SIZE;
 use crate::print::{self, TextWriter};
 use crate::stack::StackString;
 
@@ -30,44 +31,158 @@ const DT_BASE_PATH: &str = "/sys/firmware/devicetree/base";
 
 /// Maximum recursion depth for traversing devicetree (defense against stack overflow).
 /// Real devicetrees rarely exceed 10-15 levels; 64 is generous.
+/// Also the most --max-depth allows, since each level costs stack.
 const MAX_RECURSION_DEPTH: usize = 64;
 
 /// Maximum number of nodes to process (defense against symlink loops or huge trees).
 const MAX_NODE_COUNT: usize = 4096;
 
-/// Maximum property file size to read (64 KiB - defense against large file attacks).
-const MAX_PROPERTY_SIZE: u64 = 64 * 1024;
+/// Most nodes --max-nodes allows, for big server trees. Unlike depth,
+/// node count doesn't cost stack, only time.
+const MAX_NODE_COUNT_LIMIT: usize = 65536;
 
 /// Maximum number of properties to output per node.
 const MAX_PROPERTIES_PER_NODE: usize = 64;
 
+/// Most node paths one `kv dt` takes (more than fit in ExtraArgs anyway).
+const MAX_NODE_PATHS: usize = 8;
+
+/// Node paths given on the command line, in order.
+pub struct NodePaths {
+    paths: [StackString<256>; MAX_NODE_PATHS],
+    count: usize,
+}
+
+impl NodePaths {
+    pub const fn new() -> Self {
+        NodePaths {
+            paths: [const { StackString::new() }; MAX_NODE_PATHS],
+            count: 0,
+        }
+    }
+
+    /// Add a path (ignored if full).
+    pub fn push(&mut self, path: &str) {
+        if self.count < MAX_NODE_PATHS {
+            self.paths[self.count] = StackString::from_str(path);
+            self.count += 1;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.paths[..self.count].iter().map(|s| s.as_str())
+    }
+
+    /// The paths, or just "/" when none were given.
+    fn roots(&self) -> impl Iterator<Item = &str> {
+        self.iter().chain(self.is_empty().then_some("/"))
+    }
+}
+
+/// Undo %XX escapes in a node path argument.
+///
+/// Lets names a shell would otherwise mangle be passed escaped, e.g.
+/// `/soc/pinctrl%2C0`. Bad escapes are kept as-is; if the result isn't
+/// valid UTF-8 the argument is used unchanged.
+fn decode_path_arg(arg: &str) -> StackString<256> {
+    let bytes = arg.as_bytes();
+    let mut out = [0u8; 256];
+    let mut len = 0;
+    let mut i = 0;
+    while i < bytes.len() && len < out.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| core::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                out[len] = b;
+                i += 3;
+            }
+            None => {
+                out[len] = bytes[i];
+                i += 1;
+            }
+        }
+        len += 1;
+    }
+    match core::str::from_utf8(&out[..len]) {
+        Ok(s) => StackString::from_str(s),
+        Err(_) => StackString::from_str(arg),
+    }
+}
+
 /// Options specific to the dt subcommand.
-#[derive(Default)]
 pub struct DtOptions {
     /// Show only disabled nodes
     pub disabled_only: bool,
-    /// Specific node path to inspect
-    pub node_path: Option<StackString<256>>,
+    /// Node paths to inspect, in the order given
+    pub node_paths: NodePaths,
+    /// Dump as .dts-style text instead of flat lines
+    pub dts: bool,
+    /// Deepest level to descend to (the root is 0)
+    pub max_depth: usize,
+    /// Most nodes to visit
+    pub max_nodes: usize,
+}
+
+impl Default for DtOptions {
+    fn default() -> Self {
+        DtOptions {
+            disabled_only: false,
+            node_paths: NodePaths::new(),
+            dts: false,
+            max_depth: MAX_RECURSION_DEPTH,
+            max_nodes: MAX_NODE_COUNT,
+        }
+    }
 }
 
 impl DtOptions {
     /// Parse dt-specific options from remaining arguments.
-    pub fn parse(args: &ExtraArgs) -> Self {
+    /// Prints an error and returns None if a limit isn't a number.
+    pub fn parse(args: &ExtraArgs) -> Option<Self> {
         let mut opts = DtOptions::default();
 
-        for arg in args.iter() {
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
             match arg {
                 "-d" | "--disabled" => {
                     opts.disabled_only = true;
                 }
+                "--dts" => {
+                    opts.dts = true;
+                }
+                "--max-depth" => match iter.next().and_then(|v| v.parse::<usize>().ok()) {
+                    Some(n) => opts.max_depth = n.min(MAX_RECURSION_DEPTH),
+                    None => {
+                        print::eprintln("Error: --max-depth expects a number");
+                        return None;
+                    }
+                },
+                "--max-nodes" => match iter.next().and_then(|v| v.parse::<usize>().ok()) {
+                    Some(n) => opts.max_nodes = n.min(MAX_NODE_COUNT_LIMIT),
+                    None => {
+                        print::eprintln("Error: --max-nodes expects a number");
+                        return None;
+                    }
+                },
                 s if s.starts_with('/') => {
-                    opts.node_path = Some(StackString::from_str(s));
+                    opts.node_paths.push(decode_path_arg(s).as_str());
                 }
                 _ => {}
             }
         }
 
-        opts
+        Some(opts)
     }
 }
 
@@ -90,79 +205,188 @@ impl DtNodeInfo {
             false
         }
     }
+}
+
+impl Filterable for DtNodeInfo {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::PATH, self.path.as_str());
+        visit(f::COMPATIBLE, self.compatible.as_ref().map(|s| s.as_str()).unwrap_or(""));
+    }
+
+    fn filter_keyed_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::NAME, self.name.as_str());
+        visit(f::STATUS, self.status.as_ref().map(|s| s.as_str()).unwrap_or(""));
+    }
+}
+
+/// How a known numeric property should be shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CellFormat {
+    /// Single cell as a plain decimal integer (e.g. `clock-frequency=24000000`)
+    Decimal,
+    /// Cell list in dtc style (e.g. `reg=<0x12340000 0x1000>`)
+    HexList,
+}
+
+/// Properties we know hold big-endian u32 cells rather than strings.
+fn numeric_property_format(name: &str) -> Option<CellFormat> {
+    match name {
+        "#address-cells" | "#size-cells" | "clock-frequency" => Some(CellFormat::Decimal),
+        "reg" | "interrupts" => Some(CellFormat::HexList),
+        _ => None,
+    }
+}
+
+/// Push a u32 as lowercase hex without leading zeros (`0x1000`).
+fn push_hex_u32(out: &mut StackString<512>, val: u32) {
+    out.push_str("0x");
+    let mut started = false;
+    for shift in (0..8).rev() {
+        let nibble = ((val >> (shift * 4)) & 0xf) as u8;
+        if nibble != 0 || started || shift == 0 {
+            out.push(io::HexNibble::hex_lo(nibble));
+            started = true;
+        }
+    }
+}
 
-    /// Check if this node matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let compat = self.compatible.as_ref().map(|s| s.as_str()).unwrap_or("");
-        let fields = [self.path.as_str(), compat];
-        matches_any(&fields, pattern, case_insensitive)
+/// Decode big-endian u32 cells. Returns None if the length isn't a multiple of 4.
+fn format_cells(bytes: &[u8], format: CellFormat) -> Option<StackString<512>> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut result: StackString<512> = StackString::new();
+    let mut cells = bytes.chunks_exact(4).map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]));
+
+    if format == CellFormat::Decimal && bytes.len() == 4 {
+        let mut buf = itoa::Buffer::new();
+        result.push_str(buf.format(cells.next()?));
+        return Some(result);
     }
+
+    // Leave room for " ...>" so a long list still ends cleanly
+    result.push('<');
+    for (i, cell) in cells.enumerate() {
+        if result.len() + 16 > 512 {
+            result.push_str(" ...");
+            break;
+        }
+        if i > 0 {
+            result.push(' ');
+        }
+        push_hex_u32(&mut result, cell);
+    }
+    result.push('>');
+    Some(result)
+}
+
+/// Turn raw property bytes into something readable.
+///
+/// Known numeric properties are decoded as cells, printable data is shown
+/// as strings, and anything else falls back to hex bytes.
+fn format_property(name: &str, bytes: &[u8]) -> StackString<512> {
+    if bytes.is_empty() {
+        return StackString::new();
+    }
+
+    if let Some(cells) = numeric_property_format(name).and_then(|fmt| format_cells(bytes, fmt)) {
+        return cells;
+    }
+
+    // Strings (null-separated) or a hex fallback for binary data
+    io::format_bytes(bytes)
 }
 
-/// Read a property file and try to interpret it as a readable string.
+/// Read a property file's raw bytes into `buf`, returning the length.
 /// Skips symlinks and files larger than MAX_PROPERTY_SIZE for safety.
-fn read_property(path: &str) -> Option<StackString<512>> {
+fn read_property_bytes(path: &str, buf: &mut [u8]) -> Option<usize> {
     // Skip symlinks - they could point outside the DT base
     if io::is_symlink(path) {
         return None;
     }
 
     // Skip files that are too large
-    if let Some(size) = io::file_size(path) {
-        if size > MAX_PROPERTY_SIZE {
-            return None;
-        }
+    if io::file_size(path).is_some_and(|size| size > MAX_PROPERTY_SIZE) {
+        return None;
     }
 
-    // Read the raw bytes
-    let data: Option<StackString<4096>> = io::read_file_stack(path);
-    let data = data?;
-    let bytes = data.as_str().as_bytes();
+    // Read the raw bytes - binary properties aren't valid UTF-8
+    io::read_file_bytes(path, buf)
+}
 
-    if bytes.is_empty() {
-        return Some(StackString::new());
+/// Read a property file and format it for display (see `format_property`).
+/// Boolean properties (`dma-coherent`, `ranges`) are empty files and give
+/// an empty value; None means the file couldn't be read.
+fn read_property(path: &str) -> Option<StackString<512>> {
+    let mut buf = [0u8; 4096];
+    let n = read_property_bytes(path, &mut buf)?;
+    let name = path.rsplit('/').next().unwrap_or(path);
+    Some(format_property(name, &buf[..n]))
+}
+
+/// Does this look like a dtc string list ("a\0b\0")?
+/// Must be null-terminated, start with a character, and have no empty strings.
+fn is_string_list(bytes: &[u8]) -> bool {
+    if bytes.len() < 2 || bytes[0] == 0 || bytes[bytes.len() - 1] != 0 {
+        return false;
+    }
+    if bytes.windows(2).any(|w| w[0] == 0 && w[1] == 0) {
+        return false;
     }
+    bytes.iter().all(|&b| b == 0 || (0x20..0x7f).contains(&b) || b == b'\n' || b == b'\t')
+}
 
-    // Try to interpret as string(s) - devicetree strings are null-terminated
-    let is_stringy = bytes.iter().all(|&b| {
-        b == 0 || (b >= 0x20 && b < 0x7f) || b == b'\n' || b == b'\t'
-    });
+/// Format a property value the way a .dts file would show it.
+///
+/// Strings are quoted (`"a", "b"`), cell data becomes `<0x1 0x2>` and
+/// anything else is a byte string `[01 02 03]`. Returns an empty string
+/// for boolean (empty) properties, which have no `= value` part.
+fn format_property_dts(name: &str, bytes: &[u8]) -> StackString<512> {
+    let mut result: StackString<512> = StackString::new();
+    if bytes.is_empty() {
+        return result;
+    }
 
-    if is_stringy {
-        // Split by null bytes and join with ", "
-        let mut result: StackString<512> = StackString::new();
-        let mut first = true;
-        for part in bytes.split(|&b| b == 0) {
-            if part.is_empty() {
-                continue;
+    if numeric_property_format(name).is_none() && is_string_list(bytes) {
+        for (i, part) in bytes[..bytes.len() - 1].split(|&b| b == 0).enumerate() {
+            if i > 0 {
+                result.push_str(", ");
             }
-            if let Ok(s) = core::str::from_utf8(part) {
-                if !first {
-                    result.push_str(", ");
-                }
-                result.push_str(s);
-                first = false;
+            result.push('"');
+            for &b in part {
+                match b {
+                    b'"' => result.push_str("\\\""),
+                    b'\\' => result.push_str("\\\\"),
+                    b'\n' => result.push_str("\\n"),
+                    b'\t' => result.push_str("\\t"),
+                    _ => result.push(b as char),
+                };
             }
+            result.push('"');
         }
-        if !result.as_str().is_empty() {
-            return Some(result);
-        }
+        return result;
     }
 
-    // Fall back to hex for binary data (limit to 32 bytes)
-    let mut result: StackString<512> = StackString::new();
-    let limit = if bytes.len() > 32 { 32 } else { bytes.len() };
-    for (i, &b) in bytes[..limit].iter().enumerate() {
+    if let Some(cells) = format_cells(bytes, CellFormat::HexList) {
+        return cells;
+    }
+
+    // Leave room for " ...]" so a long blob still ends cleanly
+    result.push('[');
+    for (i, &b) in bytes.iter().enumerate() {
+        if result.len() + 8 > 512 {
+            result.push_str(" ...");
+            break;
+        }
         if i > 0 {
             result.push(' ');
         }
         result.push(io::HexNibble::hex_hi(b));
         result.push(io::HexNibble::hex_lo(b));
     }
-    if bytes.len() > 32 {
-        result.push_str("...");
-    }
-    Some(result)
+    result.push(']');
+    result
 }
 
 /// Sanitize a relative path, rejecting any path traversal attempts.
@@ -220,7 +444,7 @@ fn read_node_info(base_path: &str, relative_path: &str) -> Option<DtNodeInfo> {
 /// Output a node's properties inline (reads from disk during output).
 fn output_properties_text(full_path: &str) {
     let mut count = 0;
-    io::for_each_dir_entry(full_path, |name| {
+    io::for_each_dir_entry_sorted(full_path, io::natural_cmp, |name| {
         if count >= MAX_PROPERTIES_PER_NODE {
             return;
         }
@@ -240,13 +464,14 @@ fn output_properties_text(full_path: &str) {
             if value.as_str().contains(' ') || value.as_str().contains(',') || value.as_str().is_empty() {
                 print::print("  ");
                 print::print(name);
-                print::print("=\"");
+                print::print_sep();
+                print::print("\"");
                 print::print(value.as_str());
                 print::println("\"");
             } else {
                 print::print("  ");
                 print::print(name);
-                print::print("=");
+                print::print_sep();
                 print::println(value.as_str());
             }
             count += 1;
@@ -259,7 +484,7 @@ fn output_properties_json(w: &mut StreamingJsonWriter, full_path: &str) {
     w.field_object(f::PROPERTIES);
 
     let mut count = 0;
-    io::for_each_dir_entry(full_path, |name| {
+    io::for_each_dir_entry_sorted(full_path, io::natural_cmp, |name| {
         if count >= MAX_PROPERTIES_PER_NODE {
             return;
         }
@@ -282,48 +507,60 @@ fn output_properties_json(w: &mut StreamingJsonWriter, full_path: &str) {
     w.end_field_object();
 }
 
-/// Counter for limiting nodes during traversal.
+/// Counter for limiting depth and nodes during traversal.
 struct NodeCounter {
     count: usize,
+    max_depth: usize,
+    max_nodes: usize,
+    /// Set once a node was skipped because of a limit
+    truncated: bool,
 }
 
 impl NodeCounter {
-    fn new() -> Self {
-        Self { count: 0 }
+    fn new(dt_opts: &DtOptions) -> Self {
+        Self {
+            count: 0,
+            max_depth: dt_opts.max_depth,
+            max_nodes: dt_opts.max_nodes,
+            truncated: false,
+        }
     }
 
-    fn increment(&mut self) -> bool {
-        if self.count >= MAX_NODE_COUNT {
-            false
-        } else {
-            self.count += 1;
-            true
+    /// Count a node at `depth`. Returns false (and notes the truncation)
+    /// if it's past either limit and shouldn't be visited.
+    fn enter(&mut self, depth: usize) -> bool {
+        if depth > self.max_depth || self.count >= self.max_nodes {
+            self.truncated = true;
+            return false;
         }
+        self.count += 1;
+        true
     }
 }
 
-/// Recursively count nodes (for summary).
-fn count_nodes_recursive(base_path: &str, relative_path: &str, depth: usize) -> usize {
-    if depth > MAX_RECURSION_DEPTH {
-        return 0;
+/// Tell the user the tree was cut short by --max-depth/--max-nodes.
+fn warn_truncated(counter: &NodeCounter) {
+    if counter.truncated {
+        print::eprintln("Note: devicetree truncated, raise --max-depth/--max-nodes to see more");
+    }
+}
+
+/// Recursively count nodes (for summary). The total ends up in `counter.count`.
+fn count_nodes_recursive(base_path: &str, relative_path: &str, depth: usize, counter: &mut NodeCounter) {
+    if !counter.enter(depth) {
+        return;
     }
 
     let full_path = match sanitize_relative_path(base_path, relative_path) {
         Some(p) => p,
-        None => return 0,
+        None => return,
     };
 
     if !io::is_dir(full_path.as_str()) {
-        return 0;
+        return;
     }
 
-    let mut count = 1; // This node
-
     io::for_each_dir_entry(full_path.as_str(), |name| {
-        if count >= MAX_NODE_COUNT {
-            return;
-        }
-
         let child_full_path: StackString<512> = io::join_path(full_path.as_str(), name);
 
         // Skip symlinks
@@ -349,10 +586,8 @@ fn count_nodes_recursive(base_path: &str, relative_path: &str, depth: usize) ->
             p
         };
 
-        count += count_nodes_recursive(base_path, child_path.as_str(), depth + 1);
+        count_nodes_recursive(base_path, child_path.as_str(), depth + 1, counter);
     });
-
-    count
 }
 
 /// Recursively traverse and output nodes (streaming).
@@ -364,7 +599,7 @@ fn traverse_and_output_text(
     opts: &GlobalOptions,
     dt_opts: &DtOptions,
 ) {
-    if depth > MAX_RECURSION_DEPTH || !counter.increment() {
+    if !counter.enter(depth) {
         return;
     }
 
@@ -384,10 +619,8 @@ fn traverse_and_output_text(
         if dt_opts.disabled_only && !info.is_disabled() {
             skip = true;
         }
-        if let Some(ref pattern) = opts.filter {
-            if !info.matches_filter(pattern.as_str(), opts.filter_case_insensitive) {
-                skip = true;
-            }
+        if !info.matches_filter(&opts.filter) {
+            skip = true;
         }
 
         if !skip {
@@ -404,7 +637,7 @@ fn traverse_and_output_text(
                     w.field_quoted(f::MODEL, model.as_str());
                 }
             }
-            w.finish();
+            w.finish_record();
         }
     }
 
@@ -447,7 +680,7 @@ fn traverse_and_output_json(
     opts: &GlobalOptions,
     dt_opts: &DtOptions,
 ) {
-    if depth > MAX_RECURSION_DEPTH || !counter.increment() {
+    if !counter.enter(depth) {
         return;
     }
 
@@ -467,14 +700,13 @@ fn traverse_and_output_json(
         if dt_opts.disabled_only && !info.is_disabled() {
             skip = true;
         }
-        if let Some(ref pattern) = opts.filter {
-            if !info.matches_filter(pattern.as_str(), opts.filter_case_insensitive) {
-                skip = true;
-            }
+        if !info.matches_filter(&opts.filter) {
+            skip = true;
         }
 
         if !skip {
-            w.array_object_begin();
+            w.record_begin();
+            w.field_str(kind::KIND, kind::DT_NODE);
             w.field_str(f::PATH, info.path.as_str());
             w.field_str(f::NAME, info.name.as_str());
             w.field_str_opt(f::COMPATIBLE, info.compatible.as_ref().map(|s| s.as_str()));
@@ -517,13 +749,146 @@ fn traverse_and_output_json(
     });
 }
 
+/// Print one tab per nesting level (dtc's own indentation).
+fn print_dts_indent(depth: usize) {
+    for _ in 0..depth {
+        print::print("\t");
+    }
+}
+
+/// The name a node's block opens with: its last path component, or `/`
+/// for the root. A `--dts /soc/serial@1000` subtree starts `serial@1000 {`.
+fn dts_node_name(relative_path: &str) -> &str {
+    let trimmed = relative_path.trim_end_matches('/');
+    match trimmed.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name,
+        _ => "/",
+    }
+}
+
+/// Recursively print a node and its children as .dts-style text.
+///
+/// Properties come first, then child nodes, each child in its own
+/// `name { ... };` block. The output is meant for reading and archiving;
+/// dtc may not accept it back (phandle references aren't resolved).
+fn traverse_and_output_dts(
+    base_path: &str,
+    relative_path: &str,
+    depth: usize,
+    counter: &mut NodeCounter,
+) {
+    if !counter.enter(depth) {
+        return;
+    }
+
+    let full_path = match sanitize_relative_path(base_path, relative_path) {
+        Some(p) => p,
+        None => return,
+    };
+
+    if !io::is_dir(full_path.as_str()) {
+        return;
+    }
+
+    print::note_data();
+    print_dts_indent(depth);
+    print::print(dts_node_name(relative_path));
+    print::println(" {");
+
+    // Properties first
+    let mut count = 0;
+    io::for_each_dir_entry(full_path.as_str(), |prop| {
+        if count >= MAX_PROPERTIES_PER_NODE || prop == "name" {
+            return;
+        }
+
+        let prop_path: StackString<512> = io::join_path(full_path.as_str(), prop);
+        if !io::is_file(prop_path.as_str()) {
+            return;
+        }
+
+        let mut buf = [0u8; 4096];
+        let Some(n) = read_property_bytes(prop_path.as_str(), &mut buf) else {
+            return;
+        };
+
+        print_dts_indent(depth + 1);
+        print::print(prop);
+        let value = format_property_dts(prop, &buf[..n]);
+        if !value.is_empty() {
+            print::print(" = ");
+            print::print(value.as_str());
+        }
+        print::println(";");
+        count += 1;
+    });
+
+    // Then child nodes
+    io::for_each_dir_entry(full_path.as_str(), |child| {
+        let child_full_path: StackString<512> = io::join_path(full_path.as_str(), child);
+
+        if io::is_symlink(child_full_path.as_str()) || !io::is_dir(child_full_path.as_str()) {
+            return;
+        }
+
+        let mut child_path: StackString<512> = StackString::new();
+        if relative_path != "/" {
+            child_path.push_str(relative_path);
+        }
+        child_path.push('/');
+        child_path.push_str(child);
+
+        print::println_empty();
+        traverse_and_output_dts(base_path, child_path.as_str(), depth + 1, counter);
+    });
+
+    print_dts_indent(depth);
+    print::println("};");
+}
+
+/// Dump the tree (or the subtrees at the given paths) as .dts-style text.
+///
+/// Paths that don't exist are reported on stderr and skipped.
+fn run_dts(opts: &GlobalOptions, dt_opts: &DtOptions) -> i32 {
+    if opts.json {
+        print::eprintln("Warning: --dts is text only, ignoring --json");
+    }
+
+    let mut counter = NodeCounter::new(dt_opts);
+    let mut header = false;
+    for root in dt_opts.node_paths.roots() {
+        let exists = sanitize_relative_path(DT_BASE_PATH, root)
+            .is_some_and(|p| io::is_dir(p.as_str()));
+        if !exists {
+            warn_node_not_found(root);
+            continue;
+        }
+        if !header {
+            print::println("/dts-v1/;");
+            print::println_empty();
+            header = true;
+        }
+        traverse_and_output_dts(DT_BASE_PATH, root, 0, &mut counter);
+    }
+    warn_truncated(&counter);
+
+    0
+}
+
+fn warn_node_not_found(node_path: &str) {
+    print::eprint("dt: node not found: ");
+    print::eprintln(node_path);
+}
+
 /// Entry point for `kv dt` subcommand.
 pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
-    let dt_opts = DtOptions::parse(args);
+    let Some(dt_opts) = DtOptions::parse(args) else {
+        return 1;
+    };
 
     if !io::path_exists(DT_BASE_PATH) {
         if opts.json {
-            let mut w = begin_kv_output_streaming(opts.pretty, "dt");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
             w.key("data");
             w.value_null();
             w.field_str("error", "devicetree not found");
@@ -537,13 +902,20 @@ pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
         return 0;
     }
 
-    // Mode 1: Specific node path
-    if let Some(ref node_path) = dt_opts.node_path {
-        return run_single_node(opts, node_path.as_str());
+    // .dts dump of the whole tree or one subtree
+    if dt_opts.dts {
+        return run_dts(opts, &dt_opts);
+    }
+
+    // Mode 1: Specific node path(s)
+    match dt_opts.node_paths.len() {
+        0 => {}
+        1 => return run_single_node(opts, dt_opts.node_paths.iter().next().unwrap_or("/")),
+        _ => return run_nodes(opts, &dt_opts.node_paths),
     }
 
     // Mode 2: Filtered list (disabled or global filter pattern)
-    if dt_opts.disabled_only || opts.filter.is_some() {
+    if dt_opts.disabled_only || opts.filter.is_active() {
         return run_filtered(opts, &dt_opts);
     }
 
@@ -552,42 +924,186 @@ pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
         return run_full_list(opts, &dt_opts);
     }
 
-    run_summary(opts)
+    run_summary(opts, &dt_opts)
+}
+
+/// Cell counts the spec assumes when the root doesn't say.
+const DEFAULT_ADDRESS_CELLS: u32 = 2;
+const DEFAULT_SIZE_CELLS: u32 = 1;
+
+/// Read a single-cell property like `#address-cells`.
+fn read_cell_count(node_path: &str, name: &str) -> Option<u32> {
+    let path: StackString<512> = io::join_path(node_path, name);
+    let mut buf = [0u8; 4];
+    let n = read_property_bytes(path.as_str(), &mut buf)?;
+    (n == 4).then(|| u32::from_be_bytes(buf))
+}
+
+/// Read `count` big-endian cells (at most 2) as one number.
+fn read_cells(bytes: &[u8], count: u32) -> u64 {
+    bytes
+        .chunks_exact(4)
+        .take(count as usize)
+        .fold(0, |acc, c| (acc << 32) | u32::from_be_bytes([c[0], c[1], c[2], c[3]]) as u64)
+}
+
+/// Sum the sizes in a `reg` property of (address, size) pairs.
+///
+/// Returns None if the cell counts are unusable or the length doesn't
+/// divide into whole entries.
+pub fn reg_total_size(reg: &[u8], address_cells: u32, size_cells: u32) -> Option<u64> {
+    if size_cells == 0 || size_cells > 2 || address_cells > 4 {
+        return None;
+    }
+    let entry_len = (address_cells + size_cells) as usize * 4;
+    if reg.is_empty() || !reg.len().is_multiple_of(entry_len) {
+        return None;
+    }
+    let addr_len = address_cells as usize * 4;
+    Some(
+        reg.chunks_exact(entry_len)
+            .map(|entry| read_cells(&entry[addr_len..], size_cells))
+            .fold(0u64, |total, size| total.saturating_add(size)),
+    )
+}
+
+/// Total RAM described by the `/memory` and `/memory@*` nodes, in bytes.
+fn read_memory_size() -> Option<u64> {
+    let address_cells = read_cell_count(DT_BASE_PATH, "#address-cells").unwrap_or(DEFAULT_ADDRESS_CELLS);
+    let size_cells = read_cell_count(DT_BASE_PATH, "#size-cells").unwrap_or(DEFAULT_SIZE_CELLS);
+
+    let mut total: Option<u64> = None;
+    io::for_each_dir_entry(DT_BASE_PATH, |name| {
+        if name != "memory" && !name.starts_with("memory@") {
+            return;
+        }
+        let node: StackString<512> = io::join_path(DT_BASE_PATH, name);
+        let reg_path: StackString<512> = io::join_path(node.as_str(), "reg");
+        let mut buf = [0u8; 4096];
+        let size = read_property_bytes(reg_path.as_str(), &mut buf)
+            .and_then(|n| reg_total_size(&buf[..n], address_cells, size_cells));
+        if let Some(size) = size {
+            total = Some(total.unwrap_or(0).saturating_add(size));
+        }
+    });
+    total
+}
+
+/// The board's `serial-number` root property, if it has one.
+///
+/// Bootloaders fill this in from OTP or an EEPROM, so it identifies the
+/// board across reinstalls. Most boards don't set it.
+fn read_serial_number(base_path: &str) -> Option<StackString<512>> {
+    let path: StackString<512> = io::join_path(base_path, "serial-number");
+    read_property(path.as_str()).filter(|s| !s.is_empty())
+}
+
+/// Visit the text properties of `/chosen` (bootargs, stdout-path, ...).
+///
+/// Binary ones like `rng-seed` and `linux,initrd-start` are skipped.
+fn for_each_chosen_string(base_path: &str, mut visit: impl FnMut(&str, &str)) {
+    let chosen: StackString<512> = io::join_path(base_path, "chosen");
+    let mut count = 0;
+    io::for_each_dir_entry(chosen.as_str(), |name| {
+        if count >= MAX_PROPERTIES_PER_NODE || name == "name" {
+            return;
+        }
+        count += 1;
+        let path: StackString<512> = io::join_path(chosen.as_str(), name);
+        let mut buf = [0u8; 4096];
+        let Some(n) = read_property_bytes(path.as_str(), &mut buf) else {
+            return;
+        };
+        if is_string_list(&buf[..n]) {
+            visit(name, format_property(name, &buf[..n]).as_str());
+        }
+    });
+}
+
+/// `chosen_stdout_path` for the `stdout-path` property.
+fn chosen_key(name: &str) -> StackString<128> {
+    let mut key: StackString<128> = StackString::from_str("chosen_");
+    for c in name.chars() {
+        key.push(if c.is_ascii_alphanumeric() { c } else { '_' });
+    }
+    key
 }
 
 /// Show summary: root node info + node count.
-fn run_summary(opts: &GlobalOptions) -> i32 {
+fn run_summary(opts: &GlobalOptions, dt_opts: &DtOptions) -> i32 {
     let root = read_node_info(DT_BASE_PATH, "/");
-    let count = count_nodes_recursive(DT_BASE_PATH, "/", 0);
+    let mut counter = NodeCounter::new(dt_opts);
+    count_nodes_recursive(DT_BASE_PATH, "/", 0, &mut counter);
+    let count = counter.count;
+    let memory = read_memory_size();
+    let serial = read_serial_number(DT_BASE_PATH);
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "dt");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
+        print::note_data();
         w.field_object("data");
 
         if let Some(ref node) = root {
             w.field_str_opt(f::MODEL, node.model.as_ref().map(|s| s.as_str()));
             w.field_str_opt(f::COMPATIBLE, node.compatible.as_ref().map(|s| s.as_str()));
         }
+        w.field_str_opt(f::SERIAL_NUMBER, serial.as_ref().map(|s| s.as_str()));
         w.field_u64(f::NODE_COUNT, count as u64);
+        w.field_u64_opt(f::MEMORY_BYTES, memory);
+        let mut chosen_open = false;
+        for_each_chosen_string(DT_BASE_PATH, |name, value| {
+            if !chosen_open {
+                w.field_object(f::CHOSEN);
+                chosen_open = true;
+            }
+            w.field_str(name, value);
+        });
+        if chosen_open {
+            w.end_field_object();
+        }
 
         w.end_field_object();
+        if counter.truncated {
+            w.field_bool(f::TRUNCATED, true);
+        }
         w.end_object();
         w.finish();
     } else {
         if let Some(ref node) = root {
             if let Some(ref model) = node.model {
-                print::print("MODEL=\"");
-                print::print(model.as_str());
-                print::println("\"");
+                let mut w = TextWriter::new();
+                w.field_quoted(f::MODEL, model.as_str());
+                w.finish_record();
             }
             if let Some(ref compat) = node.compatible {
-                print::print("COMPATIBLE=\"");
-                print::print(compat.as_str());
-                print::println("\"");
+                let mut w = TextWriter::new();
+                w.field_quoted(f::COMPATIBLE, compat.as_str());
+                w.finish_record();
+            }
+        }
+        if let Some(ref serial) = serial {
+            let mut w = TextWriter::new();
+            w.field_quoted(f::SERIAL, serial.as_str());
+            w.finish_record();
+        }
+        for_each_chosen_string(DT_BASE_PATH, |name, value| {
+            let mut w = TextWriter::new();
+            w.field_quoted(chosen_key(name).as_str(), value);
+            w.finish_record();
+        });
+        if let Some(bytes) = memory {
+            let mut w = TextWriter::new();
+            if opts.human {
+                w.field_str(f::MEMORY, io::format_human_size(bytes).as_str());
+            } else {
+                w.field_u64(f::MEMORY_BYTES, bytes);
             }
+            w.finish_record();
         }
-        print::print("NODES=");
-        print::println_u64(count as u64);
+        let mut w = TextWriter::new();
+        w.field_u64("nodes", count as u64);
+        w.finish_record();
+        warn_truncated(&counter);
         print::println_empty();
         print::println("Use -v for full list, -f <pattern> to search, -d for disabled nodes");
     }
@@ -601,7 +1117,7 @@ fn run_single_node(opts: &GlobalOptions, node_path: &str) -> i32 {
         Some(p) => p,
         None => {
             if opts.json {
-                let mut w = begin_kv_output_streaming(opts.pretty, "dt");
+                let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
                 w.key("data");
                 w.value_null();
                 w.field_str("error", "invalid path");
@@ -619,7 +1135,7 @@ fn run_single_node(opts: &GlobalOptions, node_path: &str) -> i32 {
         Some(n) => n,
         None => {
             if opts.json {
-                let mut w = begin_kv_output_streaming(opts.pretty, "dt");
+                let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
                 w.key("data");
                 w.value_null();
                 w.field_str("error", "node not found");
@@ -634,7 +1150,8 @@ fn run_single_node(opts: &GlobalOptions, node_path: &str) -> i32 {
     };
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "dt");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
+        print::note_data();
         w.field_object("data");
         w.field_str(f::PATH, info.path.as_str());
         w.field_str(f::NAME, info.name.as_str());
@@ -645,25 +1162,81 @@ fn run_single_node(opts: &GlobalOptions, node_path: &str) -> i32 {
         w.end_object();
         w.finish();
     } else {
-        print::print("PATH=");
-        print::println(info.path.as_str());
+        let mut w = TextWriter::new();
+        w.field_str(f::PATH, info.path.as_str());
+        w.finish_record();
         output_properties_text(full_path.as_str());
     }
 
     0
 }
 
+/// Look up a node named on the command line. Warns on stderr if it's missing.
+fn find_node(node_path: &str) -> Option<(DtNodeInfo, StackString<512>)> {
+    let Some(full_path) = sanitize_relative_path(DT_BASE_PATH, node_path) else {
+        print::eprint("dt: invalid path: ");
+        print::eprintln(node_path);
+        return None;
+    };
+    let Some(info) = read_node_info(DT_BASE_PATH, node_path) else {
+        warn_node_not_found(node_path);
+        return None;
+    };
+    Some((info, full_path))
+}
+
+/// Show several nodes in detail, in the order given.
+///
+/// Missing nodes are reported on stderr and skipped. In JSON, `data` is
+/// an array of nodes rather than the single object `run_single_node` writes.
+fn run_nodes(opts: &GlobalOptions, node_paths: &NodePaths) -> i32 {
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
+        w.field_array("data");
+        for node_path in node_paths.iter() {
+            let Some((info, full_path)) = find_node(node_path) else { continue };
+            w.record_begin();
+            w.field_str(kind::KIND, kind::DT_NODE);
+            w.field_str(f::PATH, info.path.as_str());
+            w.field_str(f::NAME, info.name.as_str());
+            output_properties_json(&mut w, full_path.as_str());
+            w.array_object_end();
+        }
+        w.end_field_array();
+        w.end_object();
+        w.finish();
+    } else {
+        let mut first = true;
+        for node_path in node_paths.iter() {
+            let Some((info, full_path)) = find_node(node_path) else { continue };
+            if !first {
+                print::println_empty();
+            }
+            first = false;
+            let mut w = TextWriter::new();
+            w.field_str(f::PATH, info.path.as_str());
+            w.finish_record();
+            output_properties_text(full_path.as_str());
+        }
+    }
+
+    0
+}
+
 /// Show filtered list of nodes.
 fn run_filtered(opts: &GlobalOptions, dt_opts: &DtOptions) -> i32 {
-    let mut counter = NodeCounter::new();
+    let mut counter = NodeCounter::new(dt_opts);
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "dt");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
         w.field_array("data");
 
         traverse_and_output_json(&mut w, DT_BASE_PATH, "/", 0, &mut counter, opts, dt_opts);
 
         w.end_field_array();
+        if counter.truncated {
+            w.field_bool(f::TRUNCATED, true);
+        }
         w.end_object();
         w.finish();
     } else {
@@ -677,6 +1250,7 @@ fn run_filtered(opts: &GlobalOptions, dt_opts: &DtOptions) -> i32 {
             print::print_u64(counter.count as u64);
             print::println(" nodes)");
         }
+        warn_truncated(&counter);
     }
 
     0
@@ -684,35 +1258,42 @@ fn run_filtered(opts: &GlobalOptions, dt_opts: &DtOptions) -> i32 {
 
 /// Show full list of all nodes.
 fn run_full_list(opts: &GlobalOptions, dt_opts: &DtOptions) -> i32 {
-    let mut counter = NodeCounter::new();
+    let mut counter = NodeCounter::new(dt_opts);
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "dt");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "dt");
         w.field_array("data");
 
         traverse_and_output_json(&mut w, DT_BASE_PATH, "/", 0, &mut counter, opts, dt_opts);
 
         w.end_field_array();
+        if counter.truncated {
+            w.field_bool(f::TRUNCATED, true);
+        }
         w.end_object();
         w.finish();
     } else {
         traverse_and_output_text(DT_BASE_PATH, "/", 0, &mut counter, opts, dt_opts);
+        warn_truncated(&counter);
     }
 
     0
 }
 
 /// Write DT nodes to JSON writer (for snapshot).
+///
+/// Without a devicetree the section is left out, or written as `[]` with
+/// `include_empty`.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
-    if !io::path_exists(DT_BASE_PATH) {
+pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool, include_empty: bool) {
+    if !include_empty && !io::path_exists(DT_BASE_PATH) {
         return;
     }
 
     w.key("dt");
     w.begin_array();
 
-    let mut counter = NodeCounter::new();
+    let mut counter = NodeCounter::new(&DtOptions::default());
     traverse_and_output_json_snapshot(w, DT_BASE_PATH, "/", 0, &mut counter, verbose);
 
     w.end_array();
@@ -728,7 +1309,7 @@ fn traverse_and_output_json_snapshot(
     counter: &mut NodeCounter,
     verbose: bool,
 ) {
-    if depth > MAX_RECURSION_DEPTH || !counter.increment() {
+    if !counter.enter(depth) {
         return;
     }
 
@@ -742,7 +1323,8 @@ fn traverse_and_output_json_snapshot(
     }
 
     if let Some(info) = read_node_info(base_path, relative_path) {
-        w.array_object_begin();
+        w.record_begin();
+        w.field_str(kind::KIND, kind::DT_NODE);
         w.field_str(f::PATH, info.path.as_str());
         w.field_str(f::NAME, info.name.as_str());
         w.field_str_opt(f::COMPATIBLE, info.compatible.as_ref().map(|s| s.as_str()));
@@ -755,7 +1337,7 @@ fn traverse_and_output_json_snapshot(
         w.array_object_end();
     }
 
-    io::for_each_dir_entry(full_path.as_str(), |name| {
+    io::for_each_dir_entry_sorted(full_path.as_str(), io::natural_cmp, |name| {
         let child_full_path: StackString<512> = io::join_path(full_path.as_str(), name);
 
         if io::is_symlink(child_full_path.as_str()) {
@@ -782,3 +1364,196 @@ fn traverse_and_output_json_snapshot(
         traverse_and_output_json_snapshot(w, base_path, child_path.as_str(), depth + 1, counter, verbose);
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::testing::TempDir;
+
+    #[test]
+    fn max_depth_stops_recursion() {
+        let mut args = ExtraArgs::new();
+        args.push("--max-depth");
+        args.push("1");
+        args.push("--max-nodes");
+        args.push("999999");
+        let opts = DtOptions::parse(&args).unwrap();
+        assert_eq!(opts.max_depth, 1);
+        assert_eq!(opts.max_nodes, MAX_NODE_COUNT_LIMIT);
+
+        let mut counter = NodeCounter::new(&opts);
+        assert!(counter.enter(0));
+        assert!(counter.enter(1));
+        assert!(!counter.truncated);
+        assert!(!counter.enter(2));
+        assert!(counter.truncated);
+        assert_eq!(counter.count, 2);
+    }
+
+    #[test]
+    fn bad_limits_are_errors() {
+        for (flag, value) in [("--max-depth", Some("x")), ("--max-nodes", Some("-1")), ("--max-depth", None)] {
+            let mut args = ExtraArgs::new();
+            args.push(flag);
+            if let Some(value) = value {
+                args.push(value);
+            }
+            assert!(DtOptions::parse(&args).is_none(), "{flag} {value:?}");
+        }
+    }
+
+    #[test]
+    fn parse_multiple_node_paths() {
+        let mut args = ExtraArgs::new();
+        args.push("/soc/uart@1000");
+        args.push("-d");
+        args.push("/cpus");
+        args.push("/soc/pinctrl%2C0");
+        let opts = DtOptions::parse(&args).unwrap();
+        assert_eq!(opts.node_paths.len(), 3);
+        let mut paths = opts.node_paths.iter();
+        assert_eq!(paths.next(), Some("/soc/uart@1000"));
+        assert_eq!(paths.next(), Some("/cpus"));
+        assert_eq!(paths.next(), Some("/soc/pinctrl,0"));
+        assert!(opts.node_paths.roots().eq(opts.node_paths.iter()));
+
+        assert!(DtOptions::default().node_paths.roots().eq(["/"]));
+    }
+
+    #[test]
+    fn decode_path_escapes() {
+        assert_eq!(decode_path_arg("/a%2cb%40c").as_str(), "/a,b@c");
+        assert_eq!(decode_path_arg("/100%").as_str(), "/100%");
+        assert_eq!(decode_path_arg("/%zz").as_str(), "/%zz");
+        // Escaped bytes that aren't UTF-8 leave the argument alone
+        assert_eq!(decode_path_arg("/%ff").as_str(), "/%ff");
+    }
+
+    #[test]
+    fn max_nodes_caps_count() {
+        let opts = DtOptions { max_nodes: 2, ..DtOptions::default() };
+        let mut counter = NodeCounter::new(&opts);
+        assert!(counter.enter(0) && counter.enter(1));
+        assert!(!counter.enter(1));
+        assert!(counter.truncated);
+    }
+
+    #[test]
+    fn reg_two_cells() {
+        let reg = [0x12, 0x34, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00];
+        assert_eq!(format_property("reg", &reg).as_str(), "<0x12340000 0x1000>");
+    }
+
+    #[test]
+    fn single_cell_decimal() {
+        let freq = 24_000_000u32.to_be_bytes();
+        assert_eq!(format_property("clock-frequency", &freq).as_str(), "24000000");
+        assert_eq!(format_property("#address-cells", &[0, 0, 0, 2]).as_str(), "2");
+    }
+
+    #[test]
+    fn zero_cell() {
+        assert_eq!(format_property("interrupts", &[0, 0, 0, 0]).as_str(), "<0x0>");
+    }
+
+    #[test]
+    fn odd_length_falls_back_to_hex() {
+        assert_eq!(format_property("reg", &[0x01, 0x02, 0x03]).as_str(), "01 02 03");
+    }
+
+    #[test]
+    fn unknown_props_unchanged() {
+        assert_eq!(format_property("compatible", b"acme,board\0acme,soc\0").as_str(), "acme,board, acme,soc");
+        assert_eq!(format_property("phandle", &[0, 0, 0, 1]).as_str(), "00 00 00 01");
+    }
+
+    #[test]
+    fn dts_strings_quoted() {
+        assert_eq!(format_property_dts("compatible", b"acme,board\0acme,soc\0").as_str(), "\"acme,board\", \"acme,soc\"");
+        assert_eq!(format_property_dts("label", b"say \"hi\"\0").as_str(), "\"say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn dts_cells_and_bytes() {
+        assert_eq!(format_property_dts("#address-cells", &[0, 0, 0, 2]).as_str(), "<0x2>");
+        assert_eq!(format_property_dts("phandle", &[0, 0, 0, 1]).as_str(), "<0x1>");
+        assert_eq!(format_property_dts("mac-address", &[0x02, 0x00, 0xde, 0xad, 0xbe, 0xef]).as_str(), "[02 00 de ad be ef]");
+        // Looks like a string but is a known cell property
+        assert_eq!(format_property_dts("reg", b"abc\0").as_str(), "<0x61626300>");
+    }
+
+    #[test]
+    fn reg_total_size_two_cells() {
+        // 2 GiB at 0x40000000
+        let reg = [0, 0, 0, 0, 0x40, 0, 0, 0, 0, 0, 0, 0, 0x80, 0, 0, 0];
+        assert_eq!(reg_total_size(&reg, 2, 2), Some(0x8000_0000));
+    }
+
+    #[test]
+    fn reg_total_size_banks() {
+        // Two banks, 1 cell each: 256 MiB + 512 MiB
+        let reg = [0x40, 0, 0, 0, 0x10, 0, 0, 0, 0x80, 0, 0, 0, 0x20, 0, 0, 0];
+        assert_eq!(reg_total_size(&reg, 1, 1), Some(0x3000_0000));
+        // 64-bit size
+        let big = [0, 0, 0, 0x01, 0, 0, 0, 0];
+        assert_eq!(reg_total_size(&big, 0, 2), Some(1 << 32));
+    }
+
+    #[test]
+    fn reg_total_size_rejects_bad_layout() {
+        assert_eq!(reg_total_size(&[0; 12], 2, 2), None);
+        assert_eq!(reg_total_size(&[0; 16], 2, 0), None);
+        assert_eq!(reg_total_size(&[], 2, 1), None);
+    }
+
+    #[test]
+    fn dts_boolean_property_empty() {
+        assert!(format_property_dts("dma-coherent", &[]).is_empty());
+    }
+
+    #[test]
+    fn boolean_property_reads_empty() {
+        let node = TempDir::new("dt");
+        let flag = node.write("interrupt-controller", b"");
+        let value = read_property(flag.as_str());
+        assert_eq!(value.as_ref().map(|v| v.as_str()), Some(""));
+        assert!(read_property(node.join("missing").as_str()).is_none());
+    }
+
+    #[test]
+    fn dts_subtree_root_keeps_its_name() {
+        let root = TempDir::new("dt");
+        root.mkdir("soc");
+        root.mkdir("soc/serial@1000");
+
+        for (path, name) in [("/", "/"), ("/soc", "soc"), ("/soc/serial@1000", "serial@1000"), ("/soc/", "soc")] {
+            let dir = sanitize_relative_path(root.path(), path).unwrap();
+            assert!(io::is_dir(dir.as_str()), "{path}");
+            assert_eq!(dts_node_name(path), name);
+        }
+    }
+
+    #[test]
+    fn board_serial_and_chosen() {
+        // A fake root node with a serial number and a /chosen child
+        let root = TempDir::new("dt");
+        let chosen = root.mkdir("chosen");
+        root.write("serial-number", b"10000000a1b2c3d4\0");
+        root.write("chosen/stdout-path", b"serial0:115200n8\0");
+        root.write("chosen/rng-seed", &[0x8f, 0x00, 0x3c, 0xd1]);
+
+        let serial = read_serial_number(root.path());
+        let mut seen: StackString<128> = StackString::new();
+        for_each_chosen_string(root.path(), |name, value| {
+            seen.push_str(chosen_key(name).as_str());
+            seen.push('=');
+            seen.push_str(value);
+        });
+        let missing = read_serial_number(chosen.as_str());
+
+        assert_eq!(serial.as_ref().map(|s| s.as_str()), Some("10000000a1b2c3d4"));
+        // The binary seed is left out
+        assert_eq!(seen.as_str(), "chosen_stdout_path=serial0:115200n8");
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,78 @@
+//! Short descriptions of enumerated field values (`--explain`).
+//!
+//! `POWER_STATE=D3hot` means nothing to most people. With --explain, text
+//! output follows such values with a few words: `POWER_STATE=D3hot (off,
+//! can wake)`. Only fields whose printers ask for it via
+//! `TextWriter::field_explained` are annotated, and JSON never is.
+
+#![allow(dead_code)]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::fields::{net, pci, power};
+
+/// Global --explain flag, set once at startup (same approach as the debug flag).
+static EXPLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Turn annotations on or off. Called once from main after parsing args.
+pub fn set_enabled(enabled: bool) {
+    EXPLAIN.store(enabled, Ordering::Relaxed);
+}
+
+/// Is --explain in effect?
+#[inline]
+pub fn is_enabled() -> bool {
+    EXPLAIN.load(Ordering::Relaxed)
+}
+
+/// (field, value, description). Values are as the kernel writes them.
+const DESCRIPTIONS: &[(&str, &str, &str)] = &[
+    // PCI device power states (power_state in sysfs)
+    (pci::POWER_STATE, "D0", "fully on"),
+    (pci::POWER_STATE, "D1", "light sleep"),
+    (pci::POWER_STATE, "D2", "deeper sleep"),
+    (pci::POWER_STATE, "D3hot", "off, can wake"),
+    (pci::POWER_STATE, "D3cold", "power removed"),
+    (pci::POWER_STATE, "unknown", "state not reported"),
+    (pci::POWER_STATE, "error", "device not responding"),
+    // Network operstate (RFC 2863)
+    (net::STATE, "up", "ready to pass packets"),
+    (net::STATE, "down", "not passing packets"),
+    (net::STATE, "dormant", "link up, waiting for auth or an event"),
+    (net::STATE, "lowerlayerdown", "the device under it is down"),
+    (net::STATE, "notpresent", "hardware missing"),
+    (net::STATE, "testing", "in test mode"),
+    (net::STATE, "unknown", "driver doesn't report state"),
+    (net::STATUS, "down(admin)", "not brought up, see 'ip link set <if> up'"),
+    (net::STATUS, "down(no-carrier)", "brought up, but no cable or link partner"),
+    // Battery status
+    (power::STATUS, "Charging", "taking charge"),
+    (power::STATUS, "Discharging", "running on battery"),
+    (power::STATUS, "Full", "charged, on external power"),
+    (power::STATUS, "Not charging", "on external power, held below full"),
+    (power::STATUS, "Unknown", "firmware doesn't say"),
+];
+
+/// What `value` means for `field`, if it's one we know.
+pub fn describe(field: &str, value: &str) -> Option<&'static str> {
+    DESCRIPTIONS
+        .iter()
+        .find(|&&(f, v, _)| f == field && v == value)
+        .map(|&(_, _, description)| description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe(pci::POWER_STATE, "D3hot"), Some("off, can wake"));
+        assert_eq!(describe(net::STATE, "dormant"), Some("link up, waiting for auth or an event"));
+        assert_eq!(describe(power::STATUS, "Not charging"), Some("on external power, held below full"));
+        // Exact matches only, and the field matters
+        assert_eq!(describe(net::STATE, "UP"), None);
+        assert_eq!(describe(pci::POWER_STATE, "up"), None);
+        assert_eq!(describe("mtu", "1500"), None);
+    }
+}
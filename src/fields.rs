@@ -9,12 +9,34 @@
 
 #![allow(dead_code)]
 
+/// Record kinds: every element of a `data` array starts with
+/// `"kind": "<kind>"`, so records from different subcommands can be mixed
+/// in one stream and still told apart. These values are stable.
+pub mod kind {
+    pub const KIND: &str = "kind";
+    pub const PCI_DEVICE: &str = "pci_device";
+    pub const USB_DEVICE: &str = "usb_device";
+    pub const BLOCK_DEVICE: &str = "block_device";
+    pub const BLOCK_NODE: &str = "block_node";
+    pub const NET_INTERFACE: &str = "net_interface";
+    pub const NET_ADDRESS: &str = "net_address";
+    pub const NET_COUNTERS: &str = "net_counters";
+    pub const MOUNT: &str = "mount";
+    pub const POWER_SUPPLY: &str = "power_supply";
+    pub const THERMAL_SENSOR: &str = "thermal_sensor";
+    pub const COOLING_DEVICE: &str = "cooling_device";
+    pub const DT_NODE: &str = "dt_node";
+}
+
 /// Network interface fields (kv net)
 pub mod net {
     pub const NAME: &str = "name";
     pub const MAC: &str = "mac";
+    pub const MAC_VENDOR: &str = "mac_vendor";
     pub const MTU: &str = "mtu";
     pub const STATE: &str = "state";
+    pub const STATUS: &str = "status";
+    pub const ADMIN_UP: &str = "admin_up";
     pub const SPEED: &str = "speed";
     pub const DUPLEX: &str = "duplex";
     pub const CARRIER: &str = "carrier";
@@ -24,6 +46,8 @@ pub mod net {
     pub const SIGNAL: &str = "signal";
     pub const LINK: &str = "link";
     pub const NOISE: &str = "noise";
+    pub const WIFI_PHY: &str = "wifi_phy";
+    pub const WIFI_PHY_INDEX: &str = "wifi_phy_index";
     pub const RX_BYTES: &str = "rx_bytes";
     pub const TX_BYTES: &str = "tx_bytes";
     pub const RX_PACKETS: &str = "rx_packets";
@@ -32,6 +56,21 @@ pub mod net {
     pub const TX_ERRORS: &str = "tx_errors";
     pub const RX_DROPPED: &str = "rx_dropped";
     pub const TX_DROPPED: &str = "tx_dropped";
+    pub const DEFAULT: &str = "default";
+    pub const DEFAULT_ROUTE: &str = "default_route";
+    pub const GATEWAY: &str = "gateway";
+    pub const DRIVER: &str = "driver";
+    pub const BUS_INFO: &str = "bus_info";
+    // --baseline
+    pub const RX_UTIL: &str = "rx_util";
+    pub const TX_UTIL: &str = "tx_util";
+    pub const RX_UTIL_PERCENT: &str = "rx_util_percent";
+    pub const TX_UTIL_PERCENT: &str = "tx_util_percent";
+    // --addresses
+    pub const IFACE: &str = "iface";
+    pub const FAMILY: &str = "family";
+    pub const ADDRESS: &str = "address";
+    pub const PREFIX: &str = "prefix";
 }
 
 /// Memory fields (kv mem)
@@ -65,6 +104,23 @@ pub mod mem {
     pub const SUNRECLAIM: &str = "sunreclaim";
     pub const DIRTY: &str = "dirty";
     pub const WRITEBACK: &str = "writeback";
+
+    // Changes between --watch samples (signed; human mode drops _kb)
+    pub const MEM_FREE_DELTA_KB: &str = "mem_free_delta_kb";
+    pub const MEM_AVAILABLE_DELTA_KB: &str = "mem_available_delta_kb";
+    pub const DIRTY_DELTA_KB: &str = "dirty_delta_kb";
+    pub const WRITEBACK_DELTA_KB: &str = "writeback_delta_kb";
+    pub const MEM_FREE_DELTA: &str = "mem_free_delta";
+    pub const MEM_AVAILABLE_DELTA: &str = "mem_available_delta";
+    pub const DIRTY_DELTA: &str = "dirty_delta";
+    pub const WRITEBACK_DELTA: &str = "writeback_delta";
+
+    // Hugepage pool (counts are pages, not KB)
+    pub const HUGEPAGES_TOTAL: &str = "hugepages_total";
+    pub const HUGEPAGES_FREE: &str = "hugepages_free";
+    pub const HUGEPAGES_RSVD: &str = "hugepages_rsvd";
+    pub const HUGEPAGESIZE_KB: &str = "hugepagesize_kb";
+    pub const HUGEPAGESIZE: &str = "hugepagesize";
 }
 
 /// PCI device fields (kv pci)
@@ -72,6 +128,8 @@ pub mod pci {
     pub const BDF: &str = "bdf";
     pub const VENDOR_ID: &str = "vendor_id";
     pub const DEVICE_ID: &str = "device_id";
+    pub const VENDOR_NAME: &str = "vendor_name";
+    pub const DEVICE_NAME: &str = "device_name";
     pub const CLASS: &str = "class";
     pub const DRIVER: &str = "driver";
     pub const SUBSYS_VENDOR: &str = "subsys_vendor";
@@ -82,6 +140,18 @@ pub mod pci {
     pub const ENABLED: &str = "enabled";
     pub const POWER_STATE: &str = "power_state";
     pub const IS_BRIDGE: &str = "is_bridge";
+    pub const PARENT: &str = "parent";
+    pub const CHILDREN: &str = "children";
+    pub const NUM_VFS: &str = "num_vfs";
+    pub const TOTAL_VFS: &str = "total_vfs";
+    pub const PHYSICAL_FUNCTION: &str = "physical_function";
+    /// Text key prefix: BAR0, BAR1, ...
+    pub const BAR: &str = "bar";
+    pub const BARS: &str = "bars";
+    pub const BAR_INDEX: &str = "index";
+    pub const BAR_SIZE: &str = "size_bytes";
+    pub const BAR_TYPE: &str = "type";
+    pub const BAR_PREFETCHABLE: &str = "prefetchable";
 }
 
 /// Block device fields (kv block)
@@ -101,6 +171,26 @@ pub mod block {
     pub const MODEL: &str = "model";
     pub const ROTATIONAL: &str = "rotational";
     pub const SCHEDULER: &str = "scheduler";
+    pub const ZONED: &str = "zoned";
+    pub const ZONES: &str = "zones";
+    pub const NR_ZONES: &str = "nr_zones";
+    pub const READS: &str = "reads";
+    pub const READ_SECTORS: &str = "read_sectors";
+    pub const READ_SIZE: &str = "read_size";
+    pub const WRITES: &str = "writes";
+    pub const WRITE_SECTORS: &str = "write_sectors";
+    pub const WRITE_SIZE: &str = "write_size";
+    pub const IO_IN_PROGRESS: &str = "io_in_progress";
+    pub const DM_NAME: &str = "dm_name";
+    pub const MD_LEVEL: &str = "md_level";
+    pub const RAID_DISKS: &str = "raid_disks";
+    pub const HOLDERS: &str = "holders";
+    pub const SLAVES: &str = "slaves";
+    pub const SIZE_BYTES: &str = "size_bytes";
+    pub const CHILDREN: &str = "children";
+    pub const TOTAL_DISKS: &str = "total_disks";
+    pub const TOTAL_CAPACITY: &str = "total_capacity";
+    pub const TOTAL_CAPACITY_BYTES: &str = "total_capacity_bytes";
 }
 
 /// CPU fields (kv cpu)
@@ -117,8 +207,29 @@ pub mod cpu {
     pub const STEPPING: &str = "stepping";
     pub const CPU_MHZ: &str = "cpu_mhz";
     pub const CACHE_SIZE: &str = "cache_size";
+    pub const CACHE: &str = "cache";
+    pub const CACHES: &str = "caches";
+    pub const LEVEL: &str = "level";
+    pub const TYPE: &str = "type";
+    pub const SIZE_KB: &str = "size_kb";
+    pub const SHARED_CPU_LIST: &str = "shared_cpu_list";
     pub const ARCHITECTURE: &str = "architecture";
     pub const FLAGS: &str = "flags";
+    pub const CPU_USAGE: &str = "cpu_usage";
+    pub const PER_CPU: &str = "per_cpu";
+    pub const CPU: &str = "cpu";
+    pub const USAGE: &str = "usage";
+    pub const SMT: &str = "smt";
+    pub const THROTTLES: &str = "throttles";
+    pub const PACKAGE_THROTTLES: &str = "package_throttles";
+    pub const THROTTLE_COUNT: &str = "throttle_count";
+    pub const TOPOLOGY: &str = "topology";
+    pub const PACKAGES: &str = "packages";
+    pub const PACKAGE_ID: &str = "package_id";
+    pub const CORE_ID: &str = "core_id";
+    pub const THREAD_SIBLINGS: &str = "thread_siblings";
+    pub const CORES: &str = "cores";
+    pub const THREADS: &str = "threads";
 }
 
 /// Thermal fields (kv thermal)
@@ -131,6 +242,7 @@ pub mod thermal {
     pub const TEMP_CRIT_MILLICELSIUS: &str = "temp_crit_millicelsius";
     pub const TRIPS: &str = "trips";
     pub const TRIP_POINTS: &str = "trip_points";
+    pub const HYST_MILLICELSIUS: &str = "hyst_millicelsius";
     pub const POLICY: &str = "policy";
     pub const SOURCE: &str = "source";
     pub const NAME: &str = "name";
@@ -142,6 +254,25 @@ pub mod thermal {
     pub const STATE: &str = "state";
 }
 
+/// Summary line fields (kv summary)
+pub mod summary {
+    pub const HOSTNAME: &str = "hostname";
+    pub const CPU_MODEL: &str = "cpu_model";
+    pub const CPUS: &str = "cpus";
+    pub const MEM_TOTAL_KB: &str = "mem_total_kb";
+    pub const MEM_TOTAL: &str = "mem_total";
+    pub const UPTIME_S: &str = "uptime_s";
+    pub const UPTIME: &str = "uptime";
+    pub const LOAD: &str = "load";
+    pub const LOAD_1: &str = "load_1";
+    pub const LOAD_5: &str = "load_5";
+    pub const LOAD_15: &str = "load_15";
+    pub const TEMP_MAX: &str = "temp_max";
+    pub const TEMP_MAX_MILLICELSIUS: &str = "temp_max_millicelsius";
+    pub const TEMP_MAX_SENSOR: &str = "temp_max_sensor";
+    pub const HOT: &str = "hot";
+}
+
 /// Power supply fields (kv power)
 pub mod power {
     pub const NAME: &str = "name";
@@ -151,6 +282,7 @@ pub mod power {
     pub const CAPACITY: &str = "capacity";
     pub const CAPACITY_PERCENT: &str = "capacity_percent";
     pub const USB_TYPE: &str = "usb_type";
+    pub const USB_TYPE_AMBIGUOUS: &str = "usb_type_ambiguous";
     pub const VOLTAGE_UV: &str = "voltage_uv";
     pub const VOLTAGE_V: &str = "voltage_v";
     pub const VOLTAGE: &str = "voltage";
@@ -176,10 +308,21 @@ pub mod power {
     pub const CURRENT_MAX: &str = "current_max";
     pub const CYCLES: &str = "cycles";
     pub const CYCLE_COUNT: &str = "cycle_count";
+    pub const CHARGE_LIMIT: &str = "charge_limit";
+    pub const CHARGE_START_THRESHOLD: &str = "charge_start_threshold";
+    pub const CHARGE_END_THRESHOLD: &str = "charge_end_threshold";
     pub const TECHNOLOGY: &str = "technology";
     pub const MODEL_NAME: &str = "model_name";
     pub const MODEL: &str = "model";
     pub const MANUFACTURER: &str = "manufacturer";
+    pub const TOTAL_POWER: &str = "total_power";
+    pub const TOTAL_POWER_W: &str = "total_power_w";
+    pub const ON_AC: &str = "on_ac";
+    pub const BATTERY: &str = "battery";
+    pub const BATTERY_PERCENT: &str = "battery_percent";
+    pub const VOLTAGE_IS_AVG: &str = "voltage_is_avg";
+    pub const CURRENT_IS_AVG: &str = "current_is_avg";
+    pub const POWER_IS_AVG: &str = "power_is_avg";
 }
 
 /// USB device fields (kv usb)
@@ -192,6 +335,7 @@ pub mod usb {
     pub const SPEED_MBPS: &str = "speed_mbps";
     pub const DEVICE_CLASS: &str = "device_class";
     pub const CLASS: &str = "class";
+    pub const CLASS_NAME: &str = "class_name";
     pub const BUSNUM: &str = "busnum";
     pub const BUS: &str = "bus";
     pub const DEVNUM: &str = "devnum";
@@ -202,6 +346,9 @@ pub mod usb {
     pub const CONFIGURATION: &str = "configuration";
     pub const MAX_POWER_MA: &str = "max_power_ma";
     pub const DRIVER: &str = "driver";
+    pub const INTERFACES: &str = "interfaces";
+    pub const INTERFACE: &str = "interface";
+    pub const PROTOCOL: &str = "protocol";
 }
 
 /// Device tree fields (kv dt)
@@ -214,6 +361,12 @@ pub mod dt {
     pub const NODE_COUNT: &str = "node_count";
     pub const PROPERTIES: &str = "properties";
     pub const REG: &str = "reg";
+    pub const MEMORY: &str = "memory";
+    pub const MEMORY_BYTES: &str = "memory_bytes";
+    pub const SERIAL: &str = "serial";
+    pub const SERIAL_NUMBER: &str = "serial_number";
+    pub const CHOSEN: &str = "chosen";
+    pub const TRUNCATED: &str = "truncated";
 }
 
 /// Mount point fields (kv mounts)
@@ -224,6 +377,46 @@ pub mod mounts {
     pub const OPTIONS: &str = "options";
     pub const DUMP_FREQ: &str = "dump_freq";
     pub const PASS_NUM: &str = "pass_num";
+    pub const IS_PSEUDO: &str = "is_pseudo";
+}
+
+/// Process and thread counts (kv tasks)
+pub mod tasks {
+    pub const PROCESSES: &str = "processes";
+    pub const THREADS: &str = "threads";
+    pub const RUNNING: &str = "running";
+    pub const TRUNCATED: &str = "truncated";
+}
+
+/// DMI fields (kv dmi), named as in /sys/class/dmi/id
+pub mod dmi {
+    pub const SYS_VENDOR: &str = "sys_vendor";
+    pub const PRODUCT_NAME: &str = "product_name";
+    pub const PRODUCT_SERIAL: &str = "product_serial";
+    pub const BOARD_VENDOR: &str = "board_vendor";
+    pub const BOARD_NAME: &str = "board_name";
+    pub const BIOS_VENDOR: &str = "bios_vendor";
+    pub const BIOS_VERSION: &str = "bios_version";
+    pub const BIOS_DATE: &str = "bios_date";
+    pub const CHASSIS_TYPE: &str = "chassis_type";
+    pub const CHASSIS_NAME: &str = "chassis_name";
+}
+
+/// Kernel fields (kv kernel)
+pub mod kernel {
+    pub const RELEASE: &str = "release";
+    pub const COMPILER: &str = "compiler";
+    pub const BUILD_DATE: &str = "build_date";
+    pub const BUILDER: &str = "builder";
+    pub const VERSION_RAW: &str = "version_raw";
+    pub const CMDLINE: &str = "cmdline";
+}
+
+/// Single attribute fields (kv read)
+pub mod read {
+    pub const PATH: &str = "path";
+    pub const VALUE: &str = "value";
+    pub const ENTRIES: &str = "entries";
 }
 
 #[cfg(test)]
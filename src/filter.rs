@@ -3,16 +3,399 @@
 //! Provides centralized case-sensitive/insensitive matching so individual
 //! subcommand modules don't need to handle this logic.
 //!
+//! A filter is a small list of patterns (one per `-f`/`-F` on the command
+//! line) combined with AND or OR. Each pattern is either:
+//! - a plain substring, checked against every searchable field, or
+//! - `field=value`, which only looks at the named field and wants an exact
+//!   match (e.g. `removable=1`, `type=disk`), or
+//! - `field>value`, `field<value`, `field>=value` or `field<=value`, which
+//!   compares a numeric field (e.g. `mtu>1500`, `capacity<20`).
+//!
+//! `-f -` reads a whole list of patterns from stdin instead. The list counts
+//! as one pattern that matches when any line of it does, so
+//! `cat bdfs.txt | kv pci -f -` shows every device named in the file.
+//!
 //! # For Contributors
 //!
 //! When adding a new subcommand with filterable items, implement the `Filterable`
 //! trait on your struct. You only need to implement `filter_fields()` - the
-//! `matches_filter()` method is provided automatically.
+//! `matches_filter()` method is provided automatically. Numbers that are
+//! worth comparing go in `filter_numeric_fields()`.
 
 #![allow(dead_code)]
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::limits::{MAX_FILTERS, MAX_FILTER_LEN, MAX_PATTERN_LIST_SIZE};
+use crate::print;
 use crate::stack::StackString;
 
+/// Type alias for a filter pattern string.
+pub type FilterStr = StackString<MAX_FILTER_LEN>;
+
+/// How multiple patterns are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Every pattern must match (each `-f` narrows the result).
+    #[default]
+    And,
+    /// Any pattern may match.
+    Or,
+}
+
+impl FilterMode {
+    /// Parse a `--filter-mode` value.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "and" | "AND" => Some(FilterMode::And),
+            "or" | "OR" => Some(FilterMode::Or),
+            _ => None,
+        }
+    }
+}
+
+/// Operator of a `field>value` style pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    /// Does `field <op> bound` hold?
+    pub fn holds(self, field: i64, bound: i64) -> bool {
+        match self {
+            CmpOp::Lt => field < bound,
+            CmpOp::Le => field <= bound,
+            CmpOp::Gt => field > bound,
+            CmpOp::Ge => field >= bound,
+        }
+    }
+}
+
+/// Does `key` look like one of our field names (lowercase, digits, underscores)?
+fn is_field_name(key: &str) -> bool {
+    !key.is_empty() && key.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+/// A single filter pattern as given on the command line.
+#[derive(Clone, Default)]
+pub struct Pattern {
+    /// The pattern text (already lowercased when case-insensitive)
+    pub text: FilterStr,
+    /// Whether this came from `-F` (case-insensitive)
+    pub case_insensitive: bool,
+}
+
+impl Pattern {
+    /// Split a `field=value` pattern. Returns None for plain substrings.
+    ///
+    /// The field part must look like one of our field names (lowercase,
+    /// digits, underscores), so patterns like "rw,relatime" or "a=b c"
+    /// with odd keys are still treated as plain text.
+    pub fn keyed(&self) -> Option<(&str, &str)> {
+        let (key, value) = self.text.as_str().split_once('=')?;
+        if !is_field_name(key) {
+            return None;
+        }
+        Some((key, value))
+    }
+
+    /// Split a `field>value` pattern (or `<`, `>=`, `<=`). The value is
+    /// returned as written; see `numeric_comparison` for the parsed form.
+    pub fn comparison(&self) -> Option<(&str, CmpOp, &str)> {
+        let text = self.text.as_str();
+        let (key, rest) = text.split_at(text.find(['<', '>'])?);
+        if !is_field_name(key) {
+            return None;
+        }
+        let (op, value) = match rest.as_bytes() {
+            [b'>', b'=', ..] => (CmpOp::Ge, &rest[2..]),
+            [b'<', b'=', ..] => (CmpOp::Le, &rest[2..]),
+            [b'>', ..] => (CmpOp::Gt, &rest[1..]),
+            _ => (CmpOp::Lt, &rest[1..]),
+        };
+        Some((key, op, value))
+    }
+
+    /// A comparison whose value is a number. Anything else (`a>b`) is
+    /// matched as plain text.
+    pub fn numeric_comparison(&self) -> Option<(&str, CmpOp, i64)> {
+        let (key, op, value) = self.comparison()?;
+        let bound = value.parse().ok()?;
+        Some((key, op, bound))
+    }
+}
+
+/// Newline-separated patterns from `-f -`, any of which may match.
+#[derive(Clone, Default)]
+pub struct PatternList {
+    /// One pattern per line, blank lines dropped
+    text: StackString<MAX_PATTERN_LIST_SIZE>,
+    case_insensitive: bool,
+    /// Input didn't fit and the rest was dropped
+    truncated: bool,
+}
+
+impl PatternList {
+    /// Load patterns from `read`, which fills a buffer like read(2) and
+    /// returns 0 at end of input.
+    ///
+    /// Lines are trimmed, blank ones skipped, and lowercased for `-F`.
+    /// Input that isn't UTF-8 gives an empty list.
+    pub fn load(mut read: impl FnMut(&mut [u8]) -> usize, case_insensitive: bool) -> Self {
+        let mut list = PatternList { case_insensitive, ..PatternList::default() };
+        let mut buf = [0u8; MAX_PATTERN_LIST_SIZE];
+        let mut len = 0;
+        while len < buf.len() {
+            let n = read(&mut buf[len..]);
+            if n == 0 {
+                break;
+            }
+            len += n;
+        }
+        if len == buf.len() {
+            let mut probe = [0u8; 1];
+            list.truncated = read(&mut probe) > 0;
+        }
+
+        let Ok(input) = core::str::from_utf8(&buf[..len]) else {
+            return list;
+        };
+        // A truncated last line would match the wrong things; drop it
+        let input = match (list.truncated, input.rfind('\n')) {
+            (true, Some(end)) => &input[..end],
+            (true, None) => "",
+            (false, _) => input,
+        };
+        for line in input.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if !list.text.is_empty() {
+                list.text.push('\n');
+            }
+            if case_insensitive {
+                push_lowercase(&mut list.text, line);
+            } else {
+                list.text.push_str(line);
+            }
+        }
+        list
+    }
+
+    /// No patterns at all (empty stdin).
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Some input was dropped because it didn't fit.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Number of patterns.
+    pub fn len(&self) -> usize {
+        self.lines().count()
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.as_str().split('\n').filter(|l| !l.is_empty())
+    }
+
+    /// Does any pattern in the list match? An empty list matches nothing.
+    pub fn matches<T: Filterable + ?Sized>(&self, item: &T) -> bool {
+        self.lines().any(|line| {
+            let pattern = Pattern {
+                text: FilterStr::from_str(line),
+                case_insensitive: self.case_insensitive,
+            };
+            item.matches_pattern(&pattern)
+        })
+    }
+}
+
+/// The complete filter: up to MAX_FILTERS patterns plus a combine mode.
+#[derive(Clone, Default)]
+pub struct Filter {
+    patterns: [Pattern; MAX_FILTERS],
+    count: usize,
+    /// Patterns from stdin (`-f -`), combined with the rest as one pattern
+    list: Option<PatternList>,
+    /// How patterns are combined (default: AND)
+    pub mode: FilterMode,
+}
+
+impl Filter {
+    /// Add a pattern. Returns false if the list is already full.
+    pub fn push(&mut self, text: FilterStr, case_insensitive: bool) -> bool {
+        if self.count >= MAX_FILTERS {
+            return false;
+        }
+        self.patterns[self.count] = Pattern { text, case_insensitive };
+        self.count += 1;
+        true
+    }
+
+    /// Use a pattern list from stdin (replacing any earlier one).
+    pub fn set_list(&mut self, list: PatternList) {
+        self.list = Some(list);
+    }
+
+    /// Has a pattern list been set?
+    pub fn has_list(&self) -> bool {
+        self.list.is_some()
+    }
+
+    /// Are there any patterns at all?
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.count > 0 || self.list.is_some()
+    }
+
+    /// Number of patterns.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Iterate over the patterns.
+    pub fn patterns(&self) -> impl Iterator<Item = &Pattern> {
+        self.patterns[..self.count].iter()
+    }
+
+    /// Check an item against all patterns. An empty filter matches everything.
+    pub fn matches<T: Filterable + ?Sized>(&self, item: &T) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let list = self.list.as_ref();
+        match self.mode {
+            FilterMode::And => {
+                self.patterns().all(|p| item.matches_pattern(p)) && list.is_none_or(|l| l.matches(item))
+            }
+            FilterMode::Or => {
+                self.patterns().any(|p| item.matches_pattern(p)) || list.is_some_and(|l| l.matches(item))
+            }
+        }
+    }
+}
+
+/// Implemented by every record type that can be filtered with `-f`/`-F`.
+pub trait Filterable {
+    /// Visit the (field name, value) pairs that plain patterns search.
+    /// Field names should come from `crate::fields`.
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str));
+
+    /// Visit extra fields that are only reachable via `field=value`.
+    ///
+    /// Use this for flags like `removable=1` - searching "1" as a plain
+    /// substring across every boolean would match nearly everything.
+    fn filter_keyed_fields(&self, _visit: &mut dyn FnMut(&str, &str)) {}
+
+    /// Visit the numeric fields that `field>value` patterns compare.
+    ///
+    /// Give the raw number, not the text form: `capacity` is 85, not "85%".
+    fn filter_numeric_fields(&self, _visit: &mut dyn FnMut(&str, i64)) {}
+
+    /// Check a single pattern against this item.
+    ///
+    /// Override to understand domain-specific pattern syntax (like partial
+    /// PCI addresses), falling back to `pattern_matches` for the rest.
+    fn matches_pattern(&self, pattern: &Pattern) -> bool {
+        pattern_matches(pattern, self)
+    }
+
+    /// Check this item against the filter.
+    fn matches_filter(&self, filter: &Filter) -> bool {
+        filter.matches(self)
+    }
+}
+
+/// Set once the "isn't numeric" warning has been printed, so a filter
+/// over a hundred items doesn't repeat it a hundred times.
+static NOT_NUMERIC_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Compare a numeric field against a bound. A field that only exists as
+/// text never matches, with a warning (once per run).
+fn compare_matches<T: Filterable + ?Sized>(pattern: &Pattern, item: &T, key: &str, op: CmpOp, bound: i64) -> bool {
+    let mut numeric = false;
+    let mut found = false;
+    item.filter_numeric_fields(&mut |name, value| {
+        if name == key {
+            numeric = true;
+            found = found || op.holds(value, bound);
+        }
+    });
+    if numeric {
+        return found;
+    }
+
+    let mut textual = false;
+    let mut check = |name: &str, _: &str| textual = textual || name == key;
+    item.filter_fields(&mut check);
+    item.filter_keyed_fields(&mut check);
+    if textual && !NOT_NUMERIC_WARNED.swap(true, Ordering::Relaxed) {
+        print::eprint("Warning: ");
+        print::eprint(key);
+        print::eprint(" isn't a numeric field, so ");
+        print::eprint(pattern.text.as_str());
+        print::eprintln(" matches nothing");
+    }
+    false
+}
+
+/// Check a single pattern against an item (substring, exact for `field=value`,
+/// numeric for `field>value`).
+pub fn pattern_matches<T: Filterable + ?Sized>(pattern: &Pattern, item: &T) -> bool {
+    let ci = pattern.case_insensitive;
+    let mut found = false;
+
+    if let Some((key, op, bound)) = pattern.numeric_comparison() {
+        return compare_matches(pattern, item, key, op, bound);
+    }
+
+    if let Some((key, value)) = pattern.keyed() {
+        let mut check = |name: &str, field: &str| {
+            if !found && name == key && equals(field, value, ci) {
+                found = true;
+            }
+        };
+        item.filter_fields(&mut check);
+        item.filter_keyed_fields(&mut check);
+    } else {
+        let text = pattern.text.as_str();
+        item.filter_fields(&mut |_, field| {
+            if !found && matches_any(&[field], text, ci) {
+                found = true;
+            }
+        });
+    }
+
+    found
+}
+
+/// Append `s` lowercased to `out`, a character at a time.
+///
+/// `-F` patterns and the fields they're matched against both go through
+/// this, so non-ASCII text folds the same way on each side ("ÜRÜN" and
+/// "Ürün" both become "ürün").
+pub fn push_lowercase<const N: usize>(out: &mut StackString<N>, s: &str) {
+    for lc in s.chars().flat_map(char::to_lowercase) {
+        out.push(lc);
+    }
+}
+
+/// Exact comparison, folding case when requested.
+/// Pattern is assumed to be already lowercase in the case-insensitive path.
+fn equals(field: &str, pattern: &str, case_insensitive: bool) -> bool {
+    if !case_insensitive {
+        return field == pattern;
+    }
+    let mut lower: StackString<256> = StackString::new();
+    push_lowercase(&mut lower, field);
+    lower.as_str() == pattern
+}
+
 /// Check if any of the given fields contain the pattern.
 ///
 /// When `case_insensitive` is true, the pattern is assumed to be already
@@ -36,23 +419,14 @@ pub fn matches_any(fields: &[&str], pattern: &str, case_insensitive: bool) -> bo
 /// Check if field (lowercased) contains pattern.
 /// Pattern is assumed to be already lowercase.
 fn contains_lowercase(field: &str, pattern: &str) -> bool {
-    // Simple brute-force search with case folding
-    let pattern_len = pattern.len();
-    if pattern_len == 0 {
+    if pattern.is_empty() {
         return true;
     }
-    if field.len() < pattern_len {
-        return false;
-    }
 
-    // Convert field to lowercase into a stack buffer
+    // No shortcut on length: lowercasing can make text longer in bytes
+    // ("İ" is 2 bytes, its lowercase "i̇" is 3)
     let mut lower: StackString<256> = StackString::new();
-    for c in field.chars() {
-        for lc in c.to_lowercase() {
-            lower.push(lc);
-        }
-    }
-
+    push_lowercase(&mut lower, field);
     lower.as_str().contains(pattern)
 }
 
@@ -63,7 +437,253 @@ pub fn opt_str<T: AsRef<str>>(opt: &Option<T>) -> &str {
     opt.as_ref().map(|s| s.as_ref()).unwrap_or("")
 }
 
+/// "1" or "0" for boolean fields that are filterable via `field=value`.
+#[inline]
+pub fn bool_str(v: bool) -> &'static str {
+    if v { "1" } else { "0" }
+}
+
+/// Records for tests of code that works on any `Filterable`.
+#[cfg(test)]
+pub mod testing {
+    use super::Filterable;
+
+    /// A made-up network interface.
+    pub struct FakeNic {
+        pub name: &'static str,
+        pub state: &'static str,
+        pub mtu: Option<u32>,
+        pub rx_bytes: Option<u64>,
+        pub tx_bytes: u64,
+    }
+
+    impl Filterable for FakeNic {
+        fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+            visit("name", self.name);
+            visit("state", self.state);
+        }
+
+        fn filter_numeric_fields(&self, visit: &mut dyn FnMut(&str, i64)) {
+            if let Some(mtu) = self.mtu {
+                visit("mtu", mtu as i64);
+            }
+            if let Some(rx) = self.rx_bytes {
+                visit("rx_bytes", rx as i64);
+            }
+            visit("tx_bytes", self.tx_bytes as i64);
+        }
+    }
+
+    pub const ETH: FakeNic = FakeNic { name: "eth0", state: "up", mtu: Some(1500), rx_bytes: Some(2_500_000), tx_bytes: 400_000 };
+    pub const SLIP: FakeNic = FakeNic { name: "sl0", state: "up", mtu: Some(576), rx_bytes: Some(0), tx_bytes: 0 };
+    /// No MTU and no receive counter
+    pub const TUN: FakeNic = FakeNic { name: "tun0", state: "unknown", mtu: None, rx_bytes: None, tx_bytes: 24 };
+}
+
 #[cfg(test)]
 mod tests {
-    // Tests removed for no_std build
+    use super::testing::{ETH, SLIP};
+    use super::*;
+
+    /// A made-up block device: removable USB stick, mounted.
+    struct FakeDisk {
+        name: &'static str,
+        mountpoint: &'static str,
+        removable: bool,
+    }
+
+    impl Filterable for FakeDisk {
+        fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+            visit("name", self.name);
+            visit("mountpoint", self.mountpoint);
+        }
+
+        fn filter_keyed_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+            visit("removable", bool_str(self.removable));
+        }
+    }
+
+    fn filter(mode: FilterMode, patterns: &[(&str, bool)]) -> Filter {
+        let mut f = Filter { mode, ..Filter::default() };
+        for (p, ci) in patterns {
+            f.push(StackString::from_str(p), *ci);
+        }
+        f
+    }
+
+    const STICK: FakeDisk = FakeDisk { name: "sdb1", mountpoint: "/media/usb", removable: true };
+    const ROOT: FakeDisk = FakeDisk { name: "nvme0n1p2", mountpoint: "/", removable: false };
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert!(Filter::default().matches(&STICK));
+        assert!(Filter::default().matches(&ROOT));
+    }
+
+    #[test]
+    fn two_patterns_and() {
+        let f = filter(FilterMode::And, &[("removable=1", false), ("media", false)]);
+        assert!(f.matches(&STICK));
+        assert!(!f.matches(&ROOT));
+
+        // Second pattern fails for the stick -> no match under AND
+        let f = filter(FilterMode::And, &[("removable=1", false), ("nvme", false)]);
+        assert!(!f.matches(&STICK));
+        assert!(!f.matches(&ROOT));
+    }
+
+    #[test]
+    fn two_patterns_or() {
+        let f = filter(FilterMode::Or, &[("removable=1", false), ("nvme", false)]);
+        assert!(f.matches(&STICK));
+        assert!(f.matches(&ROOT));
+
+        let f = filter(FilterMode::Or, &[("sda", false), ("mmcblk", false)]);
+        assert!(!f.matches(&STICK));
+        assert!(!f.matches(&ROOT));
+    }
+
+    /// A reader over `input` that hands it out `chunk` bytes at a time.
+    fn reader(input: &'static str, chunk: usize) -> impl FnMut(&mut [u8]) -> usize {
+        let mut rest = input.as_bytes();
+        move |buf| {
+            let n = rest.len().min(buf.len()).min(chunk);
+            buf[..n].copy_from_slice(&rest[..n]);
+            rest = &rest[n..];
+            n
+        }
+    }
+
+    #[test]
+    fn pattern_list_from_reader() {
+        let list = PatternList::load(reader("sda\n\n  SDB1 \nnvme0n1p2\n", 3), true);
+        assert_eq!(list.len(), 3);
+        assert!(!list.is_truncated());
+        assert!(list.matches(&STICK)); // "SDB1" folded to "sdb1"
+        assert!(list.matches(&ROOT));
+
+        // Combined with -f as a single OR'ed pattern
+        let mut f = filter(FilterMode::And, &[("removable=1", false)]);
+        f.set_list(list);
+        assert!(f.matches(&STICK));
+        assert!(!f.matches(&ROOT));
+    }
+
+    #[test]
+    fn endless_pattern_list_is_truncated() {
+        // stdin that never hits EOF: we stop at the cap instead of reading on
+        let endless = |buf: &mut [u8]| {
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = b"sdb1\n"[i % 5];
+            }
+            buf.len()
+        };
+        let list = PatternList::load(endless, false);
+        assert!(list.is_truncated());
+        assert_eq!(list.len(), MAX_PATTERN_LIST_SIZE / 5);
+        assert!(list.matches(&STICK));
+    }
+
+    #[test]
+    fn empty_pattern_list_matches_nothing() {
+        let list = PatternList::load(reader("\n  \n", 8), false);
+        assert!(list.is_empty());
+        let mut f = Filter::default();
+        f.set_list(list);
+        assert!(f.is_active());
+        assert!(!f.matches(&STICK));
+        assert!(!f.matches(&ROOT));
+    }
+
+    #[test]
+    fn keyed_pattern_is_exact_and_field_scoped() {
+        // "/" as a plain substring matches both; mountpoint=/ only matches root
+        let f = filter(FilterMode::And, &[("mountpoint=/", false)]);
+        assert!(!f.matches(&STICK));
+        assert!(f.matches(&ROOT));
+
+        // Keyed-only fields aren't searched by plain patterns
+        let f = filter(FilterMode::And, &[("1", false)]);
+        assert!(f.matches(&STICK)); // "sdb1" contains "1"
+        let f = filter(FilterMode::And, &[("0", false)]);
+        assert!(f.matches(&ROOT)); // "nvme0n1p2" contains "0"
+        let f = filter(FilterMode::And, &[("0", false)]);
+        assert!(!f.matches(&STICK)); // removable=0 isn't visible to plain search
+    }
+
+    #[test]
+    fn case_insensitive_keyed() {
+        // -F lowercases the pattern at parse time
+        let f = filter(FilterMode::And, &[("name=sdb1", true)]);
+        let upper = FakeDisk { name: "SDB1", mountpoint: "", removable: true };
+        assert!(f.matches(&upper));
+        let f = filter(FilterMode::And, &[("name=sdb1", false)]);
+        assert!(!f.matches(&upper));
+    }
+
+    #[test]
+    fn case_insensitive_unicode() {
+        let vendor = FakeDisk { name: "Ürün Elektronik", mountpoint: "", removable: false };
+        // -F "ÜRÜN" and -F "ürün" both reach here as "ürün"
+        let mut pattern: StackString<16> = StackString::new();
+        push_lowercase(&mut pattern, "ÜRÜN");
+        assert_eq!(pattern.as_str(), "ürün");
+        assert!(filter(FilterMode::And, &[("ürün", true)]).matches(&vendor));
+        assert!(filter(FilterMode::And, &[("name=ürün elektronik", true)]).matches(&vendor));
+        assert!(!filter(FilterMode::And, &[("ürün", false)]).matches(&vendor));
+
+        // A field shorter in bytes than its lowercase still matches
+        assert!(matches_any(&["İ"], "i\u{307}", true));
+    }
+
+    #[test]
+    fn numeric_comparison() {
+        let f = filter(FilterMode::And, &[("mtu>1000", false)]);
+        assert!(f.matches(&ETH));
+        assert!(!f.matches(&SLIP));
+
+        let f = filter(FilterMode::And, &[("mtu<=576", false)]);
+        assert!(!f.matches(&ETH));
+        assert!(f.matches(&SLIP));
+        let f = filter(FilterMode::And, &[("mtu>=1500", false), ("eth", false)]);
+        assert!(f.matches(&ETH));
+        let f = filter(FilterMode::And, &[("mtu<1500", false)]);
+        assert!(!f.matches(&ETH));
+    }
+
+    #[test]
+    fn comparison_on_text_field_matches_nothing() {
+        let f = filter(FilterMode::And, &[("name>1", false)]);
+        assert!(!f.matches(&ETH));
+        // Unknown fields don't match either
+        let f = filter(FilterMode::And, &[("speed>10", false)]);
+        assert!(!f.matches(&ETH));
+    }
+
+    #[test]
+    fn parse_comparison() {
+        let p = Pattern { text: StackString::from_str("capacity<20"), case_insensitive: false };
+        assert_eq!(p.numeric_comparison(), Some(("capacity", CmpOp::Lt, 20)));
+        let p = Pattern { text: StackString::from_str("temp>=-5"), case_insensitive: false };
+        assert_eq!(p.numeric_comparison(), Some(("temp", CmpOp::Ge, -5)));
+        // Not a number: a comparison in shape, but matched as text
+        let p = Pattern { text: StackString::from_str("mtu>big"), case_insensitive: false };
+        assert_eq!(p.comparison(), Some(("mtu", CmpOp::Gt, "big")));
+        assert_eq!(p.numeric_comparison(), None);
+        let p = Pattern { text: StackString::from_str("<none>"), case_insensitive: false };
+        assert_eq!(p.comparison(), None);
+        // >= isn't mistaken for field=value
+        let p = Pattern { text: StackString::from_str("mtu>=9000"), case_insensitive: false };
+        assert!(p.keyed().is_none());
+    }
+
+    #[test]
+    fn odd_keys_fall_back_to_substring() {
+        let p = Pattern { text: StackString::from_str("rw,relatime=x"), case_insensitive: false };
+        assert!(p.keyed().is_none());
+        let p = Pattern { text: StackString::from_str("=x"), case_insensitive: false };
+        assert!(p.keyed().is_none());
+        let p = Pattern { text: StackString::from_str("ro=1"), case_insensitive: false };
+        assert_eq!(p.keyed(), Some(("ro", "1")));
+    }
 }
@@ -11,13 +11,186 @@
 
 #![allow(dead_code)]
 
+use core::cell::UnsafeCell;
 use core::mem::MaybeUninit;
 use core::str::FromStr;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 use rustix::fs::{openat, Mode, OFlags, RawDir, CWD};
 use rustix::io::read;
 
-use crate::stack::StackString;
+use crate::print;
+use crate::profile::{Phase, Timer};
+use crate::stack::{StackBuf, StackString};
+
+// ============================================================================
+// Path recorder (--list-paths)
+// ============================================================================
+
+/// Maximum number of distinct paths we remember.
+const MAX_RECORDED_PATHS: usize = 1024;
+
+/// Longest path we keep; longer ones are truncated.
+const MAX_RECORDED_PATH_LEN: usize = 128;
+
+/// Set once from main when --list-paths is given.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// A set of unique paths, in first-seen order.
+struct PathRecorder<const N: usize> {
+    paths: [StackString<MAX_RECORDED_PATH_LEN>; N],
+    count: usize,
+    dropped: usize,
+}
+
+impl<const N: usize> PathRecorder<N> {
+    const fn new() -> Self {
+        PathRecorder {
+            paths: [const { StackString::new() }; N],
+            count: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Add a path unless it's already there. Counts it as dropped when full.
+    fn insert(&mut self, path: &str) {
+        if self.paths[..self.count].iter().any(|p| p.as_str() == path) {
+            return;
+        }
+        if self.count < N {
+            self.paths[self.count] = StackString::from_str(path);
+            self.count += 1;
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    /// The recorded paths, sorted.
+    fn sorted(&mut self) -> &[StackString<MAX_RECORDED_PATH_LEN>] {
+        let paths = &mut self.paths[..self.count];
+        paths.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+        paths
+    }
+}
+
+struct RecorderCell<const N: usize>(UnsafeCell<PathRecorder<N>>);
+
+// SAFETY: kv is single-threaded; the recorders are only touched from the main thread.
+unsafe impl<const N: usize> Sync for RecorderCell<N> {}
+
+impl<const N: usize> RecorderCell<N> {
+    /// # Safety
+    /// Single-threaded use only, and the reference must not outlive the caller.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get(&self) -> &mut PathRecorder<N> {
+        unsafe { &mut *self.0.get() }
+    }
+}
+
+static RECORDER: RecorderCell<MAX_RECORDED_PATHS> = RecorderCell(UnsafeCell::new(PathRecorder::new()));
+
+/// Start (or stop) recording every path the read helpers open.
+pub fn set_recording(enabled: bool) {
+    RECORDING.store(enabled, Ordering::Relaxed);
+}
+
+/// Remember a path we're about to read, if recording.
+#[inline]
+fn record_path(path: &str) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+    // SAFETY: single-threaded, and no reference into the recorder outlives this call
+    unsafe { RECORDER.get() }.insert(path);
+}
+
+/// Print the recorded paths to stdout, sorted, one per line.
+pub fn print_recorded_paths() {
+    // SAFETY: single-threaded, recording is finished
+    let rec = unsafe { RECORDER.get() };
+    for p in rec.sorted() {
+        print::println(p.as_str());
+    }
+    if rec.dropped > 0 {
+        let mut buf = itoa::Buffer::new();
+        print::eprint("Warning: path list full, ");
+        print::eprint(buf.format(rec.dropped));
+        print::eprintln(" more paths not shown");
+    }
+}
+
+// ============================================================================
+// Required reads (--strict)
+// ============================================================================
+
+/// Maximum number of failed required reads we list.
+const MAX_FAILED_READS: usize = 64;
+
+/// Set once from main when --strict is given.
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+static FAILED_READS: RecorderCell<MAX_FAILED_READS> = RecorderCell(UnsafeCell::new(PathRecorder::new()));
+
+/// Start (or stop) keeping track of required reads that fail.
+pub fn set_strict(enabled: bool) {
+    STRICT.store(enabled, Ordering::Relaxed);
+}
+
+/// Note a failed read of a file the caller can't do without.
+fn required<T>(path: &str, value: Option<T>) -> Option<T> {
+    if value.is_none() && STRICT.load(Ordering::Relaxed) {
+        // SAFETY: single-threaded, and no reference into the log outlives this call
+        unsafe { FAILED_READS.get() }.insert(path);
+    }
+    value
+}
+
+/// read_file_stack for a file that should always be there (see --strict).
+pub fn read_required_stack<const N: usize>(path: &str) -> Option<StackString<N>> {
+    required(path, read_file_stack(path))
+}
+
+/// read_file_parse for a file that should always be there (see --strict).
+pub fn read_required_parse<T: FromStr>(path: &str) -> Option<T> {
+    required(path, read_file_parse(path))
+}
+
+/// read_file_hex for a file that should always be there (see --strict).
+pub fn read_required_hex<T: FromStrRadix>(path: &str) -> Option<T> {
+    required(path, read_file_hex(path))
+}
+
+/// Read from stdin into `buf`, returning the byte count (0 at EOF or on error).
+pub fn read_stdin(buf: &mut [u8]) -> usize {
+    // SAFETY: fd 0 stays open for the life of the process
+    read(unsafe { rustix::stdio::stdin() }, buf).unwrap_or(0)
+}
+
+/// read_file_bytes for a file that should always be there (see --strict).
+pub fn read_required_bytes(path: &str, buf: &mut [u8]) -> Option<usize> {
+    required(path, read_file_bytes(path, buf))
+}
+
+/// Print failed required reads to stderr. Returns true if there were any.
+pub fn report_failed_reads() -> bool {
+    // SAFETY: single-threaded, the subcommand has finished
+    let log = unsafe { FAILED_READS.get() };
+    if log.count == 0 {
+        return false;
+    }
+    print::eprintln("Error: required files could not be read:");
+    for p in log.sorted() {
+        print::eprint("    ");
+        print::eprintln(p.as_str());
+    }
+    if log.dropped > 0 {
+        let mut buf = itoa::Buffer::new();
+        print::eprint("    ... and ");
+        print::eprint(buf.format(log.dropped));
+        print::eprintln(" more");
+    }
+    true
+}
 
 // ============================================================================
 // Directory iteration (stack-based, no allocation)
@@ -30,6 +203,8 @@ pub fn for_each_dir_entry<F>(path: &str, mut callback: F)
 where
     F: FnMut(&str),
 {
+    record_path(path);
+    let mut timer = Timer::start(Phase::DirScans);
     let Ok(fd) = openat(CWD, path, OFlags::RDONLY | OFlags::DIRECTORY, Mode::empty()) else {
         return;
     };
@@ -47,7 +222,10 @@ where
                 continue;
             }
             if let Ok(name_str) = core::str::from_utf8(name_bytes) {
+                // The caller's work on each entry isn't part of the scan
+                timer.pause();
                 callback(name_str);
+                timer.resume();
             }
         }
         if !found_any {
@@ -56,9 +234,141 @@ where
     }
 }
 
+/// Most entries `for_each_dir_entry_sorted` puts in order.
+const MAX_SORTED_ENTRIES: usize = 256;
+
+/// Longest name `for_each_dir_entry_sorted` puts in order.
+const MAX_SORTED_NAME_LEN: usize = 64;
+
+/// Compare names the way people count: runs of digits compare as numbers,
+/// so "eth2" comes before "eth10" and "thermal_zone9" before "thermal_zone10".
+/// Not for hex: PCI addresses sort right byte by byte, and wrong this way.
+pub fn natural_cmp(a: &str, b: &str) -> core::cmp::Ordering {
+    use core::cmp::Ordering;
+
+    let (a_bytes, b_bytes) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a_bytes.len() && j < b_bytes.len() {
+        let a_run = digit_run(&a_bytes[i..]);
+        let b_run = digit_run(&b_bytes[j..]);
+        if !a_run.is_empty() && !b_run.is_empty() {
+            let (a_num, b_num) = (trim_zeros(a_run), trim_zeros(b_run));
+            let order = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+            if order != Ordering::Equal {
+                return order;
+            }
+            (i, j) = (i + a_run.len(), j + b_run.len());
+        } else if a_bytes[i] != b_bytes[j] {
+            return a_bytes[i].cmp(&b_bytes[j]);
+        } else {
+            (i, j) = (i + 1, j + 1);
+        }
+    }
+    (a_bytes.len() - i).cmp(&(b_bytes.len() - j)).then_with(|| a.cmp(b))
+}
+
+/// The digits `bytes` starts with.
+fn digit_run(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().take_while(|c| c.is_ascii_digit()).count();
+    &bytes[..len]
+}
+
+/// A run of digits without its leading zeros.
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&c| c == b'0').count();
+    &digits[zeros..]
+}
+
+/// Like `for_each_dir_entry`, but in `cmp` order (usually `natural_cmp`)
+/// rather than whatever order the kernel hands entries out in, so the same
+/// system always lists the same way. Entries past the first 256, or with very
+/// long names, still come - after the sorted ones, unsorted.
+pub fn for_each_dir_entry_sorted<F>(path: &str, cmp: fn(&str, &str) -> core::cmp::Ordering, mut callback: F)
+where
+    F: FnMut(&str),
+{
+    let mut names: [StackString<MAX_SORTED_NAME_LEN>; MAX_SORTED_ENTRIES] = core::array::from_fn(|_| StackString::new());
+    let mut count = 0;
+    let mut overflow = false;
+    // Insertion keeps it simple; directories this big are rare
+    for_each_dir_entry(path, |name| {
+        if count < MAX_SORTED_ENTRIES && name.len() <= MAX_SORTED_NAME_LEN {
+            let at = names[..count].partition_point(|n| cmp(n.as_str(), name).is_lt());
+            names[at..=count].rotate_right(1);
+            names[at] = StackString::from_str(name);
+            count += 1;
+        } else {
+            overflow = true;
+        }
+    });
+
+    let names = &names[..count];
+    for name in names {
+        callback(name.as_str());
+    }
+
+    if overflow {
+        for_each_dir_entry(path, |name| {
+            if names.binary_search_by(|n| cmp(n.as_str(), name)).is_err() {
+                callback(name);
+            }
+        });
+    }
+}
+
+// ============================================================================
+// Line-by-line reads of big files
+// ============================================================================
+
+/// Longest line `for_each_line` passes on; the rest of a longer one is dropped.
+const MAX_LINE_LEN: usize = 512;
+
+/// Call `callback` on each line of a file too big to read into one buffer
+/// (an OUI list is megabytes), a block at a time. Lines that aren't UTF-8
+/// are skipped. The callback returns false to stop reading early.
+///
+/// Returns false if the file couldn't be opened.
+pub fn for_each_line<F>(path: &str, mut callback: F) -> bool
+where
+    F: FnMut(&str) -> bool,
+{
+    record_path(path);
+    let _timer = Timer::start(Phase::FileReads);
+    let Ok(fd) = openat(CWD, path, OFlags::RDONLY, Mode::empty()) else {
+        return false;
+    };
+
+    let mut buf = [0u8; 4096];
+    let mut line = [0u8; MAX_LINE_LEN];
+    let mut len = 0;
+    let mut emit = |bytes: &[u8]| core::str::from_utf8(bytes).map_or(true, &mut callback);
+    while let Ok(n) = read(&fd, &mut buf) {
+        if n == 0 {
+            break;
+        }
+        for &b in &buf[..n] {
+            if b == b'\n' {
+                if !emit(&line[..len]) {
+                    return true;
+                }
+                len = 0;
+            } else if len < MAX_LINE_LEN {
+                line[len] = b;
+                len += 1;
+            }
+        }
+    }
+    if len > 0 {
+        emit(&line[..len]);
+    }
+    true
+}
+
 /// Read a symlink target into a StackString.
 /// Returns the full symlink path, not just the final component.
 pub fn read_symlink<const N: usize>(path: &str) -> Option<StackString<N>> {
+    record_path(path);
+
     // Open the symlink's parent directory and read it
     let fd = openat(CWD, path, OFlags::RDONLY | OFlags::PATH | OFlags::NOFOLLOW, Mode::empty()).ok()?;
 
@@ -68,13 +378,45 @@ pub fn read_symlink<const N: usize>(path: &str) -> Option<StackString<N>> {
     let mut itoa_buf = itoa::Buffer::new();
     proc_path.push_str(itoa_buf.format(rustix::fd::AsRawFd::as_raw_fd(&fd)));
 
-    // Read the link target
+    // Read the link target (not recorded - /proc/self/fd/N is our own plumbing)
     let mut buf = [0u8; 256];
-    let n = read_file_bytes(proc_path.as_str(), &mut buf)?;
+    let n = read_bytes(proc_path.as_str(), &mut buf)?;
     let link_path = core::str::from_utf8(&buf[..n]).ok()?;
     Some(StackString::from_str(link_path))
 }
 
+/// Where `path` really is, with every symlink along the way resolved.
+///
+/// Reads the /proc/self/fd link of an O_PATH descriptor, so it's None
+/// when /proc isn't mounted (or the path doesn't exist).
+pub fn canonical_path<const N: usize>(path: &str) -> Option<StackString<N>> {
+    let fd = openat(CWD, path, OFlags::PATH | OFlags::CLOEXEC, Mode::empty()).ok()?;
+
+    let mut proc_path: StackString<64> = StackString::new();
+    proc_path.push_str("/proc/self/fd/");
+    let mut itoa_buf = itoa::Buffer::new();
+    proc_path.push_str(itoa_buf.format(rustix::fd::AsRawFd::as_raw_fd(&fd)));
+
+    let mut buf = [0u8; 512];
+    let n = rustix::fs::readlinkat_raw(CWD, proc_path.as_str(), &mut buf[..]).ok()?;
+    // A full buffer means the path was cut short
+    if n >= buf.len() || n > N {
+        return None;
+    }
+    Some(StackString::from_str(core::str::from_utf8(&buf[..n]).ok()?))
+}
+
+/// Does `path` resolve to somewhere strictly inside `dir`?
+/// `dir` must already be canonical (see `canonical_path`).
+pub fn resolves_under(path: &str, dir: &str) -> bool {
+    let Some(real) = canonical_path::<512>(path) else {
+        return false;
+    };
+    real.as_str()
+        .strip_prefix(dir)
+        .is_some_and(|rest| rest.len() > 1 && rest.starts_with('/'))
+}
+
 /// Read a symlink and extract just the final component (filename).
 /// Used for reading driver symlinks like /sys/bus/pci/devices/XXX/driver -> ../../../drivers/NAME
 pub fn read_symlink_name<const N: usize>(path: &str) -> Option<StackString<N>> {
@@ -87,11 +429,38 @@ pub fn read_symlink_name<const N: usize>(path: &str) -> Option<StackString<N>> {
     }
 }
 
-/// Read raw bytes from a file into a buffer.
-/// Returns the number of bytes read.
-fn read_file_bytes(path: &str, buf: &mut [u8]) -> Option<usize> {
+/// Run `read` for an entry of a sysfs listing, once more if it came back
+/// empty while `dir` still exists.
+///
+/// Hot-plug can catch a device between the directory listing and its
+/// attribute reads. A single retry smooths that over without ever looping.
+pub fn retry_if_present<T>(dir: &str, read: impl FnMut() -> Option<T>) -> Option<T> {
+    retry_once(read, || path_exists(dir))
+}
+
+/// Retry logic behind `retry_if_present`, with the existence check injectable for tests.
+fn retry_once<T>(mut read: impl FnMut() -> Option<T>, still_there: impl FnOnce() -> bool) -> Option<T> {
+    match read() {
+        Some(v) => Some(v),
+        None if still_there() => read(),
+        None => None,
+    }
+}
+
+/// Read raw bytes from a file into a buffer, until it's full or the file
+/// ends. Returns the number of bytes read; a caller that needs to know
+/// whether the file was bigger can pass a buffer one byte longer than it
+/// takes.
+pub fn read_file_bytes(path: &str, buf: &mut [u8]) -> Option<usize> {
+    record_path(path);
+    read_bytes(path, buf)
+}
+
+/// read_file_bytes without recording the path.
+fn read_bytes(path: &str, buf: &mut [u8]) -> Option<usize> {
+    let _timer = Timer::start(Phase::FileReads);
     let fd = openat(CWD, path, OFlags::RDONLY, Mode::empty()).ok()?;
-    let n = read(&fd, buf).ok()?;
+    let (n, _) = read_capped(|b| read(&fd, b).ok(), buf)?;
     Some(n)
 }
 
@@ -117,13 +486,124 @@ impl HexNibble for u8 {
     }
 }
 
+/// Most bytes `format_bytes` shows as hex before giving up with "...".
+const MAX_HEX_BYTES: usize = 32;
+
+/// Format a raw attribute value for display.
+///
+/// Printable text is shown as-is, with null-separated parts (devicetree
+/// string lists) joined by ", ". Anything else becomes space-separated
+/// hex bytes, cut off after 32.
+pub fn format_bytes<const N: usize>(bytes: &[u8]) -> StackString<N> {
+    let is_stringy = bytes.iter().all(|&b| {
+        b == 0 || (0x20..0x7f).contains(&b) || b == b'\n' || b == b'\t'
+    });
+
+    if is_stringy {
+        let mut result: StackString<N> = StackString::new();
+        let mut first = true;
+        for part in bytes.split(|&b| b == 0) {
+            if part.is_empty() {
+                continue;
+            }
+            if let Ok(s) = core::str::from_utf8(part) {
+                if !first {
+                    result.push_str(", ");
+                }
+                result.push_str(s);
+                first = false;
+            }
+        }
+        if !result.as_str().is_empty() {
+            return result;
+        }
+    }
+
+    let mut result: StackString<N> = StackString::new();
+    let limit = bytes.len().min(MAX_HEX_BYTES);
+    for (i, &b) in bytes[..limit].iter().enumerate() {
+        if i > 0 {
+            result.push(' ');
+        }
+        result.push(b.hex_hi());
+        result.push(b.hex_lo());
+    }
+    if bytes.len() > MAX_HEX_BYTES {
+        result.push_str("...");
+    }
+    result
+}
+
 // ============================================================================
 // Core file reading functions (stack-based, no allocation)
 // ============================================================================
 
+/// Most bytes we take from one file, whatever the caller's buffer. Sysfs
+/// attributes are a page at most, but a few procfs files are unbounded.
+const MAX_FILE_READ: usize = 1024 * 1024;
+
+/// Fill `buf` from `read` (a read(2)-like source) until it's full or the
+/// source runs dry, taking at most `MAX_FILE_READ` bytes.
+///
+/// Returns the byte count and whether anything was left over, found by
+/// asking for one byte more once the buffer is full.
+fn read_capped(mut read: impl FnMut(&mut [u8]) -> Option<usize>, buf: &mut [u8]) -> Option<(usize, bool)> {
+    let limit = buf.len().min(MAX_FILE_READ);
+    let mut len = 0;
+    while len < limit {
+        match read(&mut buf[len..limit])? {
+            0 => return Some((len, false)),
+            n => len += n,
+        }
+    }
+    let mut probe = [0u8; 1];
+    Some((len, read(&mut probe).is_some_and(|n| n > 0)))
+}
+
 /// Read a file into a stack buffer and return trimmed content.
 /// Returns None if the file can't be read or isn't valid UTF-8.
 pub fn read_file_stack<const N: usize>(path: &str) -> Option<StackString<N>> {
+    read_file_text(path, true)
+}
+
+/// Reader for the files of one sysfs directory, as the `from_files`
+/// constructors take it: given a file name, the trimmed contents (or None).
+/// Tests hand those constructors `testing::fixture` instead of sysfs.
+pub fn dir_files<const N: usize>(dir: &str) -> impl Fn(&str) -> Option<StackString<N>> + '_ {
+    move |name| {
+        let path: StackString<256> = join_path(dir, name);
+        read_file_stack(path.as_str())
+    }
+}
+
+/// Set once from main when --raw is given.
+static RAW: AtomicBool = AtomicBool::new(false);
+
+/// Turn off kv's cleanup of displayed values (--raw).
+pub fn set_raw(enabled: bool) {
+    RAW.store(enabled, Ordering::Relaxed);
+}
+
+/// Should values be shown exactly as the kernel wrote them?
+#[inline]
+pub fn is_raw() -> bool {
+    RAW.load(Ordering::Relaxed)
+}
+
+/// Read a value for display: trimmed like read_file_stack, or with --raw
+/// exactly as the kernel wrote it, trailing newline included.
+///
+/// Only for values that are printed as-is; anything parsed afterwards
+/// should keep using read_file_stack.
+pub fn read_file_value<const N: usize>(path: &str) -> Option<StackString<N>> {
+    read_file_text(path, !is_raw())
+}
+
+/// read_file_stack, optionally without the trim.
+fn read_file_text<const N: usize>(path: &str, trim: bool) -> Option<StackString<N>> {
+    record_path(path);
+    let _timer = Timer::start(Phase::FileReads);
+
     // Open file read-only
     let fd = match openat(CWD, path, OFlags::RDONLY, Mode::empty()) {
         Ok(fd) => fd,
@@ -133,21 +613,23 @@ pub fn read_file_stack<const N: usize>(path: &str) -> Option<StackString<N>> {
         }
     };
 
-    // Read into buffer
-    let mut buf = [0u8; 4096];
-    let n = match read(&fd, &mut buf) {
-        Ok(n) => n,
-        Err(_e) => {
-            crate::dbg_fail!(path, _e);
-            return None;
-        }
-    };
+    // Read into buffer; anything past what the caller can hold is dropped
+    let mut buf: StackBuf<N> = StackBuf::new();
+    let (n, truncated) = read_capped(|b| read(&fd, b).ok(), buf.as_mut_slice())?;
+    buf.set_len(n);
+    if truncated {
+        crate::debug::truncated(path, n);
+    }
 
-    // Convert to string and trim
-    let s = match core::str::from_utf8(&buf[..n]) {
-        Ok(s) => s.trim(),
+    // Convert to string and trim. A cut can land inside a character,
+    // which costs that character rather than the whole value.
+    let bytes = buf.as_bytes();
+    let s = match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) if truncated => core::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
         Err(_) => return None,
     };
+    let s = if trim { s.trim() } else { s };
 
     if s.is_empty() {
         None
@@ -162,6 +644,51 @@ pub fn read_file_parse<T: FromStr>(path: &str) -> Option<T> {
     s.as_str().parse().ok()
 }
 
+/// How many extra reads `read_file_parse_stable` makes before giving up.
+const STABLE_READ_RETRIES: usize = 4;
+
+/// Read a counter file until two consecutive reads agree.
+///
+/// Sysfs counters (e.g. net statistics) can be caught mid-update and return
+/// a torn value. This costs a few extra reads, but the result is consistent.
+/// If the value keeps moving, the last read is accepted as long as it didn't
+/// go backwards; a counter that shrinks between reads gives None.
+pub fn read_file_parse_stable<T: FromStr + PartialOrd>(path: &str) -> Option<T> {
+    stable_read(|| read_file_parse(path))
+}
+
+/// `read_file_parse_stable` for a file holding several counters, like a
+/// block device's stat. `parse` turns the contents into a record and
+/// `behind` says whether a read went backwards from the one before.
+pub fn read_file_stable<T: PartialEq>(
+    path: &str,
+    parse: impl Fn(&str) -> Option<T>,
+    behind: impl Fn(&T, &T) -> bool,
+) -> Option<T> {
+    stable_read_by(|| parse(read_file_stack::<256>(path)?.as_str()), behind)
+}
+
+/// Retry logic behind `read_file_parse_stable`, with the reader injectable for tests.
+fn stable_read<T: PartialOrd>(read: impl FnMut() -> Option<T>) -> Option<T> {
+    stable_read_by(read, |next, prev| next < prev)
+}
+
+fn stable_read_by<T: PartialEq>(mut read: impl FnMut() -> Option<T>, behind: impl Fn(&T, &T) -> bool) -> Option<T> {
+    let mut prev = read()?;
+    let mut went_back = false;
+    for _ in 0..STABLE_READ_RETRIES {
+        let next = read()?;
+        if next == prev {
+            return Some(next);
+        }
+        // Any step backwards counts, not just the last one
+        went_back |= behind(&next, &prev);
+        prev = next;
+    }
+    // Still changing: fine for a busy counter, as long as it moved forward
+    if went_back { None } else { Some(prev) }
+}
+
 /// Trait for types that can be parsed from a string with a radix.
 pub trait FromStrRadix: Sized {
     fn from_str_radix(s: &str, radix: u32) -> Option<Self>;
@@ -195,6 +722,22 @@ pub fn read_file_hex<T: FromStrRadix>(path: &str) -> Option<T> {
     parse_hex(s.as_str())
 }
 
+/// Current wall-clock time in seconds since the Unix epoch.
+/// Returns None if the clock is set before 1970 (e.g. a board with no RTC battery).
+pub fn unix_time() -> Option<u64> {
+    let ts = rustix::time::clock_gettime(rustix::time::ClockId::Realtime);
+    u64::try_from(ts.tv_sec).ok()
+}
+
+/// Sleep for `ms` milliseconds (e.g. between two samples of a counter).
+pub fn sleep_ms(ms: u32) {
+    let request = rustix::thread::Timespec {
+        tv_sec: (ms / 1000) as _,
+        tv_nsec: ((ms % 1000) * 1_000_000) as _,
+    };
+    let _ = rustix::thread::nanosleep(&request);
+}
+
 /// Check if a path exists.
 pub fn path_exists(path: &str) -> bool {
     rustix::fs::access(path, rustix::fs::Access::EXISTS).is_ok()
@@ -208,6 +751,14 @@ pub fn is_dir(path: &str) -> bool {
     }
 }
 
+/// Check if a path is a directory, or a symlink to one (sysfs class entries).
+pub fn is_dir_followed(path: &str) -> bool {
+    match rustix::fs::stat(path) {
+        Ok(stat) => rustix::fs::FileType::from_raw_mode(stat.st_mode) == rustix::fs::FileType::Directory,
+        Err(_) => false,
+    }
+}
+
 /// Check if a path is a regular file (not following symlinks).
 pub fn is_file(path: &str) -> bool {
     match rustix::fs::lstat(path) {
@@ -254,6 +805,15 @@ pub fn join_path<const N: usize>(base: &str, name: &str) -> StackString<N> {
 pub fn format_hex_u16(val: u16) -> StackString<16> {
     let mut s = StackString::new();
     s.push_str("0x");
+    s.push_str(format_hex_u16_bare(val).as_str());
+    s
+}
+
+/// Format a u16 as 4 hex digits without a prefix ("10de"), the way
+/// lspci -n and lsusb print IDs.
+#[allow(dead_code)]
+pub fn format_hex_u16_bare(val: u16) -> StackString<16> {
+    let mut s = StackString::new();
     let hi = (val >> 8) as u8;
     let lo = val as u8;
     s.push(hi.hex_hi());
@@ -302,41 +862,403 @@ impl KbToBytes for u64 {
     }
 }
 
-/// Format bytes as human-readable size (e.g., "16G", "512M", "4K").
+/// Divisor and suffixes for human-readable sizes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeUnits {
+    base: u64,
+    /// For base^1 up to base^4
+    suffixes: [&'static str; 4],
+}
+
+impl SizeUnits {
+    /// Powers of 1024, like `free -h` and `ls -h` (the default).
+    pub const BINARY: SizeUnits = SizeUnits { base: 1024, suffixes: ["K", "M", "G", "T"] };
+    /// Powers of 1000, like `df -H` and disk labels (--si).
+    pub const SI: SizeUnits = SizeUnits { base: 1000, suffixes: ["KB", "MB", "GB", "TB"] };
+}
+
+/// Set once from main when --si is given.
+static SI: AtomicBool = AtomicBool::new(false);
+
+/// Use powers of 1000 for human-readable sizes (--si).
+pub fn set_si(enabled: bool) {
+    SI.store(enabled, Ordering::Relaxed);
+}
+
+/// Units for human-readable sizes, as picked on the command line.
+pub fn size_units() -> SizeUnits {
+    if SI.load(Ordering::Relaxed) {
+        SizeUnits::SI
+    } else {
+        SizeUnits::BINARY
+    }
+}
+
+/// Format bytes as human-readable size (e.g., "16G", "512M", "4K", or
+/// "17GB" with --si).
 pub fn format_human_size(bytes: u64) -> StackString<16> {
-    const KI: u64 = 1024;
-    const MI: u64 = 1024 * 1024;
-    const GI: u64 = 1024 * 1024 * 1024;
-    const TI: u64 = 1024 * 1024 * 1024 * 1024;
+    format_human_size_in(bytes, size_units())
+}
 
+/// format_human_size with explicit units. Values are truncated, not rounded.
+pub fn format_human_size_in(bytes: u64, units: SizeUnits) -> StackString<16> {
     let mut s = StackString::new();
     let mut buf = itoa::Buffer::new();
+    let (divisor, suffix) = human_unit(bytes, units);
+    s.push_str(buf.format(bytes / divisor));
+    s.push_str(suffix);
+    s
+}
 
-    if bytes >= TI {
-        s.push_str(buf.format(bytes / TI));
-        s.push('T');
-    } else if bytes >= GI {
-        s.push_str(buf.format(bytes / GI));
-        s.push('G');
-    } else if bytes >= MI {
-        s.push_str(buf.format(bytes / MI));
-        s.push('M');
-    } else if bytes >= KI {
-        s.push_str(buf.format(bytes / KI));
-        s.push('K');
-    } else {
-        s.push_str(buf.format(bytes));
+/// format_human_size with tenths, for values where "1M" hides too much:
+/// "1.2M", still truncated. A ".0" is left off ("512K", "2M").
+pub fn format_human_size_tenths(bytes: u64) -> StackString<16> {
+    format_human_size_tenths_in(bytes, size_units())
+}
+
+/// format_human_size_tenths with explicit units.
+pub fn format_human_size_tenths_in(bytes: u64, units: SizeUnits) -> StackString<16> {
+    let mut s = StackString::new();
+    let mut buf = itoa::Buffer::new();
+    let (divisor, suffix) = human_unit(bytes, units);
+    s.push_str(buf.format(bytes / divisor));
+    let tenths = (bytes % divisor) * 10 / divisor;
+    if tenths > 0 {
+        s.push('.');
+        s.push_str(buf.format(tenths));
     }
+    s.push_str(suffix);
     s
 }
 
+/// Largest unit that fits `bytes`, if any: its size and suffix.
+fn human_unit(bytes: u64, units: SizeUnits) -> (u64, &'static str) {
+    let mut divisor = 1;
+    let mut suffix = "";
+    for unit in units.suffixes {
+        if bytes / divisor < units.base {
+            break;
+        }
+        divisor *= units.base;
+        suffix = unit;
+    }
+    (divisor, suffix)
+}
+
 /// Format a sector count as human-readable size (e.g., "500G", "1T").
 pub fn format_sectors_human(sectors: u64, sector_size: u32) -> StackString<16> {
     let bytes = sectors * sector_size as u64;
     format_human_size(bytes)
 }
 
+/// Most decimal places --precision allows.
+pub const MAX_PRECISION: u8 = 6;
+
+/// Stored when --precision wasn't given.
+const DEFAULT_PRECISION: u8 = u8::MAX;
+
+/// Decimal places for float output, set once at startup (same approach as the debug flag).
+static PRECISION: AtomicU8 = AtomicU8::new(DEFAULT_PRECISION);
+
+/// Set decimal places for float output. None keeps each formatter's default.
+pub fn set_precision(places: Option<u8>) {
+    let stored = places.map_or(DEFAULT_PRECISION, |p| p.min(MAX_PRECISION));
+    PRECISION.store(stored, Ordering::Relaxed);
+}
+
+/// Decimal places to print: the --precision value, or the formatter's `default`.
+pub fn precision_or(default: u8) -> u8 {
+    match PRECISION.load(Ordering::Relaxed) {
+        DEFAULT_PRECISION => default,
+        places => places,
+    }
+}
+
+/// Append `value / 10^scale` with `places` decimals, truncated like the
+/// rest of kv (44500 millidegrees at scale 3, 1 place -> "44.5").
+pub fn push_fixed<const N: usize>(s: &mut StackString<N>, value: i64, scale: u8, places: u8) {
+    let places = places.min(MAX_PRECISION) as u32;
+    let scale = scale.min(MAX_PRECISION) as u32;
+    let abs = value.unsigned_abs();
+    let unit = 10u64.pow(scale);
+    let whole = abs / unit;
+    let frac = if places <= scale {
+        (abs % unit) / 10u64.pow(scale - places)
+    } else {
+        (abs % unit) * 10u64.pow(places - scale)
+    };
+
+    // No "-0.0" for values that truncate to zero
+    if value < 0 && (whole > 0 || frac > 0) {
+        s.push('-');
+    }
+    let mut buf = itoa::Buffer::new();
+    s.push_str(buf.format(whole));
+    if places > 0 {
+        s.push('.');
+        let digits = buf.format(frac);
+        for _ in digits.len()..places as usize {
+            s.push('0');
+        }
+        s.push_str(digits);
+    }
+}
+
+/// Scratch directories for tests that need real files to read, and
+/// made-up ones for those that don't.
+#[cfg(test)]
+pub mod testing {
+    use rustix::fs::{mkdirat, openat, symlinkat, unlinkat, AtFlags, Mode, OFlags, CWD};
+
+    use crate::stack::StackString;
+
+    /// A directory under /tmp, removed with everything in it when dropped -
+    /// also when an assertion fails before the end of the test.
+    pub struct TempDir {
+        path: StackString<64>,
+    }
+
+    impl TempDir {
+        /// Make /tmp/kv-<tag>-test-<nsec>.
+        pub fn new(tag: &str) -> Self {
+            let now = rustix::time::clock_gettime(rustix::time::ClockId::Monotonic);
+            let mut path: StackString<64> = StackString::from_str("/tmp/kv-");
+            path.push_str(tag);
+            path.push_str("-test-");
+            path.push_str(itoa::Buffer::new().format(now.tv_nsec));
+            mkdirat(CWD, path.as_str(), Mode::from_raw_mode(0o755)).unwrap();
+            Self { path }
+        }
+
+        pub fn path(&self) -> &str {
+            self.path.as_str()
+        }
+
+        /// Path of `name` (which may have slashes) inside the directory.
+        pub fn join(&self, name: &str) -> StackString<128> {
+            super::join_path(self.path.as_str(), name)
+        }
+
+        /// Make a subdirectory; its parent must exist.
+        pub fn mkdir(&self, name: &str) -> StackString<128> {
+            let path = self.join(name);
+            mkdirat(CWD, path.as_str(), Mode::from_raw_mode(0o755)).unwrap();
+            path
+        }
+
+        /// Create or replace a file.
+        pub fn write(&self, name: &str, contents: &[u8]) -> StackString<128> {
+            let path = self.join(name);
+            let flags = OFlags::WRONLY | OFlags::CREATE | OFlags::TRUNC;
+            let fd = openat(CWD, path.as_str(), flags, Mode::from_raw_mode(0o644)).unwrap();
+            rustix::io::write(&fd, contents).unwrap();
+            path
+        }
+
+        /// Make `name` a symlink to `target`.
+        pub fn symlink(&self, target: &str, name: &str) -> StackString<128> {
+            let path = self.join(name);
+            symlinkat(target, CWD, path.as_str()).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            remove_tree(self.path.as_str());
+        }
+    }
+
+    /// Made-up files of one sysfs directory, read the way `dir_files` reads
+    /// the real ones.
+    pub fn fixture<'a, const N: usize>(files: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<StackString<N>> + 'a {
+        move |name| files.iter().find(|(n, _)| *n == name).map(|(_, v)| StackString::from_str(v))
+    }
+
+    /// Remove a directory and what's in it, without following symlinks.
+    fn remove_tree(path: &str) {
+        super::for_each_dir_entry(path, |name| {
+            let child: StackString<256> = super::join_path(path, name);
+            if super::is_dir(child.as_str()) {
+                remove_tree(child.as_str());
+            } else {
+                let _ = unlinkat(CWD, child.as_str(), AtFlags::empty());
+            }
+        });
+        let _ = unlinkat(CWD, path, AtFlags::REMOVEDIR);
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // Tests removed for no_std build
+    use super::*;
+
+    /// A read(2) stand-in for a file of `size` bytes of 'x', handing out
+    /// at most `chunk` bytes per call like procfs does a page at a time.
+    fn fake_file(size: usize, chunk: usize) -> impl FnMut(&mut [u8]) -> Option<usize> {
+        let mut left = size;
+        move |buf| {
+            let n = buf.len().min(chunk).min(left);
+            buf[..n].fill(b'x');
+            left -= n;
+            Some(n)
+        }
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        use core::cmp::Ordering::*;
+        assert_eq!(natural_cmp("eth2", "eth10"), Less);
+        assert_eq!(natural_cmp("thermal_zone10", "thermal_zone9"), Greater);
+        assert_eq!(natural_cmp("sda", "sda1"), Less);
+        assert_eq!(natural_cmp("nvme0n1", "nvme0n1"), Equal);
+        assert_eq!(natural_cmp("1-1.2", "1-1.10"), Less);
+        // Same number, different spelling: byte order breaks the tie
+        assert_eq!(natural_cmp("hwmon01", "hwmon1"), Less);
+        assert_eq!(natural_cmp("BAT0", "AC"), Greater);
+    }
+
+    #[test]
+    fn test_read_capped() {
+        let mut buf = [0u8; 64];
+        // Fits, over several reads
+        assert_eq!(read_capped(fake_file(50, 16), &mut buf), Some((50, false)));
+        // Exactly full is not truncated
+        assert_eq!(read_capped(fake_file(64, 16), &mut buf), Some((64, false)));
+        // Oversized: the buffer's worth, and a note that there was more
+        assert_eq!(read_capped(fake_file(10_000, 4096), &mut buf), Some((64, true)));
+        assert_eq!(read_capped(|_| None, &mut buf), None);
+    }
+
+    #[test]
+    fn test_read_capped_global_limit() {
+        // No file is read past MAX_FILE_READ, however big the buffer
+        let mut buf = [0u8; MAX_FILE_READ + 16];
+        assert_eq!(read_capped(fake_file(MAX_FILE_READ * 2, 4096), &mut buf), Some((MAX_FILE_READ, true)));
+    }
+
+    /// Reader that hands out a fixed sequence of values, then None.
+    fn sequence(values: &[u64]) -> impl FnMut() -> Option<u64> + '_ {
+        let mut i = 0;
+        move || {
+            let v = values.get(i).copied();
+            i += 1;
+            v
+        }
+    }
+
+    #[test]
+    fn test_format_human_size_units() {
+        // Truncated, not rounded: 976.56K
+        assert_eq!(format_human_size_in(1_000_000, SizeUnits::BINARY).as_str(), "976K");
+        assert_eq!(format_human_size_in(1_000_000, SizeUnits::SI).as_str(), "1MB");
+        assert_eq!(format_human_size_in(16 << 30, SizeUnits::BINARY).as_str(), "16G");
+        assert_eq!(format_human_size_in(16 << 30, SizeUnits::SI).as_str(), "17GB");
+        assert_eq!(format_human_size_in(2_000_000_000_000, SizeUnits::SI).as_str(), "2TB");
+        assert_eq!(format_human_size_in(999, SizeUnits::SI).as_str(), "999");
+        assert_eq!(format_human_size_in(1023, SizeUnits::BINARY).as_str(), "1023");
+        assert_eq!(format_human_size_in(1024, SizeUnits::BINARY).as_str(), "1K");
+    }
+
+    #[test]
+    fn test_format_human_size_tenths() {
+        // 976.56K, truncated
+        assert_eq!(format_human_size_tenths_in(1_000_000, SizeUnits::BINARY).as_str(), "976.5K");
+        assert_eq!(format_human_size_tenths_in(1_258_496, SizeUnits::BINARY).as_str(), "1.2M");
+        assert_eq!(format_human_size_tenths_in(1_250_000, SizeUnits::SI).as_str(), "1.2MB");
+        assert_eq!(format_human_size_tenths_in(2 << 20, SizeUnits::BINARY).as_str(), "2M");
+        assert_eq!(format_human_size_tenths_in(1023, SizeUnits::BINARY).as_str(), "1023");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes::<512>(b"1500\n").as_str(), "1500\n");
+        assert_eq!(format_bytes::<512>(b"acme,board\0acme,soc\0").as_str(), "acme,board, acme,soc");
+        assert_eq!(format_bytes::<512>(&[0xde, 0xad, 0x00, 0x01]).as_str(), "de ad 00 01");
+        assert!(format_bytes::<512>(&[0xff; 40]).as_str().ends_with("ff..."));
+    }
+
+    #[test]
+    fn test_retry_once() {
+        // Vanished for one read, then back
+        let mut calls = 0;
+        let got = retry_once(|| { calls += 1; (calls > 1).then_some(7) }, || true);
+        assert_eq!(got, Some(7));
+        assert_eq!(calls, 2);
+
+        // Directory really gone: no second read
+        let mut calls = 0;
+        let got = retry_once(|| { calls += 1; None::<u32> }, || false);
+        assert_eq!(got, None);
+        assert_eq!(calls, 1);
+
+        // Still failing: bounded to one retry
+        let mut calls = 0;
+        let got = retry_once(|| { calls += 1; None::<u32> }, || true);
+        assert_eq!(got, None);
+        assert_eq!(calls, 2);
+    }
+
+    fn fixed(value: i64, scale: u8, places: u8) -> StackString<16> {
+        let mut s = StackString::new();
+        push_fixed(&mut s, value, scale, places);
+        s
+    }
+
+    #[test]
+    fn test_push_fixed() {
+        assert_eq!(fixed(44500, 3, 1).as_str(), "44.5");
+        assert_eq!(fixed(44500, 3, 0).as_str(), "44");
+        assert_eq!(fixed(44500, 3, 3).as_str(), "44.500");
+        assert_eq!(fixed(44500, 3, 6).as_str(), "44.500000");
+        assert_eq!(fixed(12_050_000, 6, 2).as_str(), "12.05");
+        assert_eq!(fixed(-1_500_000, 6, 3).as_str(), "-1.500");
+        assert_eq!(fixed(-40, 3, 1).as_str(), "0.0");
+        // Clamped to MAX_PRECISION
+        assert_eq!(fixed(1, 0, 9).as_str(), "1.000000");
+    }
+
+    #[test]
+    fn stable_read_agreeing_reads() {
+        assert_eq!(stable_read(sequence(&[100, 100])), Some(100));
+    }
+
+    #[test]
+    fn stable_read_retries_torn_value() {
+        // Second read is torn (smaller), then it settles
+        assert_eq!(stable_read(sequence(&[5000, 4096, 5000, 5000])), Some(5000));
+    }
+
+    #[test]
+    fn stable_read_busy_counter() {
+        // Never agrees but only moves forward: accept the latest value
+        assert_eq!(stable_read(sequence(&[1, 2, 3, 4, 5, 6])), Some(5));
+    }
+
+    #[test]
+    fn stable_read_gives_up_when_going_backwards() {
+        assert_eq!(stable_read(sequence(&[1, 2, 3, 4, 2])), None);
+        // Going back earlier on is just as bad, even if it recovers
+        assert_eq!(stable_read(sequence(&[1, 3, 2, 4, 5])), None);
+    }
+
+    #[test]
+    fn stable_read_missing_file() {
+        assert_eq!(stable_read(sequence(&[])), None);
+        assert_eq!(stable_read(sequence(&[7])), None);
+    }
+
+    #[test]
+    fn path_recorder_dedups_and_sorts() {
+        let mut rec: PathRecorder<2> = PathRecorder::new();
+        rec.insert("/sys/b");
+        rec.insert("/sys/a");
+        rec.insert("/sys/b");
+        rec.insert("/sys/c");
+        assert_eq!(rec.dropped, 1);
+        let sorted = rec.sorted();
+        assert_eq!(sorted.len(), 2);
+        assert_eq!(sorted[0].as_str(), "/sys/a");
+        assert_eq!(sorted[1].as_str(), "/sys/b");
+    }
 }
@@ -11,8 +11,11 @@
 
 #![allow(dead_code)]
 
-use crate::io::HexNibble;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use crate::io::{self, HexNibble};
 use crate::print;
+use crate::stack::StackString;
 
 /// Escape types for JSON string escaping.
 /// Inspired by serde_json's approach - use a lookup table for speed.
@@ -64,12 +67,265 @@ static ESCAPE_TABLE: [EscapeType; 128] = {
     ]
 };
 
+/// Global --flatten switch, read by every new writer.
+static FLATTEN: AtomicBool = AtomicBool::new(false);
+
+/// Enable flattened JSON output globally. Called once from main after parsing args.
+pub fn set_flatten(enabled: bool) {
+    FLATTEN.store(enabled, Ordering::Relaxed);
+}
+
+/// Widest indent `--indent` accepts, in spaces.
+pub const MAX_INDENT_SPACES: u8 = 8;
+
+/// One level of pretty-print indentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// This many spaces (at most MAX_INDENT_SPACES)
+    Spaces(u8),
+    /// A tab character
+    Tab,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        IndentStyle::Spaces(2)
+    }
+}
+
+impl IndentStyle {
+    /// Parse an `--indent` value: a number of spaces or "tab".
+    pub fn parse(s: &str) -> Option<Self> {
+        if s == "tab" {
+            return Some(IndentStyle::Tab);
+        }
+        let n: u8 = s.parse().ok()?;
+        (n <= MAX_INDENT_SPACES).then_some(IndentStyle::Spaces(n))
+    }
+
+    /// The text printed per nesting level.
+    fn unit(self) -> &'static str {
+        const SPACES: &str = "        ";
+        match self {
+            IndentStyle::Spaces(n) => &SPACES[..(n.min(MAX_INDENT_SPACES) as usize)],
+            IndentStyle::Tab => "\t",
+        }
+    }
+
+    fn to_raw(self) -> u8 {
+        match self {
+            IndentStyle::Spaces(n) => n,
+            IndentStyle::Tab => u8::MAX,
+        }
+    }
+
+    fn from_raw(raw: u8) -> Self {
+        match raw {
+            u8::MAX => IndentStyle::Tab,
+            n => IndentStyle::Spaces(n),
+        }
+    }
+}
+
+/// Global --indent setting, read by every new writer.
+static INDENT: AtomicU8 = AtomicU8::new(2);
+
+/// Set the pretty-print indentation globally. Called once from main after parsing args.
+pub fn set_indent(style: IndentStyle) {
+    INDENT.store(style.to_raw(), Ordering::Relaxed);
+}
+
+/// Where a `--merge` section's envelope goes: into the merged document's
+/// "data", at its indent, after a comma if other sections came before.
+/// Taken from the merged writer with `StreamingJsonWriter::section`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Section {
+    indent_level: usize,
+    comma: bool,
+}
+
+/// How an envelope is written: pretty or compact, and as a document of
+/// its own or as one section of a merged one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub pretty: bool,
+    pub section: Option<Section>,
+}
+
+impl Layout {
+    /// A document of its own.
+    pub fn pretty(pretty: bool) -> Self {
+        Layout { pretty, section: None }
+    }
+}
+
+/// Maximum container nesting tracked when flattening.
+const MAX_FLAT_DEPTH: usize = 16;
+
+/// Maximum length of a flattened key like "data.3.addresses.0".
+const MAX_FLAT_KEY_LEN: usize = 256;
+
+/// One open object or array while flattening.
+#[derive(Clone, Copy, Default)]
+struct FlatFrame {
+    /// Path length when the container was entered (its own key prefix)
+    base_len: usize,
+    /// Arrays number their elements, objects use the keys given
+    is_array: bool,
+    /// Next array index to hand out
+    next_index: usize,
+}
+
+/// Tracks the dotted key path for --flatten.
+///
+/// The writer tells us about keys, array elements and container
+/// boundaries as it goes; we keep the current path in a fixed buffer.
+/// Object keys become `a.b`, array elements become `a.0`, `a.1`, ...
+pub struct FlatPath {
+    path: StackString<MAX_FLAT_KEY_LEN>,
+    frames: [FlatFrame; MAX_FLAT_DEPTH],
+    depth: usize,
+    /// Containers opened beyond MAX_FLAT_DEPTH (flattened into their parent)
+    overflow: usize,
+}
+
+impl FlatPath {
+    pub fn new() -> Self {
+        Self {
+            path: StackString::new(),
+            frames: [FlatFrame::default(); MAX_FLAT_DEPTH],
+            depth: 0,
+            overflow: 0,
+        }
+    }
+
+    /// Current nesting depth (0 = outside any container).
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The key for the value about to be written.
+    pub fn as_str(&self) -> &str {
+        self.path.as_str()
+    }
+
+    /// Replace the last path segment of the innermost frame with `segment`.
+    fn set_segment(&mut self, segment: &str) {
+        let base = if self.depth > 0 { self.frames[self.depth - 1].base_len } else { 0 };
+        self.path.truncate(base);
+        if base > 0 {
+            self.path.push('.');
+        }
+        self.path.push_str(segment);
+    }
+
+    /// An object key was written.
+    pub fn key(&mut self, name: &str) {
+        self.set_segment(name);
+    }
+
+    /// A value or container is about to be written. Inside an array this
+    /// picks the next index; inside an object the key is already set.
+    pub fn element(&mut self) {
+        if self.depth == 0 || self.overflow > 0 {
+            return;
+        }
+        let frame = &mut self.frames[self.depth - 1];
+        if frame.is_array {
+            let index = frame.next_index;
+            frame.next_index += 1;
+            let mut buf = itoa::Buffer::new();
+            self.set_segment(buf.format(index));
+        }
+    }
+
+    /// Open an object or array at the current path.
+    pub fn enter(&mut self, is_array: bool) {
+        self.element();
+        if self.depth >= MAX_FLAT_DEPTH {
+            self.overflow += 1;
+            return;
+        }
+        self.frames[self.depth] = FlatFrame {
+            base_len: self.path.len(),
+            is_array,
+            next_index: 0,
+        };
+        self.depth += 1;
+    }
+
+    /// Close the innermost object or array.
+    pub fn leave(&mut self) {
+        if self.overflow > 0 {
+            self.overflow -= 1;
+            return;
+        }
+        if self.depth > 0 {
+            self.depth -= 1;
+            self.path.truncate(self.frames[self.depth].base_len);
+        }
+    }
+}
+
+/// Deepest nesting the debug-build balance check remembers. Containers
+/// below that are counted but their kind isn't checked.
+const MAX_CHECKED_DEPTH: usize = 32;
+
+/// Containers currently open, so debug builds can catch an `end_*` call
+/// without a matching begin (or closing an array as an object). Release
+/// builds don't have this at all.
+#[cfg(debug_assertions)]
+struct Nesting {
+    /// true for an array, false for an object
+    is_array: [bool; MAX_CHECKED_DEPTH],
+    depth: usize,
+}
+
+#[cfg(debug_assertions)]
+impl Nesting {
+    const fn new() -> Self {
+        Nesting { is_array: [false; MAX_CHECKED_DEPTH], depth: 0 }
+    }
+
+    fn push(&mut self, is_array: bool) {
+        if self.depth < MAX_CHECKED_DEPTH {
+            self.is_array[self.depth] = is_array;
+        }
+        self.depth += 1;
+    }
+
+    fn pop(&mut self, is_array: bool) {
+        let what = if is_array { "array" } else { "object" };
+        debug_assert!(self.depth > 0, "JSON: closing an {what} with nothing open");
+        self.depth -= 1;
+        if self.depth < MAX_CHECKED_DEPTH {
+            debug_assert!(self.is_array[self.depth] == is_array, "JSON: closing an {what} that wasn't the innermost container");
+        }
+    }
+}
+
 /// JSON writer that streams directly to stdout.
 /// This avoids heap allocation by writing output immediately.
+///
+/// With --flatten, nested containers are not printed; instead every
+/// scalar becomes a field of the outermost object, keyed by its dotted
+/// path (`data.mem_total_kb`, `data.2.name`). Empty containers vanish.
 pub struct StreamingJsonWriter {
     pretty: bool,
+    indent: IndentStyle,
     indent_level: usize,
     needs_comma: bool,
+    flat: Option<FlatPath>,
+    /// Containers open; inside the envelope's "data" it's 2 or more
+    depth: usize,
+    /// Started by begin_kv_output_streaming: closing it adds "error"
+    envelope: bool,
+    /// The envelope already has an "error" field
+    has_error: bool,
+    /// Writing one section of a merged document (see `section`)
+    section: bool,
+    #[cfg(debug_assertions)]
+    nesting: Nesting,
 }
 
 impl StreamingJsonWriter {
@@ -77,20 +333,130 @@ impl StreamingJsonWriter {
     pub fn new(pretty: bool) -> Self {
         Self {
             pretty,
+            indent: IndentStyle::from_raw(INDENT.load(Ordering::Relaxed)),
             indent_level: 0,
             needs_comma: false,
+            flat: if FLATTEN.load(Ordering::Relaxed) { Some(FlatPath::new()) } else { None },
+            depth: 0,
+            envelope: false,
+            has_error: false,
+            section: false,
+            #[cfg(debug_assertions)]
+            nesting: Nesting::new(),
+        }
+    }
+
+    /// Note a container being opened (and check nesting in debug builds).
+    #[inline]
+    fn opened(&mut self, is_array: bool) {
+        self.depth += 1;
+        #[cfg(debug_assertions)]
+        self.nesting.push(is_array);
+        #[cfg(not(debug_assertions))]
+        let _ = is_array;
+    }
+
+    /// Note a container being closed; panics in debug builds if it wasn't open.
+    #[inline]
+    fn closed(&mut self, is_array: bool) {
+        self.depth = self.depth.saturating_sub(1);
+        #[cfg(debug_assertions)]
+        self.nesting.pop(is_array);
+        #[cfg(not(debug_assertions))]
+        let _ = is_array;
+    }
+
+    /// In flatten mode, open a nested container without printing it.
+    /// Returns false for the outermost container, which is printed as `{`.
+    fn flat_enter(&mut self, is_array: bool) -> bool {
+        match self.flat {
+            Some(ref mut flat) if flat.depth() > 0 => {
+                flat.enter(is_array);
+                true
+            }
+            Some(ref mut flat) => {
+                flat.enter(false);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// In flatten mode, close a nested container without printing it.
+    /// Returns false when the outermost container closes.
+    fn flat_leave(&mut self) -> bool {
+        match self.flat {
+            Some(ref mut flat) => {
+                flat.leave();
+                flat.depth() > 0
+            }
+            None => false,
         }
     }
 
-    /// Finish writing (just outputs a newline).
+    /// In flatten mode, print the dotted key for the value that follows.
+    fn flat_value_key(&mut self) {
+        if self.flat.is_none() {
+            return;
+        }
+        self.write_separator();
+        self.write_indent();
+        if let Some(ref mut flat) = self.flat {
+            flat.element();
+            print::print("\"");
+            print_escaped(flat.as_str());
+            print::print("\":");
+        }
+        if self.pretty {
+            print::print(" ");
+        }
+    }
+
+    /// Finish writing (just outputs a newline). A section of a merged
+    /// document leaves the line to the writer it was written into.
     pub fn finish(self) {
-        print::println_empty();
+        if !self.section {
+            print::println_empty();
+        }
+    }
+
+    /// Where an envelope written next goes if it's to be a member of this
+    /// writer's open object, named after its subcommand (`kv --merge`).
+    /// Pass it in the `Layout` of the subcommand that writes it.
+    pub fn section(&self) -> Section {
+        Section { indent_level: self.indent_level, comma: self.needs_comma }
+    }
+
+    /// Note that a section was written into the open object, so what
+    /// follows needs a comma.
+    pub fn section_written(&mut self) {
+        self.needs_comma = true;
+    }
+
+    /// A writer that continues inside an open section, at its indent and
+    /// (flattened) under its "data." path.
+    fn for_section(pretty: bool, section: Section) -> Self {
+        let mut w = Self::new(pretty);
+        w.section = true;
+        w.indent_level = section.indent_level;
+        w.needs_comma = section.comma;
+        if let Some(ref mut flat) = w.flat {
+            flat.enter(false);
+            flat.key("data");
+            flat.enter(false);
+        }
+        w
+    }
+
+    /// What one indent level prints as; None in compact mode.
+    fn indent_unit(&self) -> Option<&'static str> {
+        self.pretty.then(|| self.indent.unit())
     }
 
     fn write_indent(&mut self) {
-        if self.pretty {
+        if let Some(unit) = self.indent_unit() {
             for _ in 0..self.indent_level {
-                print::print("  ");
+                print::print(unit);
             }
         }
     }
@@ -111,6 +477,10 @@ impl StreamingJsonWriter {
 
     /// Begin a JSON object `{`.
     pub fn begin_object(&mut self) {
+        self.opened(false);
+        if self.flat_enter(false) {
+            return;
+        }
         self.write_separator();
         self.write_indent();
         print::print("{");
@@ -120,7 +490,18 @@ impl StreamingJsonWriter {
     }
 
     /// End a JSON object `}`.
+    ///
+    /// Closing a kv envelope that has no "error" field writes
+    /// `"error": null`, so every subcommand's output has one.
     pub fn end_object(&mut self) {
+        if self.envelope && self.depth == 1 && !self.has_error {
+            self.key("error");
+            self.value_null();
+        }
+        self.closed(false);
+        if self.flat_leave() {
+            return;
+        }
         self.write_newline();
         self.indent_level -= 1;
         self.write_indent();
@@ -130,9 +511,13 @@ impl StreamingJsonWriter {
 
     /// Begin a JSON array `[`.
     pub fn begin_array(&mut self) {
+        self.opened(true);
+        if self.flat_enter(true) {
+            return;
+        }
         self.write_separator();
         self.write_indent();
-        print::print("[");
+        print::print(if self.flat.is_some() { "{" } else { "[" });
         self.write_newline();
         self.indent_level += 1;
         self.needs_comma = false;
@@ -140,15 +525,26 @@ impl StreamingJsonWriter {
 
     /// End a JSON array `]`.
     pub fn end_array(&mut self) {
+        self.closed(true);
+        if self.flat_leave() {
+            return;
+        }
         self.write_newline();
         self.indent_level -= 1;
         self.write_indent();
-        print::print("]");
+        print::print(if self.flat.is_some() { "}" } else { "]" });
         self.needs_comma = true;
     }
 
     /// Write an object key.
     pub fn key(&mut self, name: &str) {
+        if self.depth == 1 && name == "error" {
+            self.has_error = true;
+        }
+        if let Some(ref mut flat) = self.flat {
+            flat.key(name);
+            return;
+        }
         self.write_separator();
         self.write_indent();
         print::print("\"");
@@ -162,6 +558,7 @@ impl StreamingJsonWriter {
 
     /// Write a string value.
     pub fn value_string(&mut self, value: &str) {
+        self.flat_value_key();
         print::print("\"");
         print_escaped(value);
         print::print("\"");
@@ -170,6 +567,7 @@ impl StreamingJsonWriter {
 
     /// Write an unsigned integer value.
     pub fn value_u64(&mut self, value: u64) {
+        self.flat_value_key();
         let mut buf = itoa::Buffer::new();
         print::print(buf.format(value));
         self.needs_comma = true;
@@ -177,6 +575,7 @@ impl StreamingJsonWriter {
 
     /// Write a signed integer value.
     pub fn value_i64(&mut self, value: i64) {
+        self.flat_value_key();
         let mut buf = itoa::Buffer::new();
         print::print(buf.format(value));
         self.needs_comma = true;
@@ -184,12 +583,21 @@ impl StreamingJsonWriter {
 
     /// Write a boolean value.
     pub fn value_bool(&mut self, value: bool) {
+        self.flat_value_key();
         print::print(if value { "true" } else { "false" });
         self.needs_comma = true;
     }
 
+    /// Write an already-formatted number (e.g. "12.50") as-is.
+    pub fn value_number(&mut self, digits: &str) {
+        self.flat_value_key();
+        print::print(digits);
+        self.needs_comma = true;
+    }
+
     /// Write a null value.
     pub fn value_null(&mut self) {
+        self.flat_value_key();
         print::print("null");
         self.needs_comma = true;
     }
@@ -226,6 +634,12 @@ impl StreamingJsonWriter {
         self.value_i64(value);
     }
 
+    /// Write a key-value pair with an already-formatted numeric value.
+    pub fn field_number(&mut self, key: &str, digits: &str) {
+        self.key(key);
+        self.value_number(digits);
+    }
+
     /// Write a key-value pair with a boolean value.
     pub fn field_bool(&mut self, key: &str, value: bool) {
         self.key(key);
@@ -235,6 +649,10 @@ impl StreamingJsonWriter {
     /// Begin an object value for a key.
     pub fn field_object(&mut self, key: &str) {
         self.key(key);
+        self.opened(false);
+        if self.flat_enter(false) {
+            return;
+        }
         self.needs_comma = false;
         print::print("{");
         self.write_newline();
@@ -243,6 +661,10 @@ impl StreamingJsonWriter {
 
     /// End an object that was started with field_object.
     pub fn end_field_object(&mut self) {
+        self.closed(false);
+        if self.flat_leave() {
+            return;
+        }
         self.write_newline();
         self.indent_level -= 1;
         self.write_indent();
@@ -253,6 +675,10 @@ impl StreamingJsonWriter {
     /// Begin an array value for a key.
     pub fn field_array(&mut self, key: &str) {
         self.key(key);
+        self.opened(true);
+        if self.flat_enter(true) {
+            return;
+        }
         self.needs_comma = false;
         print::print("[");
         self.write_newline();
@@ -261,6 +687,10 @@ impl StreamingJsonWriter {
 
     /// End an array that was started with field_array.
     pub fn end_field_array(&mut self) {
+        self.closed(true);
+        if self.flat_leave() {
+            return;
+        }
         self.write_newline();
         self.indent_level -= 1;
         self.write_indent();
@@ -270,6 +700,10 @@ impl StreamingJsonWriter {
 
     /// Write an array element that's a string.
     pub fn array_string(&mut self, value: &str) {
+        if self.flat.is_some() {
+            self.value_string(value);
+            return;
+        }
         self.write_separator();
         self.write_indent();
         print::print("\"");
@@ -278,8 +712,20 @@ impl StreamingJsonWriter {
         self.needs_comma = true;
     }
 
+    /// Begin an array element that's one record of the output (a device,
+    /// an interface): counts as data for --probe. Objects nested in a
+    /// record use `array_object_begin`.
+    pub fn record_begin(&mut self) {
+        print::note_data();
+        self.array_object_begin();
+    }
+
     /// Begin an array element that's an object.
     pub fn array_object_begin(&mut self) {
+        self.opened(false);
+        if self.flat_enter(false) {
+            return;
+        }
         self.write_separator();
         self.write_indent();
         print::print("{");
@@ -290,6 +736,10 @@ impl StreamingJsonWriter {
 
     /// End an array element that's an object.
     pub fn array_object_end(&mut self) {
+        self.closed(false);
+        if self.flat_leave() {
+            return;
+        }
         self.write_newline();
         self.indent_level -= 1;
         self.write_indent();
@@ -324,11 +774,369 @@ fn print_escaped(s: &str) {
     }
 }
 
+/// Where the kernel keeps the hostname (same as uname's nodename).
+pub const HOSTNAME_PATH: &str = "/proc/sys/kernel/hostname";
+
 /// Helper to create the standard kv JSON envelope (streaming version).
-pub fn begin_kv_output_streaming(pretty: bool, subcommand: &str) -> StreamingJsonWriter {
-    let mut w = StreamingJsonWriter::new(pretty);
-    w.begin_object();
+///
+/// Besides the version and subcommand, the envelope carries a wall-clock
+/// timestamp and the hostname so archived output says where and when it
+/// was captured. Both are best-effort and left out if unavailable.
+///
+/// The envelope always ends with "error": a message when the subcommand
+/// couldn't read anything (with `"data": null`), null otherwise.
+pub fn begin_kv_output_streaming(layout: Layout, subcommand: &str) -> StreamingJsonWriter {
+    let mut w = match layout.section {
+        Some(section) => {
+            let mut w = StreamingJsonWriter::for_section(layout.pretty, section);
+            w.field_object(subcommand);
+            w
+        }
+        None => {
+            let mut w = StreamingJsonWriter::new(layout.pretty);
+            w.begin_object();
+            w
+        }
+    };
+    w.envelope = true;
     w.field_str("kv_version", env!("CARGO_PKG_VERSION"));
     w.field_str("subcommand", subcommand);
+    if let Some(ts) = io::unix_time() {
+        w.field_u64("timestamp_unix", ts);
+    }
+    if let Some(host) = io::read_file_stack::<72>(HOSTNAME_PATH) {
+        w.field_str("hostname", host.as_str());
+    }
     w
 }
+
+// =============================================================================
+// Reading (files kv wrote earlier, e.g. --baseline)
+// =============================================================================
+
+/// A borrowed JSON value inside a larger document.
+///
+/// This is a minimal, allocation-free reader: members and array items are
+/// found by scanning the text on demand instead of building a tree. It's
+/// meant for reading back kv's own output, not for validating arbitrary JSON.
+#[derive(Clone, Copy, Debug)]
+pub struct JsonRef<'a> {
+    text: &'a str,
+}
+
+fn skip_ws(b: &[u8], mut i: usize) -> usize {
+    while i < b.len() && b[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Index just past the string starting at `i` (which must be a quote).
+fn string_end(b: &[u8], i: usize) -> Option<usize> {
+    if b.get(i) != Some(&b'"') {
+        return None;
+    }
+    let mut j = i + 1;
+    while j < b.len() {
+        match b[j] {
+            b'\\' => j += 2,
+            b'"' => return Some(j + 1),
+            _ => j += 1,
+        }
+    }
+    None
+}
+
+/// Index just past the value starting at `i`.
+fn value_end(b: &[u8], i: usize) -> Option<usize> {
+    match *b.get(i)? {
+        b'"' => string_end(b, i),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut j = i;
+            while j < b.len() {
+                match b[j] {
+                    b'"' => {
+                        j = string_end(b, j)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                j += 1;
+            }
+            None
+        }
+        _ => {
+            // Number or literal: runs until a delimiter
+            let mut j = i;
+            while j < b.len() && !matches!(b[j], b',' | b'}' | b']') && !b[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if j == i { None } else { Some(j) }
+        }
+    }
+}
+
+impl<'a> JsonRef<'a> {
+    /// Wrap a whole document. Returns None if it isn't a single value.
+    pub fn parse(text: &'a str) -> Option<Self> {
+        let b = text.as_bytes();
+        let start = skip_ws(b, 0);
+        let end = value_end(b, start)?;
+        if skip_ws(b, end) != b.len() {
+            return None;
+        }
+        Some(JsonRef { text: &text[start..end] })
+    }
+
+    /// Look up an object member. Keys are compared without unescaping.
+    pub fn get(&self, key: &str) -> Option<JsonRef<'a>> {
+        if !self.text.starts_with('{') {
+            return None;
+        }
+        self.entries().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Iterate the items of an array (nothing for other values).
+    pub fn items(&self) -> impl Iterator<Item = JsonRef<'a>> {
+        let is_array = self.text.starts_with('[');
+        self.entries().filter(move |_| is_array).map(|(_, v)| v)
+    }
+
+    /// The value as an unsigned integer, if it is one.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.text.parse().ok()
+    }
+
+    /// The value as a string. Strings with escapes aren't supported (None).
+    pub fn as_str(&self) -> Option<&'a str> {
+        let inner = self.text.strip_prefix('"')?.strip_suffix('"')?;
+        if inner.contains('\\') { None } else { Some(inner) }
+    }
+
+    /// Members of an object or items of an array, as (raw key, value).
+    /// Array items have an empty key.
+    fn entries(&self) -> JsonEntries<'a> {
+        JsonEntries {
+            text: self.text,
+            pos: 1,
+            object: self.text.starts_with('{'),
+        }
+    }
+}
+
+/// Iterator over the contents of an object or array.
+struct JsonEntries<'a> {
+    text: &'a str,
+    pos: usize,
+    object: bool,
+}
+
+impl<'a> Iterator for JsonEntries<'a> {
+    type Item = (&'a str, JsonRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let b = self.text.as_bytes();
+        let mut i = skip_ws(b, self.pos);
+        if b.get(i) == Some(&b',') {
+            i = skip_ws(b, i + 1);
+        }
+        if i >= b.len() || matches!(b[i], b'}' | b']') {
+            self.pos = b.len();
+            return None;
+        }
+
+        let key = if self.object {
+            let end = string_end(b, i)?;
+            let key = &self.text[i + 1..end - 1];
+            i = skip_ws(b, end);
+            if b.get(i) != Some(&b':') {
+                return None;
+            }
+            i = skip_ws(b, i + 1);
+            key
+        } else {
+            ""
+        };
+
+        let end = value_end(b, i)?;
+        self.pos = end;
+        Some((key, JsonRef { text: &self.text[i..end] }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writer(pretty: bool, indent: IndentStyle) -> StreamingJsonWriter {
+        let mut w = StreamingJsonWriter::new(pretty);
+        w.indent = indent;
+        w
+    }
+
+    #[test]
+    fn test_indent_style() {
+        assert_eq!(IndentStyle::parse("4"), Some(IndentStyle::Spaces(4)));
+        assert_eq!(IndentStyle::parse("tab"), Some(IndentStyle::Tab));
+        assert_eq!(IndentStyle::parse("9"), None);
+        assert_eq!(IndentStyle::parse("two"), None);
+        assert_eq!(IndentStyle::from_raw(IndentStyle::Tab.to_raw()), IndentStyle::Tab);
+
+        assert_eq!(writer(true, IndentStyle::default()).indent_unit(), Some("  "));
+        assert_eq!(writer(true, IndentStyle::Spaces(4)).indent_unit(), Some("    "));
+        assert_eq!(writer(true, IndentStyle::Tab).indent_unit(), Some("\t"));
+        // Compact output has no indentation, whatever was asked for
+        assert_eq!(writer(false, IndentStyle::Spaces(4)).indent_unit(), None);
+        assert_eq!(writer(false, IndentStyle::Tab).indent_unit(), None);
+    }
+
+    /// Walk {"kv_version", "data": {"mem_total_kb", "net": [{"name", "addrs": ["a", "b"]}, {"name"}]}}
+    /// the same way the writer does and collect the keys scalars end up under.
+    #[test]
+    fn test_flat_path_nested_sample() {
+        let mut p = FlatPath::new();
+        let mut keys: [StackString<64>; 8] = Default::default();
+        let mut n = 0;
+        let mut value = |p: &mut FlatPath| {
+            p.element();
+            keys[n] = StackString::from_str(p.as_str());
+            n += 1;
+        };
+
+        p.enter(false);
+        p.key("kv_version");
+        value(&mut p);
+        p.key("data");
+        p.enter(false);
+        p.key("mem_total_kb");
+        value(&mut p);
+        p.key("net");
+        p.enter(true);
+        for _ in 0..2 {
+            p.enter(false);
+            p.key("name");
+            value(&mut p);
+            p.leave();
+        }
+        p.enter(false);
+        p.key("addrs");
+        p.enter(true);
+        value(&mut p);
+        value(&mut p);
+        p.leave();
+        p.leave();
+        p.leave();
+        p.key("swap_total_kb");
+        value(&mut p);
+        p.leave();
+        p.leave();
+        assert_eq!(p.depth(), 0);
+
+        let expected = [
+            "kv_version",
+            "data.mem_total_kb",
+            "data.net.0.name",
+            "data.net.1.name",
+            "data.net.2.addrs.0",
+            "data.net.2.addrs.1",
+            "data.swap_total_kb",
+        ];
+        assert_eq!(n, expected.len());
+        for (got, want) in keys.iter().zip(expected) {
+            assert_eq!(got.as_str(), want);
+        }
+    }
+
+    #[test]
+    fn test_flat_path_too_deep() {
+        let mut p = FlatPath::new();
+        p.enter(false);
+        p.key("a");
+        for _ in 0..MAX_FLAT_DEPTH + 4 {
+            p.enter(false);
+        }
+        for _ in 0..MAX_FLAT_DEPTH + 4 {
+            p.leave();
+        }
+        p.key("b");
+        p.element();
+        assert_eq!(p.as_str(), "b");
+        p.leave();
+        assert_eq!(p.depth(), 0);
+    }
+
+    #[test]
+    fn test_balanced_nesting() {
+        let mut w = StreamingJsonWriter::new(false);
+        w.begin_object();
+        w.field_object("data");
+        w.field_array("items");
+        w.array_object_begin();
+        w.field_u64("n", 1);
+        w.array_object_end();
+        w.end_field_array();
+        w.end_field_object();
+        w.end_object();
+        #[cfg(debug_assertions)]
+        assert_eq!(w.nesting.depth, 0);
+        w.finish();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "nothing open")]
+    fn test_unbalanced_end_object() {
+        let mut w = StreamingJsonWriter::new(false);
+        w.begin_object();
+        w.end_object();
+        w.end_object();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "innermost")]
+    fn test_mismatched_end() {
+        let mut w = StreamingJsonWriter::new(false);
+        w.begin_object();
+        w.field_array("items");
+        w.end_field_object();
+    }
+
+    #[test]
+    fn test_json_ref_lookup() {
+        let doc = r#"{"kv_version":"0.1","data":[
+            {"name":"eth0","rx_bytes":100,"ipv4":["10.0.0.1"]},
+            {"name":"lo","nested":{"s":"}]","n":[1,2]},"rx_bytes":7}
+        ],"note":"a \"q\""}"#;
+        let root = JsonRef::parse(doc).unwrap();
+        assert_eq!(root.get("kv_version").and_then(|v| v.as_str()), Some("0.1"));
+        assert!(root.get("note").unwrap().as_str().is_none());
+        assert!(root.get("missing").is_none());
+
+        let data = root.get("data").unwrap();
+        assert_eq!(data.items().count(), 2);
+        let lo = data.items().find(|i| i.get("name").and_then(|n| n.as_str()) == Some("lo")).unwrap();
+        assert_eq!(lo.get("rx_bytes").and_then(|v| v.as_u64()), Some(7));
+        assert_eq!(lo.get("nested").unwrap().get("n").unwrap().items().count(), 2);
+
+        // get() on an array and items() on an object find nothing
+        assert!(data.get("name").is_none());
+        assert_eq!(root.items().count(), 0);
+    }
+
+    #[test]
+    fn test_json_ref_rejects_garbage() {
+        assert!(JsonRef::parse("").is_none());
+        assert!(JsonRef::parse("{\"a\":1").is_none());
+        assert!(JsonRef::parse("{} trailing").is_none());
+        assert_eq!(JsonRef::parse(" {} ").unwrap().get("a").map(|v| v.text), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! Kernel release, build info and command line (`kv kernel`).
+//!
+//! The three things a support ticket always asks for: which kernel, how it
+//! was built, and what it was booted with. Reads /proc/sys/kernel/osrelease,
+//! /proc/version and /proc/cmdline.
+//!
+//! /proc/version looks like
+//!
+//! ```text
+//! Linux version 6.8.0-45-generic (buildd@lcy02-amd64-075) (x86_64-linux-gnu-gcc-13
+//! (Ubuntu 13.2.0-23ubuntu4) 13.2.0, GNU ld (GNU Binutils for Ubuntu) 2.42)
+//! #45-Ubuntu SMP PREEMPT_DYNAMIC Fri Aug 30 12:02:04 UTC 2024
+//! ```
+//!
+//! (on one line). Older kernels leave out the linker, and some builders
+//! leave out the user@host part, so every piece but the release is optional.
+
+#![allow(dead_code)]
+
+use crate::cli::GlobalOptions;
+use crate::fields::kernel as f;
+use crate::io;
+use crate::json::{begin_kv_output_streaming, Layout};
+use crate::print::{self, TextWriter};
+use crate::stack::StackString;
+
+const OSRELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+const VERSION_PATH: &str = "/proc/version";
+const CMDLINE_PATH: &str = "/proc/cmdline";
+
+/// The build date starts at the weekday, e.g. "Fri Aug 30 12:02:04 UTC 2024".
+const WEEKDAYS: &[&str] = &["Mon ", "Tue ", "Wed ", "Thu ", "Fri ", "Sat ", "Sun "];
+
+/// The pieces of a /proc/version line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct KernelVersion<'a> {
+    /// "6.8.0-45-generic"
+    pub release: &'a str,
+    /// Who built it, "buildd@lcy02-amd64-075"
+    pub builder: Option<&'a str>,
+    /// Compiler, without the linker: "x86_64-linux-gnu-gcc-13 (Ubuntu 13.2.0-23ubuntu4) 13.2.0"
+    pub compiler: Option<&'a str>,
+    /// "Fri Aug 30 12:02:04 UTC 2024"
+    pub build_date: Option<&'a str>,
+}
+
+/// Split off a leading "(...)" group, allowing nested parentheses.
+/// Returns the text inside and what follows.
+fn take_group(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start().strip_prefix('(')?;
+    let mut depth = 1;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&s[..i], &s[i + 1..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The compiler part of "gcc (...) 13.2.0, GNU ld (...) 2.42".
+fn strip_linker(group: &str) -> &str {
+    let mut depth = 0;
+    for (i, c) in group.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => return group[..i].trim_end(),
+            _ => {}
+        }
+    }
+    group.trim()
+}
+
+/// Parse the contents of /proc/version.
+pub fn parse_version(content: &str) -> Option<KernelVersion<'_>> {
+    let rest = content.trim().strip_prefix("Linux version ")?;
+    let (release, mut rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    if release.is_empty() {
+        return None;
+    }
+
+    let mut builder = None;
+    let mut compiler = None;
+    while let Some((group, tail)) = take_group(rest) {
+        // user@host comes first when present, then the toolchain
+        if builder.is_none() && compiler.is_none() && group.contains('@') && !group.contains(' ') {
+            builder = Some(group);
+        } else if compiler.is_none() {
+            compiler = Some(strip_linker(group));
+        }
+        rest = tail;
+    }
+
+    let build_date = rest
+        .match_indices(' ')
+        .map(|(i, _)| i + 1)
+        .find(|&i| rest.get(i..i + 4).is_some_and(|day| WEEKDAYS.contains(&day)))
+        .map(|i| rest[i..].trim_end());
+
+    Some(KernelVersion { release, builder, compiler, build_date })
+}
+
+/// Split a kernel command line into parameters.
+///
+/// Spaces inside double quotes don't split, as the kernel's own parser
+/// does: `dyndbg="file foo.c +p"` is one parameter.
+pub fn cmdline_tokens(cmdline: &str) -> impl Iterator<Item = &str> {
+    let mut rest = cmdline;
+    core::iter::from_fn(move || {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let mut quoted = false;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c.is_whitespace() && !quoted
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        let (token, tail) = rest.split_at(end);
+        rest = tail;
+        Some(token)
+    })
+}
+
+/// Raw contents of the three files. Any of them may be missing.
+struct KernelInfo {
+    osrelease: Option<StackString<128>>,
+    version: Option<StackString<512>>,
+    cmdline: Option<StackString<4096>>,
+}
+
+impl KernelInfo {
+    fn read() -> Self {
+        KernelInfo {
+            osrelease: io::read_file_stack(OSRELEASE_PATH),
+            version: io::read_file_stack(VERSION_PATH),
+            cmdline: io::read_file_stack(CMDLINE_PATH),
+        }
+    }
+
+    fn parsed(&self) -> Option<KernelVersion<'_>> {
+        self.version.as_ref().and_then(|v| parse_version(v.as_str()))
+    }
+
+    /// osrelease, or the release from /proc/version.
+    fn release(&self) -> Option<&str> {
+        match self.osrelease {
+            Some(ref r) => Some(r.as_str()),
+            None => self.parsed().map(|v| v.release),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.osrelease.is_none() && self.version.is_none() && self.cmdline.is_none()
+    }
+
+    fn print_text(&self, verbose: bool) {
+        let parsed = self.parsed();
+        let mut w = TextWriter::new();
+        w.field_str_opt(f::RELEASE, self.release());
+        w.field_quoted_opt(f::COMPILER, parsed.as_ref().and_then(|v| v.compiler));
+        w.field_quoted_opt(f::BUILD_DATE, parsed.as_ref().and_then(|v| v.build_date));
+        if verbose {
+            w.field_str_opt(f::BUILDER, parsed.as_ref().and_then(|v| v.builder));
+        }
+        w.finish_record();
+
+        if let Some(ref cmdline) = self.cmdline {
+            let mut w = TextWriter::new();
+            w.field_quoted(f::CMDLINE, cmdline.as_str());
+            w.finish_record();
+        }
+        match self.version {
+            Some(ref version) if verbose => {
+                let mut w = TextWriter::new();
+                w.field_quoted(f::VERSION_RAW, version.as_str());
+                w.finish_record();
+            }
+            _ => {}
+        }
+    }
+
+    fn print_json(&self, layout: Layout, verbose: bool) {
+        let parsed = self.parsed();
+        let mut w = begin_kv_output_streaming(layout, "kernel");
+        print::note_data();
+        w.field_object("data");
+        w.field_str_opt(f::RELEASE, self.release());
+        w.field_str_opt(f::COMPILER, parsed.as_ref().and_then(|v| v.compiler));
+        w.field_str_opt(f::BUILD_DATE, parsed.as_ref().and_then(|v| v.build_date));
+        if verbose {
+            w.field_str_opt(f::BUILDER, parsed.as_ref().and_then(|v| v.builder));
+        }
+        w.field_str_opt(f::VERSION_RAW, self.version.as_ref().map(|s| s.as_str()));
+        if let Some(ref cmdline) = self.cmdline {
+            w.field_array(f::CMDLINE);
+            for token in cmdline_tokens(cmdline.as_str()) {
+                w.array_string(token);
+            }
+            w.end_field_array();
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    }
+}
+
+/// Entry point for `kv kernel`.
+pub fn run(opts: &GlobalOptions) -> i32 {
+    let info = KernelInfo::read();
+    if info.is_empty() {
+        if opts.json {
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "kernel");
+            w.key("data");
+            w.value_null();
+            w.field_str("error", "cannot read /proc/version");
+            w.end_object();
+            w.finish();
+        } else {
+            print::print("kernel: cannot read ");
+            print::println(VERSION_PATH);
+        }
+        return 0;
+    }
+
+    if opts.json {
+        info.print_json(opts.json_layout(), opts.verbose);
+    } else {
+        info.print_text(opts.verbose);
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UBUNTU: &str = "Linux version 6.8.0-45-generic (buildd@lcy02-amd64-075) \
+        (x86_64-linux-gnu-gcc-13 (Ubuntu 13.2.0-23ubuntu4) 13.2.0, GNU ld (GNU Binutils for Ubuntu) 2.42) \
+        #45-Ubuntu SMP PREEMPT_DYNAMIC Fri Aug 30 12:02:04 UTC 2024\n";
+
+    #[test]
+    fn test_parse_version() {
+        let v = parse_version(UBUNTU).unwrap();
+        assert_eq!(v.release, "6.8.0-45-generic");
+        assert_eq!(v.builder, Some("buildd@lcy02-amd64-075"));
+        assert_eq!(v.compiler, Some("x86_64-linux-gnu-gcc-13 (Ubuntu 13.2.0-23ubuntu4) 13.2.0"));
+        assert_eq!(v.build_date, Some("Fri Aug 30 12:02:04 UTC 2024"));
+    }
+
+    #[test]
+    fn test_parse_version_minimal() {
+        // Embedded build: no user@host, old-style compiler string, no linker
+        let v = parse_version("Linux version 5.10.0 (gcc version 9.2.0 (GCC)) #1 PREEMPT Tue Jan 5 10:00:00 CET 2021").unwrap();
+        assert_eq!(v.release, "5.10.0");
+        assert_eq!(v.builder, None);
+        assert_eq!(v.compiler, Some("gcc version 9.2.0 (GCC)"));
+        assert_eq!(v.build_date, Some("Tue Jan 5 10:00:00 CET 2021"));
+
+        let v = parse_version("Linux version 6.1.0").unwrap();
+        assert_eq!((v.compiler, v.build_date), (None, None));
+        assert_eq!(parse_version("FreeBSD 14.0"), None);
+    }
+
+    #[test]
+    fn test_cmdline_tokens() {
+        let mut tokens = cmdline_tokens("BOOT_IMAGE=/vmlinuz root=UUID=abc  ro dyndbg=\"file foo.c +p\" quiet\n");
+        assert_eq!(tokens.next(), Some("BOOT_IMAGE=/vmlinuz"));
+        assert_eq!(tokens.next(), Some("root=UUID=abc"));
+        assert_eq!(tokens.next(), Some("ro"));
+        assert_eq!(tokens.next(), Some("dyndbg=\"file foo.c +p\""));
+        assert_eq!(tokens.next(), Some("quiet"));
+        assert_eq!(tokens.next(), None);
+        assert_eq!(cmdline_tokens("  ").next(), None);
+    }
+}
@@ -0,0 +1,40 @@
+//! Size caps on input that comes from outside kv.
+//!
+//! Everything kv keeps lives on the stack, so anything a user or another
+//! program hands us - arguments, `-f -` on stdin, a `--baseline` file -
+//! has a fixed ceiling. Input past the cap is truncated or rejected with
+//! a warning, never allowed to grow a buffer.
+//!
+//! Limits on what the kernel gives us (sysfs tables, devicetree walks)
+//! stay next to the code that reads them; this module is for the ones a
+//! user can push against.
+
+#![allow(dead_code)]
+
+/// Maximum length for subcommand name.
+pub const MAX_SUBCMD_LEN: usize = 32;
+
+/// Maximum number of extra arguments to store.
+pub const MAX_EXTRA_ARGS: usize = 8;
+
+/// Maximum length for each extra argument.
+pub const MAX_ARG_LEN: usize = 256;
+
+/// Maximum length for a single filter pattern (defense against memory exhaustion).
+/// 1024 chars is plenty for any reasonable substring match.
+pub const MAX_FILTER_LEN: usize = 1024;
+
+/// Maximum number of `-f`/`-F` patterns we keep. Extra ones are ignored.
+pub const MAX_FILTERS: usize = 8;
+
+/// Most stdin we take for `-f -`. Room for a few hundred names or BDFs.
+/// The rest is dropped with a warning.
+pub const MAX_PATTERN_LIST_SIZE: usize = 8192;
+
+/// Largest `--baseline` file we read (a verbose snapshot fits comfortably).
+/// Bigger files are rejected rather than parsed half-read.
+pub const MAX_BASELINE_SIZE: usize = 64 * 1024;
+
+/// Maximum devicetree property file size to read (64 KiB - defense against
+/// large file attacks). Overlays can put arbitrary blobs in the tree.
+pub const MAX_PROPERTY_SIZE: u64 = 64 * 1024;
@@ -3,21 +3,32 @@
 //! A tiny, dependency-free system inspector for embedded Linux.
 //! See README.md for full documentation.
 
-#![no_std]
-#![no_main]
+// Unit tests build against std with the normal test harness
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
 
 // Force link origin to get startup code and mem functions
+#[cfg(not(test))]
 extern crate origin;
 
+mod baseline;
 mod cli;
+mod completions;
 #[macro_use]
 mod debug;
+mod explain;
 mod fields;
 mod filter;
 mod io;
 mod json;
+mod limits;
+mod page;
 mod print;
+mod profile;
 mod stack;
+mod sum;
+mod template;
+mod wanted;
 
 // Subcommand modules - conditionally compiled based on features.
 // For now, we only enable mem for the no_std conversion.
@@ -34,6 +45,10 @@ mod usb;
 mod block;
 #[cfg(feature = "net")]
 mod net;
+#[cfg(feature = "net")]
+mod oui;
+#[cfg(feature = "pci")]
+mod pciids;
 #[cfg(feature = "cpu")]
 mod cpu;
 #[cfg(feature = "mounts")]
@@ -44,6 +59,16 @@ mod thermal;
 mod power;
 #[cfg(feature = "snapshot")]
 mod snapshot;
+#[cfg(feature = "summary")]
+mod summary;
+#[cfg(feature = "read")]
+mod read;
+#[cfg(feature = "kernel")]
+mod kernel;
+#[cfg(feature = "tasks")]
+mod tasks;
+#[cfg(feature = "dmi")]
+mod dmi;
 
 #[cfg(all(
     feature = "dt",
@@ -56,15 +81,28 @@ mod dt;
     not(any(target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64", target_arch = "powerpc64", target_arch = "mips"))
 ))]
 mod dt {
-    pub fn run(_opts: &crate::cli::GlobalOptions, _args: &crate::cli::ExtraArgs) -> i32 {
-        crate::print::println("dt: devicetree not typically available on this architecture");
+    const UNAVAILABLE: &str = "devicetree not typically available on this architecture";
+
+    pub fn run(opts: &crate::cli::GlobalOptions, _args: &crate::cli::ExtraArgs) -> i32 {
+        if opts.json {
+            let mut w = crate::json::begin_kv_output_streaming(opts.json_layout(), "dt");
+            w.key("data");
+            w.value_null();
+            w.field_str("error", UNAVAILABLE);
+            w.end_object();
+            w.finish();
+        } else {
+            crate::print::print("dt: ");
+            crate::print::println(UNAVAILABLE);
+        }
         0
     }
 }
 
-use cli::{Invocation, print_help, print_version, print_subcommand_help};
+use cli::{ExtraArgs, GlobalOptions, Invocation, has_features, print_help, print_help_all, print_version, print_subcommand_help};
 
 /// Panic handler - minimal, just exits
+#[cfg(not(test))]
 #[panic_handler]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
     // In release builds, just exit immediately
@@ -74,16 +112,32 @@ fn panic(_info: &core::panic::PanicInfo) -> ! {
 /// Entry point called by origin.
 /// Origin calls this after performing program initialization.
 #[unsafe(no_mangle)]
-unsafe fn origin_main(argc: usize, argv: *mut *mut u8, _envp: *mut *mut u8) -> i32 {
-    // SAFETY: origin guarantees argc/argv are valid
-    let inv = unsafe { Invocation::parse_from_raw(argc as i32, argv as *const *const u8) };
+unsafe fn origin_main(argc: usize, argv: *mut *mut u8, envp: *mut *mut u8) -> i32 {
+    // SAFETY: origin guarantees argc/argv/envp are valid
+    let inv = unsafe { Invocation::parse_from_raw(argc as i32, argv as *const *const u8, envp as *const *const u8) };
     run(inv)
 }
 
 fn run(inv: Invocation) -> i32 {
 
-    // Initialize debug mode from CLI flag (env var is checked during parse)
+    // Initialize debug mode from -D (KV_DEBUG is folded in during parse)
     debug::set_enabled(inv.options.debug);
+    print::set_text_style(inv.options.text_style);
+    explain::set_enabled(inv.options.explain);
+    json::set_flatten(inv.options.flatten);
+    json::set_indent(inv.options.indent);
+    io::set_strict(inv.options.strict);
+    io::set_raw(inv.options.raw);
+    io::set_precision(inv.options.precision);
+    io::set_si(inv.options.si);
+    profile::set_enabled(inv.options.profile_timings);
+    #[cfg(feature = "thermal")]
+    thermal::set_fahrenheit(inv.options.fahrenheit);
+
+    // Bad flags were reported during parsing; don't guess what was meant
+    if inv.parse_error {
+        return 1;
+    }
 
     if inv.options.debug {
         dbg_print!("kv {} starting", env!("CARGO_PKG_VERSION"));
@@ -92,11 +146,25 @@ fn run(inv: Invocation) -> i32 {
 
     // Handle version request
     if inv.wants_version() {
-        print_version();
+        print_version(inv.options.json, inv.options.pretty);
         return 0;
     }
 
+    // Handle feature query: silent, the exit status is the answer
+    if let Some(list) = inv.has_feature_list() {
+        return if has_features(list) { 0 } else { 1 };
+    }
+
+    // Handle completion script request
+    if let Some(shell) = inv.completions_shell() {
+        return completions::run(shell);
+    }
+
     // Handle help request
+    if inv.options.help_all {
+        print_help_all();
+        return 0;
+    }
     if inv.wants_help() {
         match inv.help_subject() {
             Some(subcmd) => print_subcommand_help(subcmd),
@@ -113,41 +181,189 @@ fn run(inv: Invocation) -> i32 {
         return 1;
     };
 
+    // An option the subcommand would ignore is a mistake, not a no-op
+    let names = core::iter::once(subcommand.as_str()).chain(inv.merged.iter());
+    for name in names {
+        if !options_apply(name, &inv.options) {
+            return 1;
+        }
+    }
+
+    // --output: data goes to a file, errors stay on stderr
+    if let Some(ref path) = inv.options.output {
+        let opened = print::set_output_file(path.as_str(), inv.options.append);
+        if !opened {
+            print::eprint("Error: cannot open output file ");
+            print::eprintln(path.as_str());
+            return 1;
+        }
+    }
+
+    // --list-paths: run the subcommand with stdout muted and report what it read
+    let code = if inv.options.list_paths {
+        io::set_recording(true);
+        print::set_muted(true);
+        let code = dispatch_merged(subcommand.as_str(), &inv);
+        print::set_muted(false);
+        io::set_recording(false);
+        io::print_recorded_paths();
+        code
+    } else {
+        dispatch_merged(subcommand.as_str(), &inv)
+    };
+
+    // --profile-timings: after the output, so it's easy to tell apart
+    profile::print_summary();
+
+    // --strict: a missing required file fails an otherwise successful run
+    if code == 0 && io::report_failed_reads() {
+        return 3;
+    }
+    // --probe: so does a run that found nothing to show
+    if code == 0 && inv.options.probe && !print::wrote_data() {
+        return 4;
+    }
+    code
+}
+
+/// Check the options that only some subcommands take, like `--sum`,
+/// against `name`. False (after an error) if `name` would ignore one.
+fn options_apply(name: &str, opts: &GlobalOptions) -> bool {
+    if opts.sum.is_some() && !sum::SUBCOMMANDS.contains(&name) {
+        reject_option("--sum", name, &sum::SUBCOMMANDS);
+        return false;
+    }
+    if opts.template.is_some() && !template::SUBCOMMANDS.contains(&name) {
+        reject_option("--template", name, &template::SUBCOMMANDS);
+        return false;
+    }
+    true
+}
+
+/// "Error: kv pci doesn't take --sum; it works with kv block, net and power"
+fn reject_option(option: &str, name: &str, takers: &[&str]) {
+    print::eprint("Error: kv ");
+    print::eprint(name);
+    print::eprint(" doesn't take ");
+    print::eprint(option);
+    print::eprint("; it works with kv ");
+    for (i, taker) in takers.iter().enumerate() {
+        if i > 0 {
+            print::eprint(if i + 1 == takers.len() { " and " } else { ", " });
+        }
+        print::eprint(taker);
+    }
+    print::eprintln_empty();
+}
+
+/// Run `first` and then each subcommand --merge added, with the same options.
+///
+/// Text output gets a `== cpu ==` header per section. JSON output is one
+/// envelope (subcommand "merge") whose data holds each subcommand's usual
+/// envelope, keyed by name; `GlobalOptions::section` tells each
+/// subcommand where in the document to write it.
+/// The exit code is the first failure's, but every subcommand still runs.
+fn dispatch_merged(first: &str, inv: &Invocation) -> i32 {
+    if inv.merged.is_empty() {
+        return dispatch(first, &inv.options, &inv.args);
+    }
+
+    let names = core::iter::once(first).chain(inv.merged.iter());
+    let mut code = 0;
+    if inv.options.json {
+        let mut w = json::begin_kv_output_streaming(inv.options.json_layout(), "merge");
+        w.field_object("data");
+        let mut opts = inv.options.clone();
+        for name in names {
+            // Each envelope goes into "data"; one that failed before
+            // writing anything is left out
+            opts.section = Some(w.section());
+            let before = print::written();
+            let section = dispatch(name, &opts, &inv.args);
+            if print::written() != before {
+                w.section_written();
+            }
+            if code == 0 {
+                code = section;
+            }
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+        return code;
+    }
+
+    for (i, name) in names.enumerate() {
+        if i > 0 {
+            print::println_empty();
+        }
+        print::print("== ");
+        print::print(name);
+        print::println(" ==");
+        let section = dispatch(name, &inv.options, &inv.args);
+        if code == 0 {
+            code = section;
+        }
+    }
+    code
+}
+
+/// Run a subcommand by name.
+// With no subcommand features enabled, `opts` and `args` go unused.
+#[allow(unused_variables)]
+fn dispatch(subcommand: &str, opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let _timer = profile::Timer::start(profile::Phase::Total);
+
     // Dispatch to the appropriate subcommand.
     // Each match arm is conditionally compiled - if feature is off, it's not here.
-    match subcommand.as_str() {
+    match subcommand {
         #[cfg(feature = "pci")]
-        "pci" => pci::run(&inv.options),
+        "pci" => pci::run(opts, args),
 
         #[cfg(feature = "usb")]
-        "usb" => usb::run(&inv.options),
+        "usb" => usb::run(opts, args),
 
         #[cfg(feature = "block")]
-        "block" => block::run(&inv.options),
+        "block" => block::run(opts, args),
 
         #[cfg(feature = "net")]
-        "net" => net::run(&inv.options),
+        "net" => net::run(opts, args),
 
         #[cfg(feature = "cpu")]
-        "cpu" => cpu::run(&inv.options),
+        "cpu" => cpu::run(opts, args),
 
         #[cfg(feature = "mem")]
-        "mem" => mem::run(&inv.options),
+        "mem" => mem::run(opts, args),
 
         #[cfg(feature = "mounts")]
-        "mounts" => mounts::run(&inv.options),
+        "mounts" => mounts::run(opts, args),
 
         #[cfg(feature = "thermal")]
-        "thermal" => thermal::run(&inv.options),
+        "thermal" => thermal::run(opts, args),
 
         #[cfg(feature = "power")]
-        "power" => power::run(&inv.options),
+        "power" => power::run(opts),
 
         #[cfg(feature = "dt")]
-        "dt" => dt::run(&inv.options, &inv.args),
+        "dt" => dt::run(opts, args),
 
         #[cfg(feature = "snapshot")]
-        "snapshot" => snapshot::run(&inv.options),
+        "snapshot" => snapshot::run(opts, args),
+
+        #[cfg(feature = "summary")]
+        "summary" => summary::run(opts),
+
+        #[cfg(feature = "read")]
+        "read" => read::run(opts, args),
+
+        #[cfg(feature = "kernel")]
+        "kernel" => kernel::run(opts),
+
+        #[cfg(feature = "tasks")]
+        "tasks" => tasks::run(opts),
+
+        #[cfg(feature = "dmi")]
+        "dmi" => dmi::run(opts),
 
         _unknown => {
             print::eprintln("Error: unknown subcommand");
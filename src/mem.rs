@@ -8,11 +8,11 @@
 //! since Linux 1.0 and the format hasn't changed much. Backwards compatibility
 //! is a beautiful and rare thing in this world of change.
 
-use crate::cli::GlobalOptions;
+use crate::cli::{ExtraArgs, GlobalOptions};
 use crate::fields::mem as f;
 use crate::io::{self, KbToBytes};
-use crate::json::begin_kv_output_streaming;
-use crate::print;
+use crate::json::{begin_kv_output_streaming, Layout, StreamingJsonWriter};
+use crate::print::{self, TextWriter};
 use crate::stack::StackString;
 
 /// Path to meminfo. Could be different in containers or chroots,
@@ -20,7 +20,8 @@ use crate::stack::StackString;
 const MEMINFO_PATH: &str = "/proc/meminfo";
 
 /// Memory information structure.
-/// All values in KB, because that's what the kernel gives us.
+/// All values in KB, because that's what the kernel gives us
+/// (except the hugepage counts, which are page counts).
 #[derive(Default)]
 pub struct MemInfo {
     pub mem_total_kb: Option<u64>,
@@ -36,6 +37,10 @@ pub struct MemInfo {
     pub sunreclaim_kb: Option<u64>,
     pub dirty_kb: Option<u64>,
     pub writeback_kb: Option<u64>,
+    pub hugepages_total: Option<u64>,
+    pub hugepages_free: Option<u64>,
+    pub hugepages_rsvd: Option<u64>,
+    pub hugepagesize_kb: Option<u64>,
 }
 
 impl MemInfo {
@@ -50,7 +55,7 @@ impl MemInfo {
     /// Read from a custom path (useful for testing).
     pub fn read_from(path: &str) -> Option<Self> {
         // Use stack-based read - meminfo is typically ~1.5KB
-        let contents: StackString<4096> = io::read_file_stack(path)?;
+        let contents: StackString<4096> = io::read_required_stack(path)?;
         Some(Self::parse(contents.as_str()))
     }
 
@@ -60,7 +65,11 @@ impl MemInfo {
         let mut info = MemInfo::default();
 
         for line in content.lines() {
-            if let Some((key, value)) = parse_meminfo_line(line) {
+            let parsed = parse_meminfo_line(line);
+            if parsed.is_none() && !line.trim().is_empty() {
+                crate::dbg_parse!(MEMINFO_PATH, line);
+            }
+            if let Some((key, value)) = parsed {
                 match key {
                     "MemTotal" => info.mem_total_kb = Some(value),
                     "MemFree" => info.mem_free_kb = Some(value),
@@ -75,6 +84,10 @@ impl MemInfo {
                     "SUnreclaim" => info.sunreclaim_kb = Some(value),
                     "Dirty" => info.dirty_kb = Some(value),
                     "Writeback" => info.writeback_kb = Some(value),
+                    "HugePages_Total" => info.hugepages_total = Some(value),
+                    "HugePages_Free" => info.hugepages_free = Some(value),
+                    "HugePages_Rsvd" => info.hugepages_rsvd = Some(value),
+                    "Hugepagesize" => info.hugepagesize_kb = Some(value),
                     _ => {} // Ignore fields we don't care about
                 }
             }
@@ -83,124 +96,216 @@ impl MemInfo {
         info
     }
 
-    /// Output as text (KEY=VALUE format).
-    pub fn print_text(&self, verbose: bool, human: bool) {
-        let mut w = print::TextWriter::new();
+    /// True if any hugepages are configured. The hugepage fields are
+    /// noise on the (many) systems that never set up a pool.
+    pub fn has_hugepages(&self) -> bool {
+        self.hugepages_total.is_some_and(|n| n > 0)
+    }
 
-        if human {
-            // Human-readable sizes like "16G", "512M"
-            w.field_str_opt(f::MEM_TOTAL, self.mem_total_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-            w.field_str_opt(f::MEM_FREE, self.mem_free_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-            w.field_str_opt(f::MEM_AVAILABLE, self.mem_available_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-            w.field_str_opt(f::SWAP_TOTAL, self.swap_total_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-            w.field_str_opt(f::SWAP_FREE, self.swap_free_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-
-            if verbose {
-                w.field_str_opt(f::BUFFERS, self.buffers_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-                w.field_str_opt(f::CACHED, self.cached_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-                w.field_str_opt(f::SWAP_CACHED, self.swap_cached_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-                w.field_str_opt(f::SHMEM, self.shmem_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-                w.field_str_opt(f::SRECLAIMABLE, self.sreclaimable_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-                w.field_str_opt(f::SUNRECLAIM, self.sunreclaim_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-                w.field_str_opt(f::DIRTY, self.dirty_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-                w.field_str_opt(f::WRITEBACK, self.writeback_kb.map(|v| io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
-            }
-        } else {
-            // Raw KB values
-            w.field_u64_opt(f::MEM_TOTAL_KB, self.mem_total_kb);
-            w.field_u64_opt(f::MEM_FREE_KB, self.mem_free_kb);
-            w.field_u64_opt(f::MEM_AVAILABLE_KB, self.mem_available_kb);
-            w.field_u64_opt(f::SWAP_TOTAL_KB, self.swap_total_kb);
-            w.field_u64_opt(f::SWAP_FREE_KB, self.swap_free_kb);
-
-            if verbose {
-                w.field_u64_opt(f::BUFFERS_KB, self.buffers_kb);
-                w.field_u64_opt(f::CACHED_KB, self.cached_kb);
-                w.field_u64_opt(f::SWAP_CACHED_KB, self.swap_cached_kb);
-                w.field_u64_opt(f::SHMEM_KB, self.shmem_kb);
-                w.field_u64_opt(f::SRECLAIMABLE_KB, self.sreclaimable_kb);
-                w.field_u64_opt(f::SUNRECLAIM_KB, self.sunreclaim_kb);
-                w.field_u64_opt(f::DIRTY_KB, self.dirty_kb);
-                w.field_u64_opt(f::WRITEBACK_KB, self.writeback_kb);
+    /// Every field a sample can print, in output order. `shown` says
+    /// whether this one does with `verbose`.
+    fn fields(&self, verbose: bool) -> [MemField; 17] {
+        let kb = |key, key_kb, value| MemField { key, key_kb, value, kb: true, shown: true };
+        let extra = |key, key_kb, value| MemField { key, key_kb, value, kb: true, shown: verbose };
+        // Hugepage counts are pages, with the same key either way
+        let pages = |key, value| MemField { key, key_kb: key, value, kb: false, shown: verbose && self.has_hugepages() };
+        [
+            kb(f::MEM_TOTAL, f::MEM_TOTAL_KB, self.mem_total_kb),
+            kb(f::MEM_FREE, f::MEM_FREE_KB, self.mem_free_kb),
+            kb(f::MEM_AVAILABLE, f::MEM_AVAILABLE_KB, self.mem_available_kb),
+            kb(f::SWAP_TOTAL, f::SWAP_TOTAL_KB, self.swap_total_kb),
+            kb(f::SWAP_FREE, f::SWAP_FREE_KB, self.swap_free_kb),
+            extra(f::BUFFERS, f::BUFFERS_KB, self.buffers_kb),
+            extra(f::CACHED, f::CACHED_KB, self.cached_kb),
+            extra(f::SWAP_CACHED, f::SWAP_CACHED_KB, self.swap_cached_kb),
+            extra(f::SHMEM, f::SHMEM_KB, self.shmem_kb),
+            extra(f::SRECLAIMABLE, f::SRECLAIMABLE_KB, self.sreclaimable_kb),
+            extra(f::SUNRECLAIM, f::SUNRECLAIM_KB, self.sunreclaim_kb),
+            extra(f::DIRTY, f::DIRTY_KB, self.dirty_kb),
+            extra(f::WRITEBACK, f::WRITEBACK_KB, self.writeback_kb),
+            pages(f::HUGEPAGES_TOTAL, self.hugepages_total),
+            pages(f::HUGEPAGES_FREE, self.hugepages_free),
+            pages(f::HUGEPAGES_RSVD, self.hugepages_rsvd),
+            MemField { key_kb: f::HUGEPAGESIZE_KB, kb: true, ..pages(f::HUGEPAGESIZE, self.hugepagesize_kb) },
+        ]
+    }
+
+    /// The fields to print: the shown ones that have a value, and with
+    /// `since` (--only-changes), only those that differ from it.
+    fn fields_since(&self, verbose: bool, since: Option<&MemInfo>) -> impl Iterator<Item = MemField> {
+        let before = since.map(|s| s.fields(verbose));
+        self.fields(verbose)
+            .into_iter()
+            .enumerate()
+            .filter(move |(i, field)| field.shown && field.value.is_some() && before.as_ref().is_none_or(|b| b[*i].value != field.value))
+            .map(|(_, field)| field)
+    }
+
+    /// Output as text (KEY=VALUE format), followed by the changes since
+    /// the previous sample under --watch. With `since`, only the fields
+    /// that changed from it.
+    pub fn print_text(&self, verbose: bool, human: bool, delta: Option<&MemDelta>, since: Option<&MemInfo>) {
+        let mut w = print::TextWriter::new();
+        for field in self.fields_since(verbose, since) {
+            match field.value {
+                // Human-readable sizes like "16G", "512M"
+                Some(v) if human && field.kb => w.field_str(field.key, io::format_human_size(v.kb()).as_str()),
+                Some(v) => w.field_u64(if human { field.key } else { field.key_kb }, v),
+                None => {}
             }
         }
-
-        w.finish();
+        if let Some(delta) = delta {
+            delta.write_text(&mut w, human);
+        }
+        w.finish_record();
     }
 
     /// Output as JSON (streaming - writes directly to stdout).
-    pub fn print_json(&self, pretty: bool, verbose: bool, human: bool) {
-        let mut w = begin_kv_output_streaming(pretty, "mem");
+    pub fn print_json(&self, layout: Layout, verbose: bool, human: bool, delta: Option<&MemDelta>, since: Option<&MemInfo>) {
+        let mut w = begin_kv_output_streaming(layout, "mem");
 
+        print::note_data();
         w.field_object("data");
-
-        if human {
-            // Human-readable string values
-            if let Some(v) = self.mem_total_kb {
-                w.field_str(f::MEM_TOTAL, io::format_human_size(v.kb()).as_str());
-            }
-            if let Some(v) = self.mem_free_kb {
-                w.field_str(f::MEM_FREE, io::format_human_size(v.kb()).as_str());
-            }
-            if let Some(v) = self.mem_available_kb {
-                w.field_str(f::MEM_AVAILABLE, io::format_human_size(v.kb()).as_str());
-            }
-            if let Some(v) = self.swap_total_kb {
-                w.field_str(f::SWAP_TOTAL, io::format_human_size(v.kb()).as_str());
-            }
-            if let Some(v) = self.swap_free_kb {
-                w.field_str(f::SWAP_FREE, io::format_human_size(v.kb()).as_str());
+        for field in self.fields_since(verbose, since) {
+            match field.value {
+                Some(v) if human && field.kb => w.field_str(field.key, io::format_human_size(v.kb()).as_str()),
+                Some(v) => w.field_u64(if human { field.key } else { field.key_kb }, v),
+                None => {}
             }
+        }
+        if let Some(delta) = delta {
+            delta.write_json(&mut w, human);
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    }
+}
 
-            if verbose {
-                if let Some(v) = self.buffers_kb {
-                    w.field_str(f::BUFFERS, io::format_human_size(v.kb()).as_str());
-                }
-                if let Some(v) = self.cached_kb {
-                    w.field_str(f::CACHED, io::format_human_size(v.kb()).as_str());
-                }
-                if let Some(v) = self.swap_cached_kb {
-                    w.field_str(f::SWAP_CACHED, io::format_human_size(v.kb()).as_str());
-                }
-                if let Some(v) = self.shmem_kb {
-                    w.field_str(f::SHMEM, io::format_human_size(v.kb()).as_str());
-                }
-                if let Some(v) = self.sreclaimable_kb {
-                    w.field_str(f::SRECLAIMABLE, io::format_human_size(v.kb()).as_str());
-                }
-                if let Some(v) = self.sunreclaim_kb {
-                    w.field_str(f::SUNRECLAIM, io::format_human_size(v.kb()).as_str());
-                }
-                if let Some(v) = self.dirty_kb {
-                    w.field_str(f::DIRTY, io::format_human_size(v.kb()).as_str());
-                }
-                if let Some(v) = self.writeback_kb {
-                    w.field_str(f::WRITEBACK, io::format_human_size(v.kb()).as_str());
-                }
+/// One value a sample can print.
+struct MemField {
+    /// Key with -h
+    key: &'static str,
+    /// Key for the raw number
+    key_kb: &'static str,
+    value: Option<u64>,
+    /// The value is KB, which -h turns into a size
+    kb: bool,
+    shown: bool,
+}
+
+/// How much the counters `--watch` follows changed between two samples,
+/// in KB. Negative means it went down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemDelta {
+    pub mem_free_kb: Option<i64>,
+    pub mem_available_kb: Option<i64>,
+    pub dirty_kb: Option<i64>,
+    pub writeback_kb: Option<i64>,
+}
+
+impl MemDelta {
+    /// The change from `before` to `after`. A counter missing from either
+    /// sample has no change.
+    pub fn between(before: &MemInfo, after: &MemInfo) -> Self {
+        let change = |before: Option<u64>, after: Option<u64>| Some(after? as i64 - before? as i64);
+        MemDelta {
+            mem_free_kb: change(before.mem_free_kb, after.mem_free_kb),
+            mem_available_kb: change(before.mem_available_kb, after.mem_available_kb),
+            dirty_kb: change(before.dirty_kb, after.dirty_kb),
+            writeback_kb: change(before.writeback_kb, after.writeback_kb),
+        }
+    }
+
+    /// Leave out the counters that didn't move, for --only-changes.
+    pub fn changed_only(self) -> Self {
+        let moved = |change: Option<i64>| change.filter(|&kb| kb != 0);
+        MemDelta {
+            mem_free_kb: moved(self.mem_free_kb),
+            mem_available_kb: moved(self.mem_available_kb),
+            dirty_kb: moved(self.dirty_kb),
+            writeback_kb: moved(self.writeback_kb),
+        }
+    }
+
+    /// True if no counter has a change to print.
+    pub fn is_empty(&self) -> bool {
+        self.fields().iter().all(|(_, _, change)| change.is_none())
+    }
+
+    /// (human key, KB key, change) for each counter.
+    fn fields(&self) -> [(&'static str, &'static str, Option<i64>); 4] {
+        [
+            (f::MEM_FREE_DELTA, f::MEM_FREE_DELTA_KB, self.mem_free_kb),
+            (f::MEM_AVAILABLE_DELTA, f::MEM_AVAILABLE_DELTA_KB, self.mem_available_kb),
+            (f::DIRTY_DELTA, f::DIRTY_DELTA_KB, self.dirty_kb),
+            (f::WRITEBACK_DELTA, f::WRITEBACK_DELTA_KB, self.writeback_kb),
+        ]
+    }
+
+    fn write_text(&self, w: &mut TextWriter, human: bool) {
+        for (key, key_kb, change) in self.fields() {
+            match change {
+                Some(kb) if human => w.field_str(key, format_change(kb).as_str()),
+                Some(kb) => w.field_i64(key_kb, kb),
+                None => {}
             }
-        } else {
-            // Raw KB numeric values
-            w.field_u64_opt(f::MEM_TOTAL_KB, self.mem_total_kb);
-            w.field_u64_opt(f::MEM_FREE_KB, self.mem_free_kb);
-            w.field_u64_opt(f::MEM_AVAILABLE_KB, self.mem_available_kb);
-            w.field_u64_opt(f::SWAP_TOTAL_KB, self.swap_total_kb);
-            w.field_u64_opt(f::SWAP_FREE_KB, self.swap_free_kb);
-
-            if verbose {
-                w.field_u64_opt(f::BUFFERS_KB, self.buffers_kb);
-                w.field_u64_opt(f::CACHED_KB, self.cached_kb);
-                w.field_u64_opt(f::SWAP_CACHED_KB, self.swap_cached_kb);
-                w.field_u64_opt(f::SHMEM_KB, self.shmem_kb);
-                w.field_u64_opt(f::SRECLAIMABLE_KB, self.sreclaimable_kb);
-                w.field_u64_opt(f::SUNRECLAIM_KB, self.sunreclaim_kb);
-                w.field_u64_opt(f::DIRTY_KB, self.dirty_kb);
-                w.field_u64_opt(f::WRITEBACK_KB, self.writeback_kb);
+        }
+    }
+
+    fn write_json(&self, w: &mut StreamingJsonWriter, human: bool) {
+        for (key, key_kb, change) in self.fields() {
+            match change {
+                Some(kb) if human => w.field_str(key, format_change(kb).as_str()),
+                Some(kb) => w.field_i64(key_kb, kb),
+                None => {}
             }
         }
+    }
+}
 
-        w.end_field_object();
-        w.end_object();
-        w.finish();
+/// A change in KB as a signed size: "-1.2M", "+512K", "0".
+fn format_change(kb: i64) -> StackString<24> {
+    let mut s = StackString::new();
+    if kb < 0 {
+        s.push('-');
+    } else if kb > 0 {
+        s.push('+');
+    }
+    s.push_str(io::format_human_size_tenths(kb.unsigned_abs().kb()).as_str());
+    s
+}
+
+/// Options specific to the mem subcommand.
+#[derive(Default)]
+pub struct MemOptions {
+    /// Seconds between samples (--watch)
+    pub watch_s: Option<u32>,
+    /// Under --watch, print only the fields that changed since the sample
+    /// before (--only-changes)
+    pub only_changes: bool,
+}
+
+impl MemOptions {
+    /// Parse mem-specific options from remaining arguments. None (after an
+    /// error) if `--watch` isn't given a whole number of seconds.
+    pub fn parse(args: &ExtraArgs) -> Option<Self> {
+        let mut opts = MemOptions::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg {
+                "--watch" => match iter.next().and_then(|v| v.parse::<u32>().ok()).filter(|&n| n > 0) {
+                    Some(n) => opts.watch_s = Some(n),
+                    None => {
+                        print::eprintln("Error: --watch expects a number of seconds (1 or more)");
+                        return None;
+                    }
+                },
+                "--only-changes" => opts.only_changes = true,
+                _ => {}
+            }
+        }
+        Some(opts)
     }
 }
 
@@ -223,13 +328,67 @@ fn parse_meminfo_line(line: &str) -> Option<(&str, u64)> {
     Some((key, value))
 }
 
+/// What `--watch` prints of a sample after the first: the changes since
+/// `prev`, and with `only_changes` just the fields and counters that
+/// moved. `None` (with `only_changes`) when nothing the sample would show
+/// did.
+fn watch_sample<'a>(prev: &'a MemInfo, info: &MemInfo, verbose: bool, only_changes: bool) -> Option<(MemDelta, Option<&'a MemInfo>)> {
+    let delta = MemDelta::between(prev, info);
+    if !only_changes {
+        return Some((delta, None));
+    }
+    let delta = delta.changed_only();
+    if delta.is_empty() && info.fields_since(verbose, Some(prev)).next().is_none() {
+        return None;
+    }
+    Some((delta, Some(prev)))
+}
+
+/// `kv mem --watch <secs>`: a sample every interval, each after the
+/// first with the changes since the one before. Runs until interrupted.
+fn run_watch(opts: &GlobalOptions, interval_s: u32, only_changes: bool) -> i32 {
+    let mut prev: Option<MemInfo> = None;
+    loop {
+        let Some(info) = MemInfo::read() else {
+            print::eprint("mem: cannot read ");
+            print::eprintln(MEMINFO_PATH);
+            return 1;
+        };
+        // A skipped sample differs from the last one printed only in
+        // fields that aren't shown, so comparing with it is the same
+        let sample = match &prev {
+            None => Some((None, None)),
+            Some(before) => watch_sample(before, &info, opts.verbose, only_changes).map(|(delta, since)| (Some(delta), since)),
+        };
+        if let Some((delta, since)) = sample {
+            if opts.json {
+                info.print_json(opts.json_layout(), opts.verbose, opts.human, delta.as_ref(), since);
+            } else {
+                info.print_text(opts.verbose, opts.human, delta.as_ref(), since);
+            }
+        }
+        prev = Some(info);
+        io::sleep_ms(interval_s.saturating_mul(1000));
+    }
+}
+
 /// Entry point for `kv mem` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let Some(mem_opts) = MemOptions::parse(args) else {
+        return 1;
+    };
+    if let Some(interval_s) = mem_opts.watch_s {
+        return run_watch(opts, interval_s, mem_opts.only_changes);
+    }
+    if mem_opts.only_changes {
+        print::eprintln("Warning: --only-changes only applies with --watch");
+    }
+
     let Some(info) = MemInfo::read() else {
         // Can't read /proc/meminfo - this is unusual but not fatal
         if opts.json {
             // Even errors get JSON wrapper for consistency (streaming)
-            let mut w = begin_kv_output_streaming(opts.pretty, "mem");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "mem");
             w.key("data");
             w.value_null();
             w.key("error");
@@ -244,9 +403,9 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     };
 
     if opts.json {
-        info.print_json(opts.pretty, opts.verbose, opts.human);
+        info.print_json(opts.json_layout(), opts.verbose, opts.human, None, None);
     } else {
-        info.print_text(opts.verbose, opts.human);
+        info.print_text(opts.verbose, opts.human, None, None);
     }
 
     0
@@ -281,8 +440,71 @@ KReclaimable:     444444 kB
 Slab:             555555 kB
 SReclaimable:     444444 kB
 SUnreclaim:       111111 kB
+HugePages_Total:      64
+HugePages_Free:       60
+HugePages_Rsvd:        2
+HugePages_Surp:        0
+Hugepagesize:       2048 kB
 "#;
 
+    #[test]
+    fn test_watch_only_changes() {
+        let first = MemInfo::parse(SAMPLE_MEMINFO);
+        let same = MemInfo::parse(SAMPLE_MEMINFO);
+        let changed = MemInfo::parse(&SAMPLE_MEMINFO.replace("MemFree:          123456 kB", "MemFree:          120000 kB"));
+        // Nothing moved: skipped
+        assert!(watch_sample(&first, &same, false, true).is_none());
+        // Only MemFree moved: just it and its change
+        let (delta, since) = watch_sample(&same, &changed, false, true).unwrap();
+        let mut fields = changed.fields_since(false, since);
+        assert_eq!(fields.next().map(|f| f.key_kb), Some(f::MEM_FREE_KB));
+        assert!(fields.next().is_none());
+        assert_eq!(delta, MemDelta { mem_free_kb: Some(-3456), ..MemDelta::default() });
+        // Without --only-changes, every sample in full
+        let (delta, since) = watch_sample(&first, &same, false, false).unwrap();
+        assert!(since.is_none());
+        assert_eq!(delta.dirty_kb, Some(0));
+        assert_eq!(same.fields_since(false, since).count(), 5);
+    }
+
+    fn watch_option(args: &[&str]) -> Option<Option<u32>> {
+        let mut extra = ExtraArgs::new();
+        for arg in args {
+            extra.push(arg);
+        }
+        MemOptions::parse(&extra).map(|o| o.watch_s)
+    }
+
+    #[test]
+    fn test_watch_needs_seconds() {
+        assert_eq!(watch_option(&["--watch", "2"]), Some(Some(2)));
+        assert_eq!(watch_option(&[]), Some(None));
+        assert_eq!(watch_option(&["--watch", "abc"]), None);
+        assert_eq!(watch_option(&["--watch", "0"]), None);
+        assert_eq!(watch_option(&["--watch"]), None);
+    }
+
+    #[test]
+    fn test_fields_keys() {
+        let info = MemInfo::parse(SAMPLE_MEMINFO);
+        let keys = |verbose| info.fields_since(verbose, None).map(|f| f.key_kb);
+        assert!(keys(false).eq([f::MEM_TOTAL_KB, f::MEM_FREE_KB, f::MEM_AVAILABLE_KB, f::SWAP_TOTAL_KB, f::SWAP_FREE_KB]));
+        assert_eq!(keys(true).count(), 17);
+        assert_eq!(keys(true).last(), Some(f::HUGEPAGESIZE_KB));
+        let human = info.fields_since(true, None).map(|f| f.key).last();
+        assert_eq!(human, Some(f::HUGEPAGESIZE));
+    }
+
+    #[test]
+    fn test_hidden_change_is_skipped() {
+        // Cached only shows with -v
+        let first = MemInfo::parse(SAMPLE_MEMINFO);
+        let changed = MemInfo::parse(&SAMPLE_MEMINFO.replace("Cached:          3456789 kB", "Cached:          3456000 kB"));
+        assert!(watch_sample(&first, &changed, false, true).is_none());
+        let (_, since) = watch_sample(&first, &changed, true, true).unwrap();
+        assert_eq!(changed.fields_since(true, since).map(|f| f.key_kb).next(), Some(f::CACHED_KB));
+    }
+
     #[test]
     fn parse_meminfo() {
         let info = MemInfo::parse(SAMPLE_MEMINFO);
@@ -299,6 +521,18 @@ SUnreclaim:       111111 kB
         assert_eq!(info.sunreclaim_kb, Some(111111));
         assert_eq!(info.dirty_kb, Some(123));
         assert_eq!(info.writeback_kb, Some(0));
+        assert_eq!(info.hugepages_total, Some(64));
+        assert_eq!(info.hugepages_free, Some(60));
+        assert_eq!(info.hugepages_rsvd, Some(2));
+        assert_eq!(info.hugepagesize_kb, Some(2048));
+        assert!(info.has_hugepages());
+    }
+
+    #[test]
+    fn no_hugepages_when_pool_empty() {
+        let info = MemInfo::parse("MemTotal: 1024 kB\nHugePages_Total:       0\nHugepagesize:       2048 kB\n");
+        assert!(!info.has_hugepages());
+        assert!(!MemInfo::parse("MemTotal: 1024 kB\n").has_hugepages());
     }
 
     #[test]
@@ -319,4 +553,37 @@ SUnreclaim:       111111 kB
         assert!(parse_meminfo_line("not a valid line").is_none());
         assert!(parse_meminfo_line("").is_none());
     }
+
+    #[test]
+    fn malformed_line_reported_under_debug() {
+        // Debug mode is global; turn it back off so other tests stay quiet
+        crate::debug::set_enabled(true);
+        let before = crate::debug::parse_error_count();
+        let info = MemInfo::parse("MemTotal:       16384 kB\nMemFree: lots kB\n\n");
+        let after = crate::debug::parse_error_count();
+        crate::debug::set_enabled(false);
+
+        assert_eq!(info.mem_total_kb, Some(16384));
+        assert_eq!(info.mem_free_kb, None);
+        assert!(after > before);
+    }
+
+    #[test]
+    fn delta_between_samples() {
+        let before = MemInfo::parse("MemFree: 2000000 kB\nMemAvailable: 5000000 kB\nDirty: 100 kB\nWriteback: 0 kB\n");
+        let after = MemInfo::parse("MemFree: 800000 kB\nMemAvailable: 5000000 kB\nDirty: 612 kB\n");
+        let delta = MemDelta::between(&before, &after);
+        assert_eq!(delta, MemDelta {
+            mem_free_kb: Some(-1_200_000),
+            mem_available_kb: Some(0),
+            dirty_kb: Some(512),
+            // Missing from the second sample
+            writeback_kb: None,
+        });
+        assert_eq!(format_change(512).as_str(), "+512K");
+        assert_eq!(format_change(-2048).as_str(), "-2M");
+        assert_eq!(format_change(-1229).as_str(), "-1.2M");
+        assert_eq!(format_change(-1_200_000).as_str(), "-1.1G");
+        assert_eq!(format_change(0).as_str(), "0");
+    }
 }
@@ -6,16 +6,37 @@
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
-use crate::fields::mounts as f;
-use crate::filter::matches_any;
+use crate::cli::{ExtraArgs, GlobalOptions};
+use crate::fields::{kind, mounts as f};
+use crate::filter::Filterable;
 use crate::io;
 use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::page::Page;
 use crate::print::{self, TextWriter};
 use crate::stack::StackString;
+use crate::template::Template;
 
 const MOUNTS_PATH: &str = "/proc/self/mounts";
 
+/// Filesystem types that never have real storage behind them.
+const PSEUDO_FSTYPES: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs",
+    "devpts", "devtmpfs", "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs",
+    "proc", "pstore", "ramfs", "rpc_pipefs", "securityfs", "selinuxfs", "sysfs",
+    "tmpfs", "tracefs",
+];
+
+/// Is this filesystem virtual (kernel-provided or memory-backed)?
+///
+/// overlay counts as pseudo unless it names a real source path; containers
+/// mount it with source "overlay", which isn't a device you'd care about.
+pub fn is_pseudo_fs(fstype: &str, source: &str) -> bool {
+    if fstype == "overlay" {
+        return !source.starts_with('/');
+    }
+    PSEUDO_FSTYPES.contains(&fstype)
+}
+
 /// A single mount point entry.
 pub struct MountEntry {
     /// Source device or pseudo-filesystem (e.g., "/dev/sda1" or "tmpfs")
@@ -38,8 +59,15 @@ impl MountEntry {
     /// Format: device mountpoint fstype options dump pass
     /// Example: /dev/sda1 / ext4 rw,relatime 0 0
     ///
-    /// Note: Spaces in paths are escaped as \040 (octal), and we decode them.
+    /// Note: Spaces in paths are escaped as \040 (octal), and we decode them
+    /// (unless --raw is given).
     pub fn parse(line: &str) -> Option<Self> {
+        Self::parse_with(line, io::is_raw())
+    }
+
+    /// `parse`, keeping the octal escapes when `raw` is set.
+    fn parse_with(line: &str, raw: bool) -> Option<Self> {
+        let path = |s: &str| if raw { StackString::from_str(s) } else { decode_mount_escapes(s) };
         let mut parts = line.split_whitespace();
 
         let source_raw = parts.next()?;
@@ -52,8 +80,8 @@ impl MountEntry {
         let pass_num: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
 
         Some(MountEntry {
-            source: decode_mount_escapes(source_raw),
-            target: decode_mount_escapes(target_raw),
+            source: path(source_raw),
+            target: path(target_raw),
             fstype: StackString::from_str(fstype),
             options: StackString::from_str(options),
             dump_freq,
@@ -61,10 +89,9 @@ impl MountEntry {
         })
     }
 
-    /// Check if this mount matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let fields = [self.source.as_str(), self.target.as_str(), self.fstype.as_str()];
-        matches_any(&fields, pattern, case_insensitive)
+    /// Is this a pseudo filesystem (proc, sysfs, tmpfs, cgroup, ...)?
+    pub fn is_pseudo(&self) -> bool {
+        is_pseudo_fs(self.fstype.as_str(), self.source.as_str())
     }
 
     /// Output as text (single line, KEY=VALUE format).
@@ -74,12 +101,13 @@ impl MountEntry {
         w.field_quoted(f::TARGET, self.target.as_str());
         w.field_str(f::FSTYPE, self.fstype.as_str());
         w.field_quoted(f::OPTIONS, self.options.as_str());
-        w.finish();
+        w.finish_record();
     }
 
     /// Output as JSON object fields.
     fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool) {
-        w.array_object_begin();
+        w.record_begin();
+        w.field_str(kind::KIND, kind::MOUNT);
         w.field_str(f::SOURCE, self.source.as_str());
         w.field_str(f::TARGET, self.target.as_str());
         w.field_str(f::FSTYPE, self.fstype.as_str());
@@ -87,11 +115,27 @@ impl MountEntry {
         if verbose {
             w.field_u64(f::DUMP_FREQ, self.dump_freq as u64);
             w.field_u64(f::PASS_NUM, self.pass_num as u64);
+            w.field_bool(f::IS_PSEUDO, self.is_pseudo());
         }
         w.array_object_end();
     }
 }
 
+impl Filterable for MountEntry {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::SOURCE, self.source.as_str());
+        visit(f::TARGET, self.target.as_str());
+        visit(f::FSTYPE, self.fstype.as_str());
+    }
+}
+
+/// Every `--template` field, whether or not a record has it.
+const TEMPLATE_FIELDS: &[&str] = &[
+    f::SOURCE,
+    f::TARGET,
+    f::FSTYPE,
+];
+
 /// Decode mount escape sequences.
 ///
 /// The kernel escapes special characters in mount paths using octal:
@@ -132,14 +176,53 @@ fn is_octal_digit(b: u8) -> bool {
     b >= b'0' && b <= b'7'
 }
 
+/// Options specific to the mounts subcommand.
+#[derive(Default)]
+pub struct MountsOptions {
+    /// Hide pseudo filesystems, leaving real storage
+    pub real_only: bool,
+    /// Show only pseudo filesystems
+    pub pseudo_only: bool,
+}
+
+impl MountsOptions {
+    /// Parse mounts-specific options from remaining arguments.
+    pub fn parse(args: &ExtraArgs) -> Self {
+        let mut opts = MountsOptions::default();
+
+        for arg in args.iter() {
+            match arg {
+                "--real" | "--no-pseudo" => opts.real_only = true,
+                "--pseudo-only" => opts.pseudo_only = true,
+                _ => {}
+            }
+        }
+
+        opts
+    }
+
+    /// Should this mount be shown?
+    fn wants(&self, mount: &MountEntry) -> bool {
+        if self.real_only && mount.is_pseudo() {
+            return false;
+        }
+        if self.pseudo_only && !mount.is_pseudo() {
+            return false;
+        }
+        true
+    }
+}
+
 /// Entry point for `kv mounts` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let mounts_opts = MountsOptions::parse(args);
+
     // Read the entire mounts file
-    let contents: StackString<8192> = match io::read_file_stack(MOUNTS_PATH) {
+    let contents: StackString<8192> = match io::read_required_stack(MOUNTS_PATH) {
         Some(c) => c,
         None => {
             if opts.json {
-                let mut w = begin_kv_output_streaming(opts.pretty, "mounts");
+                let mut w = begin_kv_output_streaming(opts.json_layout(), "mounts");
                 w.field_array("data");
                 w.end_field_array();
                 w.end_object();
@@ -151,51 +234,61 @@ pub fn run(opts: &GlobalOptions) -> i32 {
         }
     };
 
-    let filter = opts.filter.as_ref().map(|s| s.as_str());
-    let case_insensitive = opts.filter_case_insensitive;
+    let filter = &opts.filter;
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "mounts");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "mounts");
         w.field_array("data");
 
         let mut count = 0;
+        let mut page = Page::from_options(opts);
         for line in contents.as_str().lines() {
             if let Some(mount) = MountEntry::parse(line) {
                 // Apply filter if present
-                if let Some(pattern) = filter {
-                    if !mount.matches_filter(pattern, case_insensitive) {
-                        continue;
-                    }
+                if !mounts_opts.wants(&mount) || !mount.matches_filter(filter) {
+                    continue;
                 }
-                mount.write_json(&mut w, opts.verbose);
                 count += 1;
+                if page.take() {
+                    mount.write_json(&mut w, opts.verbose);
+                }
             }
         }
 
         w.end_field_array();
+        page.write_json(&mut w);
         w.end_object();
         w.finish();
 
-        if count == 0 && filter.is_some() {
+        if count == 0 && filter.is_active() {
             // Empty result with filter is not an error, just no matches
         }
     } else {
         let mut count = 0;
+        let mut template = Template::from_options(opts, TEMPLATE_FIELDS);
+        let mut page = Page::from_options(opts);
         for line in contents.as_str().lines() {
             if let Some(mount) = MountEntry::parse(line) {
                 // Apply filter if present
-                if let Some(pattern) = filter {
-                    if !mount.matches_filter(pattern, case_insensitive) {
-                        continue;
-                    }
+                if !mounts_opts.wants(&mount) || !mount.matches_filter(filter) {
+                    continue;
                 }
-                mount.print_text();
                 count += 1;
+                if !page.take() {
+                    continue;
+                }
+                match template.as_mut() {
+                    Some(t) => t.print(&mount, &[]),
+                    None => mount.print_text(),
+                }
             }
         }
+        if let Some(t) = &template {
+            t.finish();
+        }
 
         if count == 0 {
-            if filter.is_some() {
+            if filter.is_active() || mounts_opts.real_only || mounts_opts.pseudo_only {
                 print::println("mounts: no matching mounts");
             } else {
                 print::println("mounts: no mounts found");
@@ -206,20 +299,58 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     0
 }
 
-/// Write mounts to JSON writer (for snapshot).
+/// Write mounts to JSON writer (for snapshot), from /proc/self/mounts
+/// contents read by the caller.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
-    let contents: StackString<8192> = match io::read_file_stack(MOUNTS_PATH) {
-        Some(c) => c,
-        None => return,
-    };
-
+pub fn write_snapshot_with(w: &mut StreamingJsonWriter, verbose: bool, contents: &str) {
     w.key("mounts");
     w.begin_array();
-    for line in contents.as_str().lines() {
+    for line in contents.lines() {
         if let Some(mount) = MountEntry::parse(line) {
             mount.write_json(w, verbose);
         }
     }
     w.end_array();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudo_fstypes() {
+        for fstype in ["proc", "sysfs", "tmpfs", "devtmpfs", "cgroup", "cgroup2", "debugfs", "devpts"] {
+            assert!(is_pseudo_fs(fstype, fstype), "{} should be pseudo", fstype);
+        }
+    }
+
+    #[test]
+    fn test_real_fstypes() {
+        for fstype in ["ext4", "xfs", "btrfs", "vfat", "nfs", "squashfs", "fuseblk"] {
+            assert!(!is_pseudo_fs(fstype, "/dev/sda1"), "{} should be real", fstype);
+        }
+    }
+
+    #[test]
+    fn test_overlay_depends_on_source() {
+        assert!(is_pseudo_fs("overlay", "overlay"));
+        assert!(!is_pseudo_fs("overlay", "/dev/mapper/root"));
+    }
+
+    #[test]
+    fn test_mount_entry_is_pseudo() {
+        let m = MountEntry::parse("/dev/nvme0n1p2 / ext4 rw,relatime 0 0").unwrap();
+        assert!(!m.is_pseudo());
+        let m = MountEntry::parse("tmpfs /run tmpfs rw,nosuid,nodev 0 0").unwrap();
+        assert!(m.is_pseudo());
+    }
+
+    #[test]
+    fn test_raw_keeps_escapes() {
+        let line = "/dev/sdb1 /media/My\\040Stick vfat rw 0 0";
+        let m = MountEntry::parse_with(line, false).unwrap();
+        assert_eq!(m.target.as_str(), "/media/My Stick");
+        let m = MountEntry::parse_with(line, true).unwrap();
+        assert_eq!(m.target.as_str(), "/media/My\\040Stick");
+    }
+}
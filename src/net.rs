@@ -4,23 +4,47 @@
 //! statistics, IP addresses, and wireless signal info.
 //!
 //! IP addresses are parsed from /proc/net/fib_trie (IPv4) and /proc/net/if_inet6 (IPv6).
-//! Wireless signal quality comes from /proc/net/wireless.
+//! Traffic counters come from each interface's `statistics/` directory;
+//! where that's missing (some virtual interfaces, some containers) they're
+//! taken from /proc/net/dev instead.
+//! Wireless signal quality comes from /proc/net/wireless, or from the
+//! interface's `wireless/` sysfs directory when that has it and procfs
+//! doesn't. The cfg80211 phy (`phy80211` link) is shown with -v.
+//!
+//! With `--oui-file`, MAC addresses are looked up in an IEEE OUI list for
+//! MAC_VENDOR= (see `oui`).
+//!
+//! The SSID and channel of the current connection aren't in sysfs or procfs;
+//! only nl80211 (netlink) has them, which is out of scope for kv.
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
-use crate::fields::net as f;
-use crate::filter::{matches_any, opt_str};
+use crate::baseline::{self, Baseline, Since};
+use crate::cli::{self, ExtraArgs, GlobalOptions};
+use crate::fields::{kind, net as f};
+use crate::filter::{opt_str, Filterable};
 use crate::io;
 use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::oui::{MacVendors, VendorName};
+use crate::page::Page;
 use crate::print::{self, TextWriter};
+use crate::profile::{Phase, Timer};
 use crate::stack::StackString;
+use crate::sum::Totals;
+use crate::template::Template;
 
 const NET_SYSFS_PATH: &str = "/sys/class/net";
 const PROC_NET_WIRELESS: &str = "/proc/net/wireless";
 const PROC_NET_IF_INET6: &str = "/proc/net/if_inet6";
 const PROC_NET_FIB_TRIE: &str = "/proc/net/fib_trie";
 const PROC_NET_ROUTE: &str = "/proc/net/route";
+const PROC_NET_DEV: &str = "/proc/net/dev";
+
+/// IFF_UP in /sys/class/net/<if>/flags: the interface is administratively up.
+const IFF_UP: u32 = 0x1;
+
+/// Where local IPv4 addresses go when no route ties them to an interface.
+const UNKNOWN_IFACE: &str = "unknown";
 
 // =============================================================================
 // Stack-based lookup tables and limits
@@ -38,6 +62,25 @@ const MAX_TOTAL_IPS: usize = 256;
 /// Maximum number of routes to track.
 const MAX_ROUTES: usize = 256;
 
+/// Why a link is or isn't passing traffic, from the interface flags,
+/// carrier and operstate.
+///
+/// operstate "down" covers both "nobody ran `ip link set up`" and "the
+/// cable is out"; IFF_UP and carrier tell them apart. Returns "up",
+/// "down(admin)", "down(no-carrier)", "dormant", or plain "down" when
+/// the kernel says down for some other reason.
+pub fn flags_to_status(flags: u32, carrier: Option<bool>, operstate: Option<&str>) -> &'static str {
+    if flags & IFF_UP == 0 {
+        return "down(admin)";
+    }
+    match (operstate, carrier) {
+        (Some("dormant"), _) => "dormant",
+        (_, Some(false)) => "down(no-carrier)",
+        (Some("down") | Some("lowerlayerdown") | Some("notpresent"), _) => "down",
+        _ => "up",
+    }
+}
+
 /// Wireless signal information.
 #[derive(Clone, Copy)]
 pub struct WirelessInfo {
@@ -81,6 +124,10 @@ impl IpList {
             None
         }
     }
+
+    fn iter(&self) -> impl Iterator<Item = &str> {
+        self.ips[..self.count].iter().map(|s| s.as_str())
+    }
 }
 
 /// Stack-based IPv4 address map.
@@ -197,6 +244,8 @@ impl WirelessMap {
 struct RouteTable {
     entries: [(StackString<16>, u32, u32); MAX_ROUTES],
     count: usize,
+    /// Default route (0.0.0.0/0) with the lowest metric: (iface, gateway, metric)
+    default: Option<(StackString<16>, u32, u32)>,
 }
 
 impl RouteTable {
@@ -204,6 +253,24 @@ impl RouteTable {
         Self {
             entries: core::array::from_fn(|_| (StackString::new(), 0, 0)),
             count: 0,
+            default: None,
+        }
+    }
+
+    /// Remember a default route, keeping the one with the lowest metric.
+    fn set_default(&mut self, iface: &str, gateway: u32, metric: u32) {
+        if matches!(self.default, Some((_, _, best)) if metric >= best) {
+            return;
+        }
+        self.default = Some((StackString::from_str(iface), gateway, metric));
+    }
+
+    /// Gateway of the default route if it goes through this interface.
+    /// Returns Some(0) for a default route without a gateway (point-to-point links).
+    fn default_gateway(&self, iface: &str) -> Option<u32> {
+        match self.default {
+            Some((ref name, gateway, _)) if name.as_str() == iface => Some(gateway),
+            _ => None,
         }
     }
 
@@ -259,6 +326,8 @@ pub struct NetInterface {
     pub name: StackString<16>,
     /// MAC address (e.g., "00:11:22:33:44:55")
     pub mac_address: Option<StackString<32>>,
+    /// Who made it, from the --oui-file list (see `set_mac_vendor`)
+    pub mac_vendor: Option<VendorName>,
     /// MTU (Maximum Transmission Unit)
     pub mtu: Option<u32>,
     /// Operational state (up, down, unknown, etc.)
@@ -271,10 +340,18 @@ pub struct NetInterface {
     pub tx_queue_len: Option<u32>,
     /// Is the interface up (carrier detected)?
     pub carrier: Option<bool>,
+    /// Has it been brought up (IFF_UP), whatever the link is doing?
+    pub admin_up: Option<bool>,
+    /// operstate, admin state and carrier folded into one (see flags_to_status)
+    pub status: Option<&'static str>,
     /// Duplex mode (full, half)
     pub duplex: Option<StackString<16>>,
     /// Wireless info
     pub wireless: Option<WirelessInfo>,
+    /// cfg80211 phy the interface belongs to (e.g., "phy0")
+    pub wifi_phy: Option<StackString<16>>,
+    /// Index of that phy (the N in phyN)
+    pub wifi_phy_index: Option<u32>,
     /// Bytes received
     pub rx_bytes: Option<u64>,
     /// Bytes transmitted
@@ -291,15 +368,40 @@ pub struct NetInterface {
     pub rx_dropped: Option<u64>,
     /// Transmit dropped
     pub tx_dropped: Option<u64>,
+    /// Does the default route (0.0.0.0/0) go through this interface?
+    pub default_route: bool,
+    /// Default gateway (e.g., "192.168.1.1"), only on the default route interface
+    pub gateway: Option<StackString<16>>,
+    /// Bound driver (e.g., "e1000e"); virtual interfaces have none
+    pub driver: Option<StackString<32>>,
+    /// Address of the underlying device (e.g., "0000:00:1f.6" for PCI), like `ethtool -i`
+    pub bus_info: Option<StackString<32>>,
 }
 
 impl NetInterface {
-    /// Read interface with pre-parsed IP and wireless data.
+    /// Read interface with pre-parsed IP and wireless data, retrying once
+    /// if we raced a hot-plug.
     fn read_with_extra(
+        name: &str,
+        ipv4_map: &Ipv4Map,
+        ipv6_map: &Ipv6Map,
+        wireless_map: &WirelessMap,
+        routes: &RouteTable,
+        stable_read: bool,
+    ) -> Option<Self> {
+        let base: StackString<64> = io::join_path(NET_SYSFS_PATH, name);
+        io::retry_if_present(base.as_str(), || {
+            Self::read_once(name, ipv4_map, ipv6_map, wireless_map, routes, stable_read)
+        })
+    }
+
+    fn read_once(
         name: &str,
         _ipv4_map: &Ipv4Map,
         _ipv6_map: &Ipv6Map,
         wireless_map: &WirelessMap,
+        routes: &RouteTable,
+        stable_read: bool,
     ) -> Option<Self> {
         let base: StackString<64> = io::join_path(NET_SYSFS_PATH, name);
 
@@ -314,7 +416,15 @@ impl NetInterface {
         let type_path: StackString<128> = io::join_path(base.as_str(), "type");
         let txq_path: StackString<128> = io::join_path(base.as_str(), "tx_queue_len");
         let carrier_path: StackString<128> = io::join_path(base.as_str(), "carrier");
+        let flags_path: StackString<128> = io::join_path(base.as_str(), "flags");
         let duplex_path: StackString<128> = io::join_path(base.as_str(), "duplex");
+        // Only hardware-backed interfaces have a device link (not lo, bridges, ...)
+        let device_path: StackString<128> = io::join_path(base.as_str(), "device");
+        let driver_path: StackString<128> = io::join_path(device_path.as_str(), "driver");
+        // Wireless interfaces link to their cfg80211 phy
+        let phy_path: StackString<128> = io::join_path(base.as_str(), "phy80211");
+        let phy_name_path: StackString<128> = io::join_path(phy_path.as_str(), "name");
+        let phy_index_path: StackString<128> = io::join_path(phy_path.as_str(), "index");
 
         let stats_base: StackString<128> = io::join_path(base.as_str(), "statistics");
         let rx_bytes_path: StackString<128> = io::join_path(stats_base.as_str(), "rx_bytes");
@@ -326,40 +436,122 @@ impl NetInterface {
         let rx_dropped_path: StackString<128> = io::join_path(stats_base.as_str(), "rx_dropped");
         let tx_dropped_path: StackString<128> = io::join_path(stats_base.as_str(), "tx_dropped");
 
-        Some(NetInterface {
+        let default_gateway = routes.default_gateway(name);
+
+        // Statistics can be caught mid-update; --stable-read re-reads them
+        let counter = |path: &str| -> Option<u64> {
+            if stable_read {
+                io::read_file_parse_stable(path)
+            } else {
+                io::read_file_parse(path)
+            }
+        };
+
+        // Every interface has an mtu; if it's gone, so is the interface
+        let mtu = io::read_file_parse(mtu_path.as_str())?;
+
+        let operstate: Option<StackString<16>> = io::read_file_stack(oper_path.as_str());
+        // carrier can't be read (EINVAL) while the interface is down
+        let carrier = io::read_file_parse::<u8>(carrier_path.as_str()).map(|v| v != 0);
+        let flags: Option<u32> = io::read_file_hex(flags_path.as_str());
+        let status = flags.map(|flags| flags_to_status(flags, carrier, operstate.as_ref().map(|s| s.as_str())));
+
+        let mut iface = NetInterface {
             name: StackString::from_str(name),
             mac_address: io::read_file_stack(addr_path.as_str()),
-            mtu: io::read_file_parse(mtu_path.as_str()),
-            operstate: io::read_file_stack(oper_path.as_str()),
+            mac_vendor: None,
+            mtu: Some(mtu),
+            operstate,
             speed_mbps: io::read_file_parse(speed_path.as_str()),
             if_type: io::read_file_parse(type_path.as_str()),
             tx_queue_len: io::read_file_parse(txq_path.as_str()),
-            carrier: io::read_file_parse::<u8>(carrier_path.as_str()).map(|v| v != 0),
+            carrier,
+            admin_up: flags.map(|flags| flags & IFF_UP != 0),
+            status,
             duplex: io::read_file_stack(duplex_path.as_str()),
-            wireless: wireless_map.get(name).copied(),
-            rx_bytes: io::read_file_parse(rx_bytes_path.as_str()),
-            tx_bytes: io::read_file_parse(tx_bytes_path.as_str()),
-            rx_packets: io::read_file_parse(rx_packets_path.as_str()),
-            tx_packets: io::read_file_parse(tx_packets_path.as_str()),
-            rx_errors: io::read_file_parse(rx_errors_path.as_str()),
-            tx_errors: io::read_file_parse(tx_errors_path.as_str()),
-            rx_dropped: io::read_file_parse(rx_dropped_path.as_str()),
-            tx_dropped: io::read_file_parse(tx_dropped_path.as_str()),
-        })
+            wireless: wireless_map.get(name).copied().or_else(|| read_sysfs_wireless(base.as_str())),
+            wifi_phy: io::read_file_stack(phy_name_path.as_str()),
+            wifi_phy_index: io::read_file_parse(phy_index_path.as_str()),
+            rx_bytes: counter(rx_bytes_path.as_str()),
+            tx_bytes: counter(tx_bytes_path.as_str()),
+            rx_packets: counter(rx_packets_path.as_str()),
+            tx_packets: counter(tx_packets_path.as_str()),
+            rx_errors: counter(rx_errors_path.as_str()),
+            tx_errors: counter(tx_errors_path.as_str()),
+            rx_dropped: counter(rx_dropped_path.as_str()),
+            tx_dropped: counter(tx_dropped_path.as_str()),
+            default_route: default_gateway.is_some(),
+            gateway: default_gateway.filter(|&gw| gw != 0).map(format_ipv4),
+            driver: io::read_symlink_name(driver_path.as_str()),
+            bus_info: io::read_symlink_name(device_path.as_str()),
+        };
+        iface.fill_counters_from_proc();
+        Some(iface)
     }
 
-    /// Check if this interface matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let fields = [
-            self.name.as_str(),
-            opt_str(&self.mac_address),
-            opt_str(&self.operstate),
-        ];
-        matches_any(&fields, pattern, case_insensitive)
+    /// Look up the vendor of our MAC address (nothing without --oui-file).
+    fn set_mac_vendor(&mut self, vendors: Option<&MacVendors>) {
+        let vendor = vendors.zip(self.mac_address.as_ref()).and_then(|(v, mac)| v.get(mac.as_str()));
+        self.mac_vendor = vendor.map(StackString::from_str);
+    }
+
+    /// Fill in counters sysfs didn't have from /proc/net/dev. Some virtual
+    /// interfaces, and the /sys of some containers, have no statistics/.
+    fn fill_counters_from_proc(&mut self) {
+        if self.stats().iter().all(|&(_, v)| v.is_some()) {
+            return;
+        }
+        let Some(c) = read_proc_net_dev(self.name.as_str()) else { return };
+        self.rx_bytes = self.rx_bytes.or(Some(c.rx_bytes));
+        self.tx_bytes = self.tx_bytes.or(Some(c.tx_bytes));
+        self.rx_packets = self.rx_packets.or(Some(c.rx_packets));
+        self.tx_packets = self.tx_packets.or(Some(c.tx_packets));
+        self.rx_errors = self.rx_errors.or(Some(c.rx_errors));
+        self.tx_errors = self.tx_errors.or(Some(c.tx_errors));
+        self.rx_dropped = self.rx_dropped.or(Some(c.rx_dropped));
+        self.tx_dropped = self.tx_dropped.or(Some(c.tx_dropped));
+    }
+
+    /// Counters that --baseline reports deltas for.
+    fn counters(&self) -> [(&'static str, Option<u64>); 4] {
+        [
+            (f::RX_BYTES, self.rx_bytes),
+            (f::TX_BYTES, self.tx_bytes),
+            (f::RX_PACKETS, self.rx_packets),
+            (f::TX_PACKETS, self.tx_packets),
+        ]
+    }
+
+    /// Average RX and TX utilization since the baseline, as (text key,
+    /// JSON key, percent). Links are full duplex, so each direction has
+    /// the whole speed to itself.
+    fn utilization(&self, prev: &Since) -> [(&'static str, &'static str, Option<u64>); 2] {
+        let percent = |now: Option<u64>, key: &str| {
+            let bytes = baseline::delta(now, prev.entry.get(key).and_then(|v| v.as_u64()))?;
+            link_utilization_percent(bytes, prev.interval_s?, self.speed_mbps?)
+        };
+        [
+            (f::RX_UTIL, f::RX_UTIL_PERCENT, percent(self.rx_bytes, f::RX_BYTES)),
+            (f::TX_UTIL, f::TX_UTIL_PERCENT, percent(self.tx_bytes, f::TX_BYTES)),
+        ]
+    }
+
+    /// Counters for --stats-only, in STATS_COLUMNS order.
+    fn stats(&self) -> [(&'static str, Option<u64>); 8] {
+        [
+            (f::RX_BYTES, self.rx_bytes),
+            (f::TX_BYTES, self.tx_bytes),
+            (f::RX_PACKETS, self.rx_packets),
+            (f::TX_PACKETS, self.tx_packets),
+            (f::RX_ERRORS, self.rx_errors),
+            (f::TX_ERRORS, self.tx_errors),
+            (f::RX_DROPPED, self.rx_dropped),
+            (f::TX_DROPPED, self.tx_dropped),
+        ]
     }
 
     /// Output as text.
-    fn print_text(&self, verbose: bool, human: bool, ipv4_map: &Ipv4Map, ipv6_map: &Ipv6Map) {
+    fn print_text(&self, verbose: bool, human: bool, ipv4_map: &Ipv4Map, ipv6_map: &Ipv6Map, prev: Option<&Since>) {
         let mut w = TextWriter::new();
 
         w.field_str(f::NAME, self.name.as_str());
@@ -367,11 +559,15 @@ impl NetInterface {
         if let Some(ref mac) = self.mac_address {
             w.field_str(f::MAC, mac.as_str());
         }
+        w.field_quoted_opt(f::MAC_VENDOR, self.mac_vendor.as_ref().map(|s| s.as_str()));
         if let Some(mtu) = self.mtu {
             w.field_u64(f::MTU, mtu as u64);
         }
         if let Some(ref state) = self.operstate {
-            w.field_str(f::STATE, state.as_str());
+            w.field_explained(f::STATE, state.as_str());
+        }
+        if let Some(status) = self.status {
+            w.field_explained(f::STATUS, status);
         }
         if let Some(speed) = self.speed_mbps {
             w.field_u64(f::SPEED, speed as u64);
@@ -433,6 +629,7 @@ impl NetInterface {
                     w.field_str(f::NOISE, noise.as_str());
                 }
             }
+            w.field_str_opt(f::WIFI_PHY, self.wifi_phy.as_ref().map(|s| s.as_str()));
 
             if let Some(ref duplex) = self.duplex {
                 w.field_str(f::DUPLEX, duplex.as_str());
@@ -440,6 +637,18 @@ impl NetInterface {
             if let Some(carrier) = self.carrier {
                 w.field_u64(f::CARRIER, if carrier { 1 } else { 0 });
             }
+            if self.default_route {
+                w.field_u64(f::DEFAULT, 1);
+            }
+            if let Some(ref gw) = self.gateway {
+                w.field_str(f::GATEWAY, gw.as_str());
+            }
+            if let Some(ref driver) = self.driver {
+                w.field_str(f::DRIVER, driver.as_str());
+            }
+            if let Some(ref bus) = self.bus_info {
+                w.field_str(f::BUS_INFO, bus.as_str());
+            }
             if human {
                 if let Some(rx) = self.rx_bytes {
                     let s = io::format_human_size(rx);
@@ -471,17 +680,37 @@ impl NetInterface {
             }
         }
 
-        w.finish();
+        if let Some(prev) = prev {
+            baseline::for_each_delta(&prev.entry, &self.counters(), |key, d| w.field_u64(key, d));
+            for (key, _, percent) in self.utilization(prev) {
+                if let Some(p) = percent {
+                    let mut s: StackString<24> = StackString::new();
+                    s.push_str(itoa::Buffer::new().format(p));
+                    s.push('%');
+                    w.field_str(key, s.as_str());
+                }
+            }
+        }
+
+        w.finish_record();
     }
 
     /// Write as JSON object.
-    fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool, human: bool, ipv4_map: &Ipv4Map, ipv6_map: &Ipv6Map) {
-        w.array_object_begin();
+    fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool, human: bool, ipv4_map: &Ipv4Map, ipv6_map: &Ipv6Map, prev: Option<&Since>) {
+        w.record_begin();
+        w.field_str(kind::KIND, kind::NET_INTERFACE);
 
         w.field_str(f::NAME, self.name.as_str());
         w.field_str_opt(f::MAC, self.mac_address.as_ref().map(|s| s.as_str()));
+        if let Some(ref vendor) = self.mac_vendor {
+            w.field_str(f::MAC_VENDOR, vendor.as_str());
+        }
         w.field_u64_opt(f::MTU, self.mtu.map(|v| v as u64));
         w.field_str_opt(f::STATE, self.operstate.as_ref().map(|s| s.as_str()));
+        w.field_str_opt(f::STATUS, self.status);
+        if let Some(admin_up) = self.admin_up {
+            w.field_bool(f::ADMIN_UP, admin_up);
+        }
         w.field_u64_opt(f::SPEED, self.speed_mbps.map(|v| v as u64));
 
         // First IPv4 address
@@ -526,6 +755,8 @@ impl NetInterface {
                     w.field_i64(f::NOISE, wifi.noise_dbm as i64);
                 }
             }
+            w.field_str_opt(f::WIFI_PHY, self.wifi_phy.as_ref().map(|s| s.as_str()));
+            w.field_u64_opt(f::WIFI_PHY_INDEX, self.wifi_phy_index.map(|v| v as u64));
 
             w.field_str_opt(f::DUPLEX, self.duplex.as_ref().map(|s| s.as_str()));
             w.field_u64_opt("if_type", self.if_type.map(|v| v as u64));
@@ -533,6 +764,10 @@ impl NetInterface {
             if let Some(carrier) = self.carrier {
                 w.field_bool(f::CARRIER, carrier);
             }
+            w.field_bool(f::DEFAULT_ROUTE, self.default_route);
+            w.field_str_opt(f::GATEWAY, self.gateway.as_ref().map(|s| s.as_str()));
+            w.field_str_opt(f::DRIVER, self.driver.as_ref().map(|s| s.as_str()));
+            w.field_str_opt(f::BUS_INFO, self.bus_info.as_ref().map(|s| s.as_str()));
             if human {
                 if let Some(rx) = self.rx_bytes {
                     let s = io::format_human_size(rx);
@@ -554,12 +789,135 @@ impl NetInterface {
             w.field_u64_opt(f::TX_DROPPED, self.tx_dropped);
         }
 
+        if let Some(prev) = prev {
+            baseline::for_each_delta(&prev.entry, &self.counters(), |key, d| w.field_u64(key, d));
+            for (_, key, percent) in self.utilization(prev) {
+                w.field_u64_opt(key, percent);
+            }
+        }
+
         w.array_object_end();
     }
 }
 
+impl Filterable for NetInterface {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::NAME, self.name.as_str());
+        visit(f::MAC, opt_str(&self.mac_address));
+        visit(f::MAC_VENDOR, opt_str(&self.mac_vendor));
+        visit(f::STATE, opt_str(&self.operstate));
+        visit(f::STATUS, self.status.unwrap_or(""));
+        visit(f::DRIVER, opt_str(&self.driver));
+    }
+
+    fn filter_numeric_fields(&self, visit: &mut dyn FnMut(&str, i64)) {
+        if let Some(mtu) = self.mtu {
+            visit(f::MTU, mtu as i64);
+        }
+        if let Some(speed) = self.speed_mbps {
+            visit(f::SPEED, speed as i64);
+        }
+        for (name, value) in self.stats() {
+            if let Some(v) = value {
+                visit(name, v as i64);
+            }
+        }
+    }
+}
+
+/// Every `--template` field, whether or not a record has it.
+const TEMPLATE_FIELDS: &[&str] = &[
+    f::NAME,
+    f::MAC,
+    f::MAC_VENDOR,
+    f::STATE,
+    f::STATUS,
+    f::DRIVER,
+    f::MTU,
+    f::SPEED,
+    f::RX_BYTES,
+    f::TX_BYTES,
+    f::RX_PACKETS,
+    f::TX_PACKETS,
+    f::RX_ERRORS,
+    f::TX_ERRORS,
+    f::RX_DROPPED,
+    f::TX_DROPPED,
+    f::IP,
+];
+
+/// Percent of a link's capacity used to move `bytes` in `interval_s`
+/// seconds, rounded down. None without a speed or an interval.
+///
+/// Can go past 100 if the link was renegotiated to a lower speed since.
+pub fn link_utilization_percent(bytes: u64, interval_s: u64, speed_mbps: u32) -> Option<u64> {
+    let capacity_bits = interval_s.checked_mul(speed_mbps as u64 * 1_000_000).filter(|&c| c > 0)?;
+    Some(bytes.saturating_mul(8 * 100) / capacity_bits)
+}
+
+/// One interface's counters from /proc/net/dev.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetDevCounters {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub tx_dropped: u64,
+}
+
+/// Parse one /proc/net/dev line into the interface name and its counters.
+///
+/// Format: "  eth0: 1234 56 0 0 0 0 0 0 7890 12 0 0 0 0 0 0", eight
+/// receive columns (bytes packets errs drop fifo frame compressed
+/// multicast) then eight transmit ones (bytes packets errs drop fifo
+/// colls carrier compressed). Old kernels put no space after the colon.
+pub fn parse_proc_net_dev_line(line: &str) -> Option<(&str, NetDevCounters)> {
+    let (name, rest) = line.split_once(':')?;
+    let mut cols = [0u64; 16];
+    let mut count = 0;
+    for (col, value) in cols.iter_mut().zip(rest.split_whitespace()) {
+        *col = value.parse().ok()?;
+        count += 1;
+    }
+    if count < cols.len() {
+        return None;
+    }
+    Some((name.trim(), NetDevCounters {
+        rx_bytes: cols[0],
+        rx_packets: cols[1],
+        rx_errors: cols[2],
+        rx_dropped: cols[3],
+        tx_bytes: cols[8],
+        tx_packets: cols[9],
+        tx_errors: cols[10],
+        tx_dropped: cols[11],
+    }))
+}
+
+/// Find `name` in the contents of /proc/net/dev (two header lines, then
+/// one line per interface).
+pub fn proc_net_dev_counters(content: &str, name: &str) -> Option<NetDevCounters> {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(parse_proc_net_dev_line)
+        .find(|&(iface, _)| iface == name)
+        .map(|(_, counters)| counters)
+}
+
+/// Counters for one interface from /proc/net/dev, for when sysfs has none.
+fn read_proc_net_dev(name: &str) -> Option<NetDevCounters> {
+    let _timer = Timer::start(Phase::ProcNet);
+    let content: StackString<16384> = io::read_file_stack(PROC_NET_DEV)?;
+    proc_net_dev_counters(content.as_str(), name)
+}
+
 /// Parse /proc/net/wireless for signal info.
 fn parse_proc_net_wireless(wireless_map: &mut WirelessMap) {
+    let _timer = Timer::start(Phase::ProcNet);
     let content: Option<StackString<4096>> = io::read_file_stack(PROC_NET_WIRELESS);
     let Some(content) = content else { return };
 
@@ -609,8 +967,37 @@ fn parse_proc_net_wireless(wireless_map: &mut WirelessMap) {
     }
 }
 
+/// Wireless extensions report dBm levels as an unsigned byte (-60 dBm
+/// reads 196). /proc/net/wireless undoes that; the sysfs files don't.
+fn wext_dbm(raw: i32) -> i32 {
+    if raw > 0 { raw - 256 } else { raw }
+}
+
+/// Signal info from `<iface>/wireless/{link,level,noise}` in sysfs.
+///
+/// These mirror /proc/net/wireless, so this is only a fallback for when
+/// that file is missing or doesn't list the interface.
+fn read_sysfs_wireless(base: &str) -> Option<WirelessInfo> {
+    let dir: StackString<128> = io::join_path(base, "wireless");
+    wireless_from_files(|name| {
+        let path: StackString<160> = io::join_path(dir.as_str(), name);
+        io::read_file_parse(path.as_str())
+    })
+}
+
+/// Build WirelessInfo from the wext sysfs files; `read` returns a file's value.
+fn wireless_from_files(read: impl Fn(&str) -> Option<i32>) -> Option<WirelessInfo> {
+    Some(WirelessInfo {
+        link_quality: read("link")?,
+        signal_dbm: wext_dbm(read("level")?),
+        // "not available" is -256 in /proc/net/wireless, 0 here
+        noise_dbm: read("noise").map(wext_dbm).filter(|&n| n != 0).unwrap_or(-256),
+    })
+}
+
 /// Parse /proc/net/if_inet6 for IPv6 addresses.
 fn parse_proc_net_if_inet6(ipv6_map: &mut Ipv6Map) {
+    let _timer = Timer::start(Phase::ProcNet);
     let content: Option<StackString<8192>> = io::read_file_stack(PROC_NET_IF_INET6);
     let Some(content) = content else { return };
 
@@ -638,39 +1025,105 @@ fn parse_proc_net_if_inet6(ipv6_map: &mut Ipv6Map) {
 }
 
 /// Convert 32-char hex string to IPv6 address notation.
+///
+/// Uses the canonical short form (RFC 5952): no leading zeros, and the
+/// longest run of two or more zero groups becomes "::" (e.g. "::1").
 fn hex_to_ipv6(hex: &str) -> Option<StackString<64>> {
-    if hex.len() != 32 {
+    if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
         return None;
     }
 
+    let mut groups = [0u16; 8];
+    for (i, group) in groups.iter_mut().enumerate() {
+        *group = u16::from_str_radix(&hex[i * 4..(i + 1) * 4], 16).ok()?;
+    }
+
+    // Find the longest run of zero groups (first one wins a tie)
+    let (mut zeros_start, mut zeros_len) = (groups.len(), 0);
+    let mut i = 0;
+    while i < groups.len() {
+        let start = i;
+        while i < groups.len() && groups[i] == 0 {
+            i += 1;
+        }
+        if i - start > zeros_len {
+            zeros_start = start;
+            zeros_len = i - start;
+        }
+        i += 1;
+    }
+    if zeros_len < 2 {
+        zeros_start = groups.len();
+        zeros_len = 0;
+    }
+
     let mut s: StackString<64> = StackString::new();
-    for i in 0..8 {
-        if i > 0 {
+    let mut i = 0;
+    while i < groups.len() {
+        if i == zeros_start {
+            s.push_str("::");
+            i += zeros_len;
+            continue;
+        }
+        if i > 0 && i != zeros_start + zeros_len {
             s.push(':');
         }
-        s.push_str(&hex[i * 4..(i + 1) * 4]);
+        let group = groups[i];
+        for shift in [12, 8, 4, 0] {
+            let digit = (group >> shift) & 0xf;
+            if group >> shift != 0 || shift == 0 {
+                s.push(char::from_digit(digit as u32, 16).unwrap_or('0'));
+            }
+        }
+        i += 1;
     }
     Some(s)
 }
 
 /// Parse /proc/net/route to build route table.
 fn parse_proc_net_route(routes: &mut RouteTable) {
+    let _timer = Timer::start(Phase::ProcNet);
     let content: Option<StackString<8192>> = io::read_file_stack(PROC_NET_ROUTE);
     let Some(content) = content else { return };
+    parse_route_table(content.as_str(), routes);
+}
 
+/// Parse the contents of /proc/net/route.
+///
+/// Columns: Iface Destination Gateway Flags RefCnt Use Metric Mask MTU Window IRTT
+fn parse_route_table(content: &str, routes: &mut RouteTable) {
     // Skip header line
-    for line in content.as_str().lines().skip(1) {
+    for line in content.lines().skip(1) {
         let mut parts = line.split('\t');
         let iface = match parts.next() { Some(s) => s, None => continue };
         let dest_hex = match parts.next() { Some(s) => s, None => continue };
-        // Skip gateway, flags, refcnt, use, metric
-        for _ in 0..5 { parts.next(); }
+        let gateway_hex = match parts.next() { Some(s) => s.trim(), None => continue };
+        // Skip flags, refcnt, use
+        for _ in 0..3 { parts.next(); }
+        let metric: u32 = parts.next().and_then(|s| s.trim().parse().ok()).unwrap_or(0);
         let mask_hex = match parts.next() { Some(s) => s, None => continue };
 
         if let (Some(dest), Some(mask)) = (parse_route_hex(dest_hex), parse_route_hex(mask_hex)) {
             routes.push(iface, dest, mask);
+            let gateway = parse_route_hex(gateway_hex);
+            if let (0, 0, Some(gateway)) = (dest, mask, gateway) {
+                routes.set_default(iface, gateway, metric);
+            }
+        }
+    }
+}
+
+/// Format a network-order IPv4 address as dotted quad.
+fn format_ipv4(addr: u32) -> StackString<16> {
+    let mut s: StackString<16> = StackString::new();
+    let mut buf = itoa::Buffer::new();
+    for (i, octet) in addr.to_be_bytes().iter().enumerate() {
+        if i > 0 {
+            s.push('.');
         }
+        s.push_str(buf.format(*octet));
     }
+    s
 }
 
 /// Parse hex IP from /proc/net/route and normalize to network byte order.
@@ -682,8 +1135,44 @@ fn parse_route_hex(hex: &str) -> Option<u32> {
     Some(val)
 }
 
-/// Find which interface an IP belongs to based on routing table.
-fn find_interface_for_ip<'a>(ip: &str, routes: &'a RouteTable) -> Option<&'a str> {
+/// Find which interface a local IPv4 address belongs to.
+///
+/// Loopback addresses go to `lo`. Otherwise the most specific route
+/// covering the address decides, even if that's only the default route
+/// (a /32 address with just a host route to its gateway, as some clouds
+/// hand out). Addresses no route covers - a fresh `noprefixroute`
+/// address on a host without a default route - are grouped under
+/// UNKNOWN_IFACE rather than dropped.
+fn find_interface_for_ip<'a>(ip: &str, routes: &'a RouteTable) -> &'a str {
+    if ip.starts_with("127.") {
+        return "lo";
+    }
+    best_route(ip, routes).map_or(UNKNOWN_IFACE, |(iface, _)| iface)
+}
+
+/// Is this fib_trie line the `/32 host LOCAL` entry of a local address?
+/// Wider `host LOCAL` entries are local ranges (`127.0.0.0/8`,
+/// `ip route add local 10.9.0.0/16 ...`), whose base isn't an address
+/// of any interface.
+fn is_fib_local_address(line: &str) -> bool {
+    line == "/32 host LOCAL"
+}
+
+/// Prefix length of a local IPv4 address, from the subnet route it sits on.
+///
+/// The kernel only lists subnet routes for the main table, so loopback
+/// addresses get the usual /8.
+fn ipv4_prefix_len(ip: &str, routes: &RouteTable) -> Option<u32> {
+    if ip.starts_with("127.") {
+        return Some(8);
+    }
+    best_route(ip, routes)
+        .filter(|&(_, mask)| mask != 0)
+        .map(|(_, mask)| mask.count_ones())
+}
+
+/// The most specific route covering an IP: (interface, mask).
+fn best_route<'a>(ip: &str, routes: &'a RouteTable) -> Option<(&'a str, u32)> {
     let mut parts_iter = ip.split('.');
     let p0: u8 = parts_iter.next()?.parse().ok()?;
     let p1: u8 = parts_iter.next()?.parse().ok()?;
@@ -707,18 +1196,27 @@ fn find_interface_for_ip<'a>(ip: &str, routes: &'a RouteTable) -> Option<&'a str
         }
     }
 
-    best_match.map(|(iface, _)| iface)
+    best_match
 }
 
 /// Parse /proc/net/fib_trie to extract local IPv4 addresses.
 fn parse_proc_net_fib_trie(ipv4_map: &mut Ipv4Map, routes: &RouteTable) {
+    let _timer = Timer::start(Phase::FibTrie);
     let content: Option<StackString<65536>> = io::read_file_stack(PROC_NET_FIB_TRIE);
     let Some(content) = content else { return };
+    parse_fib_trie(content.as_str(), ipv4_map, routes);
+}
 
+/// Collect local addresses from fib_trie content into `ipv4_map`.
+///
+/// Each leaf is a `|-- <ip>` line followed by one `/<len> <scope> <type>`
+/// line per route at that key. A `/32 host LOCAL` entry marks a local
+/// address, whichever table (Main or Local) it shows up in.
+fn parse_fib_trie(content: &str, ipv4_map: &mut Ipv4Map, routes: &RouteTable) {
     let mut seen = IpSet::new();
     let mut current_ip: Option<StackString<32>> = None;
 
-    for line in content.as_str().lines() {
+    for line in content.lines() {
         if seen.count >= MAX_TOTAL_IPS {
             break;
         }
@@ -731,32 +1229,280 @@ fn parse_proc_net_fib_trie(ipv4_map: &mut Ipv4Map, routes: &RouteTable) {
             if ip_part.chars().all(|c| c.is_ascii_digit() || c == '.') {
                 current_ip = Some(StackString::from_str(ip_part));
             }
-        } else if trimmed.contains("/32 host LOCAL") {
-            if let Some(ref ip) = current_ip {
-                if !seen.insert(ip.as_str()) {
-                    continue;
-                }
+        } else if is_fib_local_address(trimmed) {
+            let Some(ref ip) = current_ip else { continue };
+            if !seen.insert(ip.as_str()) {
+                continue;
+            }
+            let iface = find_interface_for_ip(ip.as_str(), routes);
+            if let Some(ip_list) = ipv4_map.get_or_insert(iface) {
+                ip_list.push(ip.as_str());
+            }
+        }
+    }
+}
 
-                // Loopback addresses (127.x.x.x) go to 'lo'
-                if ip.as_str().starts_with("127.") {
-                    if let Some(ip_list) = ipv4_map.get_or_insert("lo") {
-                        ip_list.push(ip.as_str());
-                    }
-                } else if let Some(iface) = find_interface_for_ip(ip.as_str(), routes) {
-                    if let Some(ip_list) = ipv4_map.get_or_insert(iface) {
-                        ip_list.push(ip.as_str());
-                    }
-                }
+/// Call `f(family, address, prefix)` for each address of an interface,
+/// IPv4 first. `family` is "inet" or "inet6", as in `ip addr`.
+fn for_each_address(
+    name: &str,
+    ipv4_map: &Ipv4Map,
+    ipv6_map: &Ipv6Map,
+    routes: &RouteTable,
+    mut f: impl FnMut(&str, &str, Option<u32>),
+) {
+    if let Some(ip_list) = ipv4_map.get(name) {
+        for ip in ip_list.iter() {
+            f("inet", ip, ipv4_prefix_len(ip, routes));
+        }
+    }
+    if let Some(ip_list) = ipv6_map.get(name) {
+        // Stored as "addr/prefix"
+        for entry in ip_list.iter() {
+            let (addr, prefix) = match entry.split_once('/') {
+                Some((addr, prefix)) => (addr, prefix.parse().ok()),
+                None => (entry, None),
+            };
+            f("inet6", addr, prefix);
+        }
+    }
+}
+
+/// Print one `eth0 inet 192.168.1.5/24` line.
+fn print_address_text(name: &str, family: &str, address: &str, prefix: Option<u32>) {
+    print::note_data();
+    print::print(name);
+    print::print(" ");
+    print::print(family);
+    print::print(" ");
+    print::print(address);
+    if let Some(prefix) = prefix {
+        let mut buf = itoa::Buffer::new();
+        print::print("/");
+        print::print(buf.format(prefix));
+    }
+    print::println_empty();
+}
+
+/// `kv net --addresses`: one line per address, like `ip -br addr`.
+/// Interfaces without addresses are left out.
+fn print_addresses(
+    opts: &GlobalOptions,
+    ipv4_map: &Ipv4Map,
+    ipv6_map: &Ipv6Map,
+    wireless_map: &WirelessMap,
+    routes: &RouteTable,
+) {
+    let filter = &opts.filter;
+
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "net");
+        w.field_array("data");
+        io::for_each_dir_entry_sorted(NET_SYSFS_PATH, io::natural_cmp, |name| {
+            let Some(iface) = NetInterface::read_with_extra(name, ipv4_map, ipv6_map, wireless_map, routes, false) else {
+                return;
+            };
+            if !iface.matches_filter(filter) {
+                return;
             }
+            for_each_address(name, ipv4_map, ipv6_map, routes, |family, address, prefix| {
+                w.record_begin();
+                w.field_str(kind::KIND, kind::NET_ADDRESS);
+                w.field_str(f::IFACE, name);
+                w.field_str(f::FAMILY, family);
+                w.field_str(f::ADDRESS, address);
+                w.field_u64_opt(f::PREFIX, prefix.map(|p| p as u64));
+                w.array_object_end();
+            });
+        });
+        if !filter.is_active() {
+            for_each_address(UNKNOWN_IFACE, ipv4_map, ipv6_map, routes, |family, address, prefix| {
+                w.record_begin();
+                w.field_str(kind::KIND, kind::NET_ADDRESS);
+                w.field_str(f::IFACE, UNKNOWN_IFACE);
+                w.field_str(f::FAMILY, family);
+                w.field_str(f::ADDRESS, address);
+                w.field_u64_opt(f::PREFIX, prefix.map(|p| p as u64));
+                w.array_object_end();
+            });
         }
+        w.end_field_array();
+        w.end_object();
+        w.finish();
+    } else {
+        let mut count = 0;
+        io::for_each_dir_entry_sorted(NET_SYSFS_PATH, io::natural_cmp, |name| {
+            let Some(iface) = NetInterface::read_with_extra(name, ipv4_map, ipv6_map, wireless_map, routes, false) else {
+                return;
+            };
+            if !iface.matches_filter(filter) {
+                return;
+            }
+            for_each_address(name, ipv4_map, ipv6_map, routes, |family, address, prefix| {
+                print_address_text(name, family, address, prefix);
+                count += 1;
+            });
+        });
+        // Addresses no route ties to an interface
+        if !filter.is_active() {
+            for_each_address(UNKNOWN_IFACE, ipv4_map, ipv6_map, routes, |family, address, prefix| {
+                print_address_text(UNKNOWN_IFACE, family, address, prefix);
+                count += 1;
+            });
+        }
+        if count == 0 {
+            print::println("net: no addresses found");
+        }
+    }
+}
+
+/// Header for `--stats-only --header`. The order never changes.
+const STATS_HEADER: &str = "iface rx_bytes tx_bytes rx_packets tx_packets rx_errors tx_errors rx_dropped tx_dropped";
+
+/// One `--stats-only` line: the name, then each counter ("-" if unreadable).
+fn format_stats_line(name: &str, stats: &[(&str, Option<u64>)]) -> StackString<256> {
+    let mut line: StackString<256> = StackString::from_str(name);
+    let mut buf = itoa::Buffer::new();
+    for &(_, value) in stats {
+        line.push(' ');
+        line.push_str(value.map_or("-", |v| buf.format(v)));
     }
+    line
+}
+
+/// `kv net --stats-only`: just the counters, one interface per line.
+fn print_stats(opts: &GlobalOptions, net_opts: &NetOptions, ipv4_map: &Ipv4Map, ipv6_map: &Ipv6Map, wireless_map: &WirelessMap, routes: &RouteTable) {
+    let filter = &opts.filter;
+    let mut page = Page::from_options(opts);
+
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "net");
+        w.field_array("data");
+        io::for_each_dir_entry_sorted(NET_SYSFS_PATH, io::natural_cmp, |name| {
+            let Some(iface) = NetInterface::read_with_extra(name, ipv4_map, ipv6_map, wireless_map, routes, opts.stable_read) else {
+                return;
+            };
+            if !iface.matches_filter(filter) || !page.take() {
+                return;
+            }
+            w.record_begin();
+            w.field_str(kind::KIND, kind::NET_COUNTERS);
+            w.field_str(f::IFACE, name);
+            for (key, value) in iface.stats() {
+                w.field_u64_opt(key, value);
+            }
+            w.array_object_end();
+        });
+        w.end_field_array();
+        page.write_json(&mut w);
+        w.end_object();
+        w.finish();
+    } else {
+        // No "no interfaces" message: an empty result is still valid columns
+        if net_opts.header {
+            print::println(STATS_HEADER);
+        }
+        io::for_each_dir_entry_sorted(NET_SYSFS_PATH, io::natural_cmp, |name| {
+            let Some(iface) = NetInterface::read_with_extra(name, ipv4_map, ipv6_map, wireless_map, routes, opts.stable_read) else {
+                return;
+            };
+            if iface.matches_filter(filter) && page.take() {
+                print::println_record(format_stats_line(name, &iface.stats()).as_str());
+            }
+        });
+    }
+}
+
+/// Options specific to the net subcommand.
+#[derive(Default)]
+pub struct NetOptions {
+    /// Only list addresses, one per line
+    pub addresses: bool,
+    /// Only the traffic counters, as plain columns
+    pub stats_only: bool,
+    /// Print a column header first (with --stats-only)
+    pub header: bool,
+}
+
+impl NetOptions {
+    /// Parse net-specific options from remaining arguments.
+    pub fn parse(args: &ExtraArgs) -> Self {
+        let mut opts = NetOptions::default();
+        for arg in args.iter() {
+            match arg {
+                "-a" | "--addresses" => opts.addresses = true,
+                "--stats-only" => opts.stats_only = true,
+                "--header" => opts.header = true,
+                _ => {}
+            }
+        }
+        opts
+    }
+}
+
+/// Vendors of the interfaces' MAC addresses, from the OUI list at `path`.
+///
+/// The list is too big to keep, so we collect the OUIs we'll need first
+/// and read just those from it. None (with a warning) if it can't be read.
+fn load_mac_vendors(path: &str) -> Option<MacVendors> {
+    let mut vendors = MacVendors::new();
+    io::for_each_dir_entry(NET_SYSFS_PATH, |name| {
+        let base: StackString<64> = io::join_path(NET_SYSFS_PATH, name);
+        let addr_path: StackString<128> = io::join_path(base.as_str(), "address");
+        if let Some(mac) = io::read_file_stack::<32>(addr_path.as_str()) {
+            vendors.want(mac.as_str());
+        }
+    });
+    if !vendors.load(path) {
+        print::eprint("Warning: cannot read OUI file ");
+        print::eprintln(path);
+        return None;
+    }
+    Some(vendors)
 }
 
 /// Entry point for `kv net` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let net_opts = NetOptions::parse(args);
+
+    // One line per address isn't a list of interfaces to page or total
+    if net_opts.addresses {
+        let ignored = opts.list_option().or(baseline::parse_path(args).map(|_| "--baseline"));
+        if let Some(option) = ignored {
+            cli::reject_combination(option, "--addresses");
+            return 1;
+        }
+    }
+
+    // The columns are fixed, so there's nothing to template, total or diff
+    if net_opts.stats_only {
+        let ignored = opts
+            .sum
+            .is_some()
+            .then_some("--sum")
+            .or(opts.template.is_some().then_some("--template"))
+            .or(baseline::parse_path(args).map(|_| "--baseline"));
+        if let Some(option) = ignored {
+            cli::reject_combination(option, "--stats-only");
+            return 1;
+        }
+    }
+
+    let baseline = match baseline::parse_path(args) {
+        Some(path) => match Baseline::load(path) {
+            Some(b) => Some(b),
+            None => {
+                print::eprint("Error: cannot read baseline ");
+                print::eprintln(path);
+                return 1;
+            }
+        },
+        None => None,
+    };
+
     if !io::path_exists(NET_SYSFS_PATH) {
         if opts.json {
-            let mut w = begin_kv_output_streaming(opts.pretty, "net");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "net");
             w.field_array("data");
             w.end_field_array();
             w.end_object();
@@ -778,49 +1524,97 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     parse_proc_net_route(&mut routes);
     parse_proc_net_fib_trie(&mut ipv4_map, &routes);
 
-    let filter = opts.filter.as_ref().map(|s| s.as_str());
-    let case_insensitive = opts.filter_case_insensitive;
+    if net_opts.addresses {
+        print_addresses(opts, &ipv4_map, &ipv6_map, &wireless_map, &routes);
+        return 0;
+    }
+    if net_opts.stats_only {
+        print_stats(opts, &net_opts, &ipv4_map, &ipv6_map, &wireless_map, &routes);
+        return 0;
+    }
+
+    let filter = &opts.filter;
+    let vendors = opts.oui_file.as_ref().and_then(|path| load_mac_vendors(path.as_str()));
+
+    if let Some(fields) = &opts.sum {
+        let mut totals = Totals::new(fields);
+        io::for_each_dir_entry(NET_SYSFS_PATH, |name| {
+            if let Some(mut iface) = NetInterface::read_with_extra(name, &ipv4_map, &ipv6_map, &wireless_map, &routes, opts.stable_read) {
+                iface.set_mac_vendor(vendors.as_ref());
+                if iface.matches_filter(filter) {
+                    totals.add(&iface);
+                }
+            }
+        });
+        if opts.json {
+            totals.print_json(opts.json_layout(), opts.human, "net");
+        } else {
+            totals.print_text(opts.human);
+        }
+        return 0;
+    }
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "net");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "net");
+        if let Some(interval) = baseline.as_ref().and_then(Baseline::interval) {
+            w.field_u64("baseline_interval_s", interval);
+        }
         w.field_array("data");
 
         let mut count = 0;
-        io::for_each_dir_entry(NET_SYSFS_PATH, |name| {
-            if let Some(iface) = NetInterface::read_with_extra(name, &ipv4_map, &ipv6_map, &wireless_map) {
-                if let Some(pattern) = filter {
-                    if !iface.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+        let mut page = Page::from_options(opts);
+        io::for_each_dir_entry_sorted(NET_SYSFS_PATH, io::natural_cmp, |name| {
+            if let Some(mut iface) = NetInterface::read_with_extra(name, &ipv4_map, &ipv6_map, &wireless_map, &routes, opts.stable_read) {
+                iface.set_mac_vendor(vendors.as_ref());
+                if !iface.matches_filter(filter) {
+                    return;
                 }
-                iface.write_json(&mut w, opts.verbose, opts.human, &ipv4_map, &ipv6_map);
                 count += 1;
+                if !page.take() {
+                    return;
+                }
+                let prev = baseline.as_ref().and_then(|b| b.entry_since("net", name));
+                iface.write_json(&mut w, opts.verbose, opts.human, &ipv4_map, &ipv6_map, prev.as_ref());
             }
         });
 
         w.end_field_array();
+        page.write_json(&mut w);
         w.end_object();
         w.finish();
 
-        if count == 0 && filter.is_some() {
+        if count == 0 && filter.is_active() {
             // Empty filtered result is fine
         }
     } else {
         let mut count = 0;
-        io::for_each_dir_entry(NET_SYSFS_PATH, |name| {
-            if let Some(iface) = NetInterface::read_with_extra(name, &ipv4_map, &ipv6_map, &wireless_map) {
-                if let Some(pattern) = filter {
-                    if !iface.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+        let mut template = Template::from_options(opts, TEMPLATE_FIELDS);
+        let mut page = Page::from_options(opts);
+        io::for_each_dir_entry_sorted(NET_SYSFS_PATH, io::natural_cmp, |name| {
+            if let Some(mut iface) = NetInterface::read_with_extra(name, &ipv4_map, &ipv6_map, &wireless_map, &routes, opts.stable_read) {
+                iface.set_mac_vendor(vendors.as_ref());
+                if !iface.matches_filter(filter) {
+                    return;
                 }
-                iface.print_text(opts.verbose, opts.human, &ipv4_map, &ipv6_map);
                 count += 1;
+                if !page.take() {
+                    return;
+                }
+                if let Some(t) = template.as_mut() {
+                    let ip = ipv4_map.get(name).and_then(|ips| ips.first()).unwrap_or("");
+                    t.print(&iface, &[(f::IP, ip)]);
+                } else {
+                    let prev = baseline.as_ref().and_then(|b| b.entry_since("net", name));
+                    iface.print_text(opts.verbose, opts.human, &ipv4_map, &ipv6_map, prev.as_ref());
+                }
             }
         });
+        if let Some(t) = &template {
+            t.finish();
+        }
 
         if count == 0 {
-            if filter.is_some() {
+            if filter.is_active() {
                 print::println("net: no matching interfaces");
             } else {
                 print::println("net: no network interfaces found");
@@ -832,9 +1626,12 @@ pub fn run(opts: &GlobalOptions) -> i32 {
 }
 
 /// Write network interfaces to JSON writer (for snapshot).
+///
+/// Without /sys/class/net the section is left out, or written as `[]`
+/// with `include_empty`.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
-    if !io::path_exists(NET_SYSFS_PATH) {
+pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool, include_empty: bool) {
+    if !include_empty && !io::path_exists(NET_SYSFS_PATH) {
         return;
     }
 
@@ -851,10 +1648,260 @@ pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
 
     w.key("net");
     w.begin_array();
-    io::for_each_dir_entry(NET_SYSFS_PATH, |name| {
-        if let Some(iface) = NetInterface::read_with_extra(name, &ipv4_map, &ipv6_map, &wireless_map) {
-            iface.write_json(w, verbose, false, &ipv4_map, &ipv6_map);
+    io::for_each_dir_entry_sorted(NET_SYSFS_PATH, io::natural_cmp, |name| {
+        if let Some(iface) = NetInterface::read_with_extra(name, &ipv4_map, &ipv6_map, &wireless_map, &routes, false) {
+            iface.write_json(w, verbose, false, &ipv4_map, &ipv6_map, None);
         }
     });
     w.end_array();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // /proc/net/route prints addresses in host byte order
+    #[cfg(target_endian = "little")]
+    const SAMPLE_ROUTE: &str = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+eth0\t00000000\t0100000A\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0000000A\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0
+wlan0\t0001A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\t0\t0\t0
+";
+
+    #[cfg(target_endian = "big")]
+    const SAMPLE_ROUTE: &str = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+wlan0\t00000000\tC0A80101\t0003\t0\t0\t600\t00000000\t0\t0\t0
+eth0\t00000000\t0A000001\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0A000000\t00000000\t0001\t0\t0\t100\tFFFFFF00\t0\t0\t0
+wlan0\tC0A80100\t00000000\t0001\t0\t0\t600\tFFFFFF00\t0\t0\t0
+";
+
+    const SAMPLE_NET_DEV: &str = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  123456     789    0    0    0     0          0         0   123456     789    0    0    0     0       0          0
+  eth0:98765432  65432    3   12    0     0          0       100 12345678   4321    1    2    0     0       0          0
+  veth1: 100 2 0 0 0 0 0 0
+";
+
+    #[test]
+    fn proc_net_dev_parse() {
+        let eth0 = proc_net_dev_counters(SAMPLE_NET_DEV, "eth0").unwrap();
+        assert_eq!(eth0, NetDevCounters {
+            rx_bytes: 98765432,
+            rx_packets: 65432,
+            rx_errors: 3,
+            rx_dropped: 12,
+            tx_bytes: 12345678,
+            tx_packets: 4321,
+            tx_errors: 1,
+            tx_dropped: 2,
+        });
+        assert_eq!(proc_net_dev_counters(SAMPLE_NET_DEV, "lo").unwrap().tx_bytes, 123456);
+        // Truncated line, header lines, unknown interface
+        assert_eq!(proc_net_dev_counters(SAMPLE_NET_DEV, "veth1"), None);
+        assert_eq!(proc_net_dev_counters(SAMPLE_NET_DEV, "face |bytes"), None);
+        assert_eq!(proc_net_dev_counters(SAMPLE_NET_DEV, "wlan0"), None);
+    }
+
+    #[test]
+    fn status_from_flags() {
+        // 0x1003 = IFF_UP | IFF_BROADCAST | IFF_MULTICAST
+        assert_eq!(flags_to_status(0x1003, Some(true), Some("up")), "up");
+        assert_eq!(flags_to_status(0x1002, None, Some("down")), "down(admin)");
+        assert_eq!(flags_to_status(0x1003, Some(false), Some("down")), "down(no-carrier)");
+        assert_eq!(flags_to_status(0x1003, Some(true), Some("dormant")), "dormant");
+        assert_eq!(flags_to_status(0x1003, Some(true), Some("lowerlayerdown")), "down");
+        // lo reports operstate "unknown"
+        assert_eq!(flags_to_status(0x9, Some(true), Some("unknown")), "up");
+    }
+
+    #[test]
+    fn utilization_math() {
+        // 1 Gbit/s link, 60 s: 7.5 GB moved is 1000 Mbit/s -> 100%
+        assert_eq!(link_utilization_percent(7_500_000_000, 60, 1000), Some(100));
+        // 42% of 100 Mbit/s for 10 s = 420 Mbit = 52.5 MB
+        assert_eq!(link_utilization_percent(52_500_000, 10, 100), Some(42));
+        assert_eq!(link_utilization_percent(52_499_999, 10, 100), Some(41));
+        assert_eq!(link_utilization_percent(0, 10, 100), Some(0));
+        // No time passed or no speed: nothing to divide by
+        assert_eq!(link_utilization_percent(1000, 0, 100), None);
+        assert_eq!(link_utilization_percent(1000, 10, 0), None);
+    }
+
+    #[test]
+    fn stats_line_columns() {
+        let stats = [
+            (f::RX_BYTES, Some(1500)),
+            (f::TX_BYTES, Some(900)),
+            (f::RX_PACKETS, Some(12)),
+            (f::TX_PACKETS, Some(9)),
+            (f::RX_ERRORS, Some(0)),
+            (f::TX_ERRORS, None),
+            (f::RX_DROPPED, Some(3)),
+            (f::TX_DROPPED, Some(0)),
+        ];
+        assert_eq!(format_stats_line("eth0", &stats).as_str(), "eth0 1500 900 12 9 0 - 3 0");
+
+        // The header names the same columns in the same order
+        let mut header = STATS_HEADER.split(' ');
+        assert_eq!(header.next(), Some(f::IFACE));
+        assert!(header.eq(stats.iter().map(|&(key, _)| key)));
+    }
+
+    #[test]
+    fn default_route_lowest_metric_wins() {
+        let mut routes = RouteTable::new();
+        parse_route_table(SAMPLE_ROUTE, &mut routes);
+
+        assert_eq!(routes.count, 4);
+        assert_eq!(routes.default_gateway("wlan0"), None);
+        let gw = routes.default_gateway("eth0").unwrap();
+        assert_eq!(format_ipv4(gw).as_str(), "10.0.0.1");
+    }
+
+    #[test]
+    fn gateway_is_normalized() {
+        // Header plus the wlan0 default route only
+        let end = SAMPLE_ROUTE.match_indices('\n').nth(1).unwrap().0;
+        let mut routes = RouteTable::new();
+        parse_route_table(&SAMPLE_ROUTE[..end], &mut routes);
+
+        let gw = routes.default_gateway("wlan0").unwrap();
+        assert_eq!(format_ipv4(gw).as_str(), "192.168.1.1");
+    }
+
+    #[test]
+    fn no_default_route() {
+        let mut routes = RouteTable::new();
+        parse_route_table(SAMPLE_ROUTE.lines().next().unwrap(), &mut routes);
+        assert!(routes.default.is_none());
+        assert_eq!(routes.default_gateway("eth0"), None);
+    }
+
+    #[test]
+    fn ipv6_canonical_form() {
+        let ipv6 = |hex: &str| hex_to_ipv6(hex).unwrap();
+        assert_eq!(ipv6("00000000000000000000000000000001").as_str(), "::1");
+        assert_eq!(ipv6("00000000000000000000000000000000").as_str(), "::");
+        assert_eq!(ipv6("fe80000000000000021122fffe334455").as_str(), "fe80::211:22ff:fe33:4455");
+        assert_eq!(ipv6("20010db8000000000000000000000001").as_str(), "2001:db8::1");
+        // A single zero group stays, the longer run is compressed
+        assert_eq!(ipv6("20010db8000000010000000000000001").as_str(), "2001:db8:0:1::1");
+        assert_eq!(ipv6("20010db8000100010001000100010000").as_str(), "2001:db8:1:1:1:1:1:0");
+        assert!(hex_to_ipv6("fe80").is_none());
+        assert!(hex_to_ipv6("zz000000000000000000000000000001").is_none());
+    }
+
+    #[test]
+    fn ipv4_prefix_from_routes() {
+        let mut routes = RouteTable::new();
+        parse_route_table(SAMPLE_ROUTE, &mut routes);
+        assert_eq!(ipv4_prefix_len("10.0.0.5", &routes), Some(24));
+        assert_eq!(ipv4_prefix_len("192.168.1.20", &routes), Some(24));
+        assert_eq!(ipv4_prefix_len("127.0.0.1", &routes), Some(8));
+        // Only the default route covers it: no subnet to take a prefix from
+        assert_eq!(ipv4_prefix_len("172.16.0.1", &routes), None);
+    }
+
+    #[test]
+    fn addresses_per_interface() {
+        let mut routes = RouteTable::new();
+        parse_route_table(SAMPLE_ROUTE, &mut routes);
+        let mut ipv4_map = Ipv4Map::new();
+        let mut ipv6_map = Ipv6Map::new();
+        ipv4_map.get_or_insert("lo").unwrap().push("127.0.0.1");
+        ipv6_map.get_or_insert("lo").unwrap().push("::1/128");
+        ipv4_map.get_or_insert("eth0").unwrap().push("10.0.0.5");
+
+        let mut seen: [(StackString<8>, StackString<64>, Option<u32>); 2] = Default::default();
+        let mut n = 0;
+        for_each_address("lo", &ipv4_map, &ipv6_map, &routes, |family, address, prefix| {
+            seen[n] = (StackString::from_str(family), StackString::from_str(address), prefix);
+            n += 1;
+        });
+        assert_eq!(n, 2);
+        assert_eq!((seen[0].0.as_str(), seen[0].1.as_str(), seen[0].2), ("inet", "127.0.0.1", Some(8)));
+        assert_eq!((seen[1].0.as_str(), seen[1].1.as_str(), seen[1].2), ("inet6", "::1", Some(128)));
+
+        n = 0;
+        for_each_address("wlan0", &ipv4_map, &ipv6_map, &routes, |_, _, _| n += 1);
+        assert_eq!(n, 0);
+    }
+
+    // Trimmed-down fib_trie: 10.0.0.5 sits in eth0's /24, 172.31.5.9 is a /32
+    // only the default route covers, and 10.9.0.0/16 is a local (AnyIP) range.
+    const SAMPLE_FIB_TRIE: &str = "Main:
+  +-- 0.0.0.0/0 3 0 5
+     |-- 0.0.0.0
+        /0 universe UNICAST
+     +-- 10.0.0.0/24 2 0 2
+        |-- 10.0.0.0
+           /24 link UNICAST
+        |-- 10.0.0.5
+           /32 host LOCAL
+Local:
+  +-- 0.0.0.0/0 3 0 5
+     +-- 10.9.0.0/16 2 0 2
+        |-- 10.9.0.0
+           /16 host LOCAL
+     |-- 10.0.0.5
+        /32 host LOCAL
+     +-- 127.0.0.0/8 2 0 2
+        |-- 127.0.0.0
+           /8 host LOCAL
+        |-- 127.0.0.1
+           /32 host LOCAL
+     |-- 172.31.5.9
+        /32 host LOCAL
+";
+
+    #[test]
+    fn fib_trie_local_addresses() {
+        assert!(is_fib_local_address("/32 host LOCAL"));
+        assert!(!is_fib_local_address("/16 host LOCAL"));
+        assert!(!is_fib_local_address("/8 host LOCAL"));
+        assert!(!is_fib_local_address("/24 link UNICAST"));
+
+        let mut routes = RouteTable::new();
+        parse_route_table(SAMPLE_ROUTE, &mut routes);
+        let mut ipv4_map = Ipv4Map::new();
+        parse_fib_trie(SAMPLE_FIB_TRIE, &mut ipv4_map, &routes);
+
+        // 10.0.0.5 is listed in both tables but reported once
+        assert!(ipv4_map.get("eth0").unwrap().iter().eq(["10.0.0.5"]));
+        // Only the default route covers this one; the first default wins
+        assert!(ipv4_map.get("wlan0").unwrap().iter().eq(["172.31.5.9"]));
+        // Range bases (127.0.0.0/8, 10.9.0.0/16) aren't addresses
+        assert!(ipv4_map.get("lo").unwrap().iter().eq(["127.0.0.1"]));
+        assert!(ipv4_map.get(UNKNOWN_IFACE).is_none());
+    }
+
+    #[test]
+    fn fib_trie_without_routes_is_unknown() {
+        let routes = RouteTable::new();
+        let mut ipv4_map = Ipv4Map::new();
+        parse_fib_trie(SAMPLE_FIB_TRIE, &mut ipv4_map, &routes);
+        let unknown = ipv4_map.get(UNKNOWN_IFACE).unwrap();
+        assert!(unknown.iter().eq(["10.0.0.5", "172.31.5.9"]));
+        assert!(ipv4_map.get("lo").unwrap().iter().eq(["127.0.0.1"]));
+    }
+
+    #[test]
+    fn sysfs_wireless_levels() {
+        let files = [("link", 55), ("level", 196), ("noise", 0)];
+        let wifi = wireless_from_files(|name| files.iter().find(|f| f.0 == name).map(|f| f.1)).unwrap();
+        assert_eq!(wifi.link_quality, 55);
+        assert_eq!(wifi.signal_dbm, -60);
+        assert_eq!(wifi.noise_dbm, -256);
+
+        // No wireless/ directory (wired interface, or no wext support)
+        assert!(wireless_from_files(|_| None).is_none());
+    }
+
+    #[test]
+    fn ipv4_formatting() {
+        assert_eq!(format_ipv4(0).as_str(), "0.0.0.0");
+        assert_eq!(format_ipv4(0xC0A8_0101).as_str(), "192.168.1.1");
+    }
+}
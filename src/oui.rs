@@ -0,0 +1,175 @@
+//! MAC address vendors from an IEEE OUI list (`--oui-file`).
+//!
+//! kv doesn't carry the list itself - it's megabytes and changes weekly.
+//! Point `--oui-file` (or `KV_OUI_FILE`) at a copy of IEEE's oui.txt and
+//! `kv net` adds MAC_VENDOR= to each interface.
+//!
+//! The file is far bigger than anything we keep on the stack, so rather
+//! than loading all of it we note the OUIs (first three octets) of the
+//! interfaces at hand and stream the file once, keeping just those (see
+//! `wanted`).
+
+#![allow(dead_code)]
+
+use crate::io;
+use crate::stack::StackString;
+use crate::wanted::WantedList;
+
+/// Most distinct OUIs looked up in one run (there's one per interface).
+const MAX_OUIS: usize = 64;
+
+/// Organizationally Unique Identifier: the first three octets of a MAC.
+pub type Oui = [u8; 3];
+
+/// A vendor name; longer ones are cut short (at a character boundary).
+pub type VendorName = StackString<64>;
+
+/// Three hex octets from `parts`, e.g. ["00", "1b", "21"].
+fn parse_octets<'a>(mut parts: impl Iterator<Item = &'a str>) -> Option<Oui> {
+    let mut oui = [0u8; 3];
+    for octet in &mut oui {
+        let part = parts.next().filter(|p| p.len() == 2)?;
+        *octet = u8::from_str_radix(part, 16).ok()?;
+    }
+    Some(oui)
+}
+
+/// The OUI of a MAC address like "00:1b:21:3a:4f:10".
+///
+/// Locally administered addresses (random MACs, most virtual interfaces)
+/// weren't assigned to anyone, so they have none.
+pub fn mac_oui(mac: &str) -> Option<Oui> {
+    parse_octets(mac.split(':')).filter(|oui| oui[0] & 0x02 == 0)
+}
+
+/// Parse an assignment line from oui.txt: "00-1B-21   (hex)\t\tIntel Corporate".
+///
+/// The "(base 16)" line that repeats it, the address lines and the
+/// header don't parse.
+pub fn parse_oui_line(line: &str) -> Option<(Oui, &str)> {
+    let (prefix, vendor) = line.split_once("(hex)")?;
+    let mut parts = prefix.trim().split('-');
+    let oui = parse_octets(&mut parts)?;
+    let vendor = vendor.trim();
+    if parts.next().is_some() || vendor.is_empty() {
+        return None;
+    }
+    Some((oui, vendor))
+}
+
+/// Record `line`'s vendor if its OUI is one of `entries`.
+fn add_vendor(entries: &mut [(Oui, Option<VendorName>)], line: &str) {
+    if let Some((oui, vendor)) = parse_oui_line(line) {
+        let wanted = entries.iter_mut().find(|(o, v)| *o == oui && v.is_none());
+        if let Some((_, slot)) = wanted {
+            *slot = Some(StackString::from_str(vendor));
+        }
+    }
+}
+
+/// Vendors for the OUIs of a few MAC addresses.
+pub struct MacVendors {
+    list: WantedList<Oui, Option<VendorName>, MAX_OUIS>,
+}
+
+impl MacVendors {
+    pub fn new() -> Self {
+        Self { list: WantedList::new("MAC vendors") }
+    }
+
+    /// Look up the vendor of `mac` when the list is loaded.
+    pub fn want(&mut self, mac: &str) {
+        if let Some(oui) = mac_oui(mac) {
+            self.list.want(oui);
+        }
+    }
+
+    /// Take the vendor from one oui.txt line if it's one we want.
+    /// False once every wanted OUI has a vendor, so reading can stop.
+    pub fn add_line(&mut self, line: &str) -> bool {
+        self.list.add_line(line, add_vendor, Option::is_some)
+    }
+
+    /// Read the vendors we want from an OUI list. False if it can't be read.
+    pub fn load(&mut self, path: &str) -> bool {
+        io::for_each_line(path, |line| self.add_line(line))
+    }
+
+    /// Vendor of `mac`, if it was wanted and the list had it.
+    pub fn get(&self, mac: &str) -> Option<&str> {
+        self.list.get(mac_oui(mac)?)?.as_ref().map(|v| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few entries the way IEEE lays them out.
+    const SAMPLE_OUI: &str = "OUI/MA-L                                                    Organization
+company_id                                                  Organization
+                                                            Address
+
+00-1B-21   (hex)\t\tIntel Corporate
+001B21     (base 16)\t\tIntel Corporate
+\t\t\t\tLot 8, Jalan Hi-Tech 2/3
+\t\t\t\tKulim  Kedah  09000
+\t\t\t\tMY
+
+B8-27-EB   (hex)\t\tRaspberry Pi Foundation
+B827EB     (base 16)\t\tRaspberry Pi Foundation
+\t\t\t\tMitchell Wood House
+\t\t\t\tCaldecote  Cambridgeshire  CB23 7NU
+\t\t\t\tGB
+";
+
+    #[test]
+    fn test_parse_oui_line() {
+        assert_eq!(parse_oui_line("00-1B-21   (hex)\t\tIntel Corporate"), Some(([0x00, 0x1b, 0x21], "Intel Corporate")));
+        assert_eq!(parse_oui_line("001B21     (base 16)\t\tIntel Corporate"), None);
+        assert_eq!(parse_oui_line("00-1B-21-00   (hex)\t\tToo long"), None);
+        assert_eq!(parse_oui_line("00-1B-21   (hex)"), None);
+    }
+
+    #[test]
+    fn test_mac_oui() {
+        assert_eq!(mac_oui("b8:27:eb:12:34:56"), Some([0xb8, 0x27, 0xeb]));
+        // Locally administered (a random MAC) and garbage
+        assert_eq!(mac_oui("02:42:ac:11:00:02"), None);
+        assert_eq!(mac_oui("00:00"), None);
+    }
+
+    #[test]
+    fn test_lookup_from_list() {
+        let mut vendors = MacVendors::new();
+        vendors.want("00:1b:21:3a:4f:10");
+        vendors.want("b8:27:eb:12:34:56");
+        vendors.want("00:11:22:33:44:55");
+        for line in SAMPLE_OUI.lines() {
+            vendors.add_line(line);
+        }
+        assert_eq!(vendors.get("00:1b:21:00:00:01"), Some("Intel Corporate"));
+        assert_eq!(vendors.get("b8:27:eb:12:34:56"), Some("Raspberry Pi Foundation"));
+        // Wanted but not in the list, and never wanted
+        assert_eq!(vendors.get("00:11:22:33:44:55"), None);
+        assert_eq!(vendors.get("3c:22:fb:00:00:01"), None);
+    }
+
+    #[test]
+    fn test_stops_when_all_found() {
+        let mut vendors = MacVendors::new();
+        vendors.want("b8:27:eb:12:34:56");
+        assert!(vendors.add_line("00-1B-21   (hex)\t\tIntel Corporate"));
+        assert!(!vendors.add_line("B8-27-EB   (hex)\t\tRaspberry Pi Foundation"));
+    }
+
+    #[test]
+    fn test_long_vendor_cut_whole_chars() {
+        // 63 ASCII bytes, then a two-byte 'ö' straddling the 64-byte limit
+        let line = format!("00-1B-21   (hex)\t\t{}ö GmbH", "x".repeat(63));
+        let mut vendors = MacVendors::new();
+        vendors.want("00:1b:21:3a:4f:10");
+        vendors.add_line(&line);
+        assert_eq!(vendors.get("00:1b:21:3a:4f:10"), Some("x".repeat(63).as_str()));
+    }
+}
@@ -0,0 +1,109 @@
+//! Showing part of a listing (`--limit` and `--offset`).
+//!
+//! `kv pci --limit 5` prints the first five devices that pass the filters,
+//! `--offset 5 --limit 5` the next five. Items are still read and counted
+//! past the limit, so JSON output can say how many there were in all:
+//! `"total"` and `"shown"` follow the data array whenever either flag is
+//! given.
+
+#![allow(dead_code)]
+
+use crate::cli::GlobalOptions;
+use crate::json::StreamingJsonWriter;
+
+/// Which items of a listing get printed, and how many there were.
+#[derive(Clone, Copy, Default)]
+pub struct Page {
+    /// Items to skip first
+    offset: usize,
+    /// Most items to print after that (None = no limit)
+    limit: Option<usize>,
+    /// Items seen so far (after filtering)
+    total: usize,
+    /// Items printed so far
+    shown: usize,
+}
+
+impl Page {
+    pub fn new(offset: usize, limit: Option<usize>) -> Self {
+        Page { offset, limit, total: 0, shown: 0 }
+    }
+
+    /// The `--offset`/`--limit` given on the command line.
+    pub fn from_options(opts: &GlobalOptions) -> Self {
+        Page::new(opts.offset, opts.limit)
+    }
+
+    /// Whether --limit or --offset was given.
+    pub fn is_active(&self) -> bool {
+        self.offset > 0 || self.limit.is_some()
+    }
+
+    /// Count one item that passed the filters. True if it should be printed.
+    pub fn take(&mut self) -> bool {
+        let index = self.total;
+        self.total += 1;
+        let show = index >= self.offset && self.limit.is_none_or(|limit| index - self.offset < limit);
+        if show {
+            self.shown += 1;
+        }
+        show
+    }
+
+    /// Items that passed the filters, printed or not.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Items printed.
+    pub fn shown(&self) -> usize {
+        self.shown
+    }
+
+    /// `"total"` and `"shown"`, after the data array. Nothing unless
+    /// --limit or --offset was given, so plain output doesn't change.
+    pub fn write_json(&self, w: &mut StreamingJsonWriter) {
+        if self.is_active() {
+            w.field_u64("total", self.total as u64);
+            w.field_u64("shown", self.shown as u64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Which of `items` items a page prints.
+    fn shown(mut page: Page, items: usize) -> ([bool; 8], usize, usize) {
+        let mut taken = [false; 8];
+        for t in taken.iter_mut().take(items) {
+            *t = page.take();
+        }
+        (taken, page.total(), page.shown())
+    }
+
+    #[test]
+    fn test_limit_and_offset() {
+        let (taken, total, count) = shown(Page::new(0, Some(3)), 5);
+        assert_eq!(taken[..5], [true, true, true, false, false]);
+        assert_eq!((total, count), (5, 3));
+
+        let (taken, _, count) = shown(Page::new(2, Some(2)), 5);
+        assert_eq!(taken[..5], [false, false, true, true, false]);
+        assert_eq!(count, 2);
+
+        // Offset alone skips, past the end shows nothing
+        let (taken, _, _) = shown(Page::new(3, None), 5);
+        assert_eq!(taken[..5], [false, false, false, true, true]);
+        assert_eq!(shown(Page::new(9, Some(2)), 5).2, 0);
+        assert_eq!(shown(Page::new(0, Some(0)), 5).2, 0);
+    }
+
+    #[test]
+    fn test_inactive_page_shows_everything() {
+        let page = Page::default();
+        assert!(!page.is_active());
+        assert_eq!(shown(page, 8), ([true; 8], 8, 8));
+    }
+}
@@ -4,22 +4,87 @@
 //! and bound driver information. This is what you want when you SSH into a
 //! machine and realize lspci isn't installed.
 //!
-//! We don't do PCI ID database lookups (that would require external files),
-//! so you'll see "0x10de" instead of "NVIDIA Corporation". The hex IDs are
-//! actually more useful for scripting anyway.
+//! kv doesn't carry the PCI ID database, so by default you'll see "0x10de"
+//! instead of "NVIDIA Corporation" - the hex IDs are more useful for
+//! scripting anyway. With `--pci-ids`, names come from the system's own
+//! pci.ids (see `pciids`).
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
-use crate::fields::pci as f;
-use crate::filter::{matches_any, opt_str};
+use crate::cli::{self, ExtraArgs, GlobalOptions};
+use crate::fields::{kind, pci as f};
+use crate::filter::{opt_str, pattern_matches, Filterable, Pattern};
 use crate::io;
 use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::page::Page;
+use crate::pciids::{IdName, PciNames};
 use crate::print::{self, TextWriter};
 use crate::stack::StackString;
+use crate::template::Template;
 
 const PCI_SYSFS_PATH: &str = "/sys/bus/pci/devices";
 
+/// Maximum number of devices `--tree` can arrange.
+const MAX_TREE_DEVICES: usize = 256;
+
+/// Deepest bridge nesting `--tree` follows.
+const MAX_TREE_DEPTH: usize = 16;
+
+/// Standard BARs; `resource` lines after these are the ROM and bridge windows.
+const MAX_BARS: usize = 6;
+
+/// IORESOURCE_* flags from include/linux/ioport.h, as printed in `resource`.
+const IORESOURCE_IO: u64 = 0x100;
+const IORESOURCE_PREFETCH: u64 = 0x2000;
+
+/// One base address register (memory region) from the `resource` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bar {
+    /// Size in bytes
+    pub size: u64,
+    /// I/O port space rather than memory
+    pub is_io: bool,
+    /// Prefetchable memory
+    pub prefetchable: bool,
+}
+
+impl Bar {
+    /// "mem" or "io"
+    fn type_str(&self) -> &'static str {
+        if self.is_io { "io" } else { "mem" }
+    }
+
+    /// Text form: "16M(mem,pf)", "256(io)".
+    fn format(&self) -> StackString<32> {
+        let mut s: StackString<32> = StackString::new();
+        s.push_str(io::format_human_size(self.size).as_str());
+        s.push('(');
+        s.push_str(self.type_str());
+        if self.prefetchable {
+            s.push_str(",pf");
+        }
+        s.push(')');
+        s
+    }
+}
+
+/// Parse one `resource` line, "0x00000000fb000000 0x00000000fbffffff 0x0000000000040200".
+/// Returns None for an unused BAR (all zeros) or a line that doesn't parse.
+pub fn parse_resource_line(line: &str) -> Option<Bar> {
+    let mut parts = line.split_whitespace();
+    let start: u64 = io::parse_hex(parts.next()?)?;
+    let end: u64 = io::parse_hex(parts.next()?)?;
+    let flags: u64 = io::parse_hex(parts.next()?)?;
+    if flags == 0 || end < start {
+        return None;
+    }
+    Some(Bar {
+        size: end - start + 1,
+        is_io: flags & IORESOURCE_IO != 0,
+        prefetchable: flags & IORESOURCE_PREFETCH != 0,
+    })
+}
+
 /// Information about a PCI device.
 pub struct PciDevice {
     /// Bus:Device.Function address (e.g., "0000:01:00.0")
@@ -28,6 +93,10 @@ pub struct PciDevice {
     pub vendor_id: u16,
     /// Device ID
     pub device_id: u16,
+    /// Vendor name from the --pci-ids list (see `set_names`)
+    pub vendor_name: Option<IdName>,
+    /// Device name from the --pci-ids list
+    pub device_name: Option<IdName>,
     /// Class code (3 bytes: class, subclass, prog-if)
     pub class: u32,
     /// Subsystem vendor ID (optional)
@@ -48,11 +117,26 @@ pub struct PciDevice {
     pub enabled: Option<bool>,
     /// D-state (power state)
     pub d_state: Option<StackString<16>>,
+    /// Upstream bridge, None for devices on a root bus
+    pub parent: Option<StackString<16>>,
+    /// SR-IOV virtual functions currently enabled (physical functions only)
+    pub num_vfs: Option<u32>,
+    /// SR-IOV virtual functions supported (physical functions only)
+    pub total_vfs: Option<u32>,
+    /// BDF of the physical function (virtual functions only)
+    pub physical_function: Option<StackString<16>>,
+    /// BAR0-BAR5, None where unused
+    pub bars: [Option<Bar>; MAX_BARS],
 }
 
 impl PciDevice {
-    /// Read a PCI device from sysfs.
+    /// Read a PCI device from sysfs, retrying once if we raced a hot-plug.
     pub fn read(bdf: &str) -> Option<Self> {
+        let base: StackString<64> = io::join_path(PCI_SYSFS_PATH, bdf);
+        io::retry_if_present(base.as_str(), || Self::read_once(bdf))
+    }
+
+    fn read_once(bdf: &str) -> Option<Self> {
         // Build base path
         let base: StackString<64> = io::join_path(PCI_SYSFS_PATH, bdf);
 
@@ -61,8 +145,8 @@ impl PciDevice {
         let device_path: StackString<128> = io::join_path(base.as_str(), "device");
         let class_path: StackString<128> = io::join_path(base.as_str(), "class");
 
-        let vendor_id: u16 = io::read_file_hex(vendor_path.as_str())?;
-        let device_id: u16 = io::read_file_hex(device_path.as_str())?;
+        let vendor_id: u16 = io::read_required_hex(vendor_path.as_str())?;
+        let device_id: u16 = io::read_required_hex(device_path.as_str())?;
         let class: u32 = io::read_file_hex(class_path.as_str()).unwrap_or(0);
 
         // Subsystem IDs
@@ -99,10 +183,34 @@ impl PciDevice {
         let power_path: StackString<128> = io::join_path(base.as_str(), "power_state");
         let d_state: Option<StackString<16>> = io::read_file_stack(power_path.as_str());
 
+        // The device link shows the bridge path (.../0000:00:1c.0/0000:02:00.0)
+        let link: Option<StackString<256>> = io::read_symlink(base.as_str());
+        let parent = link.as_ref().and_then(|l| parent_from_link(l.as_str())).map(StackString::from_str);
+
+        // SR-IOV: a PF has sriov_* files, a VF links back to its PF via physfn
+        let numvfs_path: StackString<128> = io::join_path(base.as_str(), "sriov_numvfs");
+        let totalvfs_path: StackString<128> = io::join_path(base.as_str(), "sriov_totalvfs");
+        let physfn_path: StackString<128> = io::join_path(base.as_str(), "physfn");
+        let num_vfs: Option<u32> = io::read_file_parse(numvfs_path.as_str());
+        let total_vfs: Option<u32> = io::read_file_parse(totalvfs_path.as_str());
+        let physfn: Option<StackString<256>> = io::read_symlink(physfn_path.as_str());
+        let physical_function = physfn.as_ref().and_then(|l| bdf_from_link(l.as_str())).map(StackString::from_str);
+
+        // One line per resource; the first six are the BARs
+        let resource_path: StackString<128> = io::join_path(base.as_str(), "resource");
+        let mut bars = [None; MAX_BARS];
+        if let Some(resource) = io::read_file_stack::<2048>(resource_path.as_str()) {
+            for (bar, line) in bars.iter_mut().zip(resource.as_str().lines()) {
+                *bar = parse_resource_line(line);
+            }
+        }
+
         Some(PciDevice {
             bdf: StackString::from_str(bdf),
             vendor_id,
             device_id,
+            vendor_name: None,
+            device_name: None,
             class,
             subsystem_vendor_id,
             subsystem_device_id,
@@ -113,21 +221,19 @@ impl PciDevice {
             is_bridge,
             enabled,
             d_state,
+            parent,
+            num_vfs,
+            total_vfs,
+            physical_function,
+            bars,
         })
     }
 
-    /// Check if this device matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let vendor_hex = io::format_hex_u16(self.vendor_id);
-        let device_hex = io::format_hex_u16(self.device_id);
-        let driver_str = opt_str(&self.driver);
-        let fields = [
-            self.bdf.as_str(),
-            driver_str,
-            vendor_hex.as_str(),
-            device_hex.as_str(),
-        ];
-        matches_any(&fields, pattern, case_insensitive)
+    /// Look up our vendor and device names (nothing without --pci-ids).
+    fn set_names(&mut self, names: Option<&PciNames>) {
+        let Some(names) = names else { return };
+        self.vendor_name = names.vendor(self.vendor_id, self.device_id).map(StackString::from_str);
+        self.device_name = names.device(self.vendor_id, self.device_id).map(StackString::from_str);
     }
 
     /// Output as text.
@@ -137,6 +243,8 @@ impl PciDevice {
         w.field_str(f::BDF, self.bdf.as_str());
         w.field_str(f::VENDOR_ID, io::format_hex_u16(self.vendor_id).as_str());
         w.field_str(f::DEVICE_ID, io::format_hex_u16(self.device_id).as_str());
+        w.field_quoted_opt(f::VENDOR_NAME, self.vendor_name.as_ref().map(|s| s.as_str()));
+        w.field_quoted_opt(f::DEVICE_NAME, self.device_name.as_ref().map(|s| s.as_str()));
         w.field_str(f::CLASS, io::format_hex_class(self.class).as_str());
 
         if let Some(ref driver) = self.driver {
@@ -163,20 +271,75 @@ impl PciDevice {
                 w.field_u64(f::ENABLED, if v { 1 } else { 0 });
             }
             if let Some(ref state) = self.d_state {
-                w.field_str(f::POWER_STATE, state.as_str());
+                w.field_explained(f::POWER_STATE, state.as_str());
+            }
+            if let Some(ref parent) = self.parent {
+                w.field_str(f::PARENT, parent.as_str());
+            }
+            if let Some(v) = self.num_vfs {
+                w.field_u64(f::NUM_VFS, v as u64);
+            }
+            if let Some(v) = self.total_vfs {
+                w.field_u64(f::TOTAL_VFS, v as u64);
+            }
+            if let Some(ref pf) = self.physical_function {
+                w.field_str(f::PHYSICAL_FUNCTION, pf.as_str());
+            }
+            for (index, bar) in self.bars.iter().enumerate() {
+                if let Some(bar) = bar {
+                    // BAR0, BAR1, ...
+                    let mut key: StackString<8> = StackString::from_str(f::BAR);
+                    key.push((b'0' + index as u8) as char);
+                    w.field_str(key.as_str(), bar.format().as_str());
+                }
             }
         }
 
-        w.finish();
+        w.finish_record();
+    }
+
+    /// `0000:01:00.0 10de 2204` for `kv pci -n`. The IDs are bare hex,
+    /// no "0x", same as lspci -n.
+    fn numeric_line(&self) -> StackString<32> {
+        let mut s = StackString::new();
+        s.push_str(self.bdf.as_str());
+        s.push(' ');
+        s.push_str(io::format_hex_u16_bare(self.vendor_id).as_str());
+        s.push(' ');
+        s.push_str(io::format_hex_u16_bare(self.device_id).as_str());
+        s
+    }
+
+    /// Write just the address and IDs as a JSON object (`kv pci -jn`).
+    fn write_numeric_json(&self, w: &mut StreamingJsonWriter) {
+        w.record_begin();
+        self.write_numeric_json_fields(w);
+        w.array_object_end();
+    }
+
+    /// The `-jn` fields, leaving the object open (for --tree children).
+    fn write_numeric_json_fields(&self, w: &mut StreamingJsonWriter) {
+        w.field_str(kind::KIND, kind::PCI_DEVICE);
+        w.field_str(f::BDF, self.bdf.as_str());
+        w.field_str(f::VENDOR_ID, io::format_hex_u16_bare(self.vendor_id).as_str());
+        w.field_str(f::DEVICE_ID, io::format_hex_u16_bare(self.device_id).as_str());
     }
 
     /// Write as JSON object.
     fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool) {
-        w.array_object_begin();
+        w.record_begin();
+        self.write_json_fields(w, verbose);
+        w.array_object_end();
+    }
 
+    /// Write the JSON fields, leaving the object open (for --tree children).
+    fn write_json_fields(&self, w: &mut StreamingJsonWriter, verbose: bool) {
+        w.field_str(kind::KIND, kind::PCI_DEVICE);
         w.field_str(f::BDF, self.bdf.as_str());
         w.field_str(f::VENDOR_ID, io::format_hex_u16(self.vendor_id).as_str());
         w.field_str(f::DEVICE_ID, io::format_hex_u16(self.device_id).as_str());
+        w.field_str_opt(f::VENDOR_NAME, self.vendor_name.as_ref().map(|s| s.as_str()));
+        w.field_str_opt(f::DEVICE_NAME, self.device_name.as_ref().map(|s| s.as_str()));
         w.field_str(f::CLASS, io::format_hex_class(self.class).as_str());
         w.field_str_opt(f::DRIVER, self.driver.as_ref().map(|s| s.as_str()));
 
@@ -201,17 +364,323 @@ impl PciDevice {
             }
             w.field_str_opt(f::POWER_STATE, self.d_state.as_ref().map(|s| s.as_str()));
             w.field_bool(f::IS_BRIDGE, self.is_bridge);
+            w.field_str_opt(f::PARENT, self.parent.as_ref().map(|s| s.as_str()));
+            w.field_u64_opt(f::NUM_VFS, self.num_vfs.map(|v| v as u64));
+            w.field_u64_opt(f::TOTAL_VFS, self.total_vfs.map(|v| v as u64));
+            w.field_str_opt(f::PHYSICAL_FUNCTION, self.physical_function.as_ref().map(|s| s.as_str()));
+            if self.bars.iter().any(Option::is_some) {
+                w.field_array(f::BARS);
+                for (index, bar) in self.bars.iter().enumerate() {
+                    if let Some(bar) = bar {
+                        w.array_object_begin();
+                        w.field_u64(f::BAR_INDEX, index as u64);
+                        w.field_u64(f::BAR_SIZE, bar.size);
+                        w.field_str(f::BAR_TYPE, bar.type_str());
+                        w.field_bool(f::BAR_PREFETCHABLE, bar.prefetchable);
+                        w.array_object_end();
+                    }
+                }
+                w.end_field_array();
+            }
         }
+    }
+}
 
-        w.array_object_end();
+impl Filterable for PciDevice {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::BDF, self.bdf.as_str());
+        visit(f::DRIVER, opt_str(&self.driver));
+        visit(f::VENDOR_ID, io::format_hex_u16(self.vendor_id).as_str());
+        visit(f::DEVICE_ID, io::format_hex_u16(self.device_id).as_str());
+        visit(f::VENDOR_NAME, opt_str(&self.vendor_name));
+        visit(f::DEVICE_NAME, opt_str(&self.device_name));
+    }
+
+    fn filter_keyed_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::CLASS, io::format_hex_class(self.class).as_str());
+    }
+
+    /// Partial addresses like `01:00.0` or `00:1f` match by BDF prefix,
+    /// with the default domain filled in. Everything else (and anything
+    /// that doesn't match that way) goes through normal matching.
+    fn matches_pattern(&self, pattern: &Pattern) -> bool {
+        let bdf_pattern = match pattern.keyed() {
+            Some((key, value)) if key == f::BDF => Some((value, true)),
+            Some(_) => None,
+            None => Some((pattern.text.as_str(), false)),
+        };
+        let bdf = self.bdf.as_str();
+        bdf_pattern.is_some_and(|(text, exact)| bdf_matches(bdf, text, exact)) || pattern_matches(pattern, self)
+    }
+}
+
+/// Every `--template` field, whether or not a record has it.
+const TEMPLATE_FIELDS: &[&str] = &[
+    f::BDF,
+    f::DRIVER,
+    f::VENDOR_ID,
+    f::DEVICE_ID,
+    f::VENDOR_NAME,
+    f::DEVICE_NAME,
+    f::CLASS,
+];
+
+/// Default PCI domain, which sysfs always spells out.
+const DEFAULT_DOMAIN: &str = "0000:";
+
+/// Expand a partial BDF pattern to the full sysfs form.
+///
+/// Returns None unless the pattern looks like an address: hex digits with
+/// one or two colons and at most one dot. Without a 4-digit domain, the
+/// default `0000:` is prepended (`01:00.0` -> `0000:01:00.0`).
+pub fn normalize_bdf(pattern: &str) -> Option<StackString<16>> {
+    let colons = pattern.bytes().filter(|&b| b == b':').count();
+    let dots = pattern.bytes().filter(|&b| b == b'.').count();
+    if !(1..=2).contains(&colons) || dots > 1 {
+        return None;
+    }
+    if !pattern.bytes().all(|b| b.is_ascii_hexdigit() || b == b':' || b == b'.') {
+        return None;
+    }
+
+    let has_domain = colons == 2 || pattern.split(':').next().is_some_and(|d| d.len() == 4);
+    let mut full: StackString<16> = StackString::new();
+    if !has_domain {
+        full.push_str(DEFAULT_DOMAIN);
+    }
+    for c in pattern.chars() {
+        full.push(c.to_ascii_lowercase());
+    }
+    Some(full)
+}
+
+/// Does `bdf` match a (possibly partial) address pattern?
+/// Prefix match normally; `exact` for `bdf=...` patterns.
+fn bdf_matches(bdf: &str, pattern: &str, exact: bool) -> bool {
+    let Some(full) = normalize_bdf(pattern) else {
+        return false;
+    };
+    if exact {
+        bdf == full.as_str()
+    } else {
+        bdf.starts_with(full.as_str())
+    }
+}
+
+/// Is this a full sysfs address like `0000:00:1c.0`?
+fn is_full_bdf(s: &str) -> bool {
+    s.len() == 12 && normalize_bdf(s).is_some_and(|n| n.as_str() == s)
+}
+
+/// The device a sysfs link points at, e.g. a VF's `physfn` link
+/// `../0000:3b:00.0` -> `0000:3b:00.0`. None if it isn't a PCI address.
+pub fn bdf_from_link(link: &str) -> Option<&str> {
+    link.trim_end_matches('/').rsplit('/').next().filter(|name| is_full_bdf(name))
+}
+
+/// The upstream bridge in a device's sysfs link, e.g.
+/// `../../../devices/pci0000:00/0000:00:1c.0/0000:02:00.0` -> `0000:00:1c.0`.
+/// None when the device sits directly on a root bus (`pci0000:00`).
+pub fn parent_from_link(link: &str) -> Option<&str> {
+    let (dir, _) = link.trim_end_matches('/').rsplit_once('/')?;
+    bdf_from_link(dir)
+}
+
+/// A device and its upstream bridge, as arranged by `walk_tree`.
+#[derive(Default)]
+pub struct TreeNode {
+    pub bdf: StackString<16>,
+    pub parent: Option<StackString<16>>,
+}
+
+/// One step of the depth-first walk in `walk_tree`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TreeEvent {
+    /// Visit `nodes[index]`, `depth` bridges below the root
+    Enter { index: usize, depth: usize, has_children: bool },
+    /// Done with `nodes[index]` and everything below it
+    Leave { index: usize, has_children: bool },
+}
+
+/// Walk devices depth-first, children right after their bridge.
+///
+/// Siblings keep their order in `nodes`. A device whose bridge isn't in
+/// `nodes` (e.g. filtered out) is treated as a root.
+pub fn walk_tree(nodes: &[TreeNode], mut f: impl FnMut(TreeEvent)) {
+    let nodes = &nodes[..nodes.len().min(MAX_TREE_DEVICES)];
+    let mut parents: [Option<usize>; MAX_TREE_DEVICES] = [None; MAX_TREE_DEVICES];
+    for (i, node) in nodes.iter().enumerate() {
+        parents[i] = node
+            .parent
+            .as_ref()
+            .and_then(|p| nodes.iter().position(|n| n.bdf.as_str() == p.as_str()))
+            .filter(|&p| p != i);
+    }
+    walk_children(&parents[..nodes.len()], None, 0, &mut f);
+}
+
+fn walk_children(parents: &[Option<usize>], parent: Option<usize>, depth: usize, f: &mut dyn FnMut(TreeEvent)) {
+    if depth > MAX_TREE_DEPTH {
+        return;
+    }
+    for index in 0..parents.len() {
+        if parents[index] != parent {
+            continue;
+        }
+        let has_children = parents.contains(&Some(index));
+        f(TreeEvent::Enter { index, depth, has_children });
+        walk_children(parents, Some(index), depth + 1, f);
+        f(TreeEvent::Leave { index, has_children });
+    }
+}
+
+/// Options specific to the pci subcommand.
+#[derive(Default)]
+pub struct PciOptions {
+    /// Nest devices under their bridges
+    pub tree: bool,
+    /// Only the address and vendor/device IDs, like lspci -n
+    pub numeric: bool,
+}
+
+impl PciOptions {
+    /// Parse pci-specific options from remaining arguments.
+    pub fn parse(args: &ExtraArgs) -> Self {
+        let mut opts = PciOptions::default();
+        for arg in args.iter() {
+            match arg {
+                "--tree" => opts.tree = true,
+                "-n" | "--numeric" => opts.numeric = true,
+                _ => {}
+            }
+        }
+        opts
+    }
+}
+
+/// Names of the devices on the bus, from the pci.ids file at `path`.
+///
+/// The file is too big to keep, so we collect the IDs we'll need first
+/// and read just those from it. None (with a warning) if it can't be read.
+fn load_pci_names(path: &str) -> Option<PciNames> {
+    let mut names = PciNames::new();
+    io::for_each_dir_entry(PCI_SYSFS_PATH, |bdf| {
+        let base: StackString<64> = io::join_path(PCI_SYSFS_PATH, bdf);
+        let vendor_path: StackString<128> = io::join_path(base.as_str(), "vendor");
+        let device_path: StackString<128> = io::join_path(base.as_str(), "device");
+        let vendor = io::read_file_hex(vendor_path.as_str());
+        if let Some((vendor, device)) = vendor.zip(io::read_file_hex(device_path.as_str())) {
+            names.want(vendor, device);
+        }
+    });
+    if !names.load(path) {
+        print::eprint("Warning: cannot read PCI ID file ");
+        print::eprintln(path);
+        return None;
+    }
+    Some(names)
+}
+
+/// `kv pci --tree`: devices nested under the bridges they sit behind,
+/// and SR-IOV virtual functions under their physical function. With
+/// `numeric` (-n), each device is just its address and IDs.
+fn run_tree(opts: &GlobalOptions, names: Option<&PciNames>, numeric: bool) -> i32 {
+    let mut devices: [Option<PciDevice>; MAX_TREE_DEVICES] = [const { None }; MAX_TREE_DEVICES];
+    let mut count = 0;
+    let mut dropped = 0;
+    io::for_each_dir_entry(PCI_SYSFS_PATH, |bdf| {
+        let Some(mut dev) = PciDevice::read(bdf) else {
+            return;
+        };
+        dev.set_names(names);
+        if !dev.matches_filter(&opts.filter) {
+            return;
+        }
+        if count < MAX_TREE_DEVICES {
+            devices[count] = Some(dev);
+            count += 1;
+        } else {
+            dropped += 1;
+        }
+    });
+
+    let devices = &mut devices[..count];
+    devices.sort_unstable_by(|a, b| {
+        let bdf = |d: &Option<PciDevice>| d.as_ref().map(|d| d.bdf.clone()).unwrap_or_default();
+        bdf(a).as_str().cmp(bdf(b).as_str())
+    });
+    let mut nodes: [TreeNode; MAX_TREE_DEVICES] = core::array::from_fn(|_| TreeNode::default());
+    for (node, dev) in nodes.iter_mut().zip(devices.iter().flatten()) {
+        node.bdf = dev.bdf.clone();
+        node.parent = dev.physical_function.clone().or_else(|| dev.parent.clone());
+    }
+    let device = |index: usize| devices[index].as_ref();
+
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "pci");
+        w.field_array("data");
+        walk_tree(&nodes[..count], |event| match event {
+            TreeEvent::Enter { index, has_children, .. } => {
+                w.record_begin();
+                match device(index) {
+                    Some(dev) if numeric => dev.write_numeric_json_fields(&mut w),
+                    Some(dev) => dev.write_json_fields(&mut w, opts.verbose),
+                    None => {}
+                }
+                if has_children {
+                    w.field_array(f::CHILDREN);
+                }
+            }
+            TreeEvent::Leave { has_children, .. } => {
+                if has_children {
+                    w.end_field_array();
+                }
+                w.array_object_end();
+            }
+        });
+        w.end_field_array();
+        w.end_object();
+        w.finish();
+    } else {
+        walk_tree(&nodes[..count], |event| {
+            if let TreeEvent::Enter { index, depth, .. } = event {
+                for _ in 0..depth {
+                    print::print("  ");
+                }
+                match device(index) {
+                    Some(dev) if numeric => print::println_record(dev.numeric_line().as_str()),
+                    Some(dev) => dev.print_text(opts.verbose),
+                    None => {}
+                }
+            }
+        });
+        if count == 0 {
+            print::println("pci: no matching devices");
+        }
     }
+
+    if dropped > 0 {
+        let mut buf = itoa::Buffer::new();
+        print::eprint("Warning: --tree shows at most ");
+        print::eprint(buf.format(MAX_TREE_DEVICES));
+        print::eprintln(" devices");
+    }
+    0
 }
 
 /// Entry point for `kv pci` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let pci_opts = PciOptions::parse(args);
+
+    // The tree nests devices, so there's no list to page or template
+    if let Some(option) = opts.list_option().filter(|_| pci_opts.tree) {
+        cli::reject_combination(option, "--tree");
+        return 1;
+    }
+
     if !io::path_exists(PCI_SYSFS_PATH) {
         if opts.json {
-            let mut w = begin_kv_output_streaming(opts.pretty, "pci");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "pci");
             w.field_array("data");
             w.end_field_array();
             w.end_object();
@@ -222,49 +691,77 @@ pub fn run(opts: &GlobalOptions) -> i32 {
         return 0;
     }
 
-    let filter = opts.filter.as_ref().map(|s| s.as_str());
-    let case_insensitive = opts.filter_case_insensitive;
+    // lspci -n doesn't show names either
+    let names = match &opts.pci_ids {
+        Some(path) if !pci_opts.numeric => load_pci_names(path.as_str()),
+        _ => None,
+    };
+
+    if pci_opts.tree {
+        return run_tree(opts, names.as_ref(), pci_opts.numeric);
+    }
+
+    let filter = &opts.filter;
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "pci");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "pci");
         w.field_array("data");
 
         let mut count = 0;
+        let mut page = Page::from_options(opts);
         io::for_each_dir_entry(PCI_SYSFS_PATH, |bdf| {
-            if let Some(dev) = PciDevice::read(bdf) {
-                if let Some(pattern) = filter {
-                    if !dev.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+            if let Some(mut dev) = PciDevice::read(bdf) {
+                dev.set_names(names.as_ref());
+                if !dev.matches_filter(filter) {
+                    return;
                 }
-                dev.write_json(&mut w, opts.verbose);
                 count += 1;
+                if !page.take() {
+                    return;
+                }
+                if pci_opts.numeric {
+                    dev.write_numeric_json(&mut w);
+                } else {
+                    dev.write_json(&mut w, opts.verbose);
+                }
             }
         });
 
         w.end_field_array();
+        page.write_json(&mut w);
         w.end_object();
         w.finish();
 
-        if count == 0 && filter.is_some() {
+        if count == 0 && filter.is_active() {
             // Empty filtered result is fine
         }
     } else {
         let mut count = 0;
+        let mut template = Template::from_options(opts, TEMPLATE_FIELDS);
+        let mut page = Page::from_options(opts);
         io::for_each_dir_entry(PCI_SYSFS_PATH, |bdf| {
-            if let Some(dev) = PciDevice::read(bdf) {
-                if let Some(pattern) = filter {
-                    if !dev.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+            if let Some(mut dev) = PciDevice::read(bdf) {
+                dev.set_names(names.as_ref());
+                if !dev.matches_filter(filter) {
+                    return;
                 }
-                dev.print_text(opts.verbose);
                 count += 1;
+                if !page.take() {
+                    return;
+                }
+                match template.as_mut() {
+                    Some(t) => t.print(&dev, &[]),
+                    None if pci_opts.numeric => print::println_record(dev.numeric_line().as_str()),
+                    None => dev.print_text(opts.verbose),
+                }
             }
         });
+        if let Some(t) = &template {
+            t.finish();
+        }
 
         if count == 0 {
-            if filter.is_some() {
+            if filter.is_active() {
                 print::println("pci: no matching devices");
             } else {
                 print::println("pci: no PCI devices found");
@@ -275,19 +772,167 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     0
 }
 
-/// Write PCI devices to JSON writer (for snapshot).
+/// Write PCI devices to JSON writer (for snapshot). Without a PCI bus the
+/// section is left out, or written as `[]` with `include_empty`.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
-    if !io::path_exists(PCI_SYSFS_PATH) {
+pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool, include_empty: bool) {
+    if !include_empty && !io::path_exists(PCI_SYSFS_PATH) {
         return;
     }
 
     w.key("pci");
     w.begin_array();
-    io::for_each_dir_entry(PCI_SYSFS_PATH, |bdf| {
+    io::for_each_dir_entry_sorted(PCI_SYSFS_PATH, str::cmp, |bdf| {
         if let Some(dev) = PciDevice::read(bdf) {
             dev.write_json(w, verbose);
         }
     });
     w.end_array();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPU: &str = "0000:01:00.0";
+    const AUDIO: &str = "0000:00:1f.3";
+    const SMBUS: &str = "0000:00:1f.4";
+    const HOST: &str = "0000:00:00.0";
+
+    fn matching(pattern: &str) -> usize {
+        [GPU, AUDIO, SMBUS, HOST].iter().filter(|bdf| bdf_matches(bdf, pattern, false)).count()
+    }
+
+    #[test]
+    fn test_parse_resource_line() {
+        let bar = parse_resource_line("0x00000000fb000000 0x00000000fbffffff 0x0000000000040200").unwrap();
+        assert_eq!(bar, Bar { size: 16 << 20, is_io: false, prefetchable: false });
+        assert_eq!(bar.format().as_str(), "16M(mem)");
+
+        // 64-bit prefetchable
+        let bar = parse_resource_line("0x000000e000000000 0x000000efffffffff 0x000000000014220c").unwrap();
+        assert_eq!(bar.size, 64 << 30);
+        assert!(bar.prefetchable && !bar.is_io);
+        assert_eq!(bar.format().as_str(), "64G(mem,pf)");
+
+        let bar = parse_resource_line("0x000000000000f000 0x000000000000f07f 0x0000000000040101").unwrap();
+        assert_eq!(bar.format().as_str(), "128(io)");
+
+        // Unused slot (and the upper half of a 64-bit BAR)
+        assert_eq!(parse_resource_line("0x0000000000000000 0x0000000000000000 0x0000000000000000"), None);
+        assert_eq!(parse_resource_line("garbage"), None);
+    }
+
+    #[test]
+    fn test_normalize_bdf() {
+        assert_eq!(normalize_bdf("01:00.0").unwrap().as_str(), "0000:01:00.0");
+        assert_eq!(normalize_bdf("0000:01:00.0").unwrap().as_str(), "0000:01:00.0");
+        assert_eq!(normalize_bdf("00:1F").unwrap().as_str(), "0000:00:1f");
+        assert_eq!(normalize_bdf("0001:02").unwrap().as_str(), "0001:02");
+        assert!(normalize_bdf("nvidia").is_none());
+        assert!(normalize_bdf("10de").is_none());
+        assert!(normalize_bdf("a:b:c:d").is_none());
+    }
+
+    #[test]
+    fn test_partial_bdf_matches() {
+        assert!(bdf_matches(GPU, "01:00.0", false));
+        assert!(bdf_matches(GPU, "0000:01:00.0", false));
+        assert_eq!(matching("01:00.0"), 1);
+        assert_eq!(matching("00:1f"), 2);
+        // Prefix, not substring: "00:00" is the host bridge only
+        assert_eq!(matching("00:00"), 1);
+    }
+
+    fn node(bdf: &str, parent: Option<&str>) -> TreeNode {
+        TreeNode {
+            bdf: StackString::from_str(bdf),
+            parent: parent.map(StackString::from_str),
+        }
+    }
+
+    #[test]
+    fn test_parent_from_link() {
+        assert_eq!(parent_from_link("../../../devices/pci0000:00/0000:00:1c.0/0000:02:00.0"), Some("0000:00:1c.0"));
+        assert_eq!(parent_from_link("../../../devices/pci0000:00/0000:00:1f.3"), None);
+        assert_eq!(parent_from_link("0000:02:00.0"), None);
+    }
+
+    #[test]
+    fn test_bdf_from_link() {
+        assert_eq!(bdf_from_link("../0000:3b:00.0"), Some("0000:3b:00.0"));
+        assert_eq!(bdf_from_link("../../../devices/pci0000:3a/0000:3a:00.0/0000:3b:00.0/"), Some("0000:3b:00.0"));
+        assert_eq!(bdf_from_link("0000:3b:00.0"), Some("0000:3b:00.0"));
+        assert_eq!(bdf_from_link("../../../devices/pci0000:00"), None);
+        assert_eq!(bdf_from_link("3b:00.0"), None);
+        assert_eq!(bdf_from_link(""), None);
+    }
+
+    #[test]
+    fn test_walk_tree() {
+        let nodes = [
+            node(HOST, None),
+            node("0000:00:1c.0", None),
+            node("0000:02:00.0", Some("0000:00:1c.0")),
+            node("0000:03:00.0", Some("0000:02:00.0")),
+            node(AUDIO, None),
+        ];
+        let mut seen: [(usize, usize); 5] = [(0, 0); 5];
+        let mut n = 0;
+        walk_tree(&nodes, |event| {
+            if let TreeEvent::Enter { index, depth, .. } = event {
+                seen[n] = (index, depth);
+                n += 1;
+            }
+        });
+        assert_eq!(n, 5);
+        assert_eq!(seen, [(0, 0), (1, 0), (2, 1), (3, 2), (4, 0)]);
+    }
+
+    #[test]
+    fn test_walk_tree_events_balanced() {
+        let nodes = [node("0000:00:1c.0", None), node(GPU, Some("0000:00:1c.0"))];
+        let mut events: [Option<TreeEvent>; 4] = [None, None, None, None];
+        let mut n = 0;
+        walk_tree(&nodes, |event| {
+            events[n] = Some(event);
+            n += 1;
+        });
+        assert_eq!(events, [
+            Some(TreeEvent::Enter { index: 0, depth: 0, has_children: true }),
+            Some(TreeEvent::Enter { index: 1, depth: 1, has_children: false }),
+            Some(TreeEvent::Leave { index: 1, has_children: false }),
+            Some(TreeEvent::Leave { index: 0, has_children: true }),
+        ]);
+    }
+
+    #[test]
+    fn test_walk_tree_missing_bridge_is_root() {
+        // Bridge filtered out: its child still shows, at the top level
+        let nodes = [node(GPU, Some("0000:00:01.0"))];
+        let mut depth = None;
+        walk_tree(&nodes, |event| {
+            if let TreeEvent::Enter { depth: d, .. } = event {
+                depth = Some(d);
+            }
+        });
+        assert_eq!(depth, Some(0));
+    }
+
+    #[test]
+    fn test_bdf_exact() {
+        assert!(bdf_matches(AUDIO, "00:1f.3", true));
+        assert!(!bdf_matches(AUDIO, "00:1f", true));
+    }
+
+    #[test]
+    fn test_numeric_ids_have_no_prefix() {
+        let mut args = ExtraArgs::new();
+        args.push("--numeric");
+        assert!(PciOptions::parse(&args).numeric && !PciOptions::parse(&args).tree);
+        // lspci -n style: lowercase, zero-padded, no 0x
+        assert_eq!(io::format_hex_u16_bare(0x10de).as_str(), "10de");
+        assert_eq!(io::format_hex_u16_bare(0x00a1).as_str(), "00a1");
+        assert_eq!(io::format_hex_u16(0x10de).as_str(), "0x10de");
+    }
+}
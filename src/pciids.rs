@@ -0,0 +1,210 @@
+//! PCI vendor and device names from a pci.ids file (`--pci-ids`).
+//!
+//! Like the OUI list, kv doesn't carry the PCI ID database - most distros
+//! already have it at /usr/share/hwdata/pci.ids or /usr/share/misc/pci.ids.
+//! Point `--pci-ids` (or `KV_PCI_IDS`) at it and `kv pci` adds
+//! VENDOR_NAME= and DEVICE_NAME= to each device.
+//!
+//! The file is over a megabyte, so we note the IDs of the devices at hand
+//! and stream it once, keeping just their names (see `wanted`).
+
+#![allow(dead_code)]
+
+use crate::io;
+use crate::stack::StackString;
+use crate::wanted::WantedList;
+
+/// Most distinct vendor/device pairs looked up in one run.
+const MAX_IDS: usize = 128;
+
+/// A vendor or device name; longer ones are cut short (at a character boundary).
+pub type IdName = StackString<96>;
+
+/// One line of pci.ids that matters to us.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdsLine<'a> {
+    /// "10de  NVIDIA Corporation"
+    Vendor(u16, &'a str),
+    /// "\t2204  GA102 [GeForce RTX 3090]", a device of the vendor above
+    Device(u16, &'a str),
+    /// Any other top-level line, like "C 03  Display controller" where the
+    /// class list starts: what follows belongs to no vendor
+    Other,
+}
+
+/// Four hex digits, e.g. "10de".
+fn parse_id(s: &str) -> Option<u16> {
+    if s.len() != 4 {
+        return None;
+    }
+    u16::from_str_radix(s, 16).ok()
+}
+
+/// An ID and the name after it: "2204  GA102 [GeForce RTX 3090]".
+fn split_entry(s: &str) -> Option<(u16, &str)> {
+    let (id, name) = s.split_once(' ')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some((parse_id(id)?, name))
+}
+
+/// Parse a line of pci.ids. Comments, blank lines and subsystem lines
+/// (two tabs deep) give None.
+pub fn parse_ids_line(line: &str) -> Option<IdsLine<'_>> {
+    if line.starts_with("\t\t") || line.starts_with('#') || line.trim().is_empty() {
+        return None;
+    }
+    match line.strip_prefix('\t') {
+        Some(device) => split_entry(device).map(|(id, name)| IdsLine::Device(id, name)),
+        None => Some(split_entry(line).map_or(IdsLine::Other, |(id, name)| IdsLine::Vendor(id, name))),
+    }
+}
+
+/// Names found for a wanted vendor/device pair.
+#[derive(Default)]
+struct Names {
+    vendor: Option<IdName>,
+    device: Option<IdName>,
+}
+
+/// Record the names on `line` for any of `entries` they belong to.
+/// `current_vendor` tracks the vendor the device lines fall under.
+fn add_names(entries: &mut [((u16, u16), Names)], current_vendor: &mut Option<u16>, line: &str) {
+    match parse_ids_line(line) {
+        Some(IdsLine::Vendor(vendor, name)) => {
+            *current_vendor = Some(vendor);
+            for (_, names) in entries.iter_mut().filter(|((v, _), _)| *v == vendor) {
+                names.vendor = Some(StackString::from_str(name));
+            }
+        }
+        Some(IdsLine::Device(device, name)) => {
+            let vendor = *current_vendor;
+            for (_, names) in entries.iter_mut().filter(|((v, d), _)| Some(*v) == vendor && *d == device) {
+                names.device = Some(StackString::from_str(name));
+            }
+        }
+        Some(IdsLine::Other) => *current_vendor = None,
+        None => {}
+    }
+}
+
+/// Names for the vendor and device IDs of a few PCI devices.
+pub struct PciNames {
+    list: WantedList<(u16, u16), Names, MAX_IDS>,
+    /// Vendor of the device lines being read
+    current_vendor: Option<u16>,
+}
+
+impl PciNames {
+    pub fn new() -> Self {
+        Self { list: WantedList::new("PCI device names"), current_vendor: None }
+    }
+
+    /// Look up the names of this device when the list is loaded.
+    pub fn want(&mut self, vendor: u16, device: u16) {
+        self.list.want((vendor, device));
+    }
+
+    /// Take the names from one pci.ids line if they're ones we want.
+    /// False once every wanted device has a name, so reading can stop.
+    pub fn add_line(&mut self, line: &str) -> bool {
+        let current_vendor = &mut self.current_vendor;
+        self.list.add_line(line, |entries, line| add_names(entries, current_vendor, line), |n| n.device.is_some())
+    }
+
+    /// Read the names we want from a pci.ids file. False if it can't be read.
+    pub fn load(&mut self, path: &str) -> bool {
+        self.current_vendor = None;
+        io::for_each_line(path, |line| self.add_line(line))
+    }
+
+    /// Vendor name for a device that was wanted, if the list had it.
+    pub fn vendor(&self, vendor: u16, device: u16) -> Option<&str> {
+        self.list.get((vendor, device))?.vendor.as_ref().map(|n| n.as_str())
+    }
+
+    /// Device name, if it was wanted and the list had it.
+    pub fn device(&self, vendor: u16, device: u16) -> Option<&str> {
+        self.list.get((vendor, device))?.device.as_ref().map(|n| n.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few entries the way pci.ids lays them out.
+    const SAMPLE_PCI_IDS: &str = "#
+#\tList of PCI ID's
+#
+# Syntax:
+# vendor  vendor_name
+#\tdevice  device_name\t\t\t\t<-- single tab
+#\t\tsubvendor subdevice  subsystem_name\t<-- two tabs
+
+8086  Intel Corporation
+\t1237  440FX - 82441FX PMC [Natoma]
+\t15f3  Ethernet Controller I225-V
+\t\t1043 87d2  Ethernet Controller I225-V
+10de  NVIDIA Corporation
+\t2204  GA102 [GeForce RTX 3090]
+\t\t10de 1454  GeForce RTX 3090
+
+C 02  Network controller
+\t00  Ethernet controller
+";
+
+    #[test]
+    fn test_parse_ids_line() {
+        assert_eq!(parse_ids_line("10de  NVIDIA Corporation"), Some(IdsLine::Vendor(0x10de, "NVIDIA Corporation")));
+        assert_eq!(parse_ids_line("\t2204  GA102 [GeForce RTX 3090]"), Some(IdsLine::Device(0x2204, "GA102 [GeForce RTX 3090]")));
+        assert_eq!(parse_ids_line("\t\t10de 1454  GeForce RTX 3090"), None);
+        assert_eq!(parse_ids_line("# vendor  vendor_name"), None);
+        assert_eq!(parse_ids_line(""), None);
+        assert_eq!(parse_ids_line("C 02  Network controller"), Some(IdsLine::Other));
+        assert_eq!(parse_ids_line("\t00  Ethernet controller"), None);
+    }
+
+    #[test]
+    fn test_lookup_from_list() {
+        let mut names = PciNames::new();
+        names.want(0x8086, 0x15f3);
+        names.want(0x10de, 0x2204);
+        names.want(0x10de, 0x1454);
+        names.want(0x1af4, 0x1000);
+        for line in SAMPLE_PCI_IDS.lines() {
+            names.add_line(line);
+        }
+        assert_eq!(names.vendor(0x8086, 0x15f3), Some("Intel Corporation"));
+        assert_eq!(names.device(0x8086, 0x15f3), Some("Ethernet Controller I225-V"));
+        assert_eq!(names.device(0x10de, 0x2204), Some("GA102 [GeForce RTX 3090]"));
+        // Known vendor, unknown device (1454 is only a subsystem ID here)
+        assert_eq!(names.vendor(0x10de, 0x1454), Some("NVIDIA Corporation"));
+        assert_eq!(names.device(0x10de, 0x1454), None);
+        // Not in the list, and never wanted
+        assert_eq!(names.vendor(0x1af4, 0x1000), None);
+        assert_eq!(names.device(0x8086, 0x1237), None);
+    }
+
+    #[test]
+    fn test_stops_when_all_found() {
+        let mut names = PciNames::new();
+        names.want(0x8086, 0x1237);
+        assert!(names.add_line("8086  Intel Corporation"));
+        assert!(!names.add_line("\t1237  440FX - 82441FX PMC [Natoma]"));
+    }
+
+    #[test]
+    fn test_long_names_cut_whole_chars() {
+        // 95 ASCII bytes, then a two-byte 'é' straddling the 96-byte limit
+        let long = "x".repeat(95);
+        let mut names = PciNames::new();
+        names.want(0x8086, 0x1237);
+        names.add_line(&format!("8086  {long}é Corporation"));
+        names.add_line(&format!("\t1237  {long}é"));
+        assert_eq!(names.vendor(0x8086, 0x1237), Some(long.as_str()));
+        assert_eq!(names.device(0x8086, 0x1237), Some(long.as_str()));
+    }
+}
@@ -13,15 +13,22 @@
 #![allow(dead_code)]
 
 use crate::cli::GlobalOptions;
-use crate::fields::power as f;
-use crate::filter::{matches_any, opt_str};
+use crate::fields::{kind, power as f};
+use crate::filter::{opt_str, Filterable};
 use crate::io;
 use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::page::Page;
 use crate::print::{self, TextWriter};
 use crate::stack::StackString;
+use crate::sum::Totals;
+use crate::template::Template;
 
 const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
 
+/// Most supplies the text listing holds; a laptop has two or three, a
+/// dock or a phone with USB PD ports maybe a dozen.
+const MAX_SUPPLIES: usize = 32;
+
 /// Information about a single power supply.
 pub struct PowerSupply {
     /// Supply name (e.g., "BAT0", "AC", "ucsi-source-psy-...")
@@ -36,12 +43,20 @@ pub struct PowerSupply {
     pub capacity: Option<u8>,
     /// Voltage now in microvolts
     pub voltage_uv: Option<i64>,
+    /// voltage_uv came from voltage_avg (no voltage_now)
+    pub voltage_is_avg: bool,
     /// Current now in microamps (positive = charging, negative = discharging)
     pub current_ua: Option<i64>,
+    /// current_ua came from current_avg (no current_now)
+    pub current_is_avg: bool,
     /// Power now in microwatts
     pub power_uw: Option<i64>,
+    /// power_uw came from power_avg (no power_now)
+    pub power_is_avg: bool,
     /// USB type (for USB power supplies): C, PD, PD_PPS, etc.
-    pub usb_type: Option<StackString<32>>,
+    pub usb_type: Option<StackString<64>>,
+    /// usb_type listed several types without marking the active one
+    pub usb_type_ambiguous: bool,
     /// Energy now in microwatt-hours (battery)
     pub energy_now_uwh: Option<i64>,
     /// Energy full in microwatt-hours (battery design capacity)
@@ -52,6 +67,10 @@ pub struct PowerSupply {
     pub charge_full_uah: Option<i64>,
     /// Battery cycle count
     pub cycle_count: Option<i32>,
+    /// Charging starts below this percentage (charge_control_start_threshold)
+    pub charge_start_threshold: Option<u8>,
+    /// Charging stops at this percentage (charge_control_end_threshold)
+    pub charge_end_threshold: Option<u8>,
     /// Battery technology (Li-ion, Li-poly, NiMH, etc.)
     pub technology: Option<StackString<32>>,
     /// Manufacturer name
@@ -73,98 +92,88 @@ impl PowerSupply {
             return None;
         }
 
-        let type_path: StackString<256> = io::join_path(base.as_str(), "type");
-        let status_path: StackString<256> = io::join_path(base.as_str(), "status");
-        let online_path: StackString<256> = io::join_path(base.as_str(), "online");
-        let capacity_path: StackString<256> = io::join_path(base.as_str(), "capacity");
+        Some(Self::from_files(name, io::dir_files(base.as_str())))
+    }
 
-        let supply_type: Option<StackString<32>> = io::read_file_stack(type_path.as_str());
-        let status: Option<StackString<32>> = io::read_file_stack(status_path.as_str());
-        let online: Option<u8> = io::read_file_parse(online_path.as_str());
-        let capacity: Option<u8> = io::read_file_parse(capacity_path.as_str());
+    /// Build from the files of one power_supply directory, as
+    /// `io::dir_files` reads them.
+    pub fn from_files(name: &str, read: impl Fn(&str) -> Option<StackString<64>>) -> Self {
+        let num = |file: &str| read(file).and_then(|s| s.as_str().parse::<i64>().ok());
 
-        // Voltage
-        let voltage_path: StackString<256> = io::join_path(base.as_str(), "voltage_now");
-        let voltage_uv: Option<i64> = io::read_file_parse(voltage_path.as_str());
+        // Some batteries only report running averages
+        let (voltage_uv, voltage_is_avg) = now_or_avg(num("voltage_now"), || num("voltage_avg"));
+        let (current_ua, current_is_avg) = now_or_avg(num("current_now"), || num("current_avg"));
+        let (power_uw, power_is_avg) = now_or_avg(num("power_now"), || num("power_avg"));
 
-        // Current
-        let current_path: StackString<256> = io::join_path(base.as_str(), "current_now");
-        let current_ua: Option<i64> = io::read_file_parse(current_path.as_str());
+        let (usb_type, usb_type_ambiguous) = match read("usb_type") {
+            Some(s) => {
+                let (usb_type, ambiguous) = usb_type_value(s.as_str(), io::is_raw());
+                (Some(usb_type), ambiguous)
+            }
+            None => (None, false),
+        };
 
-        // Power
-        let power_path: StackString<256> = io::join_path(base.as_str(), "power_now");
-        let power_uw: Option<i64> = io::read_file_parse(power_path.as_str());
+        // Charge limits only exist on batteries whose firmware supports them
+        let is_battery = read("type").is_some_and(|t| t.as_str() == "Battery");
+        let percent = |file: &str| {
+            num(file)
+                .filter(|_| is_battery)
+                .and_then(|v| u8::try_from(v).ok())
+                .filter(|&v| v <= 100)
+        };
 
-        // USB type
-        let usb_type_path: StackString<256> = io::join_path(base.as_str(), "usb_type");
-        let usb_type: Option<StackString<32>> = io::read_file_stack::<64>(usb_type_path.as_str())
-            .map(|s| parse_usb_type(s.as_str()));
-
-        // Energy (battery)
-        let energy_now_path: StackString<256> = io::join_path(base.as_str(), "energy_now");
-        let energy_full_path: StackString<256> = io::join_path(base.as_str(), "energy_full");
-        let energy_now_uwh: Option<i64> = io::read_file_parse(energy_now_path.as_str());
-        let energy_full_uwh: Option<i64> = io::read_file_parse(energy_full_path.as_str());
-
-        // Charge (battery)
-        let charge_now_path: StackString<256> = io::join_path(base.as_str(), "charge_now");
-        let charge_full_path: StackString<256> = io::join_path(base.as_str(), "charge_full");
-        let charge_now_uah: Option<i64> = io::read_file_parse(charge_now_path.as_str());
-        let charge_full_uah: Option<i64> = io::read_file_parse(charge_full_path.as_str());
-
-        // Battery metadata
-        let cycle_path: StackString<256> = io::join_path(base.as_str(), "cycle_count");
-        let tech_path: StackString<256> = io::join_path(base.as_str(), "technology");
-        let mfr_path: StackString<256> = io::join_path(base.as_str(), "manufacturer");
-        let model_path: StackString<256> = io::join_path(base.as_str(), "model_name");
-
-        let cycle_count: Option<i32> = io::read_file_parse::<i32>(cycle_path.as_str())
-            .filter(|&c| c >= 0);
-        let technology: Option<StackString<32>> = io::read_file_stack::<32>(tech_path.as_str())
-            .filter(|s| s.as_str() != "Unknown");
-        let manufacturer: Option<StackString<64>> = io::read_file_stack(mfr_path.as_str())
-            .filter(|s| !s.is_empty());
-        let model_name: Option<StackString<64>> = io::read_file_stack(model_path.as_str())
-            .filter(|s| !s.is_empty());
-
-        // USB PD limits
-        let current_max_path: StackString<256> = io::join_path(base.as_str(), "current_max");
-        let voltage_max_path: StackString<256> = io::join_path(base.as_str(), "voltage_max");
-        let current_max_ua: Option<i64> = io::read_file_parse(current_max_path.as_str());
-        let voltage_max_uv: Option<i64> = io::read_file_parse(voltage_max_path.as_str());
-
-        Some(PowerSupply {
+        PowerSupply {
             name: StackString::from_str(name),
-            supply_type,
-            status,
-            online,
-            capacity,
+            supply_type: read("type").map(|s| StackString::from_str(s.as_str())),
+            status: read("status").map(|s| StackString::from_str(s.as_str())),
+            online: num("online").and_then(|v| u8::try_from(v).ok()),
+            capacity: num("capacity").and_then(|v| u8::try_from(v).ok()),
             voltage_uv,
+            voltage_is_avg,
             current_ua,
+            current_is_avg,
             power_uw,
+            power_is_avg,
             usb_type,
-            energy_now_uwh,
-            energy_full_uwh,
-            charge_now_uah,
-            charge_full_uah,
-            cycle_count,
-            technology,
-            manufacturer,
-            model_name,
-            current_max_ua,
-            voltage_max_uv,
-        })
-    }
-
-    /// Check if this supply matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let fields = [
-            self.name.as_str(),
-            opt_str(&self.supply_type),
-            opt_str(&self.status),
-            opt_str(&self.usb_type),
-        ];
-        matches_any(&fields, pattern, case_insensitive)
+            usb_type_ambiguous,
+            energy_now_uwh: num("energy_now"),
+            energy_full_uwh: num("energy_full"),
+            charge_now_uah: num("charge_now"),
+            charge_full_uah: num("charge_full"),
+            cycle_count: num("cycle_count")
+                .and_then(|c| i32::try_from(c).ok())
+                .filter(|&c| c >= 0),
+            charge_start_threshold: percent("charge_control_start_threshold"),
+            charge_end_threshold: percent("charge_control_end_threshold"),
+            technology: read("technology")
+                .filter(|s| s.as_str() != "Unknown")
+                .map(|s| StackString::from_str(s.as_str())),
+            manufacturer: read("manufacturer").filter(|s| !s.is_empty()),
+            model_name: read("model_name").filter(|s| !s.is_empty()),
+            // USB PD limits
+            current_max_ua: num("current_max"),
+            voltage_max_uv: num("voltage_max"),
+        }
+    }
+
+    fn is_battery(&self) -> bool {
+        self.supply_type.as_ref().map(|s| s.as_str()) == Some("Battery")
+    }
+
+    /// Power leaving a battery in microwatts, negative while it charges.
+    ///
+    /// Uses power_now, or voltage × current when that's missing. None for
+    /// other supply types and batteries with no electrical readings.
+    fn draw_uw(&self) -> Option<i64> {
+        if !self.is_battery() {
+            return None;
+        }
+        let magnitude = match self.power_uw {
+            Some(p) => p.abs(),
+            None => (self.voltage_uv?.saturating_mul(self.current_ua?) / 1_000_000).abs(),
+        };
+        let charging = self.status.as_ref().map(|s| s.as_str()) == Some("Charging");
+        Some(if charging { -magnitude } else { magnitude })
     }
 
     /// Output as text.
@@ -178,10 +187,9 @@ impl PowerSupply {
         }
 
         // For batteries, show status and capacity
-        let is_battery = self.supply_type.as_ref().map(|s| s.as_str()) == Some("Battery");
-        if is_battery {
+        if self.is_battery() {
             if let Some(ref status) = self.status {
-                w.field_str(f::STATUS, status.as_str());
+                w.field_explained(f::STATUS, status.as_str());
             }
             if let Some(cap) = self.capacity {
                 let mut cap_str: StackString<16> = StackString::new();
@@ -201,6 +209,9 @@ impl PowerSupply {
         if let Some(ref usb_type) = self.usb_type {
             w.field_str(f::USB_TYPE, usb_type.as_str());
         }
+        if self.usb_type_ambiguous {
+            w.field_str(f::USB_TYPE_AMBIGUOUS, "yes");
+        }
 
         if verbose {
             // Energy (batteries)
@@ -290,10 +301,25 @@ impl PowerSupply {
                 }
             }
 
+            // Readings that are running averages rather than instantaneous
+            if self.voltage_is_avg {
+                w.field_u64(f::VOLTAGE_IS_AVG, 1);
+            }
+            if self.current_is_avg {
+                w.field_u64(f::CURRENT_IS_AVG, 1);
+            }
+            if self.power_is_avg {
+                w.field_u64(f::POWER_IS_AVG, 1);
+            }
+
             // Battery metadata
             if let Some(cycles) = self.cycle_count {
                 w.field_u64(f::CYCLES, cycles as u64);
             }
+            if self.charge_start_threshold.is_some() || self.charge_end_threshold.is_some() {
+                let limit = format_charge_limit(self.charge_start_threshold, self.charge_end_threshold);
+                w.field_str(f::CHARGE_LIMIT, limit.as_str());
+            }
             if let Some(ref tech) = self.technology {
                 w.field_str(f::TECHNOLOGY, tech.as_str());
             }
@@ -305,12 +331,13 @@ impl PowerSupply {
             }
         }
 
-        w.finish();
+        w.finish_record();
     }
 
     /// Write as JSON object.
     fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool) {
-        w.array_object_begin();
+        w.record_begin();
+        w.field_str(kind::KIND, kind::POWER_SUPPLY);
 
         w.field_str(f::NAME, self.name.as_str());
 
@@ -333,6 +360,9 @@ impl PowerSupply {
         if let Some(ref usb_type) = self.usb_type {
             w.field_str(f::USB_TYPE, usb_type.as_str());
         }
+        if self.usb_type_ambiguous {
+            w.field_bool(f::USB_TYPE_AMBIGUOUS, true);
+        }
 
         if verbose {
             if let Some(v) = self.voltage_uv {
@@ -344,6 +374,15 @@ impl PowerSupply {
             if let Some(p) = self.power_uw {
                 w.field_i64(f::POWER_UW, p);
             }
+            if self.voltage_is_avg {
+                w.field_bool(f::VOLTAGE_IS_AVG, true);
+            }
+            if self.current_is_avg {
+                w.field_bool(f::CURRENT_IS_AVG, true);
+            }
+            if self.power_is_avg {
+                w.field_bool(f::POWER_IS_AVG, true);
+            }
             if let Some(e) = self.energy_now_uwh {
                 w.field_i64(f::ENERGY_NOW_UWH, e);
             }
@@ -365,6 +404,12 @@ impl PowerSupply {
             if let Some(cycles) = self.cycle_count {
                 w.field_i64(f::CYCLE_COUNT, cycles as i64);
             }
+            if let Some(start) = self.charge_start_threshold {
+                w.field_u64(f::CHARGE_START_THRESHOLD, start as u64);
+            }
+            if let Some(end) = self.charge_end_threshold {
+                w.field_u64(f::CHARGE_END_THRESHOLD, end as u64);
+            }
             if let Some(ref tech) = self.technology {
                 w.field_str(f::TECHNOLOGY, tech.as_str());
             }
@@ -380,48 +425,124 @@ impl PowerSupply {
     }
 }
 
-/// Parse USB type string - extract the active type marked with [brackets].
-fn parse_usb_type(s: &str) -> StackString<32> {
-    // Format: "C [PD] PD_PPS" - extract what's in brackets
-    if let Some(start) = s.find('[') {
-        if let Some(end) = s.find(']') {
-            if start < end {
-                return StackString::from_str(&s[start + 1..end]);
+impl Filterable for PowerSupply {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::NAME, self.name.as_str());
+        visit(f::TYPE, opt_str(&self.supply_type));
+        visit(f::STATUS, opt_str(&self.status));
+        visit(f::USB_TYPE, opt_str(&self.usb_type));
+    }
+
+    fn filter_numeric_fields(&self, visit: &mut dyn FnMut(&str, i64)) {
+        if let Some(capacity) = self.capacity {
+            visit(f::CAPACITY, capacity as i64);
+        }
+        let readings = [
+            (f::VOLTAGE_UV, self.voltage_uv),
+            (f::CURRENT_UA, self.current_ua),
+            (f::POWER_UW, self.power_uw),
+            (f::ENERGY_NOW_UWH, self.energy_now_uwh),
+            (f::ENERGY_FULL_UWH, self.energy_full_uwh),
+            (f::CHARGE_NOW_UAH, self.charge_now_uah),
+            (f::CHARGE_FULL_UAH, self.charge_full_uah),
+            (f::CYCLE_COUNT, self.cycle_count.map(i64::from)),
+        ];
+        for (name, value) in readings {
+            if let Some(v) = value {
+                visit(name, v);
             }
         }
     }
-    // No brackets - return as-is (trimmed)
-    StackString::from_str(s.trim())
+}
+
+/// Every `--template` field, whether or not a record has it.
+const TEMPLATE_FIELDS: &[&str] = &[
+    f::NAME,
+    f::TYPE,
+    f::STATUS,
+    f::USB_TYPE,
+    f::CAPACITY,
+    f::VOLTAGE_UV,
+    f::CURRENT_UA,
+    f::POWER_UW,
+    f::ENERGY_NOW_UWH,
+    f::ENERGY_FULL_UWH,
+    f::CHARGE_NOW_UAH,
+    f::CHARGE_FULL_UAH,
+    f::CYCLE_COUNT,
+];
+
+/// "40-80" for a battery that charges from 40% up to 80%.
+///
+/// Some drivers only have the end threshold, which shows as "0-80".
+fn format_charge_limit(start: Option<u8>, end: Option<u8>) -> StackString<16> {
+    let mut s = StackString::new();
+    let mut buf = itoa::Buffer::new();
+    s.push_str(buf.format(start.unwrap_or(0)));
+    s.push('-');
+    s.push_str(buf.format(end.unwrap_or(100)));
+    s
+}
+
+/// A `_now` reading, or the `_avg` one when that's all there is.
+/// The flag is true when the average was used.
+fn now_or_avg(now: Option<i64>, avg: impl FnOnce() -> Option<i64>) -> (Option<i64>, bool) {
+    match now {
+        Some(v) => (Some(v), false),
+        None => {
+            let avg = avg();
+            (avg, avg.is_some())
+        }
+    }
+}
+
+/// The USB_TYPE value: the active type, or every type listed with --raw.
+/// The flag is true when the active type couldn't be told apart.
+fn usb_type_value(s: &str, raw: bool) -> (StackString<64>, bool) {
+    if raw {
+        (StackString::from_str(s), false)
+    } else {
+        parse_usb_type(s)
+    }
+}
+
+/// Parse USB type string - extract the active type marked with [brackets].
+///
+/// Kernels differ: most list every type and bracket the active one
+/// ("C [PD] PD_PPS"), some write only the negotiated type ("PD"), and a
+/// few list several without brackets. In that last case we can't tell
+/// which is active, so all of them are joined with '/' ("C/PD/PD_PPS")
+/// and the flag is set.
+fn parse_usb_type(s: &str) -> (StackString<64>, bool) {
+    if let Some((start, end)) = s.find('[').zip(s.find(']')).filter(|(start, end)| start < end) {
+        return (StackString::from_str(&s[start + 1..end]), false);
+    }
+
+    let mut tokens = s.split_whitespace();
+    let Some(first) = tokens.next() else {
+        return (StackString::new(), false);
+    };
+    let mut joined: StackString<64> = StackString::from_str(first);
+    let mut ambiguous = false;
+    for token in tokens {
+        joined.push('/');
+        joined.push_str(token);
+        ambiguous = true;
+    }
+    (joined, ambiguous)
 }
 
 /// Format microvolts as human-readable (e.g., "12.5V").
 fn format_uv_human(s: &mut StackString<16>, uv: i64) {
-    // Convert to volts with 1 decimal place
-    let mv = uv / 1000;
-    let v_x10 = mv / 100;
-    let v_whole = v_x10 / 10;
-    let v_frac = (v_x10 % 10).abs();
-    let mut buf = itoa::Buffer::new();
-    s.push_str(buf.format(v_whole));
-    s.push('.');
-    s.push_str(buf.format(v_frac));
+    // Volts with 1 decimal place
+    io::push_fixed(s, uv, 6, io::precision_or(1));
     s.push('V');
 }
 
 /// Format microvolts as decimal volts (e.g., "12.50").
 fn format_uv_decimal(s: &mut StackString<16>, uv: i64) {
     // 2 decimal places
-    let mv = uv / 1000;
-    let v_x100 = mv / 10;
-    let v_whole = v_x100 / 100;
-    let v_frac = (v_x100 % 100).abs();
-    let mut buf = itoa::Buffer::new();
-    s.push_str(buf.format(v_whole));
-    s.push('.');
-    if v_frac < 10 {
-        s.push('0');
-    }
-    s.push_str(buf.format(v_frac));
+    io::push_fixed(s, uv, 6, io::precision_or(2));
 }
 
 /// Format microamps as human-readable (e.g., "1.5A" or "500mA").
@@ -432,14 +553,7 @@ fn format_ua_human(s: &mut StackString<16>, ua: i64) {
     }
     if abs_ua >= 1_000_000 {
         // >= 1A, show as X.XA
-        let ma = abs_ua / 1000;
-        let a_x10 = ma / 100;
-        let a_whole = a_x10 / 10;
-        let a_frac = a_x10 % 10;
-        let mut buf = itoa::Buffer::new();
-        s.push_str(buf.format(a_whole));
-        s.push('.');
-        s.push_str(buf.format(a_frac));
+        io::push_fixed(s, abs_ua, 6, io::precision_or(1));
         s.push('A');
     } else {
         // < 1A, show as XmA
@@ -453,80 +567,212 @@ fn format_ua_human(s: &mut StackString<16>, ua: i64) {
 /// Format microamps as decimal amps (e.g., "1.500").
 fn format_ua_decimal(s: &mut StackString<16>, ua: i64) {
     // 3 decimal places
-    let ma = ua / 1000;
-    let a_x1000 = ma;
-    let a_whole = a_x1000 / 1000;
-    let a_frac = (a_x1000 % 1000).abs();
-    let mut buf = itoa::Buffer::new();
-    s.push_str(buf.format(a_whole));
-    s.push('.');
-    if a_frac < 100 {
-        s.push('0');
-    }
-    if a_frac < 10 {
-        s.push('0');
-    }
-    s.push_str(buf.format(a_frac));
+    io::push_fixed(s, ua, 6, io::precision_or(3));
 }
 
 /// Format microwatts as human-readable (e.g., "18.8W").
 fn format_uw_human(s: &mut StackString<16>, uw: i64) {
-    // Convert to watts with 1 decimal place
-    let mw = uw / 1000;
-    let w_x10 = mw / 100;
-    let w_whole = w_x10 / 10;
-    let w_frac = (w_x10 % 10).abs();
-    let mut buf = itoa::Buffer::new();
-    s.push_str(buf.format(w_whole));
-    s.push('.');
-    s.push_str(buf.format(w_frac));
+    // Watts with 1 decimal place
+    io::push_fixed(s, uw, 6, io::precision_or(1));
     s.push('W');
 }
 
 /// Format microwatts as decimal watts (e.g., "18.75").
 fn format_uw_decimal(s: &mut StackString<16>, uw: i64) {
     // 2 decimal places
-    let mw = uw / 1000;
-    let w_x100 = mw / 10;
-    let w_whole = w_x100 / 100;
-    let w_frac = (w_x100 % 100).abs();
-    let mut buf = itoa::Buffer::new();
-    s.push_str(buf.format(w_whole));
-    s.push('.');
-    if w_frac < 10 {
-        s.push('0');
+    io::push_fixed(s, uw, 6, io::precision_or(2));
+}
+
+/// Format signed microwatts as decimal watts (e.g., "-3.25").
+fn format_uw_signed(s: &mut StackString<16>, uw: i64) {
+    if uw < 0 {
+        s.push('-');
+    }
+    format_uw_decimal(s, uw.abs());
+}
+
+/// Format signed microwatts as human-readable (e.g., "-3.2W").
+fn format_uw_signed_human(s: &mut StackString<16>, uw: i64) {
+    if uw < 0 {
+        s.push('-');
+    }
+    format_uw_human(s, uw.abs());
+}
+
+/// Add a supply's battery draw to a running total.
+///
+/// Stays None until some supply has usable readings.
+fn add_draw(total: Option<i64>, supply: &PowerSupply) -> Option<i64> {
+    match supply.draw_uw() {
+        Some(uw) => Some(total.unwrap_or(0) + uw),
+        None => total,
+    }
+}
+
+/// Total power drawn from all batteries in watts (negative while charging).
+///
+/// None if no battery reports power_now or voltage and current.
+pub fn total_power_w(supplies: &[PowerSupply]) -> Option<f64> {
+    supplies.iter().fold(None, add_draw).map(|uw| uw as f64 / 1_000_000.0)
+}
+
+/// Whether the machine runs on AC, and how full its batteries are,
+/// gathered over every supply (filters don't apply).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PowerSource {
+    /// Some Mains or USB supply is online
+    ac_online: bool,
+    /// Some battery is discharging
+    discharging: bool,
+    /// Sum of battery capacities, and how many batteries reported one
+    capacity_sum: u32,
+    batteries: u32,
+}
+
+impl PowerSource {
+    /// Take one supply into account.
+    pub fn add(mut self, supply: &PowerSupply) -> Self {
+        let supply_type = supply.supply_type.as_ref().map(|s| s.as_str());
+        if matches!(supply_type, Some("Mains" | "USB")) && supply.online == Some(1) {
+            self.ac_online = true;
+        }
+        if supply.is_battery() {
+            if supply.status.as_ref().is_some_and(|s| s.as_str() == "Discharging") {
+                self.discharging = true;
+            }
+            if let Some(cap) = supply.capacity {
+                self.capacity_sum += cap as u32;
+                self.batteries += 1;
+            }
+        }
+        self
+    }
+
+    /// Combine all of `supplies`.
+    pub fn of(supplies: &[PowerSupply]) -> Self {
+        supplies.iter().fold(PowerSource::default(), PowerSource::add)
+    }
+
+    /// True with an adapter online, false with a battery discharging,
+    /// None when neither tells (a desktop, or a full battery and no
+    /// adapter in sysfs).
+    pub fn on_ac(&self) -> Option<bool> {
+        if self.ac_online {
+            Some(true)
+        } else if self.discharging {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Battery charge in percent, averaged over the batteries that report one.
+    pub fn battery_percent(&self) -> Option<u8> {
+        (self.batteries > 0).then(|| (self.capacity_sum / self.batteries) as u8)
+    }
+
+    /// Nothing worth a summary line.
+    pub fn is_empty(&self) -> bool {
+        self.on_ac().is_none() && self.battery_percent().is_none()
+    }
+
+    /// Print the leading `ON_AC=yes BATTERY=87%` line.
+    fn print_text(&self) {
+        let mut w = TextWriter::new();
+        if let Some(on_ac) = self.on_ac() {
+            w.field_str(f::ON_AC, if on_ac { "yes" } else { "no" });
+        }
+        if let Some(pct) = self.battery_percent() {
+            let mut s: StackString<8> = StackString::new();
+            s.push_str(itoa::Buffer::new().format(pct));
+            s.push('%');
+            w.field_str(f::BATTERY, s.as_str());
+        }
+        w.finish();
+    }
+
+    /// Write `on_ac` and `battery_percent`, when known.
+    fn write_json(&self, w: &mut StreamingJsonWriter) {
+        if let Some(on_ac) = self.on_ac() {
+            w.field_bool(f::ON_AC, on_ac);
+        }
+        w.field_u64_opt(f::BATTERY_PERCENT, self.battery_percent().map(|v| v as u64));
+    }
+}
+
+/// Is the machine running on AC? See `PowerSource::on_ac`.
+pub fn on_ac(supplies: &[PowerSupply]) -> Option<bool> {
+    PowerSource::of(supplies).on_ac()
+}
+
+/// Every supply, read once so the ON_AC line can go before the list
+/// without a second pass over sysfs.
+struct SupplyList {
+    supplies: [Option<PowerSupply>; MAX_SUPPLIES],
+    count: usize,
+}
+
+impl SupplyList {
+    /// Read the supplies in /sys/class/power_supply. Past MAX_SUPPLIES
+    /// the rest are left out, with a warning.
+    fn read() -> Self {
+        let mut list = SupplyList { supplies: core::array::from_fn(|_| None), count: 0 };
+        let mut dropped = 0;
+        io::for_each_dir_entry(POWER_SUPPLY_PATH, |name| {
+            let Some(supply) = PowerSupply::read(name) else { return };
+            if list.count < MAX_SUPPLIES {
+                list.supplies[list.count] = Some(supply);
+                list.count += 1;
+            } else {
+                dropped += 1;
+            }
+        });
+        if dropped > 0 {
+            let mut buf = itoa::Buffer::new();
+            print::eprint("Warning: only the first ");
+            print::eprint(buf.format(MAX_SUPPLIES));
+            print::eprint(" power supplies are listed; ");
+            print::eprint(buf.format(dropped));
+            print::eprintln(" more left out");
+        }
+        list
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &PowerSupply> {
+        self.supplies[..self.count].iter().flatten()
+    }
+}
+
+/// Print the TOTAL_POWER line.
+fn print_total_text(total_uw: i64, human: bool) {
+    let mut w = TextWriter::new();
+    let mut s: StackString<16> = StackString::new();
+    if human {
+        format_uw_signed_human(&mut s, total_uw);
+        w.field_str(f::TOTAL_POWER, s.as_str());
+    } else {
+        format_uw_signed(&mut s, total_uw);
+        w.field_str(f::TOTAL_POWER_W, s.as_str());
     }
-    s.push_str(buf.format(w_frac));
+    w.finish();
 }
 
 /// Format energy pair as human-readable (e.g., "45.0Wh/50.0Wh").
 fn format_energy_pair(s: &mut StackString<32>, now_uwh: i64, full_uwh: i64) {
-    // Convert to Wh with 1 decimal
-    let now_wh_x10 = now_uwh / 100_000;
-    let full_wh_x10 = full_uwh / 100_000;
-    let mut buf = itoa::Buffer::new();
-    s.push_str(buf.format(now_wh_x10 / 10));
-    s.push('.');
-    s.push_str(buf.format((now_wh_x10 % 10).abs()));
+    // Wh with 1 decimal
+    let places = io::precision_or(1);
+    io::push_fixed(s, now_uwh, 6, places);
     s.push_str("Wh/");
-    s.push_str(buf.format(full_wh_x10 / 10));
-    s.push('.');
-    s.push_str(buf.format((full_wh_x10 % 10).abs()));
+    io::push_fixed(s, full_uwh, 6, places);
     s.push_str("Wh");
 }
 
 /// Format energy pair as decimal Wh (e.g., "45.0/50.0").
 fn format_uwh_pair(s: &mut StackString<32>, now_uwh: i64, full_uwh: i64) {
-    let now_wh_x10 = now_uwh / 100_000;
-    let full_wh_x10 = full_uwh / 100_000;
-    let mut buf = itoa::Buffer::new();
-    s.push_str(buf.format(now_wh_x10 / 10));
-    s.push('.');
-    s.push_str(buf.format((now_wh_x10 % 10).abs()));
+    let places = io::precision_or(1);
+    io::push_fixed(s, now_uwh, 6, places);
     s.push('/');
-    s.push_str(buf.format(full_wh_x10 / 10));
-    s.push('.');
-    s.push_str(buf.format((full_wh_x10 % 10).abs()));
+    io::push_fixed(s, full_uwh, 6, places);
 }
 
 /// Format charge pair as human-readable (e.g., "4500mAh/5000mAh").
@@ -554,7 +800,7 @@ fn format_uah_pair(s: &mut StackString<32>, now_uah: i64, full_uah: i64) {
 pub fn run(opts: &GlobalOptions) -> i32 {
     if !io::path_exists(POWER_SUPPLY_PATH) {
         if opts.json {
-            let mut w = begin_kv_output_streaming(opts.pretty, "power");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "power");
             w.field_array("data");
             w.end_field_array();
             w.end_object();
@@ -565,49 +811,103 @@ pub fn run(opts: &GlobalOptions) -> i32 {
         return 0;
     }
 
-    let filter = opts.filter.as_ref().map(|s| s.as_str());
-    let case_insensitive = opts.filter_case_insensitive;
+    let filter = &opts.filter;
+
+    if let Some(fields) = &opts.sum {
+        let mut totals = Totals::new(fields);
+        io::for_each_dir_entry(POWER_SUPPLY_PATH, |name| {
+            if let Some(supply) = PowerSupply::read(name).filter(|s| s.matches_filter(filter)) {
+                totals.add(&supply);
+            }
+        });
+        if opts.json {
+            totals.print_json(opts.json_layout(), opts.human, "power");
+        } else {
+            totals.print_text(opts.human);
+        }
+        return 0;
+    }
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "power");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "power");
         w.field_array("data");
 
         let mut count = 0;
+        let mut total = None;
+        let mut source = PowerSource::default();
+        let mut page = Page::from_options(opts);
         io::for_each_dir_entry(POWER_SUPPLY_PATH, |name| {
             if let Some(supply) = PowerSupply::read(name) {
-                if let Some(pattern) = filter {
-                    if !supply.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+                source = source.add(&supply);
+                if !supply.matches_filter(filter) {
+                    return;
                 }
-                supply.write_json(&mut w, opts.verbose);
                 count += 1;
+                if !page.take() {
+                    return;
+                }
+                supply.write_json(&mut w, opts.verbose);
+                total = add_draw(total, &supply);
             }
         });
 
         w.end_field_array();
+        page.write_json(&mut w);
+        if let Some(uw) = total {
+            let mut s: StackString<16> = StackString::new();
+            format_uw_signed(&mut s, uw);
+            w.field_number(f::TOTAL_POWER_W, s.as_str());
+        }
+        source.write_json(&mut w);
         w.end_object();
         w.finish();
 
-        if count == 0 && filter.is_some() {
+        if count == 0 && filter.is_active() {
             // Empty filtered result is fine
         }
     } else {
         let mut count = 0;
-        io::for_each_dir_entry(POWER_SUPPLY_PATH, |name| {
-            if let Some(supply) = PowerSupply::read(name) {
-                if let Some(pattern) = filter {
-                    if !supply.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+        let mut total = None;
+        let mut template = Template::from_options(opts, TEMPLATE_FIELDS);
+        let mut page = Page::from_options(opts);
+
+        // The answer to "am I on battery?" goes first. Like TOTAL, it would
+        // break a custom layout.
+        let supplies = SupplyList::read();
+        if template.is_none() {
+            let source = supplies.iter().fold(PowerSource::default(), PowerSource::add);
+            if !source.is_empty() {
+                source.print_text();
+            }
+        }
+
+        for supply in supplies.iter() {
+            if !supply.matches_filter(filter) {
+                continue;
+            }
+            count += 1;
+            if !page.take() {
+                continue;
+            }
+            match template.as_mut() {
+                Some(t) => t.print(supply, &[]),
+                None => supply.print_text(opts.verbose, opts.human),
+            }
+            total = add_draw(total, supply);
+        }
+
+        // The TOTAL line would break a custom layout
+        match &template {
+            Some(t) => t.finish(),
+            None => {
+                if let Some(uw) = total {
+                    print_total_text(uw, opts.human);
                 }
-                supply.print_text(opts.verbose, opts.human);
-                count += 1;
             }
-        });
+        }
 
         if count == 0 {
-            if filter.is_some() {
+            if filter.is_active() {
                 print::println("power: no matching power supplies");
             } else {
                 print::println("power: no power supplies found");
@@ -619,27 +919,203 @@ pub fn run(opts: &GlobalOptions) -> i32 {
 }
 
 /// Write power supplies to JSON writer (for snapshot).
+///
+/// With no power supplies the section is left out, or written as `[]`
+/// with `include_empty`.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
-    if !io::path_exists(POWER_SUPPLY_PATH) {
-        return;
-    }
-
+pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool, include_empty: bool) {
     let mut has_any = false;
     io::for_each_dir_entry(POWER_SUPPLY_PATH, |_| {
         has_any = true;
     });
 
-    if !has_any {
+    if !include_empty && !has_any {
         return;
     }
 
     w.key("power");
     w.begin_array();
-    io::for_each_dir_entry(POWER_SUPPLY_PATH, |name| {
+    io::for_each_dir_entry_sorted(POWER_SUPPLY_PATH, io::natural_cmp, |name| {
         if let Some(supply) = PowerSupply::read(name) {
             supply.write_json(w, verbose);
         }
     });
     w.end_array();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::testing::fixture;
+
+    fn supply(name: &str, kind: &str, status: &str) -> PowerSupply {
+        PowerSupply {
+            name: StackString::from_str(name),
+            supply_type: Some(StackString::from_str(kind)),
+            status: Some(StackString::from_str(status)),
+            online: None,
+            capacity: None,
+            voltage_uv: None,
+            voltage_is_avg: false,
+            current_ua: None,
+            current_is_avg: false,
+            power_uw: None,
+            power_is_avg: false,
+            usb_type: None,
+            usb_type_ambiguous: false,
+            energy_now_uwh: None,
+            energy_full_uwh: None,
+            charge_now_uah: None,
+            charge_full_uah: None,
+            cycle_count: None,
+            charge_start_threshold: None,
+            charge_end_threshold: None,
+            technology: None,
+            manufacturer: None,
+            model_name: None,
+            current_max_ua: None,
+            voltage_max_uv: None,
+        }
+    }
+
+    fn adapter(kind: &str, online: u8) -> PowerSupply {
+        PowerSupply { online: Some(online), ..supply("AC", kind, "") }
+    }
+
+    fn battery(status: &str, capacity: u8) -> PowerSupply {
+        PowerSupply { capacity: Some(capacity), ..supply("BAT0", "Battery", status) }
+    }
+
+    #[test]
+    fn test_on_ac() {
+        assert_eq!(on_ac(&[adapter("Mains", 1), battery("Charging", 80)]), Some(true));
+        // USB-C charging counts as AC
+        assert_eq!(on_ac(&[adapter("Mains", 0), adapter("USB", 1)]), Some(true));
+        assert_eq!(on_ac(&[adapter("Mains", 0), battery("Discharging", 87)]), Some(false));
+        // Nothing to go by
+        assert_eq!(on_ac(&[]), None);
+        assert_eq!(on_ac(&[battery("Full", 100)]), None);
+    }
+
+    #[test]
+    fn test_battery_percent() {
+        let source = PowerSource::of(&[adapter("Mains", 0), battery("Discharging", 87)]);
+        assert_eq!(source.battery_percent(), Some(87));
+        let two = PowerSource::of(&[battery("Discharging", 90), battery("Discharging", 61)]);
+        assert_eq!(two.battery_percent(), Some(75));
+        assert!(PowerSource::of(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_from_files_avg_fallback() {
+        let bat = PowerSupply::from_files("BAT0", fixture(&[
+            ("type", "Battery"),
+            ("status", "Discharging"),
+            ("voltage_avg", "11800000"),
+            ("current_avg", "-900000"),
+            ("power_avg", "10620000"),
+        ]));
+        assert_eq!(bat.voltage_uv, Some(11_800_000));
+        assert_eq!(bat.current_ua, Some(-900_000));
+        assert_eq!(bat.power_uw, Some(10_620_000));
+        assert!(bat.voltage_is_avg && bat.current_is_avg && bat.power_is_avg);
+        assert_eq!(bat.draw_uw(), Some(10_620_000));
+    }
+
+    #[test]
+    fn test_from_files_prefers_now() {
+        let bat = PowerSupply::from_files("BAT0", fixture(&[
+            ("type", "Battery"),
+            ("current_now", "500000"),
+            ("current_avg", "400000"),
+            ("capacity", "87"),
+            ("cycle_count", "-1"),
+        ]));
+        assert_eq!(bat.current_ua, Some(500_000));
+        assert!(!bat.current_is_avg);
+        assert_eq!(bat.voltage_uv, None);
+        assert!(!bat.voltage_is_avg);
+        assert_eq!(bat.capacity, Some(87));
+        assert_eq!(bat.cycle_count, None);
+    }
+
+    #[test]
+    fn test_from_files_charge_thresholds() {
+        let bat = PowerSupply::from_files("BAT0", fixture(&[
+            ("type", "Battery"),
+            ("charge_control_start_threshold", "40"),
+            ("charge_control_end_threshold", "80"),
+        ]));
+        assert_eq!(bat.charge_start_threshold, Some(40));
+        assert_eq!(bat.charge_end_threshold, Some(80));
+        assert_eq!(format_charge_limit(Some(40), Some(80)).as_str(), "40-80");
+        assert_eq!(format_charge_limit(None, Some(80)).as_str(), "0-80");
+
+        // Absent on most hardware, and never read for non-batteries
+        let bat = PowerSupply::from_files("BAT1", fixture(&[("type", "Battery")]));
+        assert_eq!(bat.charge_end_threshold, None);
+        let usb = PowerSupply::from_files("usb", fixture(&[
+            ("type", "USB"),
+            ("charge_control_end_threshold", "80"),
+        ]));
+        assert_eq!(usb.charge_end_threshold, None);
+    }
+
+    #[test]
+    fn test_total_power_multi_battery() {
+        let mut bat0 = supply("BAT0", "Battery", "Discharging");
+        bat0.power_uw = Some(7_250_000);
+        // No power_now: 12V × 0.5A, current sign ignored
+        let mut bat1 = supply("BAT1", "Battery", "Discharging");
+        bat1.voltage_uv = Some(12_000_000);
+        bat1.current_ua = Some(-500_000);
+        let mut ac = supply("AC", "Mains", "Unknown");
+        ac.power_uw = Some(65_000_000);
+
+        assert_eq!(total_power_w(&[bat0, bat1, ac]), Some(13.25));
+    }
+
+    #[test]
+    fn test_total_power_charging_is_negative() {
+        let mut bat0 = supply("BAT0", "Battery", "Charging");
+        bat0.power_uw = Some(20_000_000);
+        let mut bat1 = supply("BAT1", "Battery", "Discharging");
+        bat1.power_uw = Some(5_000_000);
+        assert_eq!(total_power_w(&[bat0, bat1]), Some(-15.0));
+    }
+
+    #[test]
+    fn test_total_power_needs_readings() {
+        let mut bat0 = supply("BAT0", "Battery", "Full");
+        // Voltage alone isn't enough
+        bat0.voltage_uv = Some(12_000_000);
+        let ac = supply("AC", "Mains", "Unknown");
+        assert_eq!(total_power_w(&[bat0, ac]), None);
+        assert_eq!(total_power_w(&[]), None);
+    }
+
+    #[test]
+    fn test_parse_usb_type() {
+        // Active type in brackets
+        let (t, ambiguous) = parse_usb_type("C [PD] PD_PPS\n");
+        assert_eq!((t.as_str(), ambiguous), ("PD", false));
+        // Only the negotiated type, no brackets
+        let (t, ambiguous) = parse_usb_type("PD_PPS\n");
+        assert_eq!((t.as_str(), ambiguous), ("PD_PPS", false));
+        // Several types and no way to tell which is active
+        let (t, ambiguous) = parse_usb_type("C PD PD_PPS");
+        assert_eq!((t.as_str(), ambiguous), ("C/PD/PD_PPS", true));
+        assert_eq!(usb_type_value("C PD", true).0.as_str(), "C PD");
+        assert!(parse_usb_type("  ").0.is_empty());
+    }
+
+    #[test]
+    fn test_format_uw_signed() {
+        let mut s: StackString<16> = StackString::new();
+        format_uw_signed(&mut s, -500_000);
+        assert_eq!(s.as_str(), "-0.50");
+        let mut s: StackString<16> = StackString::new();
+        format_uw_signed_human(&mut s, 13_250_000);
+        assert_eq!(s.as_str(), "13.2W");
+    }
+}
@@ -3,56 +3,170 @@
 //! These functions provide println!/eprintln!-like functionality without
 //! pulling in core::fmt machinery. They're designed to be drop-in replacements
 //! for the most common print patterns in kv.
+//!
+//! There is no userspace buffer: every call is a write(2) straight to the
+//! fd, so output reaches a pipe as soon as it's printed and nothing ever
+//! needs flushing, even when a consumer is reading as we go. A write the
+//! kernel only takes part of (a nearly full pipe) or that a signal
+//! interrupts is carried on until the whole string is out.
 
 #![allow(dead_code)]
 
-use rustix::io::write;
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+
+use rustix::fd::{BorrowedFd, IntoRawFd};
+use rustix::fs::{openat, Mode, OFlags, CWD};
+use rustix::io::{write, Errno};
 use rustix::stdio::{stdout, stderr};
 
+use crate::explain;
+
+/// When set, stdout output is discarded (used by --list-paths).
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Where data output goes: a file from --output, or -1 for stdout.
+static OUTPUT_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// Set once a subcommand prints any data, for --probe.
+static WROTE_DATA: AtomicBool = AtomicBool::new(false);
+
+/// Bytes of data output so far (not counting muted output).
+static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Discard (or stop discarding) everything written to stdout.
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+}
+
+/// Send data output to `path` instead of stdout (--output).
+///
+/// The file is truncated unless `append` is set. Errors still go to
+/// stderr. Returns false if the file can't be opened.
+pub fn set_output_file(path: &str, append: bool) -> bool {
+    let mode = if append { OFlags::APPEND } else { OFlags::TRUNC };
+    let flags = OFlags::WRONLY | OFlags::CREATE | OFlags::CLOEXEC | mode;
+    match openat(CWD, path, flags, Mode::from_raw_mode(0o644)) {
+        Ok(fd) => {
+            // Stays open until the process exits
+            OUTPUT_FD.store(fd.into_raw_fd(), Ordering::Relaxed);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Note that some data was printed (a record, a value), as opposed to a
+/// "no devices found" message or a summary of nothing. Called where a
+/// record is written: `TextWriter::finish_record`, `println_record` and
+/// the JSON writer's `record_begin` do it for you.
+pub fn note_data() {
+    WROTE_DATA.store(true, Ordering::Relaxed);
+}
+
+/// Whether any data was printed, muted or not (--probe).
+pub fn wrote_data() -> bool {
+    WROTE_DATA.load(Ordering::Relaxed)
+}
+
+/// How many bytes of data output have been written, to tell whether a
+/// step printed anything.
+pub fn written() -> usize {
+    WRITTEN.load(Ordering::Relaxed)
+}
+
+/// Hand all of `bytes` to `write`, which may take only part of them.
+///
+/// `write` returns how many bytes it took, or None on an error. Stops
+/// early on an error or a write that takes nothing (a closed pipe, a full
+/// disk): there's no one left to tell.
+fn write_all(mut write: impl FnMut(&[u8]) -> Option<usize>, mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+        match write(bytes) {
+            Some(n) if n > 0 => bytes = &bytes[n.min(bytes.len())..],
+            _ => return,
+        }
+    }
+}
+
+/// write(2) all of `bytes` to `fd`, retrying after a signal.
+fn write_fd(fd: BorrowedFd, bytes: &[u8]) {
+    write_all(
+        |rest| loop {
+            match write(fd, rest) {
+                Err(Errno::INTR) => continue,
+                result => return result.ok(),
+            }
+        },
+        bytes,
+    );
+}
+
+/// Write raw bytes to stdout (or the --output file) unless muted.
+#[inline]
+pub fn write_stdout(bytes: &[u8]) {
+    if MUTED.load(Ordering::Relaxed) {
+        return;
+    }
+    WRITTEN.fetch_add(bytes.len(), Ordering::Relaxed);
+    let fd = OUTPUT_FD.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // SAFETY: fd came from set_output_file and is never closed
+        write_fd(unsafe { BorrowedFd::borrow_raw(fd) }, bytes);
+    } else {
+        // SAFETY: stdout() is safe to call - it returns the process's stdout fd
+        write_fd(unsafe { stdout() }, bytes);
+    }
+}
+
+/// Write raw bytes to stderr.
+#[inline]
+pub fn write_stderr(bytes: &[u8]) {
+    // SAFETY: stderr() is safe to call - it returns the process's stderr fd
+    write_fd(unsafe { stderr() }, bytes);
+}
+
 /// Print a string to stdout (no newline).
 #[inline]
 pub fn print(s: &str) {
-    // SAFETY: stdout() is safe to call - it returns the process's stdout fd
-    let _ = write(unsafe { stdout() }, s.as_bytes());
+    write_stdout(s.as_bytes());
 }
 
 /// Print a string to stdout with newline.
 #[inline]
 pub fn println(s: &str) {
-    // SAFETY: stdout() is safe to call - it returns the process's stdout fd
-    let out = unsafe { stdout() };
-    let _ = write(out, s.as_bytes());
-    let _ = write(out, b"\n");
+    write_stdout(s.as_bytes());
+    write_stdout(b"\n");
 }
 
 /// Print to stderr (no newline).
 #[inline]
 pub fn eprint(s: &str) {
-    // SAFETY: stderr() is safe to call - it returns the process's stderr fd
-    let _ = write(unsafe { stderr() }, s.as_bytes());
+    write_stderr(s.as_bytes());
 }
 
 /// Print to stderr with newline.
 #[inline]
 pub fn eprintln(s: &str) {
-    // SAFETY: stderr() is safe to call - it returns the process's stderr fd
-    let err = unsafe { stderr() };
-    let _ = write(err, s.as_bytes());
-    let _ = write(err, b"\n");
+    write_stderr(s.as_bytes());
+    write_stderr(b"\n");
+}
+
+/// Print one line of data output (a record), with a newline.
+pub fn println_record(s: &str) {
+    note_data();
+    println(s);
 }
 
 /// Print an empty line to stdout.
 #[inline]
 pub fn println_empty() {
-    // SAFETY: stdout() is safe to call - it returns the process's stdout fd
-    let _ = write(unsafe { stdout() }, b"\n");
+    write_stdout(b"\n");
 }
 
 /// Print an empty line to stderr.
 #[inline]
 pub fn eprintln_empty() {
-    // SAFETY: stderr() is safe to call - it returns the process's stderr fd
-    let _ = write(unsafe { stderr() }, b"\n");
+    write_stderr(b"\n");
 }
 
 /// Print a single character to stdout.
@@ -60,8 +174,7 @@ pub fn eprintln_empty() {
 pub fn print_char(c: char) {
     let mut buf = [0u8; 4];
     let s = c.encode_utf8(&mut buf);
-    // SAFETY: stdout() is safe to call - it returns the process's stdout fd
-    let _ = write(unsafe { stdout() }, s.as_bytes());
+    write_stdout(s.as_bytes());
 }
 
 /// Print a u64 to stdout using itoa.
@@ -78,8 +191,92 @@ pub fn println_u64(n: u64) {
     println(buf.format(n));
 }
 
+// ============================================================================
+// Text output style (--text-key-case, --text-sep)
+// ============================================================================
+
+/// How field names are cased in text output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyCase {
+    /// `STATE=up` (default)
+    #[default]
+    Upper,
+    /// `state=up`
+    Lower,
+    /// Names exactly as defined in `fields.rs`
+    AsIs,
+}
+
+impl KeyCase {
+    /// Parse a `--text-key-case` value.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "upper" => Some(KeyCase::Upper),
+            "lower" => Some(KeyCase::Lower),
+            "asis" => Some(KeyCase::AsIs),
+            _ => None,
+        }
+    }
+}
+
+/// Key case and key/value separator for text output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextStyle {
+    pub key_case: KeyCase,
+    pub sep: char,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self { key_case: KeyCase::Upper, sep: '=' }
+    }
+}
+
+/// Global text style, set once at startup (same approach as the debug flag).
+static KEY_CASE: AtomicU8 = AtomicU8::new(KeyCase::Upper as u8);
+static KEY_SEP: AtomicU32 = AtomicU32::new('=' as u32);
+
+/// Set the text output style. Called once from main after parsing args.
+pub fn set_text_style(style: TextStyle) {
+    KEY_CASE.store(style.key_case as u8, Ordering::Relaxed);
+    KEY_SEP.store(style.sep as u32, Ordering::Relaxed);
+}
+
+/// Current text output style.
+pub fn text_style() -> TextStyle {
+    let key_case = match KEY_CASE.load(Ordering::Relaxed) {
+        x if x == KeyCase::Lower as u8 => KeyCase::Lower,
+        x if x == KeyCase::AsIs as u8 => KeyCase::AsIs,
+        _ => KeyCase::Upper,
+    };
+    let sep = char::from_u32(KEY_SEP.load(Ordering::Relaxed)).unwrap_or('=');
+    TextStyle { key_case, sep }
+}
+
+/// Print the key/value separator on its own, for callers that print keys
+/// themselves (e.g. devicetree property names, which keep their spelling).
+#[inline]
+pub fn print_sep() {
+    print_char(text_style().sep);
+}
+
+/// Copy a field name into `buf` with the given case applied.
+/// Returns the number of bytes written (names longer than `buf` are cut).
+fn apply_key_case(name: &str, case: KeyCase, buf: &mut [u8]) -> usize {
+    let len = name.len().min(buf.len());
+    for (i, c) in name.bytes().take(len).enumerate() {
+        buf[i] = match case {
+            KeyCase::Upper => c.to_ascii_uppercase(),
+            KeyCase::Lower => c.to_ascii_lowercase(),
+            KeyCase::AsIs => c,
+        };
+    }
+    len
+}
+
 /// Text output writer for KEY=VALUE format.
-/// Handles spacing between fields automatically.
+/// Handles spacing between fields automatically, and applies the key case
+/// and separator chosen with --text-key-case / --text-sep.
 pub struct TextWriter {
     first: bool,
 }
@@ -99,24 +296,23 @@ impl TextWriter {
         }
     }
 
-    /// Print field name in UPPERCASE (buffered to single syscall).
+    /// Print field name and separator (`NAME=` by default, buffered to single syscall).
     fn key(&self, name: &str) {
-        // Buffer uppercase conversion - field names are short (max ~20 chars)
-        let mut buf = [0u8; 32];
-        let len = name.len().min(32);
-        for (i, c) in name.bytes().take(len).enumerate() {
-            buf[i] = if c >= b'a' && c <= b'z' { c - 32 } else { c };
+        // Field names are short (max ~20 chars); leave room for a 4-byte separator
+        let style = text_style();
+        let mut buf = [0u8; 36];
+        let len = apply_key_case(name, style.key_case, &mut buf[..32]);
+        let len = len + style.sep.encode_utf8(&mut buf[len..]).len();
+        // Field names are ASCII, so this only fails for a truncated non-ASCII name
+        if let Ok(s) = core::str::from_utf8(&buf[..len]) {
+            print(s);
         }
-        // SAFETY: We're converting ASCII lowercase to uppercase, result is valid UTF-8
-        let s = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
-        print(s);
     }
 
     /// Print KEY=value (u64).
     pub fn field_u64(&mut self, name: &str, value: u64) {
         self.sep();
         self.key(name);
-        print("=");
         print_u64(value);
     }
 
@@ -124,7 +320,6 @@ impl TextWriter {
     pub fn field_i64(&mut self, name: &str, value: i64) {
         self.sep();
         self.key(name);
-        print("=");
         let mut buf = itoa::Buffer::new();
         print(buf.format(value));
     }
@@ -133,7 +328,6 @@ impl TextWriter {
     pub fn field_str(&mut self, name: &str, value: &str) {
         self.sep();
         self.key(name);
-        print("=");
         print(value);
     }
 
@@ -141,7 +335,7 @@ impl TextWriter {
     pub fn field_quoted(&mut self, name: &str, value: &str) {
         self.sep();
         self.key(name);
-        print("=\"");
+        print("\"");
         print(value);
         print("\"");
     }
@@ -167,11 +361,24 @@ impl TextWriter {
         }
     }
 
+    /// Print KEY=value (string, no quotes), followed by a short description
+    /// of the value with --explain, e.g. `STATE=dormant (link up, ...)`.
+    pub fn field_explained(&mut self, name: &str, value: &str) {
+        self.field_str(name, value);
+        if !explain::is_enabled() {
+            return;
+        }
+        if let Some(description) = explain::describe(name, value) {
+            print(" (");
+            print(description);
+            print(")");
+        }
+    }
+
     /// Print KEY=value for MHz (fixed point x100).
     pub fn field_mhz(&mut self, name: &str, mhz_x100: u32) {
         self.sep();
         self.key(name);
-        print("=");
         let whole = mhz_x100 / 100;
         let frac = mhz_x100 % 100;
         print_u64(whole as u64);
@@ -186,4 +393,82 @@ impl TextWriter {
     pub fn finish(self) {
         println_empty();
     }
+
+    /// Finish a record's line: unlike a summary or totals line, it counts
+    /// as data for --probe.
+    pub fn finish_record(self) {
+        if !self.first {
+            note_data();
+        }
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `write_all` against a sink that takes at most `chunk` bytes per
+    /// call (or fails on call `fail_at`), returning what it received.
+    fn sink(bytes: &[u8], chunk: usize, fail_at: Option<usize>) -> ([u8; 256], usize) {
+        let mut out = [0u8; 256];
+        let mut len = 0;
+        let mut calls = 0;
+        write_all(
+            |rest| {
+                calls += 1;
+                if fail_at == Some(calls) {
+                    return None;
+                }
+                let n = rest.len().min(chunk);
+                out[len..len + n].copy_from_slice(&rest[..n]);
+                len += n;
+                Some(n)
+            },
+            bytes,
+        );
+        (out, len)
+    }
+
+    #[test]
+    fn test_short_writes_are_finished() {
+        let line = "NAME=eth0 MAC=00:1b:21:3a:4f:10 STATE=up SPEED=1000 µs\n".as_bytes();
+        for chunk in [1, 3, 7, 64, 4096] {
+            let (out, len) = sink(line, chunk, None);
+            assert_eq!(&out[..len], line, "chunk {chunk}");
+        }
+        // An error stops the write instead of spinning
+        let (out, len) = sink(line, 4, Some(3));
+        assert_eq!(&out[..len], &line[..8]);
+        assert_eq!(sink(b"", 4, None).1, 0);
+    }
+
+    fn check(name: &str, case: KeyCase, expected: &str) {
+        let mut buf = [0u8; 32];
+        let len = apply_key_case(name, case, &mut buf);
+        assert_eq!(&buf[..len], expected.as_bytes());
+    }
+
+    #[test]
+    fn key_case() {
+        check("rx_bytes", KeyCase::Upper, "RX_BYTES");
+        check("rx_bytes", KeyCase::Lower, "rx_bytes");
+        check("Mixed_Name", KeyCase::Lower, "mixed_name");
+        check("Mixed_Name", KeyCase::AsIs, "Mixed_Name");
+    }
+
+    #[test]
+    fn long_keys_are_cut() {
+        let mut buf = [0u8; 32];
+        assert_eq!(apply_key_case("a_very_long_field_name_that_keeps_going", KeyCase::Upper, &mut buf), 32);
+    }
+
+    #[test]
+    fn parse_key_case() {
+        assert_eq!(KeyCase::parse("upper"), Some(KeyCase::Upper));
+        assert_eq!(KeyCase::parse("lower"), Some(KeyCase::Lower));
+        assert_eq!(KeyCase::parse("asis"), Some(KeyCase::AsIs));
+        assert_eq!(KeyCase::parse("title"), None);
+        assert_eq!(TextStyle::default().sep, '=');
+    }
 }
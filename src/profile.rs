@@ -0,0 +1,188 @@
+//! Wall-clock time per phase (`--profile-timings`).
+//!
+//! A handful of coarse call sites wrap their work in a `Timer`. When the
+//! flag is off a timer never touches the clock, so the cost is one atomic
+//! load. When it's on, main prints the totals to stderr after the normal
+//! output. Phases nest (file reads happen inside the fib_trie parse), so
+//! the times don't add up to the total.
+
+#![allow(dead_code)]
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use rustix::time::{clock_gettime, ClockId, Timespec};
+
+use crate::print;
+use crate::stack::StackString;
+
+/// Global --profile-timings flag, set once at startup.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn timing on or off. Called once from main after parsing args.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Is --profile-timings in effect?
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Something worth timing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// The whole subcommand, output included
+    Total,
+    /// Opening and reading sysfs/procfs files
+    FileReads,
+    /// Listing directories (not counting what's done with each entry)
+    DirScans,
+    /// /proc/net/wireless, if_inet6 and route
+    ProcNet,
+    /// /proc/net/fib_trie
+    FibTrie,
+}
+
+const PHASES: [Phase; 5] = [Phase::Total, Phase::FileReads, Phase::DirScans, Phase::ProcNet, Phase::FibTrie];
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Total => "total",
+            Phase::FileReads => "file reads",
+            Phase::DirScans => "dir scans",
+            Phase::ProcNet => "proc_net",
+            Phase::FibTrie => "fib_trie",
+        }
+    }
+}
+
+/// Accumulated nanoseconds and call counts, indexed by `Phase as usize`.
+static NANOS: [AtomicU64; PHASES.len()] = [const { AtomicU64::new(0) }; PHASES.len()];
+static CALLS: [AtomicU64; PHASES.len()] = [const { AtomicU64::new(0) }; PHASES.len()];
+
+fn now() -> Timespec {
+    clock_gettime(ClockId::Monotonic)
+}
+
+fn nanos_between(start: Timespec, end: Timespec) -> u64 {
+    let secs = end.tv_sec - start.tv_sec;
+    let nanos = secs * 1_000_000_000 + (end.tv_nsec - start.tv_nsec);
+    nanos.max(0) as u64
+}
+
+/// Times one call of a phase, from `start` until dropped.
+pub struct Timer {
+    phase: Phase,
+    /// None when disabled or paused
+    started: Option<Timespec>,
+    enabled: bool,
+}
+
+impl Timer {
+    /// Start timing `phase`. Does nothing unless --profile-timings is on.
+    pub fn start(phase: Phase) -> Self {
+        let enabled = is_enabled();
+        Timer {
+            phase,
+            started: enabled.then(now),
+            enabled,
+        }
+    }
+
+    /// Stop the clock, e.g. while a callback does work that isn't ours.
+    pub fn pause(&mut self) {
+        if let Some(start) = self.started.take() {
+            NANOS[self.phase as usize].fetch_add(nanos_between(start, now()), Ordering::Relaxed);
+        }
+    }
+
+    /// Restart the clock after `pause`.
+    pub fn resume(&mut self) {
+        if self.enabled && self.started.is_none() {
+            self.started = Some(now());
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if self.enabled {
+            self.pause();
+            CALLS[self.phase as usize].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Format nanoseconds as milliseconds with three decimals ("12.345").
+fn format_ms(nanos: u64) -> StackString<24> {
+    let mut s = StackString::new();
+    let mut buf = itoa::Buffer::new();
+    let micros = nanos / 1000;
+    s.push_str(buf.format(micros / 1000));
+    s.push('.');
+    let frac = micros % 1000;
+    if frac < 100 {
+        s.push('0');
+    }
+    if frac < 10 {
+        s.push('0');
+    }
+    s.push_str(buf.format(frac));
+    s
+}
+
+/// One summary line: `  file reads         8.100 ms  (340 calls)`.
+fn format_line(name: &str, nanos: u64, calls: u64) -> StackString<80> {
+    let mut line: StackString<80> = StackString::from_str("  ");
+    line.push_str(name);
+    let ms = format_ms(nanos);
+    for _ in name.len() + ms.len()..24 {
+        line.push(' ');
+    }
+    line.push_str(ms.as_str());
+    line.push_str(" ms  (");
+    line.push_str(itoa::Buffer::new().format(calls));
+    line.push_str(if calls == 1 { " call)" } else { " calls)" });
+    line
+}
+
+/// Print the per-phase totals to stderr, skipping phases that never ran.
+pub fn print_summary() {
+    if !is_enabled() {
+        return;
+    }
+    print::eprintln("profile:");
+    for phase in PHASES {
+        let calls = CALLS[phase as usize].load(Ordering::Relaxed);
+        if calls > 0 {
+            let nanos = NANOS[phase as usize].load(Ordering::Relaxed);
+            print::eprintln(format_line(phase.name(), nanos, calls).as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(format_ms(12_345_678).as_str(), "12.345");
+        assert_eq!(format_ms(5_000).as_str(), "0.005");
+        assert_eq!(format_line("fib_trie", 1_200_000, 1).as_str(), "  fib_trie           1.200 ms  (1 call)");
+        assert_eq!(format_line("file reads", 8_100_000, 340).as_str(), "  file reads         8.100 ms  (340 calls)");
+    }
+
+    #[test]
+    fn test_timer_disabled_records_nothing() {
+        // Tests run with the flag off, like a normal kv run
+        let mut t = Timer::start(Phase::Total);
+        t.pause();
+        t.resume();
+        drop(t);
+        assert!(!is_enabled());
+        assert_eq!(CALLS[Phase::Total as usize].load(Ordering::Relaxed), 0);
+    }
+}
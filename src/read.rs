@@ -0,0 +1,198 @@
+//! Read a single sysfs/procfs attribute (`kv read <path>`).
+//!
+//! For the times you just want one value and don't want to remember which
+//! subcommand shows it: `kv read /sys/class/net/eth0/mtu`. Text values are
+//! printed as-is, binary ones as hex bytes. A directory lists its entries.
+//!
+//! Only paths under /sys and /proc are accepted, and ".." is rejected, so
+//! this can't be used to read arbitrary files. The per-process links in
+//! /proc that lead back out to the filesystem (`root`, `cwd`, `exe`, `fd`,
+//! `map_files`) are refused as well.
+
+#![allow(dead_code)]
+
+use crate::cli::{ExtraArgs, GlobalOptions};
+use crate::fields::read as f;
+use crate::io;
+use crate::json::begin_kv_output_streaming;
+use crate::print;
+use crate::stack::StackString;
+
+/// Largest value we read. Sysfs attributes are a page at most; this leaves
+/// room for the bigger procfs files like /proc/meminfo and /proc/cpuinfo.
+const MAX_VALUE_SIZE: usize = 64 * 1024;
+
+/// Room for a formatted value: joining a string list with ", " can make it
+/// longer than the bytes it came from.
+const MAX_FORMATTED_SIZE: usize = 2 * MAX_VALUE_SIZE;
+
+/// Most directory entries we list (a /proc directory can be large).
+const MAX_ENTRIES: usize = 512;
+
+/// Prefixes `kv read` is allowed to look under.
+const ALLOWED_ROOTS: &[&str] = &["/sys", "/proc"];
+
+/// Per-process /proc entries that point into the regular filesystem.
+const PROC_ESCAPES: &[&str] = &["root", "cwd", "exe", "fd", "map_files"];
+
+/// Is `path` something `kv read` is allowed to open?
+///
+/// Must be absolute, under /sys or /proc, with no ".." components, and
+/// must not go through one of the /proc links in PROC_ESCAPES.
+pub fn is_allowed_path(path: &str) -> bool {
+    let Some(root) = ALLOWED_ROOTS.iter().find(|root| {
+        path.strip_prefix(**root).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }) else {
+        return false;
+    };
+
+    for component in path.split('/') {
+        if component == ".." {
+            return false;
+        }
+        if *root == "/proc" && PROC_ESCAPES.contains(&component) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Directory entry names, sorted so the output is stable.
+struct EntryList {
+    names: [StackString<64>; MAX_ENTRIES],
+    count: usize,
+}
+
+impl EntryList {
+    fn read(path: &str) -> Self {
+        let mut list = EntryList {
+            names: [const { StackString::new() }; MAX_ENTRIES],
+            count: 0,
+        };
+        io::for_each_dir_entry(path, |name| {
+            if list.count < MAX_ENTRIES {
+                list.names[list.count] = StackString::from_str(name);
+                list.count += 1;
+            }
+        });
+        list.names[..list.count].sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+        list
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names[..self.count].iter().map(|s| s.as_str())
+    }
+}
+
+/// Report a path we won't or can't read. Text goes to stderr, so a
+/// failed `v=$(kv read ...)` doesn't capture the message as the value.
+fn print_error(opts: &GlobalOptions, path: &str, error: &str) {
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "read");
+        w.key("data");
+        w.value_null();
+        w.field_str("error", error);
+        w.end_object();
+        w.finish();
+    } else {
+        print::eprint("read: ");
+        print::eprint(error);
+        if !path.is_empty() {
+            print::eprint(": ");
+            print::eprint(path);
+        }
+        print::eprintln_empty();
+    }
+}
+
+fn print_entries(opts: &GlobalOptions, path: &str) {
+    let entries = EntryList::read(path);
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "read");
+        print::note_data();
+        w.field_object("data");
+        w.field_str(f::PATH, path);
+        w.field_array(f::ENTRIES);
+        for name in entries.iter() {
+            w.array_string(name);
+        }
+        w.end_field_array();
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    } else {
+        for name in entries.iter() {
+            print::println_record(name);
+        }
+    }
+}
+
+fn print_value(opts: &GlobalOptions, path: &str, value: &str) {
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "read");
+        print::note_data();
+        w.field_object("data");
+        w.field_str(f::PATH, path);
+        w.field_str(f::VALUE, value);
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    } else {
+        print::println_record(value);
+    }
+}
+
+/// Entry point for `kv read <path>`.
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let Some(path) = args.iter().find(|a| !a.starts_with('-')) else {
+        print_error(opts, "", "expected a path under /sys or /proc");
+        return 1;
+    };
+    // "/sys/class/net/" and "/sys/class/net" are the same directory
+    let path = if path.len() > 1 { path.trim_end_matches('/') } else { path };
+
+    if !is_allowed_path(path) {
+        print_error(opts, path, "path not allowed (only /sys and /proc)");
+        return 1;
+    }
+
+    if io::is_dir_followed(path) {
+        print_entries(opts, path);
+        return 0;
+    }
+
+    // One byte over the limit tells a file that fits from one that doesn't
+    let mut buf = [0u8; MAX_VALUE_SIZE + 1];
+    let Some(n) = io::read_required_bytes(path, &mut buf) else {
+        print_error(opts, path, "cannot read");
+        return 1;
+    };
+    if n > MAX_VALUE_SIZE {
+        print_error(opts, path, "value too large (over 64K)");
+        return 1;
+    }
+    // sysfs values end in a newline; --raw keeps it like any other cleanup
+    let bytes = if io::is_raw() { &buf[..n] } else { buf[..n].trim_ascii() };
+    let value: StackString<MAX_FORMATTED_SIZE> = io::format_bytes(bytes);
+    print_value(opts, path, value.as_str());
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed_path() {
+        assert!(is_allowed_path("/sys/class/net/eth0/mtu"));
+        assert!(is_allowed_path("/proc/meminfo"));
+        assert!(is_allowed_path("/sys"));
+        assert!(!is_allowed_path("/etc/shadow"));
+        assert!(!is_allowed_path("/system/file"));
+        assert!(!is_allowed_path("sys/class"));
+        assert!(!is_allowed_path("/sys/../etc/shadow"));
+        assert!(!is_allowed_path("/proc/1/root/etc/shadow"));
+        assert!(!is_allowed_path("/proc/self/cwd"));
+        assert!(!is_allowed_path("/proc/self/fd/3"));
+    }
+}
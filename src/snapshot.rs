@@ -10,69 +10,251 @@
 //! Note: This always outputs JSON. If you want text output, run the
 //! individual subcommands instead.
 //!
+//! The output is ordered so two snapshots of the same system diff cleanly:
+//! sections always come as cpu, mem, mounts, pci, usb, block, thermal,
+//! power, net, dt, and the records in each are sorted by name (PCI by
+//! address, interfaces as eth2 before eth10). Only the values change.
+//!
 //! Currently only includes subcommands that have been converted to no_std.
 //! More sections will be added as subcommands are converted.
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
+use crate::cli::{ExtraArgs, GlobalOptions};
+#[cfg(any(feature = "mounts", feature = "block"))]
+use crate::io;
+#[cfg(feature = "mem")]
 use crate::io::KbToBytes;
 use crate::json::{StreamingJsonWriter, begin_kv_output_streaming};
+use crate::print;
+#[cfg(any(feature = "mounts", feature = "block"))]
+use crate::stack::StackString;
+
+/// Read once and shared by the mounts and block sections.
+const MOUNTS_PATH: &str = "/proc/self/mounts";
+
+/// Snapshot sections as bit flags, so a profile can pick any subset.
+pub mod section {
+    pub const CPU: u16 = 1 << 0;
+    pub const MEM: u16 = 1 << 1;
+    pub const MOUNTS: u16 = 1 << 2;
+    pub const PCI: u16 = 1 << 3;
+    pub const USB: u16 = 1 << 4;
+    pub const BLOCK: u16 = 1 << 5;
+    pub const THERMAL: u16 = 1 << 6;
+    pub const POWER: u16 = 1 << 7;
+    pub const NET: u16 = 1 << 8;
+    pub const DT: u16 = 1 << 9;
+    pub const ALL: u16 = (1 << 10) - 1;
+}
+
+/// A named, curated capture: which sections to include and whether
+/// to force verbose output for them.
+pub struct Profile {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub sections: u16,
+    pub verbose: bool,
+}
+
+/// Available profiles for `kv snapshot --profile <name>`.
+pub const PROFILES: &[Profile] = &[
+    Profile {
+        name: "full",
+        description: "everything (the default)",
+        sections: section::ALL,
+        verbose: false,
+    },
+    Profile {
+        name: "inventory",
+        description: "stable hardware identity: cpu, pci, usb, block, net, dt",
+        sections: section::CPU | section::PCI | section::USB | section::BLOCK | section::NET | section::DT,
+        verbose: false,
+    },
+    Profile {
+        name: "health",
+        description: "current state: thermal, power, mem",
+        sections: section::THERMAL | section::POWER | section::MEM,
+        verbose: true,
+    },
+];
+
+/// Look up a profile by name.
+pub fn find_profile(name: &str) -> Option<&'static Profile> {
+    PROFILES.iter().find(|p| p.name == name)
+}
+
+/// Options specific to the snapshot subcommand.
+#[derive(Default)]
+pub struct SnapshotOptions<'a> {
+    /// Profile name from --profile (validated later so we can list choices)
+    pub profile: Option<&'a str>,
+    /// Write sections with nothing in them instead of leaving them out
+    pub include_empty: bool,
+}
+
+impl<'a> SnapshotOptions<'a> {
+    /// Parse snapshot-specific options from remaining arguments.
+    pub fn parse(args: &'a ExtraArgs) -> Self {
+        let mut opts = SnapshotOptions::default();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--profile" {
+                opts.profile = iter.next();
+            } else if let Some(name) = arg.strip_prefix("--profile=") {
+                opts.profile = Some(name);
+            } else if arg == "--include-empty" {
+                opts.include_empty = true;
+            }
+        }
+
+        opts
+    }
+}
+
+/// Tell the user which profiles exist.
+fn print_profiles() {
+    print::eprintln("Available profiles:");
+    for p in PROFILES {
+        print::eprint("    ");
+        print::eprint(p.name);
+        print::eprint(" - ");
+        print::eprintln(p.description);
+    }
+}
 
 /// Entry point for `kv snapshot` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
-    let pretty = opts.pretty;
-    let verbose = opts.verbose;
+// Built with no section features, the options below have nothing to read them
+#[cfg_attr(
+    not(any(
+        feature = "cpu",
+        feature = "mem",
+        feature = "mounts",
+        feature = "pci",
+        feature = "usb",
+        feature = "block",
+        feature = "thermal",
+        feature = "power",
+        feature = "net",
+        all(feature = "dt", any(target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64", target_arch = "powerpc64", target_arch = "mips"))
+    )),
+    allow(unused_variables)
+)]
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let snapshot_opts = SnapshotOptions::parse(args);
+    let profile = match snapshot_opts.profile {
+        None => &PROFILES[0],
+        Some(name) => match find_profile(name) {
+            Some(p) => p,
+            None => {
+                print::eprint("Error: unknown profile: ");
+                print::eprintln(name);
+                print_profiles();
+                return 1;
+            }
+        },
+    };
 
-    let mut w = begin_kv_output_streaming(pretty, "snapshot");
+    let verbose = opts.verbose || profile.verbose;
+    let wants = |s: u16| profile.sections & s != 0;
+    let include_empty = snapshot_opts.include_empty;
+
+    let mut w = begin_kv_output_streaming(opts.json_layout(), "snapshot");
+    if snapshot_opts.profile.is_some() {
+        w.field_str("profile", profile.name);
+    }
 
     w.field_object("data");
 
+    // mounts and block both need /proc/self/mounts: read it once, so the
+    // two sections describe the same moment
+    #[cfg(any(feature = "mounts", feature = "block"))]
+    let mounts_table: Option<StackString<8192>> = if wants(section::MOUNTS) {
+        io::read_required_stack(MOUNTS_PATH)
+    } else if wants(section::BLOCK) {
+        io::read_file_stack(MOUNTS_PATH)
+    } else {
+        None
+    };
+
     // CPU info
     #[cfg(feature = "cpu")]
-    if let Some(info) = crate::cpu::CpuInfo::read() {
-        w.key("cpu");
-        write_cpu_json(&mut w, &info, verbose);
+    if wants(section::CPU) {
+        match crate::cpu::CpuInfo::read(verbose) {
+            Some(info) => {
+                w.key("cpu");
+                write_cpu_json(&mut w, &info, verbose);
+            }
+            None if include_empty => write_missing_object(&mut w, "cpu"),
+            None => {}
+        }
     }
 
     // Memory info
     #[cfg(feature = "mem")]
-    if let Some(info) = crate::mem::MemInfo::read() {
-        w.key("mem");
-        write_mem_json(&mut w, &info, verbose, opts.human);
+    if wants(section::MEM) {
+        match crate::mem::MemInfo::read() {
+            Some(info) => {
+                w.key("mem");
+                write_mem_json(&mut w, &info, verbose, opts.human);
+            }
+            None if include_empty => write_missing_object(&mut w, "mem"),
+            None => {}
+        }
     }
 
     // Mount points
     #[cfg(feature = "mounts")]
-    crate::mounts::write_snapshot(&mut w, verbose);
+    if wants(section::MOUNTS) {
+        match mounts_table.as_ref() {
+            Some(contents) => crate::mounts::write_snapshot_with(&mut w, verbose, contents.as_str()),
+            None if include_empty => crate::mounts::write_snapshot_with(&mut w, verbose, ""),
+            None => {}
+        }
+    }
 
     // PCI devices
     #[cfg(feature = "pci")]
-    crate::pci::write_snapshot(&mut w, verbose);
+    if wants(section::PCI) {
+        crate::pci::write_snapshot(&mut w, verbose, include_empty);
+    }
 
     // USB devices
     #[cfg(feature = "usb")]
-    crate::usb::write_snapshot(&mut w, verbose);
+    if wants(section::USB) {
+        crate::usb::write_snapshot(&mut w, verbose, include_empty);
+    }
 
     // Block devices
     #[cfg(feature = "block")]
-    crate::block::write_snapshot(&mut w, verbose);
+    if wants(section::BLOCK) {
+        crate::block::write_snapshot_with_mounts(&mut w, verbose, mounts_table.as_ref().map(|c| c.as_str()), include_empty);
+    }
 
     // Thermal sensors
     #[cfg(feature = "thermal")]
-    crate::thermal::write_snapshot(&mut w, verbose);
+    if wants(section::THERMAL) {
+        crate::thermal::write_snapshot(&mut w, verbose, include_empty);
+    }
 
     // Power supplies
     #[cfg(feature = "power")]
-    crate::power::write_snapshot(&mut w, verbose);
+    if wants(section::POWER) {
+        crate::power::write_snapshot(&mut w, verbose, include_empty);
+    }
 
     // Network interfaces
     #[cfg(feature = "net")]
-    crate::net::write_snapshot(&mut w, verbose);
+    if wants(section::NET) {
+        crate::net::write_snapshot(&mut w, verbose, include_empty);
+    }
 
     // Device tree (ARM/AArch64/RISC-V only)
     #[cfg(all(feature = "dt", any(target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64", target_arch = "powerpc64", target_arch = "mips")))]
-    crate::dt::write_snapshot(&mut w, verbose);
+    if wants(section::DT) {
+        crate::dt::write_snapshot(&mut w, verbose, include_empty);
+    }
 
     w.end_field_object();
     w.end_object();
@@ -81,6 +263,14 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     0
 }
 
+/// `"key": {}` for an object section (cpu, mem) that couldn't be read,
+/// under --include-empty, the way list sections get `[]`.
+fn write_missing_object(w: &mut StreamingJsonWriter, key: &str) {
+    w.key(key);
+    w.begin_object();
+    w.end_object();
+}
+
 /// Write CPU info as a JSON object (without the key).
 #[cfg(feature = "cpu")]
 fn write_cpu_json(w: &mut StreamingJsonWriter, info: &crate::cpu::CpuInfo, verbose: bool) {
@@ -114,6 +304,12 @@ fn write_cpu_json(w: &mut StreamingJsonWriter, info: &crate::cpu::CpuInfo, verbo
         }
         w.field_str_opt(f::CACHE_SIZE, info.cache_size.as_ref().map(|s| s.as_str()));
         w.field_str_opt(f::ARCHITECTURE, info.architecture.as_ref().map(|s| s.as_str()));
+        if !info.caches.is_empty() {
+            w.key(f::CACHES);
+            info.caches.write_json(w);
+        }
+        info.write_topology_json(w);
+        info.write_flags_json(w);
     }
 
     w.end_object();
@@ -142,6 +338,12 @@ fn write_mem_json(w: &mut StreamingJsonWriter, info: &crate::mem::MemInfo, verbo
             w.field_str_opt(f::SUNRECLAIM, info.sunreclaim_kb.map(|v| crate::io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
             w.field_str_opt(f::DIRTY, info.dirty_kb.map(|v| crate::io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
             w.field_str_opt(f::WRITEBACK, info.writeback_kb.map(|v| crate::io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
+            if info.has_hugepages() {
+                w.field_u64_opt(f::HUGEPAGES_TOTAL, info.hugepages_total);
+                w.field_u64_opt(f::HUGEPAGES_FREE, info.hugepages_free);
+                w.field_u64_opt(f::HUGEPAGES_RSVD, info.hugepages_rsvd);
+                w.field_str_opt(f::HUGEPAGESIZE, info.hugepagesize_kb.map(|v| crate::io::format_human_size(v.kb())).as_ref().map(|s| s.as_str()));
+            }
         }
     } else {
         w.field_u64_opt(f::MEM_TOTAL_KB, info.mem_total_kb);
@@ -159,6 +361,12 @@ fn write_mem_json(w: &mut StreamingJsonWriter, info: &crate::mem::MemInfo, verbo
             w.field_u64_opt(f::SUNRECLAIM_KB, info.sunreclaim_kb);
             w.field_u64_opt(f::DIRTY_KB, info.dirty_kb);
             w.field_u64_opt(f::WRITEBACK_KB, info.writeback_kb);
+            if info.has_hugepages() {
+                w.field_u64_opt(f::HUGEPAGES_TOTAL, info.hugepages_total);
+                w.field_u64_opt(f::HUGEPAGES_FREE, info.hugepages_free);
+                w.field_u64_opt(f::HUGEPAGES_RSVD, info.hugepages_rsvd);
+                w.field_u64_opt(f::HUGEPAGESIZE_KB, info.hugepagesize_kb);
+            }
         }
     }
 
@@ -67,16 +67,17 @@ impl<const N: usize> StackString<N> {
         self.push_str(encoded)
     }
 
-    /// Push a string slice, returning true if it fit (or was truncated).
+    /// Push a string slice, returning true if it all fit. One that doesn't
+    /// is cut at a character boundary, as `as_str` relies on valid UTF-8.
     #[inline]
     pub fn push_str(&mut self, s: &str) -> bool {
-        let bytes = s.as_bytes();
-        let to_copy = bytes.len().min(self.remaining());
+        let bytes = char_prefix(s, self.remaining()).as_bytes();
+        let to_copy = bytes.len();
         if to_copy > 0 {
-            self.buf[self.len..self.len + to_copy].copy_from_slice(&bytes[..to_copy]);
+            self.buf[self.len..self.len + to_copy].copy_from_slice(bytes);
             self.len += to_copy;
         }
-        to_copy == bytes.len()
+        to_copy == s.len()
     }
 
     /// Clear the string.
@@ -85,6 +86,15 @@ impl<const N: usize> StackString<N> {
         self.len = 0;
     }
 
+    /// Shorten the string to `len` bytes. No-op if already shorter.
+    /// Callers must pass a char boundary (e.g. a previously seen len()).
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+        }
+    }
+
     /// Trim whitespace from both ends (returns a new StackString).
     pub fn trim(&self) -> StackString<N> {
         StackString::from_str(self.as_str().trim())
@@ -112,6 +122,19 @@ impl<const N: usize> AsRef<str> for StackString<N> {
     }
 }
 
+/// The longest start of `s` that fits in `max` bytes without splitting a
+/// character.
+pub fn char_prefix(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
 /// A stack-allocated buffer for reading files.
 pub struct StackBuf<const N: usize> {
     buf: [u8; N],
@@ -199,6 +222,18 @@ mod tests {
         assert_eq!(s.len(), 5);
     }
 
+    #[test]
+    fn test_stack_string_truncates_at_char_boundary() {
+        // "é" is two bytes; cutting after 'caf' + one byte would split it
+        let mut s: StackString<4> = StackString::new();
+        assert!(!s.push_str("café"));
+        assert_eq!(s.as_str(), "caf");
+        assert!(s.push_str("e"));
+        assert_eq!(s.as_str(), "cafe");
+        assert_eq!(char_prefix("日本", 5), "日");
+        assert_eq!(char_prefix("日本", 6), "日本");
+    }
+
     #[test]
     fn test_stack_string_trim() {
         let s: StackString<32> = StackString::from_str("  hello  ");
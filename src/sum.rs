@@ -0,0 +1,204 @@
+//! Totals of numeric fields across a listing (`--sum`).
+//!
+//! `kv net --sum rx_bytes,tx_bytes` prints one line of totals over the
+//! (filtered) interfaces instead of a row per interface. Any field a
+//! record reports through `Filterable::filter_numeric_fields` can be
+//! summed, so the fields that `-f 'mtu>1500'` compares are the same ones
+//! `--sum` adds up.
+//!
+//! Fields that only exist as text (like `name`) are skipped with a warning.
+
+#![allow(dead_code)]
+
+use crate::filter::Filterable;
+use crate::io;
+use crate::json::{begin_kv_output_streaming, Layout};
+use crate::print::{self, TextWriter};
+use crate::stack::StackString;
+
+/// Subcommands whose listings `--sum` can total; the rest reject it.
+pub const SUBCOMMANDS: [&str; 3] = ["block", "net", "power"];
+
+/// Most fields one `--sum` takes. Extra ones are ignored.
+pub const MAX_SUM_FIELDS: usize = 8;
+
+/// Longest field name we keep.
+const MAX_FIELD_NAME: usize = 32;
+
+/// The field names given to `--sum`, in order.
+#[derive(Clone, Default)]
+pub struct SumFields {
+    names: [StackString<MAX_FIELD_NAME>; MAX_SUM_FIELDS],
+    count: usize,
+}
+
+impl SumFields {
+    /// Parse a comma-separated list like "rx_bytes,tx_bytes".
+    /// Blank entries are dropped; overlong or extra ones are dropped
+    /// with a warning.
+    pub fn parse(list: &str) -> Self {
+        let mut fields = SumFields::default();
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            if fields.names[..fields.count].iter().any(|n| n.as_str() == name) {
+                continue;
+            }
+            if fields.count == MAX_SUM_FIELDS {
+                print::eprint("Warning: only the first ");
+                print::eprint(itoa::Buffer::new().format(MAX_SUM_FIELDS));
+                print::eprintln(" --sum fields are used");
+                break;
+            }
+            let mut s = StackString::new();
+            if !s.push_str(name) {
+                print::eprint("Warning: --sum field name too long: ");
+                print::eprintln(name);
+                continue;
+            }
+            fields.names[fields.count] = s;
+            fields.count += 1;
+        }
+        fields
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names[..self.count].iter().map(|n| n.as_str())
+    }
+}
+
+/// Running totals for each `--sum` field.
+pub struct Totals<'a> {
+    fields: &'a SumFields,
+    sums: [i64; MAX_SUM_FIELDS],
+    /// Some record had this field as a number
+    numeric: [bool; MAX_SUM_FIELDS],
+    /// Some record had this field, but only as text
+    textual: [bool; MAX_SUM_FIELDS],
+}
+
+impl<'a> Totals<'a> {
+    pub fn new(fields: &'a SumFields) -> Self {
+        Totals {
+            fields,
+            sums: [0; MAX_SUM_FIELDS],
+            numeric: [false; MAX_SUM_FIELDS],
+            textual: [false; MAX_SUM_FIELDS],
+        }
+    }
+
+    /// Add one record's values.
+    pub fn add<T: Filterable + ?Sized>(&mut self, item: &T) {
+        let fields = self.fields;
+        item.filter_numeric_fields(&mut |name, value| {
+            if let Some(i) = fields.iter().position(|n| n == name) {
+                self.sums[i] = self.sums[i].saturating_add(value);
+                self.numeric[i] = true;
+            }
+        });
+        let mut check = |name: &str, _: &str| {
+            if let Some(i) = fields.iter().position(|n| n == name) {
+                self.textual[i] = true;
+            }
+        };
+        item.filter_fields(&mut check);
+        item.filter_keyed_fields(&mut check);
+    }
+
+    /// The total for `name`, if any record had it as a number.
+    pub fn get(&self, name: &str) -> Option<i64> {
+        let i = self.fields.iter().position(|n| n == name)?;
+        self.numeric[i].then_some(self.sums[i])
+    }
+
+    /// (name, total) for every field that had numbers, warning about
+    /// the rest. A total stands for the records behind it, so it counts
+    /// as data for --probe; a sum over nothing doesn't.
+    fn for_each_total(&self, mut visit: impl FnMut(&str, i64)) {
+        for (i, name) in self.fields.iter().enumerate() {
+            if self.numeric[i] {
+                print::note_data();
+                visit(name, self.sums[i]);
+            } else if self.textual[i] {
+                print::eprint("Warning: ");
+                print::eprint(name);
+                print::eprintln(" isn't a numeric field, skipped");
+            } else {
+                print::eprint("Warning: no ");
+                print::eprint(name);
+                print::eprintln(" values to sum, skipped");
+            }
+        }
+    }
+
+    /// One line of totals: `RX_BYTES=123456 TX_BYTES=7890`.
+    pub fn print_text(&self, human: bool) {
+        let mut w = TextWriter::new();
+        self.for_each_total(|name, total| match human_size(name, total, human) {
+            Some(size) => w.field_str(name, size.as_str()),
+            None => w.field_i64(name, total),
+        });
+        w.finish();
+    }
+
+    /// `{"data": {"rx_bytes": 123456, ...}}` for `subcommand`.
+    pub fn print_json(&self, layout: Layout, human: bool, subcommand: &str) {
+        let mut w = begin_kv_output_streaming(layout, subcommand);
+        w.field_object("data");
+        self.for_each_total(|name, total| match human_size(name, total, human) {
+            Some(size) => w.field_str(name, size.as_str()),
+            None => w.field_i64(name, total),
+        });
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    }
+}
+
+/// With -h, byte counts ("rx_bytes", "size_bytes") read better as "1.5G".
+/// Other totals (packets, MTUs, microwatts) stay plain numbers.
+fn human_size(name: &str, total: i64, human: bool) -> Option<StackString<16>> {
+    if !human || !name.ends_with("bytes") || total < 0 {
+        return None;
+    }
+    Some(io::format_human_size(total as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::testing::{FakeNic, ETH, TUN};
+
+    const NICS: [FakeNic; 3] = [FakeNic { name: "lo", rx_bytes: Some(1000), tx_bytes: 1000, ..ETH }, ETH, TUN];
+
+    #[test]
+    fn test_parse_sum_fields() {
+        let fields = SumFields::parse("rx_bytes, tx_bytes,,rx_bytes");
+        assert!(fields.iter().eq(["rx_bytes", "tx_bytes"]));
+        assert!(SumFields::parse(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_sum_over_interfaces() {
+        let fields = SumFields::parse("rx_bytes,tx_bytes,name,speed");
+        let mut totals = Totals::new(&fields);
+        for nic in &NICS {
+            totals.add(nic);
+        }
+        assert_eq!(totals.get("rx_bytes"), Some(2_501_000));
+        assert_eq!(totals.get("tx_bytes"), Some(401_024));
+        // Text-only and unknown fields have no total
+        assert_eq!(totals.get("name"), None);
+        assert!(totals.textual[2]);
+        assert_eq!(totals.get("speed"), None);
+    }
+
+    #[test]
+    fn test_human_sum_only_for_bytes() {
+        assert_eq!(human_size("rx_bytes", 2048, true).unwrap().as_str(), "2K");
+        assert!(human_size("rx_bytes", 2048, false).is_none());
+        assert!(human_size("rx_packets", 2048, true).is_none());
+    }
+}
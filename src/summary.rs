@@ -0,0 +1,203 @@
+//! One-line system summary for dashboards and login banners.
+//!
+//! `kv summary` (or `kv -1`) prints host name, CPU model and count, total
+//! memory, uptime, load average and the hottest temperature on a single
+//! line. It reuses the cpu, mem and thermal readers, so a section only
+//! appears if its feature is compiled in.
+//!
+//! With -j the data is a flat object (no nested arrays), easy to feed
+//! into a status bar.
+
+#![allow(dead_code)]
+
+use crate::cli::GlobalOptions;
+use crate::fields::summary as f;
+use crate::io::{self, KbToBytes};
+use crate::json::{begin_kv_output_streaming, Layout, HOSTNAME_PATH};
+use crate::print::{self, TextWriter};
+use crate::stack::StackString;
+
+const UPTIME_PATH: &str = "/proc/uptime";
+const LOADAVG_PATH: &str = "/proc/loadavg";
+
+/// Seconds since boot, from the first field of /proc/uptime ("12345.67 ...").
+pub fn parse_uptime(content: &str) -> Option<u64> {
+    let first = content.split_whitespace().next()?;
+    let whole = first.split('.').next()?;
+    whole.parse().ok()
+}
+
+/// The 1, 5 and 15 minute load averages from /proc/loadavg, as written.
+pub fn parse_loadavg(content: &str) -> Option<[&str; 3]> {
+    let mut parts = content.split_whitespace();
+    let loads = [parts.next()?, parts.next()?, parts.next()?];
+    // Each must look like a number, since JSON output writes them bare
+    let numeric = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+    loads.iter().all(|s| numeric(s)).then_some(loads)
+}
+
+/// Format seconds compactly: "3d4h", "5h12m", "7m".
+pub fn format_uptime(secs: u64) -> StackString<16> {
+    let days = secs / 86_400;
+    let hours = (secs % 86_400) / 3600;
+    let mins = (secs % 3600) / 60;
+
+    let mut s = StackString::new();
+    let mut buf = itoa::Buffer::new();
+    if days > 0 {
+        s.push_str(buf.format(days));
+        s.push('d');
+        s.push_str(buf.format(hours));
+        s.push('h');
+    } else if hours > 0 {
+        s.push_str(buf.format(hours));
+        s.push('h');
+        s.push_str(buf.format(mins));
+        s.push('m');
+    } else {
+        s.push_str(buf.format(mins));
+        s.push('m');
+    }
+    s
+}
+
+/// Everything on the summary line. Missing pieces are simply left out.
+struct Summary {
+    hostname: Option<StackString<64>>,
+    cpu_model: Option<StackString<128>>,
+    cpus: Option<u32>,
+    mem_total_kb: Option<u64>,
+    uptime_s: Option<u64>,
+    loadavg: Option<StackString<64>>,
+    hottest: Option<(StackString<64>, i64, bool)>,
+}
+
+impl Summary {
+    fn read() -> Self {
+        // Only mutated by the feature-gated sections below
+        #[allow(unused_mut)]
+        let mut summary = Summary {
+            hostname: io::read_file_stack(HOSTNAME_PATH),
+            cpu_model: None,
+            cpus: None,
+            mem_total_kb: None,
+            uptime_s: io::read_file_stack::<128>(UPTIME_PATH).and_then(|s| parse_uptime(s.as_str())),
+            loadavg: io::read_file_stack(LOADAVG_PATH),
+            hottest: None,
+        };
+
+        #[cfg(feature = "cpu")]
+        if let Some(cpu) = crate::cpu::CpuInfo::read(false) {
+            summary.cpu_model = cpu.model_name;
+            summary.cpus = Some(cpu.logical_cpus);
+        }
+
+        #[cfg(feature = "mem")]
+        {
+            summary.mem_total_kb = crate::mem::MemInfo::read().and_then(|m| m.mem_total_kb);
+        }
+
+        #[cfg(feature = "thermal")]
+        {
+            summary.hottest = crate::thermal::hottest().map(|h| (h.sensor, h.temp_millicelsius, h.hot));
+        }
+
+        summary
+    }
+
+    fn loads(&self) -> Option<[&str; 3]> {
+        self.loadavg.as_ref().and_then(|s| parse_loadavg(s.as_str()))
+    }
+
+    fn print_text(&self, human: bool) {
+        let mut w = TextWriter::new();
+        w.field_str_opt(f::HOSTNAME, self.hostname.as_ref().map(|s| s.as_str()));
+        w.field_quoted_opt(f::CPU_MODEL, self.cpu_model.as_ref().map(|s| s.as_str()));
+        w.field_u64_opt(f::CPUS, self.cpus.map(|v| v as u64));
+        if human {
+            let mem = self.mem_total_kb.map(|kb| io::format_human_size(kb.kb()));
+            w.field_str_opt(f::MEM_TOTAL, mem.as_ref().map(|s| s.as_str()));
+            let uptime = self.uptime_s.map(format_uptime);
+            w.field_str_opt(f::UPTIME, uptime.as_ref().map(|s| s.as_str()));
+        } else {
+            w.field_u64_opt(f::MEM_TOTAL_KB, self.mem_total_kb);
+            w.field_u64_opt(f::UPTIME_S, self.uptime_s);
+        }
+        if let Some([l1, l5, l15]) = self.loads() {
+            let mut load: StackString<64> = StackString::from_str(l1);
+            load.push(',');
+            load.push_str(l5);
+            load.push(',');
+            load.push_str(l15);
+            w.field_str(f::LOAD, load.as_str());
+        }
+        #[cfg(feature = "thermal")]
+        if let Some((_, temp, hot)) = self.hottest {
+            crate::thermal::format_temp_text(&mut w, f::TEMP_MAX, temp, human);
+            w.field_u64(f::HOT, hot as u64);
+        }
+        w.finish_record();
+    }
+
+    fn print_json(&self, layout: Layout) {
+        let mut w = begin_kv_output_streaming(layout, "summary");
+        print::note_data();
+        w.field_object("data");
+        w.field_str_opt(f::HOSTNAME, self.hostname.as_ref().map(|s| s.as_str()));
+        w.field_str_opt(f::CPU_MODEL, self.cpu_model.as_ref().map(|s| s.as_str()));
+        w.field_u64_opt(f::CPUS, self.cpus.map(|v| v as u64));
+        w.field_u64_opt(f::MEM_TOTAL_KB, self.mem_total_kb);
+        w.field_u64_opt(f::UPTIME_S, self.uptime_s);
+        if let Some([l1, l5, l15]) = self.loads() {
+            w.field_number(f::LOAD_1, l1);
+            w.field_number(f::LOAD_5, l5);
+            w.field_number(f::LOAD_15, l15);
+        }
+        if let Some((ref sensor, temp, hot)) = self.hottest {
+            w.field_str(f::TEMP_MAX_SENSOR, sensor.as_str());
+            w.field_i64(f::TEMP_MAX_MILLICELSIUS, temp);
+            w.field_bool(f::HOT, hot);
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    }
+}
+
+/// Entry point for `kv summary`.
+pub fn run(opts: &GlobalOptions) -> i32 {
+    let summary = Summary::read();
+    if opts.json {
+        summary.print_json(opts.json_layout());
+    } else {
+        summary.print_text(opts.human);
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_uptime() {
+        assert_eq!(parse_uptime("350735.47 234388.90\n"), Some(350735));
+        assert_eq!(parse_uptime("12 5"), Some(12));
+        assert_eq!(parse_uptime(""), None);
+    }
+
+    #[test]
+    fn test_parse_loadavg() {
+        assert_eq!(parse_loadavg("0.52 0.40 0.31 1/123 4567"), Some(["0.52", "0.40", "0.31"]));
+        assert_eq!(parse_loadavg("0.52 0.40"), None);
+        assert_eq!(parse_loadavg("0.52 x 0.31"), None);
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(3 * 86_400 + 4 * 3600 + 59).as_str(), "3d4h");
+        assert_eq!(format_uptime(5 * 3600 + 12 * 60).as_str(), "5h12m");
+        assert_eq!(format_uptime(7 * 60 + 30).as_str(), "7m");
+        assert_eq!(format_uptime(0).as_str(), "0m");
+    }
+}
@@ -0,0 +1,190 @@
+//! Process and thread counts (`kv tasks`).
+//!
+//! A cheap health metric: how many processes exist, how many threads,
+//! and how many are runnable right now. Processes are the numeric
+//! directories in /proc. Threads come from /proc/loadavg, whose fourth
+//! field is "runnable/total" scheduling entities:
+//!
+//! ```text
+//! 0.20 0.18 0.12 2/845 12345
+//! ```
+//!
+//! With -v, threads are counted from /proc/<pid>/task instead, which
+//! takes longer but only counts what kv can actually see (e.g. inside a
+//! PID namespace, where loadavg still covers the whole host).
+
+#![allow(dead_code)]
+
+use crate::cli::GlobalOptions;
+use crate::fields::tasks as f;
+use crate::io;
+use crate::json::{begin_kv_output_streaming, Layout};
+use crate::print::{self, TextWriter};
+use crate::stack::StackString;
+
+const PROC_PATH: &str = "/proc";
+const LOADAVG_PATH: &str = "/proc/loadavg";
+
+/// Most /proc entries (processes plus threads) we count before giving
+/// up, like dt's node cap. Well above the default pid_max of 32768.
+const MAX_ENTRIES: u64 = 1 << 20;
+
+/// Is this /proc entry a process (all digits)?
+pub fn is_pid(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// The "runnable/total" field of /proc/loadavg.
+pub fn parse_loadavg_tasks(content: &str) -> Option<(u64, u64)> {
+    let field = content.split_whitespace().nth(3)?;
+    let (running, total) = field.split_once('/')?;
+    Some((running.parse().ok()?, total.parse().ok()?))
+}
+
+/// Counts entries up to MAX_ENTRIES, then only notes that it stopped.
+#[derive(Default)]
+struct Counter {
+    count: u64,
+    truncated: bool,
+}
+
+impl Counter {
+    /// Count one more entry. False once the cap is reached.
+    fn add(&mut self, limit: u64) -> bool {
+        if self.count >= limit {
+            self.truncated = true;
+            return false;
+        }
+        self.count += 1;
+        true
+    }
+}
+
+/// What `kv tasks` reports.
+#[derive(Default)]
+struct TaskCounts {
+    processes: u64,
+    threads: Option<u64>,
+    running: Option<u64>,
+    /// Hit MAX_ENTRIES; the counts are lower bounds
+    truncated: bool,
+}
+
+impl TaskCounts {
+    fn read(count_threads: bool) -> Self {
+        Self::read_limited(count_threads, MAX_ENTRIES)
+    }
+
+    fn read_limited(count_threads: bool, limit: u64) -> Self {
+        let loadavg: Option<StackString<128>> = io::read_file_stack(LOADAVG_PATH);
+        let from_loadavg = loadavg.as_ref().and_then(|s| parse_loadavg_tasks(s.as_str()));
+
+        let mut entries = Counter::default();
+        let mut processes = 0;
+        let mut threads = 0;
+        io::for_each_dir_entry(PROC_PATH, |name| {
+            if !is_pid(name) || !entries.add(limit) {
+                return;
+            }
+            processes += 1;
+            if count_threads {
+                let pid_path: StackString<32> = io::join_path(PROC_PATH, name);
+                let task_path: StackString<48> = io::join_path(pid_path.as_str(), "task");
+                io::for_each_dir_entry(task_path.as_str(), |tid| {
+                    if is_pid(tid) && entries.add(limit) {
+                        threads += 1;
+                    }
+                });
+            }
+        });
+
+        TaskCounts {
+            processes,
+            threads: if count_threads { Some(threads) } else { from_loadavg.map(|(_, total)| total) },
+            running: from_loadavg.map(|(running, _)| running),
+            truncated: entries.truncated,
+        }
+    }
+
+    fn print_text(&self) {
+        let mut w = TextWriter::new();
+        w.field_u64(f::PROCESSES, self.processes);
+        w.field_u64_opt(f::THREADS, self.threads);
+        w.field_u64_opt(f::RUNNING, self.running);
+        w.finish_record();
+    }
+
+    fn print_json(&self, layout: Layout) {
+        let mut w = begin_kv_output_streaming(layout, "tasks");
+        print::note_data();
+        w.field_object("data");
+        w.field_u64(f::PROCESSES, self.processes);
+        w.field_u64_opt(f::THREADS, self.threads);
+        w.field_u64_opt(f::RUNNING, self.running);
+        if self.truncated {
+            w.field_bool(f::TRUNCATED, true);
+        }
+        w.end_field_object();
+        w.end_object();
+        w.finish();
+    }
+}
+
+/// Entry point for `kv tasks`.
+pub fn run(opts: &GlobalOptions) -> i32 {
+    let counts = TaskCounts::read(opts.verbose);
+
+    if opts.json {
+        counts.print_json(opts.json_layout());
+    } else {
+        counts.print_text();
+    }
+    if counts.truncated {
+        print::eprintln("Note: too many /proc entries, counts stop at the limit");
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_pid() {
+        assert!(is_pid("1"));
+        assert!(is_pid("12345"));
+        assert!(!is_pid("self"));
+        assert!(!is_pid("thread-self"));
+        assert!(!is_pid("1a"));
+        assert!(!is_pid(""));
+    }
+
+    #[test]
+    fn test_parse_loadavg_tasks() {
+        assert_eq!(parse_loadavg_tasks("0.20 0.18 0.12 2/845 12345\n"), Some((2, 845)));
+        assert_eq!(parse_loadavg_tasks("0.20 0.18 0.12"), None);
+        assert_eq!(parse_loadavg_tasks("0.20 0.18 0.12 2-845 12345"), None);
+    }
+
+    #[test]
+    fn test_counter_cap() {
+        let mut c = Counter::default();
+        assert!(c.add(2));
+        assert!(c.add(2));
+        assert!(!c.truncated);
+        assert!(!c.add(2));
+        assert_eq!(c.count, 2);
+        assert!(c.truncated);
+    }
+
+    #[test]
+    fn test_read_counts_self() {
+        // We're a process with at least one thread
+        let counts = TaskCounts::read(true);
+        assert!(counts.processes >= 1);
+        assert!(counts.threads.is_some_and(|t| t >= 1));
+        let capped = TaskCounts::read_limited(true, 1);
+        assert_eq!(capped.processes, 1);
+        assert_eq!(capped.threads, Some(0));
+    }
+}
@@ -0,0 +1,243 @@
+//! Custom text layouts (`--template`).
+//!
+//! `kv net --template '{name}\t{ip}\t{state}'` prints one line per record
+//! with each `{field}` replaced by that record's value. Fields are the
+//! ones `-f field=value` knows (`Filterable`), plus a few extras a
+//! subcommand passes in (like `ip` for net). `\t`, `\n` and `\\` are
+//! escapes; `{{` and `}}` are literal braces.
+//!
+//! A placeholder that no record had expands to nothing. If it isn't one
+//! of the subcommand's fields at all, that gets one warning at the end
+//! rather than one per line; a real field that's just missing everywhere
+//! (`{driver}` with no driver bound) stays quiet.
+
+#![allow(dead_code)]
+
+use crate::cli::{ArgStr, GlobalOptions};
+use crate::filter::Filterable;
+use crate::print;
+use crate::stack::StackString;
+
+/// Placeholders we track for the unknown-field warning. Later ones still
+/// render, they just can't be warned about.
+const MAX_PLACEHOLDERS: usize = 32;
+
+/// Longest line a template renders to; the rest is cut off.
+const MAX_LINE_LEN: usize = 1024;
+
+/// One piece of a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Literal text, printed as is
+    Text(&'a str),
+    /// An escaped character (`\t` is a tab)
+    Char(char),
+    /// `{name}`
+    Field(&'a str),
+}
+
+/// Split a template into tokens.
+///
+/// A `{` without a closing `}` and an unknown escape like `\x` are kept
+/// as literal text rather than rejected.
+pub fn tokens(template: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut rest = template;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let bytes = rest.as_bytes();
+        let (token, len) = match bytes[0] {
+            b'\\' if bytes.len() > 1 => match bytes[1] {
+                b't' => (Token::Char('\t'), 2),
+                b'n' => (Token::Char('\n'), 2),
+                b'\\' => (Token::Char('\\'), 2),
+                _ => (Token::Text(&rest[..1]), 1),
+            },
+            b'{' if bytes.get(1) == Some(&b'{') => (Token::Char('{'), 2),
+            b'}' if bytes.get(1) == Some(&b'}') => (Token::Char('}'), 2),
+            b'{' => match rest.find('}') {
+                Some(end) => (Token::Field(rest[1..end].trim()), end + 1),
+                None => (Token::Text(rest), rest.len()),
+            },
+            _ => {
+                // Up to the next special character
+                let end = bytes[1..]
+                    .iter()
+                    .position(|b| matches!(b, b'\\' | b'{' | b'}'))
+                    .map_or(rest.len(), |i| i + 1);
+                (Token::Text(&rest[..end]), end)
+            }
+        };
+        rest = &rest[len..];
+        Some(token)
+    })
+}
+
+/// Subcommands that print `--template` lines; the rest reject it.
+pub const SUBCOMMANDS: [&str; 6] = ["block", "mounts", "net", "pci", "power", "usb"];
+
+/// A parsed `--template`, remembering which placeholders ever matched.
+pub struct Template {
+    text: ArgStr,
+    /// Every field the records can have, present or not
+    fields: &'static [&'static str],
+    resolved: [bool; MAX_PLACEHOLDERS],
+}
+
+impl Template {
+    pub fn new(text: &str, fields: &'static [&'static str]) -> Self {
+        Template {
+            text: StackString::from_str(text),
+            fields,
+            resolved: [false; MAX_PLACEHOLDERS],
+        }
+    }
+
+    /// The `--template` given on the command line, if any. `fields` lists
+    /// the names it may use.
+    pub fn from_options(opts: &GlobalOptions, fields: &'static [&'static str]) -> Option<Self> {
+        opts.template.as_ref().map(|t| Template::new(t.as_str(), fields))
+    }
+
+    /// Fill in the template for one record.
+    ///
+    /// `extra` holds fields that aren't part of the record's `Filterable`
+    /// fields; they're checked first.
+    pub fn render<T: Filterable + ?Sized>(&mut self, item: &T, extra: &[(&str, &str)]) -> StackString<MAX_LINE_LEN> {
+        let mut line = StackString::new();
+        let mut index = 0;
+        for token in tokens(self.text.as_str()) {
+            match token {
+                Token::Text(s) => {
+                    line.push_str(s);
+                }
+                Token::Char(c) => {
+                    line.push(c);
+                }
+                Token::Field(name) => {
+                    if lookup(item, extra, name, &mut line) && index < MAX_PLACEHOLDERS {
+                        self.resolved[index] = true;
+                    }
+                    index += 1;
+                }
+            }
+        }
+        line
+    }
+
+    /// Render and print one record.
+    pub fn print<T: Filterable + ?Sized>(&mut self, item: &T, extra: &[(&str, &str)]) {
+        print::println_record(self.render(item, extra).as_str());
+    }
+
+    /// Warn about placeholders that aren't fields. Call once, after the
+    /// output.
+    pub fn finish(&self) {
+        let fields = tokens(self.text.as_str()).filter_map(|t| match t {
+            Token::Field(name) => Some(name),
+            _ => None,
+        });
+        let mut names = [""; MAX_PLACEHOLDERS];
+        for (i, name) in fields.take(MAX_PLACEHOLDERS).enumerate() {
+            names[i] = name;
+            // Once per name, even if it's used twice
+            if self.is_known(i, name) || names[..i].contains(&name) {
+                continue;
+            }
+            print::eprint("Warning: unknown template field {");
+            print::eprint(name);
+            print::eprintln("}, left empty");
+        }
+    }
+
+    /// True if placeholder `index`, `{name}`, is a field: a record had it,
+    /// or it's on the list.
+    fn is_known(&self, index: usize, name: &str) -> bool {
+        self.resolved[index] || self.fields.contains(&name)
+    }
+}
+
+/// Append the value of `name` to `line`. False if the record has no
+/// such field.
+fn lookup<T: Filterable + ?Sized, const N: usize>(item: &T, extra: &[(&str, &str)], name: &str, line: &mut StackString<N>) -> bool {
+    if let Some(&(_, value)) = extra.iter().find(|&&(key, _)| key == name) {
+        line.push_str(value);
+        return true;
+    }
+
+    let mut found = false;
+    let mut text = |key: &str, value: &str| {
+        if !found && key == name {
+            line.push_str(value);
+            found = true;
+        }
+    };
+    item.filter_fields(&mut text);
+    item.filter_keyed_fields(&mut text);
+    if found {
+        return true;
+    }
+
+    item.filter_numeric_fields(&mut |key, value| {
+        if !found && key == name {
+            line.push_str(itoa::Buffer::new().format(value));
+            found = true;
+        }
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::testing::{ETH, TUN};
+
+    /// What FakeNic has, plus the `ip` extra
+    const FIELDS: &[&str] = &["name", "state", "mtu", "rx_bytes", "tx_bytes", "ip"];
+
+    #[test]
+    fn test_tokens() {
+        let t: [Token; 8] = [
+            Token::Field("name"),
+            Token::Char('\t'),
+            Token::Text("mtu "),
+            Token::Field("mtu"),
+            Token::Char('{'),
+            // Unknown escape and unclosed brace stay literal
+            Token::Text("\\"),
+            Token::Text("x "),
+            Token::Text("{open"),
+        ];
+        assert!(tokens(r"{name}\tmtu { mtu }{{\x {open").eq(t));
+        assert_eq!(tokens("").count(), 0);
+        assert!(tokens("µs={x}").eq([Token::Text("µs="), Token::Field("x")]));
+    }
+
+    #[test]
+    fn test_render_fields_and_escapes() {
+        let mut t = Template::new(r"{name}\t{ip}\t{state} mtu={mtu}\n--", FIELDS);
+        let ip = [("ip", "192.168.1.5")];
+        assert_eq!(t.render(&ETH, &ip).as_str(), "eth0\t192.168.1.5\tup mtu=1500\n--");
+        // A field this record lacks is empty
+        assert_eq!(t.render(&TUN, &[("ip", "")]).as_str(), "tun0\t\tunknown mtu=\n--");
+        assert!(t.resolved[..4].iter().all(|&r| r));
+    }
+
+    #[test]
+    fn test_unknown_field_expands_empty() {
+        let mut t = Template::new("{name}:{bogus}:{name}", FIELDS);
+        assert_eq!(t.render(&ETH, &[]).as_str(), "eth0::eth0");
+        assert_eq!(t.resolved[..3], [true, false, true]);
+        assert!(!t.is_known(1, "bogus"));
+    }
+
+    #[test]
+    fn test_absent_field_is_known() {
+        // No record here has an MTU, but it's still a field
+        let mut t = Template::new("{name} {mtu}", FIELDS);
+        assert_eq!(t.render(&TUN, &[]).as_str(), "tun0 ");
+        assert!(!t.resolved[1]);
+        assert!(t.is_known(1, "mtu"));
+    }
+}
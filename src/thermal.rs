@@ -16,17 +16,26 @@
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
-use crate::fields::thermal as f;
-use crate::filter::{matches_any, opt_str};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::cli::{ExtraArgs, GlobalOptions};
+use crate::fields::{kind, thermal as f};
+use crate::filter::{opt_str, Filterable};
 use crate::io;
 use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::page::Page;
 use crate::print::{self, TextWriter};
 use crate::stack::StackString;
 
 const THERMAL_PATH: &str = "/sys/class/thermal";
 const HWMON_PATH: &str = "/sys/class/hwmon";
 
+/// A sensor this close to its critical trip point counts as hot.
+const HOT_MARGIN_MILLICELSIUS: i64 = 10_000;
+
+/// Without a critical trip point, this temperature counts as hot.
+const HOT_FALLBACK_MILLICELSIUS: i64 = 85_000;
+
 /// Source of thermal data.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ThermalSource {
@@ -94,24 +103,13 @@ impl ThermalZone {
         })
     }
 
-    /// Check if this zone matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let fields = [
-            self.name.as_str(),
-            opt_str(&self.zone_type),
-            opt_str(&self.label),
-        ];
-        matches_any(&fields, pattern, case_insensitive)
-    }
-
-    /// Temperature in degrees Celsius (for display).
-    fn temp_celsius_x10(&self) -> Option<i32> {
-        self.temp_millicelsius.map(|t| (t / 100) as i32)
-    }
-
-    /// Critical temperature in degrees Celsius x10.
-    fn temp_crit_celsius_x10(&self) -> Option<i32> {
-        self.temp_crit.map(|t| (t / 100) as i32)
+    /// Is the sensor close to its critical temperature (or just very warm)?
+    fn is_hot(&self) -> bool {
+        let Some(temp) = self.temp_millicelsius else { return false };
+        match self.temp_crit {
+            Some(crit) => temp >= crit - HOT_MARGIN_MILLICELSIUS,
+            None => temp >= HOT_FALLBACK_MILLICELSIUS,
+        }
     }
 
     /// Output as text.
@@ -127,13 +125,13 @@ impl ThermalZone {
             w.field_quoted(f::LABEL, label.as_str());
         }
 
-        if let Some(temp_x10) = self.temp_celsius_x10() {
-            format_temp_text(&mut w, f::TEMP, temp_x10, human);
+        if let Some(temp) = self.temp_millicelsius {
+            format_temp_text(&mut w, f::TEMP, temp, human);
         }
 
         if verbose {
-            if let Some(crit_x10) = self.temp_crit_celsius_x10() {
-                format_temp_text(&mut w, f::CRIT, crit_x10, human);
+            if let Some(crit) = self.temp_crit {
+                format_temp_text(&mut w, f::CRIT, crit, human);
             }
 
             // Show trip points in verbose mode (for thermal zones only)
@@ -148,15 +146,18 @@ impl ThermalZone {
             w.field_str(f::SOURCE, self.source.as_str());
         }
 
-        w.finish();
+        w.finish_record();
     }
 
     /// Write as JSON object.
     fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool, zone_path: &str) {
-        w.array_object_begin();
+        w.record_begin();
+        w.field_str(kind::KIND, kind::THERMAL_SENSOR);
 
         let sensor = self.zone_type.as_ref().map(|s| s.as_str()).unwrap_or(self.name.as_str());
         w.field_str(f::SENSOR, sensor);
+        // The raw thermal_zoneN/hwmonN, which sensor may not show
+        w.field_str(f::NAME, self.name.as_str());
 
         if let Some(ref label) = self.label {
             w.field_str(f::LABEL, label.as_str());
@@ -167,7 +168,6 @@ impl ThermalZone {
         }
 
         if verbose {
-            w.field_str(f::NAME, self.name.as_str());
             if let Some(crit) = self.temp_crit {
                 w.field_i64(f::TEMP_CRIT_MILLICELSIUS, crit);
             }
@@ -188,6 +188,14 @@ impl ThermalZone {
     }
 }
 
+impl Filterable for ThermalZone {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::NAME, self.name.as_str());
+        visit(f::SENSOR, opt_str(&self.zone_type));
+        visit(f::LABEL, opt_str(&self.label));
+    }
+}
+
 /// A cooling device - fan, CPU frequency scaling, throttle alert, etc.
 pub struct CoolingDevice {
     /// Device name (e.g., "cooling_device0")
@@ -225,15 +233,6 @@ impl CoolingDevice {
         })
     }
 
-    /// Check if this device matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let fields = [
-            self.name.as_str(),
-            self.device_type.as_str(),
-        ];
-        matches_any(&fields, pattern, case_insensitive)
-    }
-
     /// Output as text.
     fn print_text(&self) {
         let mut w = TextWriter::new();
@@ -250,12 +249,13 @@ impl CoolingDevice {
             w.field_u64(f::STATE, self.cur_state as u64);
         }
 
-        w.finish();
+        w.finish_record();
     }
 
     /// Write as JSON object.
     fn write_json(&self, w: &mut StreamingJsonWriter) {
-        w.array_object_begin();
+        w.record_begin();
+        w.field_str(kind::KIND, kind::COOLING_DEVICE);
         w.field_str(f::TYPE, self.device_type.as_str());
         w.field_u64(f::CUR_STATE, self.cur_state as u64);
         w.field_u64(f::MAX_STATE, self.max_state as u64);
@@ -264,6 +264,13 @@ impl CoolingDevice {
     }
 }
 
+impl Filterable for CoolingDevice {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::NAME, self.name.as_str());
+        visit(f::TYPE, self.device_type.as_str());
+    }
+}
+
 /// Read a single hwmon sensor.
 struct HwmonSensor {
     /// Sensor name (e.g., "hwmon0", "hwmon0:2")
@@ -295,20 +302,10 @@ impl HwmonSensor {
 /// Find critical temperature from trip points.
 fn find_critical_trip_point(zone_path: &str) -> Option<i64> {
     for i in 0..16 {
-        let mut type_file: StackString<128> = StackString::from_str(zone_path);
-        type_file.push_str("/trip_point_");
-        let mut buf = itoa::Buffer::new();
-        type_file.push_str(buf.format(i));
-        type_file.push_str("_type");
-
-        let trip_type: Option<StackString<16>> = io::read_file_stack(type_file.as_str());
+        let trip_type: Option<StackString<16>> = io::read_file_stack(trip_file(zone_path, i, "type").as_str());
         if let Some(ref t) = trip_type {
             if t.as_str() == "critical" {
-                let mut temp_file: StackString<128> = StackString::from_str(zone_path);
-                temp_file.push_str("/trip_point_");
-                temp_file.push_str(buf.format(i));
-                temp_file.push_str("_temp");
-                return io::read_file_parse(temp_file.as_str());
+                return io::read_file_parse(trip_file(zone_path, i, "temp").as_str());
             }
         } else {
             break;
@@ -317,60 +314,109 @@ fn find_critical_trip_point(zone_path: &str) -> Option<i64> {
     None
 }
 
-/// Format temperature for text output.
-fn format_temp_text(w: &mut TextWriter, name: &str, temp_x10: i32, human: bool) {
-    let mut s: StackString<16> = StackString::new();
-    let mut buf = itoa::Buffer::new();
-    let whole = temp_x10 / 10;
-    let frac = (temp_x10 % 10).abs();
-    s.push_str(buf.format(whole));
-    s.push('.');
-    s.push_str(buf.format(frac));
-    if human {
-        s.push('C');
-    }
-    w.field_str(name, s.as_str());
+/// Text output in Fahrenheit (--fahrenheit), set once at startup.
+static FAHRENHEIT: AtomicBool = AtomicBool::new(false);
+
+/// Show text temperatures in Fahrenheit. JSON always stays in millicelsius.
+pub fn set_fahrenheit(enabled: bool) {
+    FAHRENHEIT.store(enabled, Ordering::Relaxed);
 }
 
-/// Print trip points for text output.
-fn print_trip_points_text(w: &mut TextWriter, zone_path: &str, human: bool) {
-    let mut trips: StackString<256> = StackString::new();
-    let mut first = true;
-    let mut consecutive_misses = 0;
+fn fahrenheit() -> bool {
+    FAHRENHEIT.load(Ordering::Relaxed)
+}
 
-    for i in 0..16 {
-        let mut buf = itoa::Buffer::new();
+/// Millidegrees Celsius to millidegrees Fahrenheit.
+pub fn millicelsius_to_millifahrenheit(temp_mc: i64) -> i64 {
+    temp_mc * 9 / 5 + 32_000
+}
 
-        let mut type_file: StackString<128> = StackString::from_str(zone_path);
-        type_file.push_str("/trip_point_");
-        type_file.push_str(buf.format(i));
-        type_file.push_str("_type");
+/// Format millidegrees Celsius as degrees with `places` decimals ("44.5",
+/// or "44.5C" with -h). With `fahrenheit` the value is converted first
+/// ("112.1", or "112.1F" with -h).
+pub fn format_temp(temp_mc: i64, places: u8, human: bool, fahrenheit: bool) -> StackString<16> {
+    let mut s = StackString::new();
+    let (value, unit) = if fahrenheit {
+        (millicelsius_to_millifahrenheit(temp_mc), 'F')
+    } else {
+        (temp_mc, 'C')
+    };
+    io::push_fixed(&mut s, value, 3, places);
+    if human {
+        s.push(unit);
+    }
+    s
+}
 
-        let mut temp_file: StackString<128> = StackString::from_str(zone_path);
-        temp_file.push_str("/trip_point_");
-        temp_file.push_str(buf.format(i));
-        temp_file.push_str("_temp");
+/// Format temperature for text output (one decimal unless --precision says otherwise).
+pub fn format_temp_text(w: &mut TextWriter, name: &str, temp_mc: i64, human: bool) {
+    w.field_str(name, format_temp(temp_mc, io::precision_or(1), human, fahrenheit()).as_str());
+}
 
-        let trip_type: Option<StackString<16>> = io::read_file_stack(type_file.as_str());
-        let temp: Option<i64> = io::read_file_parse(temp_file.as_str());
+/// One trip point of a thermal zone (trip_point_N_*).
+pub struct TripPoint {
+    /// N in trip_point_N_*
+    pub index: u32,
+    /// "critical", "hot", "passive", "active"
+    pub trip_type: StackString<16>,
+    pub temp_millicelsius: i64,
+    /// How far the temperature must fall back before the trip clears.
+    /// Many drivers don't provide trip_point_N_hyst.
+    pub hyst_millicelsius: Option<i64>,
+}
 
-        if let (Some(t), Some(temp_mc)) = (trip_type, temp) {
-            if !first {
-                trips.push(',');
-            }
-            first = false;
-            trips.push_str(t.as_str());
-            trips.push(':');
-
-            let temp_x10 = (temp_mc / 100) as i32;
-            let whole = temp_x10 / 10;
-            let frac = (temp_x10 % 10).abs();
-            trips.push_str(buf.format(whole));
-            trips.push('.');
-            trips.push_str(buf.format(frac));
+impl TripPoint {
+    /// Text form: `critical:105.0`, plus `(±2.0)` when there's a hysteresis.
+    pub fn format(&self, places: u8, human: bool, fahrenheit: bool) -> StackString<64> {
+        let mut s = StackString::from_str(self.trip_type.as_str());
+        s.push(':');
+        s.push_str(format_temp(self.temp_millicelsius, places, human, fahrenheit).as_str());
+        if let Some(hyst_mc) = self.hyst_millicelsius {
+            // A difference, so no +32 when converting
+            let (value, unit) = if fahrenheit {
+                (hyst_mc * 9 / 5, 'F')
+            } else {
+                (hyst_mc, 'C')
+            };
+            s.push_str("(±");
+            io::push_fixed(&mut s, value, 3, places);
             if human {
-                trips.push('C');
+                s.push(unit);
             }
+            s.push(')');
+        }
+        s
+    }
+}
+
+/// Path of `zone_path/trip_point_<index>_<suffix>`.
+fn trip_file(zone_path: &str, index: u32, suffix: &str) -> StackString<128> {
+    let mut path: StackString<128> = StackString::from_str(zone_path);
+    path.push_str("/trip_point_");
+    path.push_str(itoa::Buffer::new().format(index));
+    path.push('_');
+    path.push_str(suffix);
+    path
+}
+
+/// Call `f` with each trip point of a zone, in index order.
+///
+/// Stops after two indices in a row without a type and temperature. A
+/// missing hyst file doesn't count as a miss.
+fn read_trip_points(zone_path: &str, mut f: impl FnMut(&TripPoint)) {
+    let mut consecutive_misses = 0;
+
+    for index in 0..16u32 {
+        let trip_type: Option<StackString<16>> = io::read_file_stack(trip_file(zone_path, index, "type").as_str());
+        let temp: Option<i64> = io::read_file_parse(trip_file(zone_path, index, "temp").as_str());
+
+        if let (Some(trip_type), Some(temp_millicelsius)) = (trip_type, temp) {
+            f(&TripPoint {
+                index,
+                trip_type,
+                temp_millicelsius,
+                hyst_millicelsius: io::read_file_parse(trip_file(zone_path, index, "hyst").as_str()),
+            });
             consecutive_misses = 0;
         } else {
             consecutive_misses += 1;
@@ -379,6 +425,18 @@ fn print_trip_points_text(w: &mut TextWriter, zone_path: &str, human: bool) {
             }
         }
     }
+}
+
+/// Print trip points for text output.
+fn print_trip_points_text(w: &mut TextWriter, zone_path: &str, human: bool) {
+    let mut trips: StackString<512> = StackString::new();
+
+    read_trip_points(zone_path, |trip| {
+        if !trips.is_empty() {
+            trips.push(',');
+        }
+        trips.push_str(trip.format(io::precision_or(1), human, fahrenheit()).as_str());
+    });
 
     if !trips.is_empty() {
         w.field_str(f::TRIPS, trips.as_str());
@@ -387,78 +445,236 @@ fn print_trip_points_text(w: &mut TextWriter, zone_path: &str, human: bool) {
 
 /// Write trip points to JSON.
 fn write_trip_points_json(w: &mut StreamingJsonWriter, zone_path: &str) {
-    let mut has_trips = false;
-    let mut consecutive_misses = 0;
+    // Only emit the array if the zone has trip points at all
+    let has_trips = (0..16u32).any(|i| io::path_exists(trip_file(zone_path, i, "type").as_str()));
+    if !has_trips {
+        return;
+    }
 
-    // First pass: check if we have any trip points
-    for i in 0..16 {
+    w.field_array(f::TRIP_POINTS);
+    read_trip_points(zone_path, |trip| {
+        w.array_object_begin();
+        w.field_u64(f::INDEX, trip.index as u64);
+        w.field_str(f::TYPE, trip.trip_type.as_str());
+        w.field_i64(f::TEMP_MILLICELSIUS, trip.temp_millicelsius);
+        if let Some(hyst_mc) = trip.hyst_millicelsius {
+            w.field_i64(f::HYST_MILLICELSIUS, hyst_mc);
+        }
+        w.array_object_end();
+    });
+    w.end_field_array();
+}
+
+/// Check if thermal zones exist.
+fn has_thermal_zones() -> bool {
+    let mut found = false;
+    io::for_each_dir_entry(THERMAL_PATH, |name| {
+        if name.starts_with("thermal_zone") {
+            found = true;
+        }
+    });
+    found
+}
+
+/// Read all hwmon temperature inputs of one hwmon device.
+/// Calls `f` for each sensor found (up to 16 per device).
+fn for_each_hwmon_sensor<F: FnMut(ThermalZone)>(hwmon_name: &str, f: &mut F) {
+    let hwmon_path: StackString<128> = io::join_path(HWMON_PATH, hwmon_name);
+    let name_path: StackString<128> = io::join_path(hwmon_path.as_str(), "name");
+    let hwmon_type: Option<StackString<64>> = io::read_file_stack(name_path.as_str());
+
+    // Check up to 16 temperature inputs
+    for i in 1..=16u32 {
         let mut buf = itoa::Buffer::new();
-        let mut type_file: StackString<128> = StackString::from_str(zone_path);
-        type_file.push_str("/trip_point_");
-        type_file.push_str(buf.format(i));
-        type_file.push_str("_type");
 
-        if io::path_exists(type_file.as_str()) {
-            has_trips = true;
-            break;
+        let mut temp_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
+        temp_file.push_str("/temp");
+        temp_file.push_str(buf.format(i));
+        temp_file.push_str("_input");
+
+        if let Some(temp) = io::read_file_parse::<i64>(temp_file.as_str()) {
+            // Read optional label
+            let mut label_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
+            label_file.push_str("/temp");
+            label_file.push_str(buf.format(i));
+            label_file.push_str("_label");
+            let label: Option<StackString<64>> = io::read_file_stack(label_file.as_str());
+
+            // Read optional critical temp
+            let mut crit_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
+            crit_file.push_str("/temp");
+            crit_file.push_str(buf.format(i));
+            crit_file.push_str("_crit");
+            let temp_crit: Option<i64> = io::read_file_parse(crit_file.as_str());
+
+            // Create sensor name
+            let sensor_name: StackString<32> = if i == 1 {
+                StackString::from_str(hwmon_name)
+            } else {
+                let mut name: StackString<32> = StackString::from_str(hwmon_name);
+                name.push(':');
+                name.push_str(buf.format(i));
+                name
+            };
+
+            let sensor = HwmonSensor {
+                name: sensor_name,
+                zone_type: hwmon_type.clone(),
+                label,
+                temp_millicelsius: temp,
+                temp_crit,
+            };
+            f(sensor.to_zone());
         }
     }
+}
 
-    if !has_trips {
-        return;
+/// Walk all temperature sensors: thermal zones first, hwmon as a fallback.
+///
+/// `f` gets each sensor and its sysfs path ("" for hwmon) and returns
+/// whether it used it. hwmon is only consulted if no thermal zone was used.
+fn for_each_sensor<F: FnMut(ThermalZone, &str) -> bool>(has_thermal: bool, has_hwmon: bool, mut f: F) {
+    let mut count = 0;
+
+    // First try thermal zones
+    if has_thermal {
+        io::for_each_dir_entry_sorted(THERMAL_PATH, io::natural_cmp, |name| {
+            if let Some(zone) = ThermalZone::read_thermal_zone(name) {
+                let zone_path: StackString<128> = io::join_path(THERMAL_PATH, name);
+                if f(zone, zone_path.as_str()) {
+                    count += 1;
+                }
+            }
+        });
     }
 
-    w.field_array(f::TRIP_POINTS);
+    // Fall back to hwmon if no thermal zones
+    if count == 0 && has_hwmon {
+        let mut hwmon_f = |zone: ThermalZone| {
+            f(zone, "");
+        };
+        io::for_each_dir_entry_sorted(HWMON_PATH, io::natural_cmp, |hwmon_name| {
+            for_each_hwmon_sensor(hwmon_name, &mut hwmon_f);
+        });
+    }
+}
 
-    for i in 0..16u32 {
-        let mut buf = itoa::Buffer::new();
+/// Maximum number of sensors collected for `--hot` sorting.
+const MAX_SORTED_SENSORS: usize = 32;
+
+/// Sensors collected for sorting, with the sysfs path each came from.
+/// Once full it keeps the hottest ones, so `--hot` stays right on
+/// machines with more sensors than that.
+struct SensorList {
+    sensors: [Option<(ThermalZone, StackString<128>)>; MAX_SORTED_SENSORS],
+    count: usize,
+    /// Cooler sensors left out because the list was full
+    dropped: usize,
+}
 
-        let mut type_file: StackString<128> = StackString::from_str(zone_path);
-        type_file.push_str("/trip_point_");
-        type_file.push_str(buf.format(i));
-        type_file.push_str("_type");
+impl SensorList {
+    fn new() -> Self {
+        Self {
+            sensors: core::array::from_fn(|_| None),
+            count: 0,
+            dropped: 0,
+        }
+    }
 
-        let mut temp_file: StackString<128> = StackString::from_str(zone_path);
-        temp_file.push_str("/trip_point_");
-        temp_file.push_str(buf.format(i));
-        temp_file.push_str("_temp");
+    /// Add a sensor. When the list is full, the coolest sensor (this one
+    /// or one already there) is dropped and false is returned.
+    fn push(&mut self, zone: ThermalZone, zone_path: &str) -> bool {
+        let entry = Some((zone, StackString::from_str(zone_path)));
+        if self.count < MAX_SORTED_SENSORS {
+            self.sensors[self.count] = entry;
+            self.count += 1;
+            return true;
+        }
 
-        let trip_type: Option<StackString<16>> = io::read_file_stack(type_file.as_str());
-        let temp: Option<i64> = io::read_file_parse(temp_file.as_str());
+        self.dropped += 1;
+        // The coolest, and of equally cool ones the last seen, goes: the
+        // same one a stable sort of all of them would have put last
+        let list = &mut self.sensors[..self.count];
+        let coolest = (1..list.len()).fold(0, |c, i| if hotter(&list[i], &list[c]) { c } else { i });
+        if hotter(&entry, &list[coolest]) {
+            // Shift the rest down so the list stays in the order seen
+            list[coolest..].rotate_left(1);
+            list[list.len() - 1] = entry;
+        }
+        false
+    }
 
-        if let (Some(t), Some(temp_mc)) = (trip_type, temp) {
-            w.array_object_begin();
-            w.field_u64(f::INDEX, i as u64);
-            w.field_str(f::TYPE, t.as_str());
-            w.field_i64(f::TEMP_MILLICELSIUS, temp_mc);
-            w.array_object_end();
-            consecutive_misses = 0;
-        } else {
-            consecutive_misses += 1;
-            if consecutive_misses >= 2 {
-                break;
+    /// Say on stderr how many cooler sensors `--hot` left out.
+    fn warn_dropped(&self) {
+        if self.dropped == 0 {
+            return;
+        }
+        let mut buf = itoa::Buffer::new();
+        print::eprint("Warning: --hot lists the ");
+        print::eprint(buf.format(MAX_SORTED_SENSORS));
+        print::eprint(" hottest sensors; ");
+        print::eprint(buf.format(self.dropped));
+        print::eprintln(" cooler ones left out");
+    }
+
+    /// Sort hottest first, sensors without a reading last.
+    ///
+    /// Insertion sort: stable (equal temps keep sysfs order) and needs no
+    /// allocation, and the list is tiny anyway.
+    fn sort_hottest_first(&mut self) {
+        let list = &mut self.sensors[..self.count];
+        for i in 1..list.len() {
+            let mut j = i;
+            while j > 0 && hotter(&list[j], &list[j - 1]) {
+                list.swap(j, j - 1);
+                j -= 1;
             }
         }
     }
 
-    w.end_field_array();
+    fn iter(&self) -> impl Iterator<Item = (&ThermalZone, &str)> {
+        self.sensors[..self.count]
+            .iter()
+            .filter_map(|s| s.as_ref().map(|(zone, path)| (zone, path.as_str())))
+    }
 }
 
-/// Check if thermal zones exist.
-fn has_thermal_zones() -> bool {
-    let mut found = false;
-    io::for_each_dir_entry(THERMAL_PATH, |name| {
-        if name.starts_with("thermal_zone") {
-            found = true;
+/// Strictly hotter? Sensors without a temperature count as coldest.
+fn hotter(a: &Option<(ThermalZone, StackString<128>)>, b: &Option<(ThermalZone, StackString<128>)>) -> bool {
+    let temp = |s: &Option<(ThermalZone, StackString<128>)>| s.as_ref().and_then(|(z, _)| z.temp_millicelsius);
+    match (temp(a), temp(b)) {
+        (Some(ta), Some(tb)) => ta > tb,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Options specific to the thermal subcommand.
+#[derive(Default)]
+pub struct ThermalOptions {
+    /// Sort sensors by temperature, hottest first
+    pub hot: bool,
+}
+
+impl ThermalOptions {
+    /// Parse thermal-specific options from remaining arguments.
+    pub fn parse(args: &ExtraArgs) -> Self {
+        let mut opts = ThermalOptions::default();
+
+        for arg in args.iter() {
+            if arg == "--hot" {
+                opts.hot = true;
+            }
         }
-    });
-    found
+
+        opts
+    }
 }
 
 /// Entry point for `kv thermal` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
-    let filter = opts.filter.as_ref().map(|s| s.as_str());
-    let case_insensitive = opts.filter_case_insensitive;
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let thermal_opts = ThermalOptions::parse(args);
+    let filter = &opts.filter;
 
     // Check if we have any thermal data
     let has_thermal = io::path_exists(THERMAL_PATH) && has_thermal_zones();
@@ -466,8 +682,8 @@ pub fn run(opts: &GlobalOptions) -> i32 {
 
     if !has_thermal && !has_hwmon {
         if opts.json {
-            let mut w = begin_kv_output_streaming(opts.pretty, "thermal");
-            w.field_array("sensors");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "thermal");
+            w.field_array("data");
             w.end_field_array();
             w.end_object();
             w.finish();
@@ -477,107 +693,50 @@ pub fn run(opts: &GlobalOptions) -> i32 {
         return 0;
     }
 
-    if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "thermal");
-        w.field_array("sensors");
-
-        let mut count = 0;
+    // With --hot, sensors are collected and sorted before output;
+    // otherwise they're streamed as they're read.
+    let mut sorted = SensorList::new();
+    let mut page = Page::from_options(opts);
 
-        // First try thermal zones
-        if has_thermal {
-            io::for_each_dir_entry(THERMAL_PATH, |name| {
-                if let Some(zone) = ThermalZone::read_thermal_zone(name) {
-                    if let Some(pattern) = filter {
-                        if !zone.matches_filter(pattern, case_insensitive) {
-                            return;
-                        }
-                    }
-                    let zone_path: StackString<128> = io::join_path(THERMAL_PATH, name);
-                    zone.write_json(&mut w, opts.verbose, zone_path.as_str());
-                    count += 1;
-                }
-            });
-        }
+    if opts.json {
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "thermal");
+        w.field_array("data");
 
-        // Fall back to hwmon if no thermal zones
-        if count == 0 && has_hwmon {
-            io::for_each_dir_entry(HWMON_PATH, |hwmon_name| {
-                let hwmon_path: StackString<128> = io::join_path(HWMON_PATH, hwmon_name);
-                let name_path: StackString<128> = io::join_path(hwmon_path.as_str(), "name");
-                let hwmon_type: Option<StackString<64>> = io::read_file_stack(name_path.as_str());
-
-                // Check up to 16 temperature inputs
-                for i in 1..=16u32 {
-                    let mut buf = itoa::Buffer::new();
-
-                    let mut temp_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                    temp_file.push_str("/temp");
-                    temp_file.push_str(buf.format(i));
-                    temp_file.push_str("_input");
-
-                    if let Some(temp) = io::read_file_parse::<i64>(temp_file.as_str()) {
-                        // Read optional label
-                        let mut label_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                        label_file.push_str("/temp");
-                        label_file.push_str(buf.format(i));
-                        label_file.push_str("_label");
-                        let label: Option<StackString<64>> = io::read_file_stack(label_file.as_str());
-
-                        // Read optional critical temp
-                        let mut crit_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                        crit_file.push_str("/temp");
-                        crit_file.push_str(buf.format(i));
-                        crit_file.push_str("_crit");
-                        let temp_crit: Option<i64> = io::read_file_parse(crit_file.as_str());
-
-                        // Create sensor name
-                        let sensor_name: StackString<32> = if i == 1 {
-                            StackString::from_str(hwmon_name)
-                        } else {
-                            let mut name: StackString<32> = StackString::from_str(hwmon_name);
-                            name.push(':');
-                            name.push_str(buf.format(i));
-                            name
-                        };
-
-                        let sensor = HwmonSensor {
-                            name: sensor_name,
-                            zone_type: hwmon_type.clone(),
-                            label,
-                            temp_millicelsius: temp,
-                            temp_crit,
-                        };
-                        let zone = sensor.to_zone();
-
-                        if let Some(pattern) = filter {
-                            if !zone.matches_filter(pattern, case_insensitive) {
-                                continue;
-                            }
-                        }
+        for_each_sensor(has_thermal, has_hwmon, |zone, zone_path| {
+            if !zone.matches_filter(filter) {
+                return false;
+            }
+            if thermal_opts.hot {
+                sorted.push(zone, zone_path);
+            } else if page.take() {
+                zone.write_json(&mut w, opts.verbose, zone_path);
+            }
+            true
+        });
 
-                        zone.write_json(&mut w, opts.verbose, "");
-                        count += 1;
-                    }
-                }
-            });
+        if thermal_opts.hot {
+            sorted.sort_hottest_first();
+            for (zone, zone_path) in sorted.iter().filter(|_| page.take()) {
+                zone.write_json(&mut w, opts.verbose, zone_path);
+            }
+            sorted.warn_dropped();
         }
 
         w.end_field_array();
+        page.write_json(&mut w);
 
         // Include cooling devices in verbose mode
         if opts.verbose {
             let mut has_cooling = false;
-            io::for_each_dir_entry(THERMAL_PATH, |name| {
+            io::for_each_dir_entry_sorted(THERMAL_PATH, io::natural_cmp, |name| {
                 if name.starts_with("cooling_device") {
                     if !has_cooling {
                         w.field_array(f::COOLING);
                         has_cooling = true;
                     }
                     if let Some(dev) = CoolingDevice::read(name) {
-                        if let Some(pattern) = filter {
-                            if !dev.matches_filter(pattern, case_insensitive) {
-                                return;
-                            }
+                        if !dev.matches_filter(filter) {
+                            return;
                         }
                         dev.write_json(&mut w);
                     }
@@ -590,100 +749,36 @@ pub fn run(opts: &GlobalOptions) -> i32 {
 
         w.end_object();
         w.finish();
-
-        if count == 0 && filter.is_some() {
-            // Empty filtered result is fine
-        }
     } else {
         let mut count = 0;
 
-        // First try thermal zones
-        if has_thermal {
-            io::for_each_dir_entry(THERMAL_PATH, |name| {
-                if let Some(zone) = ThermalZone::read_thermal_zone(name) {
-                    if let Some(pattern) = filter {
-                        if !zone.matches_filter(pattern, case_insensitive) {
-                            return;
-                        }
-                    }
-                    let zone_path: StackString<128> = io::join_path(THERMAL_PATH, name);
-                    zone.print_text(opts.verbose, opts.human, zone_path.as_str());
-                    count += 1;
-                }
-            });
-        }
-
-        // Fall back to hwmon if no thermal zones
-        if count == 0 && has_hwmon {
-            io::for_each_dir_entry(HWMON_PATH, |hwmon_name| {
-                let hwmon_path: StackString<128> = io::join_path(HWMON_PATH, hwmon_name);
-                let name_path: StackString<128> = io::join_path(hwmon_path.as_str(), "name");
-                let hwmon_type: Option<StackString<64>> = io::read_file_stack(name_path.as_str());
-
-                // Check up to 16 temperature inputs
-                for i in 1..=16u32 {
-                    let mut buf = itoa::Buffer::new();
-
-                    let mut temp_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                    temp_file.push_str("/temp");
-                    temp_file.push_str(buf.format(i));
-                    temp_file.push_str("_input");
-
-                    if let Some(temp) = io::read_file_parse::<i64>(temp_file.as_str()) {
-                        // Read optional label
-                        let mut label_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                        label_file.push_str("/temp");
-                        label_file.push_str(buf.format(i));
-                        label_file.push_str("_label");
-                        let label: Option<StackString<64>> = io::read_file_stack(label_file.as_str());
-
-                        // Read optional critical temp
-                        let mut crit_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                        crit_file.push_str("/temp");
-                        crit_file.push_str(buf.format(i));
-                        crit_file.push_str("_crit");
-                        let temp_crit: Option<i64> = io::read_file_parse(crit_file.as_str());
-
-                        // Create sensor name
-                        let sensor_name: StackString<32> = if i == 1 {
-                            StackString::from_str(hwmon_name)
-                        } else {
-                            let mut name: StackString<32> = StackString::from_str(hwmon_name);
-                            name.push(':');
-                            name.push_str(buf.format(i));
-                            name
-                        };
-
-                        let sensor = HwmonSensor {
-                            name: sensor_name,
-                            zone_type: hwmon_type.clone(),
-                            label,
-                            temp_millicelsius: temp,
-                            temp_crit,
-                        };
-                        let zone = sensor.to_zone();
-
-                        if let Some(pattern) = filter {
-                            if !zone.matches_filter(pattern, case_insensitive) {
-                                continue;
-                            }
-                        }
+        for_each_sensor(has_thermal, has_hwmon, |zone, zone_path| {
+            if !zone.matches_filter(filter) {
+                return false;
+            }
+            if thermal_opts.hot {
+                sorted.push(zone, zone_path);
+            } else if page.take() {
+                zone.print_text(opts.verbose, opts.human, zone_path);
+            }
+            count += 1;
+            true
+        });
 
-                        zone.print_text(opts.verbose, opts.human, "");
-                        count += 1;
-                    }
-                }
-            });
+        if thermal_opts.hot {
+            sorted.sort_hottest_first();
+            for (zone, zone_path) in sorted.iter().filter(|_| page.take()) {
+                zone.print_text(opts.verbose, opts.human, zone_path);
+            }
+            sorted.warn_dropped();
         }
 
         // Print cooling devices in verbose mode
         if opts.verbose {
-            io::for_each_dir_entry(THERMAL_PATH, |name| {
+            io::for_each_dir_entry_sorted(THERMAL_PATH, io::natural_cmp, |name| {
                 if let Some(dev) = CoolingDevice::read(name) {
-                    if let Some(pattern) = filter {
-                        if !dev.matches_filter(pattern, case_insensitive) {
-                            return;
-                        }
+                    if !dev.matches_filter(filter) {
+                        return;
                     }
                     dev.print_text();
                 }
@@ -691,7 +786,7 @@ pub fn run(opts: &GlobalOptions) -> i32 {
         }
 
         if count == 0 {
-            if filter.is_some() {
+            if filter.is_active() {
                 print::println("thermal: no matching sensors");
             } else {
                 print::println("thermal: no temperature sensors found");
@@ -702,81 +797,185 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     0
 }
 
+/// The warmest sensor, as reported by `hottest()`.
+pub struct Hottest {
+    /// Sensor type or name (e.g., "cpu-thermal", "coretemp")
+    pub sensor: StackString<64>,
+    /// Its temperature in millidegrees Celsius
+    pub temp_millicelsius: i64,
+    /// Whether any sensor is near its critical trip point
+    pub hot: bool,
+}
+
+/// Find the warmest sensor across thermal zones and hwmon.
+pub fn hottest() -> Option<Hottest> {
+    let has_thermal = io::path_exists(THERMAL_PATH) && has_thermal_zones();
+    let has_hwmon = io::path_exists(HWMON_PATH);
+
+    let mut result: Option<Hottest> = None;
+    let mut any_hot = false;
+    for_each_sensor(has_thermal, has_hwmon, |zone, _| {
+        any_hot |= zone.is_hot();
+        let Some(temp) = zone.temp_millicelsius else { return false };
+        if result.as_ref().is_none_or(|h| temp > h.temp_millicelsius) {
+            let sensor = zone.zone_type.as_ref().map(|s| s.as_str()).unwrap_or(zone.name.as_str());
+            result = Some(Hottest {
+                sensor: StackString::from_str(sensor),
+                temp_millicelsius: temp,
+                hot: false,
+            });
+        }
+        true
+    });
+    result.map(|h| Hottest { hot: any_hot, ..h })
+}
+
 /// Write thermal sensors to JSON writer (for snapshot).
+///
+/// With no sensors the section is left out, or written as `[]` with
+/// `include_empty`.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
+pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool, include_empty: bool) {
     let has_thermal = io::path_exists(THERMAL_PATH) && has_thermal_zones();
     let has_hwmon = io::path_exists(HWMON_PATH);
 
-    if !has_thermal && !has_hwmon {
+    if !include_empty && !has_thermal && !has_hwmon {
         return;
     }
 
     w.key("thermal");
     w.begin_array();
+    for_each_sensor(has_thermal, has_hwmon, |zone, zone_path| {
+        zone.write_json(w, verbose, zone_path);
+        true
+    });
+    w.end_array();
+}
 
-    let mut count = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::testing::TempDir;
+
+    #[test]
+    fn test_format_temp_precision() {
+        assert_eq!(format_temp(44500, 1, false, false).as_str(), "44.5");
+        assert_eq!(format_temp(44500, 0, true, false).as_str(), "44C");
+        assert_eq!(format_temp(44500, 3, true, false).as_str(), "44.500C");
+        assert_eq!(format_temp(-5250, 2, false, false).as_str(), "-5.25");
+    }
 
-    // First try thermal zones
-    if has_thermal {
-        io::for_each_dir_entry(THERMAL_PATH, |name| {
-            if let Some(zone) = ThermalZone::read_thermal_zone(name) {
-                let zone_path: StackString<128> = io::join_path(THERMAL_PATH, name);
-                zone.write_json(w, verbose, zone_path.as_str());
-                count += 1;
-            }
-        });
+    #[test]
+    fn test_trip_point_format() {
+        let mut trip = TripPoint {
+            index: 0,
+            trip_type: StackString::from_str("critical"),
+            temp_millicelsius: 105_000,
+            hyst_millicelsius: Some(2000),
+        };
+        assert_eq!(trip.format(1, false, false).as_str(), "critical:105.0(±2.0)");
+        assert_eq!(trip.format(0, true, false).as_str(), "critical:105C(±2C)");
+        // The hysteresis is a difference: scaled, not offset
+        assert_eq!(trip.format(1, true, true).as_str(), "critical:221.0F(±3.6F)");
+        trip.hyst_millicelsius = None;
+        assert_eq!(trip.format(1, false, false).as_str(), "critical:105.0");
     }
 
-    // Fall back to hwmon if no thermal zones
-    if count == 0 && has_hwmon {
-        io::for_each_dir_entry(HWMON_PATH, |hwmon_name| {
-            let hwmon_path: StackString<128> = io::join_path(HWMON_PATH, hwmon_name);
-            let name_path: StackString<128> = io::join_path(hwmon_path.as_str(), "name");
-            let hwmon_type: Option<StackString<64>> = io::read_file_stack(name_path.as_str());
-
-            for i in 1..=16u32 {
-                let mut buf = itoa::Buffer::new();
-
-                let mut temp_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                temp_file.push_str("/temp");
-                temp_file.push_str(buf.format(i));
-                temp_file.push_str("_input");
-
-                if let Some(temp) = io::read_file_parse::<i64>(temp_file.as_str()) {
-                    let mut label_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                    label_file.push_str("/temp");
-                    label_file.push_str(buf.format(i));
-                    label_file.push_str("_label");
-                    let label: Option<StackString<64>> = io::read_file_stack(label_file.as_str());
-
-                    let mut crit_file: StackString<128> = StackString::from_str(hwmon_path.as_str());
-                    crit_file.push_str("/temp");
-                    crit_file.push_str(buf.format(i));
-                    crit_file.push_str("_crit");
-                    let temp_crit: Option<i64> = io::read_file_parse(crit_file.as_str());
-
-                    let sensor_name: StackString<32> = if i == 1 {
-                        StackString::from_str(hwmon_name)
-                    } else {
-                        let mut name: StackString<32> = StackString::from_str(hwmon_name);
-                        name.push(':');
-                        name.push_str(buf.format(i));
-                        name
-                    };
-
-                    let sensor = HwmonSensor {
-                        name: sensor_name,
-                        zone_type: hwmon_type.clone(),
-                        label,
-                        temp_millicelsius: temp,
-                        temp_crit,
-                    };
-                    sensor.to_zone().write_json(w, verbose, "");
-                }
-            }
+    #[test]
+    fn test_read_trip_points() {
+        // A fake thermal zone: trip 0 with hyst, trip 1 without, then a
+        // gap of one index before trip 3
+        let zone = TempDir::new("thermal");
+        let files = [
+            ("trip_point_0_type", "critical\n"),
+            ("trip_point_0_temp", "105000\n"),
+            ("trip_point_0_hyst", "2000\n"),
+            ("trip_point_1_type", "passive\n"),
+            ("trip_point_1_temp", "95000\n"),
+            ("trip_point_3_type", "hot\n"),
+            ("trip_point_3_temp", "100000\n"),
+        ];
+        for (name, contents) in files {
+            zone.write(name, contents.as_bytes());
+        }
+
+        let mut seen: [(u32, i64, Option<i64>); 4] = [(0, 0, None); 4];
+        let mut count = 0;
+        read_trip_points(zone.path(), |trip| {
+            seen[count] = (trip.index, trip.temp_millicelsius, trip.hyst_millicelsius);
+            count += 1;
         });
+
+        assert_eq!(count, 3);
+        assert_eq!(seen[0], (0, 105_000, Some(2000)));
+        assert_eq!(seen[1], (1, 95_000, None));
+        assert_eq!(seen[2], (3, 100_000, None));
     }
 
-    w.end_array();
+    #[test]
+    fn test_format_temp_fahrenheit() {
+        assert_eq!(format_temp(0, 1, true, true).as_str(), "32.0F");
+        assert_eq!(format_temp(100_000, 1, true, true).as_str(), "212.0F");
+        assert_eq!(format_temp(44500, 1, false, true).as_str(), "112.1");
+        assert_eq!(format_temp(-40_000, 0, true, true).as_str(), "-40F");
+        assert_eq!(format_temp(-10_000, 1, false, true).as_str(), "14.0");
+    }
+
+    fn zone(name: &str, temp: Option<i64>) -> ThermalZone {
+        ThermalZone {
+            name: StackString::from_str(name),
+            zone_type: None,
+            label: None,
+            temp_millicelsius: temp,
+            policy: None,
+            temp_crit: None,
+            source: ThermalSource::ThermalZone,
+        }
+    }
+
+    fn names(list: &SensorList) -> [&str; 5] {
+        let mut out = [""; 5];
+        for (i, (z, _)) in list.iter().enumerate() {
+            out[i] = z.name.as_str();
+        }
+        out
+    }
+
+    #[test]
+    fn hottest_first_none_last() {
+        let mut list = SensorList::new();
+        list.push(zone("gpu", Some(41_000)), "");
+        list.push(zone("unknown", None), "");
+        list.push(zone("cpu", Some(55_500)), "");
+        list.push(zone("soc", Some(41_000)), "");
+        list.push(zone("ddr", Some(-5_000)), "");
+
+        list.sort_hottest_first();
+
+        // gpu and soc are equal - stable sort keeps their original order
+        assert_eq!(names(&list), ["cpu", "gpu", "soc", "ddr", "unknown"]);
+    }
+
+    #[test]
+    fn full_list_keeps_hottest() {
+        let mut list = SensorList::new();
+        list.push(zone("first", Some(1_000)), "");
+        for _ in 1..MAX_SORTED_SENSORS - 1 {
+            assert!(list.push(zone("z", Some(1_000)), ""));
+        }
+        assert!(list.push(zone("unread", None), ""));
+
+        // Full: a hot one pushes out the one without a reading, then the
+        // latest of the equally cool ones; a cool one is just dropped
+        assert!(!list.push(zone("hot", Some(90_000)), ""));
+        assert!(!list.push(zone("warm", Some(50_000)), ""));
+        assert!(!list.push(zone("cold", Some(500)), ""));
+        assert_eq!(list.dropped, 3);
+        assert_eq!(list.count, MAX_SORTED_SENSORS);
+
+        list.sort_hottest_first();
+        let kept: [&str; 3] = core::array::from_fn(|i| list.iter().nth(i).unwrap().0.name.as_str());
+        assert_eq!(kept, ["hot", "warm", "first"]);
+        assert!(list.iter().all(|(z, _)| z.temp_millicelsius.is_some_and(|t| t >= 1_000)));
+    }
 }
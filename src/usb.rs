@@ -9,16 +9,22 @@
 //!
 //! Note: USB device strings (manufacturer, product) might require special
 //! permissions to read on some systems. We gracefully handle missing strings.
+//!
+//! Interface directories ("1-1.4:1.0", config 1 interface 0) are skipped
+//! in the listing. With `--interfaces` each device lists its own, which is
+//! how you see that a headset is Audio plus HID and which drivers took it.
 
 #![allow(dead_code)]
 
-use crate::cli::GlobalOptions;
-use crate::fields::usb as f;
-use crate::filter::{matches_any, opt_str};
+use crate::cli::{self, ExtraArgs, GlobalOptions};
+use crate::fields::{kind, usb as f};
+use crate::filter::{opt_str, Filterable};
 use crate::io;
 use crate::json::{begin_kv_output_streaming, StreamingJsonWriter};
+use crate::page::Page;
 use crate::print::{self, TextWriter};
 use crate::stack::StackString;
+use crate::template::Template;
 
 const USB_SYSFS_PATH: &str = "/sys/bus/usb/devices";
 
@@ -56,9 +62,96 @@ pub struct UsbDevice {
     pub driver: Option<StackString<32>>,
 }
 
+/// One interface of a USB device, e.g. "1-1.4:1.0".
+pub struct UsbInterface {
+    /// Interface name (device, then "config.interface")
+    pub name: StackString<32>,
+    /// Interface class (bInterfaceClass)
+    pub class: u8,
+    /// Interface protocol (bInterfaceProtocol), e.g. 1 for a HID keyboard
+    pub protocol: u8,
+    /// Bound driver
+    pub driver: Option<StackString<32>>,
+}
+
+impl UsbInterface {
+    /// Read interface `name` of `device` from sysfs.
+    pub fn read(device: &str, name: &str) -> Option<Self> {
+        let device_path: StackString<64> = io::join_path(USB_SYSFS_PATH, device);
+        let base: StackString<96> = io::join_path(device_path.as_str(), name);
+        let driver_path: StackString<128> = io::join_path(base.as_str(), "driver");
+        let driver = io::read_symlink_name(driver_path.as_str());
+        Self::from_files(name, driver, |file| {
+            let path: StackString<128> = io::join_path(base.as_str(), file);
+            io::read_file_stack(path.as_str())
+        })
+    }
+
+    /// Build an interface from its attribute files, as `read` returns them.
+    fn from_files(
+        name: &str,
+        driver: Option<StackString<32>>,
+        read: impl Fn(&str) -> Option<StackString<64>>,
+    ) -> Option<Self> {
+        let hex = |file: &str| read(file).and_then(|s| io::parse_hex::<u8>(s.as_str().trim()));
+        Some(UsbInterface {
+            name: StackString::from_str(name),
+            class: hex("bInterfaceClass")?,
+            protocol: hex("bInterfaceProtocol").unwrap_or(0),
+            driver,
+        })
+    }
+
+    /// Output as an indented text line under its device.
+    fn print_text(&self) {
+        print::print("  ");
+        let mut w = TextWriter::new();
+        w.field_str(f::INTERFACE, self.name.as_str());
+        w.field_str(f::CLASS, io::format_hex_u8(self.class).as_str());
+        w.field_quoted(f::CLASS_NAME, class_name(self.class));
+        w.field_str(f::PROTOCOL, io::format_hex_u8(self.protocol).as_str());
+        w.field_str_opt(f::DRIVER, self.driver.as_ref().map(|s| s.as_str()));
+        w.finish();
+    }
+
+    /// Write as JSON object in a device's `interfaces` array.
+    fn write_json(&self, w: &mut StreamingJsonWriter) {
+        w.array_object_begin();
+        w.field_str(f::NAME, self.name.as_str());
+        w.field_str(f::CLASS, io::format_hex_u8(self.class).as_str());
+        w.field_str(f::CLASS_NAME, class_name(self.class));
+        w.field_str(f::PROTOCOL, io::format_hex_u8(self.protocol).as_str());
+        w.field_str_opt(f::DRIVER, self.driver.as_ref().map(|s| s.as_str()));
+        w.array_object_end();
+    }
+}
+
+/// Whether `entry` of device `device`'s directory is one of its interfaces.
+fn is_interface_of(device: &str, entry: &str) -> bool {
+    entry.strip_prefix(device).is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// Call `f` with each interface of `device`, in order.
+fn for_each_interface(device: &str, mut f: impl FnMut(UsbInterface)) {
+    let base: StackString<64> = io::join_path(USB_SYSFS_PATH, device);
+    io::for_each_dir_entry_sorted(base.as_str(), io::natural_cmp, |entry| {
+        if !is_interface_of(device, entry) {
+            return;
+        }
+        if let Some(interface) = UsbInterface::read(device, entry) {
+            f(interface);
+        }
+    });
+}
+
 impl UsbDevice {
-    /// Read a USB device from sysfs.
+    /// Read a USB device from sysfs, retrying once if we raced a hot-plug.
     pub fn read(name: &str) -> Option<Self> {
+        let base: StackString<64> = io::join_path(USB_SYSFS_PATH, name);
+        io::retry_if_present(base.as_str(), || Self::read_once(name))
+    }
+
+    fn read_once(name: &str) -> Option<Self> {
         // Skip root hubs (usb1, usb2, etc.) - they're not real devices
         if name.starts_with("usb") {
             return None;
@@ -74,8 +167,8 @@ impl UsbDevice {
         // Must have vendor and product IDs
         let vendor_path: StackString<128> = io::join_path(base.as_str(), "idVendor");
         let product_path: StackString<128> = io::join_path(base.as_str(), "idProduct");
-        let vendor_id: u16 = io::read_file_hex(vendor_path.as_str())?;
-        let product_id: u16 = io::read_file_hex(product_path.as_str())?;
+        let vendor_id: u16 = io::read_required_hex(vendor_path.as_str())?;
+        let product_id: u16 = io::read_required_hex(product_path.as_str())?;
 
         let class_path: StackString<128> = io::join_path(base.as_str(), "bDeviceClass");
         let busnum_path: StackString<128> = io::join_path(base.as_str(), "busnum");
@@ -134,20 +227,6 @@ impl UsbDevice {
         })
     }
 
-    /// Check if this device matches the filter pattern.
-    fn matches_filter(&self, pattern: &str, case_insensitive: bool) -> bool {
-        let vendor_hex = io::format_hex_u16(self.vendor_id);
-        let product_hex = io::format_hex_u16(self.product_id);
-        let fields = [
-            self.name.as_str(),
-            opt_str(&self.manufacturer),
-            opt_str(&self.product),
-            vendor_hex.as_str(),
-            product_hex.as_str(),
-        ];
-        matches_any(&fields, pattern, case_insensitive)
-    }
-
     /// Output as text.
     fn print_text(&self, verbose: bool) {
         let mut w = TextWriter::new();
@@ -168,6 +247,7 @@ impl UsbDevice {
 
         if verbose {
             w.field_str(f::DEVICE_CLASS, io::format_hex_u8(self.device_class).as_str());
+            w.field_quoted(f::CLASS_NAME, class_name(self.device_class));
             w.field_u64(f::BUSNUM, self.busnum as u64);
             w.field_u64(f::DEVNUM, self.devnum as u64);
             if let Some(ref serial) = self.serial {
@@ -184,12 +264,39 @@ impl UsbDevice {
             }
         }
 
-        w.finish();
+        w.finish_record();
     }
 
-    /// Write as JSON object.
-    fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool) {
-        w.array_object_begin();
+    /// `1-1.4 046d c52b` for `kv usb -n`: bare hex IDs, as lsusb prints them.
+    fn numeric_line(&self) -> StackString<32> {
+        let mut s = StackString::new();
+        s.push_str(self.name.as_str());
+        s.push(' ');
+        s.push_str(io::format_hex_u16_bare(self.vendor_id).as_str());
+        s.push(' ');
+        s.push_str(io::format_hex_u16_bare(self.product_id).as_str());
+        s
+    }
+
+    /// Write just the name and IDs as a JSON object (`kv usb -jn`).
+    fn write_numeric_json(&self, w: &mut StreamingJsonWriter) {
+        w.record_begin();
+        w.field_str(kind::KIND, kind::USB_DEVICE);
+        w.field_str(f::NAME, self.name.as_str());
+        w.field_str(f::VENDOR_ID, io::format_hex_u16_bare(self.vendor_id).as_str());
+        w.field_str(f::PRODUCT_ID, io::format_hex_u16_bare(self.product_id).as_str());
+        w.array_object_end();
+    }
+
+    /// Print the interfaces as indented lines under the device (`--interfaces`).
+    fn print_interfaces_text(&self) {
+        for_each_interface(self.name.as_str(), |interface| interface.print_text());
+    }
+
+    /// Write as JSON object, with an `interfaces` array if asked.
+    fn write_json(&self, w: &mut StreamingJsonWriter, verbose: bool, interfaces: bool) {
+        w.record_begin();
+        w.field_str(kind::KIND, kind::USB_DEVICE);
 
         w.field_str(f::NAME, self.name.as_str());
         w.field_str(f::VENDOR_ID, io::format_hex_u16(self.vendor_id).as_str());
@@ -200,6 +307,7 @@ impl UsbDevice {
 
         if verbose {
             w.field_str(f::DEVICE_CLASS, io::format_hex_u8(self.device_class).as_str());
+            w.field_str(f::CLASS_NAME, class_name(self.device_class));
             w.field_u64(f::BUSNUM, self.busnum as u64);
             w.field_u64(f::DEVNUM, self.devnum as u64);
             w.field_str_opt(f::SERIAL, self.serial.as_ref().map(|s| s.as_str()));
@@ -210,15 +318,112 @@ impl UsbDevice {
             w.field_str_opt(f::DRIVER, self.driver.as_ref().map(|s| s.as_str()));
         }
 
+        if interfaces {
+            w.field_array(f::INTERFACES);
+            for_each_interface(self.name.as_str(), |interface| interface.write_json(w));
+            w.end_field_array();
+        }
+
         w.array_object_end();
     }
 }
 
+/// Readable name for a USB base class code (bDeviceClass).
+///
+/// Many devices report 0 here, meaning each interface declares its own
+/// class; we call that "Per-Interface".
+pub fn class_name(class: u8) -> &'static str {
+    match class {
+        0x00 => "Per-Interface",
+        0x01 => "Audio",
+        0x02 => "Communications",
+        0x03 => "HID",
+        0x05 => "Physical",
+        0x06 => "Image",
+        0x07 => "Printer",
+        0x08 => "Mass Storage",
+        0x09 => "Hub",
+        0x0a => "CDC Data",
+        0x0b => "Smart Card",
+        0x0d => "Content Security",
+        0x0e => "Video",
+        0x0f => "Personal Healthcare",
+        0x10 => "Audio/Video",
+        0x11 => "Billboard",
+        0x12 => "USB-C Bridge",
+        0xdc => "Diagnostic",
+        0xe0 => "Wireless",
+        0xef => "Miscellaneous",
+        0xfe => "Application Specific",
+        0xff => "Vendor Specific",
+        _ => "Unknown",
+    }
+}
+
+impl Filterable for UsbDevice {
+    fn filter_fields(&self, visit: &mut dyn FnMut(&str, &str)) {
+        visit(f::NAME, self.name.as_str());
+        visit(f::MANUFACTURER, opt_str(&self.manufacturer));
+        visit(f::PRODUCT, opt_str(&self.product));
+        visit(f::VENDOR_ID, io::format_hex_u16(self.vendor_id).as_str());
+        visit(f::PRODUCT_ID, io::format_hex_u16(self.product_id).as_str());
+        visit(f::CLASS_NAME, class_name(self.device_class));
+    }
+}
+
+/// Every `--template` field, whether or not a record has it.
+const TEMPLATE_FIELDS: &[&str] = &[
+    f::NAME,
+    f::MANUFACTURER,
+    f::PRODUCT,
+    f::VENDOR_ID,
+    f::PRODUCT_ID,
+    f::CLASS_NAME,
+];
+
+/// Options specific to the usb subcommand.
+#[derive(Default)]
+pub struct UsbOptions {
+    /// Only the device name and vendor/product IDs
+    pub numeric: bool,
+    /// List each device's interfaces under it
+    pub interfaces: bool,
+}
+
+impl UsbOptions {
+    /// Parse usb-specific options from remaining arguments.
+    pub fn parse(args: &ExtraArgs) -> Self {
+        let mut opts = UsbOptions::default();
+        for arg in args.iter() {
+            if matches!(arg, "-n" | "--numeric") {
+                opts.numeric = true;
+            } else if arg == "--interfaces" {
+                opts.interfaces = true;
+            }
+        }
+        opts
+    }
+}
+
 /// Entry point for `kv usb` subcommand.
-pub fn run(opts: &GlobalOptions) -> i32 {
+pub fn run(opts: &GlobalOptions, args: &ExtraArgs) -> i32 {
+    let usb_opts = UsbOptions::parse(args);
+
+    // Interfaces are indented lines under a full device record
+    if usb_opts.interfaces {
+        let ignored = usb_opts
+            .numeric
+            .then_some("-n")
+            .or(opts.template.is_some().then_some("--template"));
+        if let Some(option) = ignored {
+            cli::reject_combination(option, "--interfaces");
+            return 1;
+        }
+    }
+
     if !io::path_exists(USB_SYSFS_PATH) {
         if opts.json {
-            let mut w = begin_kv_output_streaming(opts.pretty, "usb");
+            let mut w = begin_kv_output_streaming(opts.json_layout(), "usb");
             w.field_array("data");
             w.end_field_array();
             w.end_object();
@@ -229,49 +434,70 @@ pub fn run(opts: &GlobalOptions) -> i32 {
         return 0;
     }
 
-    let filter = opts.filter.as_ref().map(|s| s.as_str());
-    let case_insensitive = opts.filter_case_insensitive;
+    let filter = &opts.filter;
 
     if opts.json {
-        let mut w = begin_kv_output_streaming(opts.pretty, "usb");
+        let mut w = begin_kv_output_streaming(opts.json_layout(), "usb");
         w.field_array("data");
 
         let mut count = 0;
+        let mut page = Page::from_options(opts);
         io::for_each_dir_entry(USB_SYSFS_PATH, |name| {
             if let Some(dev) = UsbDevice::read(name) {
-                if let Some(pattern) = filter {
-                    if !dev.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+                if !dev.matches_filter(filter) {
+                    return;
                 }
-                dev.write_json(&mut w, opts.verbose);
                 count += 1;
+                if !page.take() {
+                    return;
+                }
+                if usb_opts.numeric {
+                    dev.write_numeric_json(&mut w);
+                } else {
+                    dev.write_json(&mut w, opts.verbose, usb_opts.interfaces);
+                }
             }
         });
 
         w.end_field_array();
+        page.write_json(&mut w);
         w.end_object();
         w.finish();
 
-        if count == 0 && filter.is_some() {
+        if count == 0 && filter.is_active() {
             // Empty filtered result is fine
         }
     } else {
         let mut count = 0;
+        let mut template = Template::from_options(opts, TEMPLATE_FIELDS);
+        let mut page = Page::from_options(opts);
         io::for_each_dir_entry(USB_SYSFS_PATH, |name| {
             if let Some(dev) = UsbDevice::read(name) {
-                if let Some(pattern) = filter {
-                    if !dev.matches_filter(pattern, case_insensitive) {
-                        return;
-                    }
+                if !dev.matches_filter(filter) {
+                    return;
                 }
-                dev.print_text(opts.verbose);
                 count += 1;
+                if !page.take() {
+                    return;
+                }
+                match template.as_mut() {
+                    Some(t) => t.print(&dev, &[]),
+                    None if usb_opts.numeric => print::println_record(dev.numeric_line().as_str()),
+                    None => {
+                        dev.print_text(opts.verbose);
+                        if usb_opts.interfaces {
+                            dev.print_interfaces_text();
+                        }
+                    }
+                }
             }
         });
+        if let Some(t) = &template {
+            t.finish();
+        }
 
         if count == 0 {
-            if filter.is_some() {
+            if filter.is_active() {
                 print::println("usb: no matching devices");
             } else {
                 print::println("usb: no USB devices found");
@@ -282,19 +508,59 @@ pub fn run(opts: &GlobalOptions) -> i32 {
     0
 }
 
-/// Write USB devices to JSON writer (for snapshot).
+/// Write USB devices to JSON writer (for snapshot). Without a USB bus the
+/// section is left out, or written as `[]` with `include_empty`.
 #[cfg(feature = "snapshot")]
-pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool) {
-    if !io::path_exists(USB_SYSFS_PATH) {
+pub fn write_snapshot(w: &mut StreamingJsonWriter, verbose: bool, include_empty: bool) {
+    if !include_empty && !io::path_exists(USB_SYSFS_PATH) {
         return;
     }
 
     w.key("usb");
     w.begin_array();
-    io::for_each_dir_entry(USB_SYSFS_PATH, |name| {
+    io::for_each_dir_entry_sorted(USB_SYSFS_PATH, io::natural_cmp, |name| {
         if let Some(dev) = UsbDevice::read(name) {
-            dev.write_json(w, verbose);
+            dev.write_json(w, verbose, false);
         }
     });
     w.end_array();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_name() {
+        assert_eq!(class_name(0x00), "Per-Interface");
+        assert_eq!(class_name(0x03), "HID");
+        assert_eq!(class_name(0x08), "Mass Storage");
+        assert_eq!(class_name(0x09), "Hub");
+        assert_eq!(class_name(0x0e), "Video");
+        assert_eq!(class_name(0xff), "Vendor Specific");
+        assert_eq!(class_name(0x42), "Unknown");
+    }
+
+    #[test]
+    fn test_interface_from_files() {
+        let files = |file: &str| match file {
+            "bInterfaceClass" => Some(StackString::from_str("03\n")),
+            "bInterfaceProtocol" => Some(StackString::from_str("01\n")),
+            _ => None,
+        };
+        let interface = UsbInterface::from_files("1-1.4:1.0", Some(StackString::from_str("usbhid")), files).unwrap();
+        assert_eq!(interface.name.as_str(), "1-1.4:1.0");
+        assert_eq!((interface.class, interface.protocol), (0x03, 0x01));
+        assert_eq!(class_name(interface.class), "HID");
+
+        // No class, no interface
+        assert!(UsbInterface::from_files("1-1.4:1.1", None, |_| None).is_none());
+    }
+
+    #[test]
+    fn test_is_interface_of() {
+        assert!(is_interface_of("1-1.4", "1-1.4:1.0"));
+        assert!(!is_interface_of("1-1", "1-1.4:1.0"));
+        assert!(!is_interface_of("1-1.4", "bInterfaceClass"));
+    }
+}
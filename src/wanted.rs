@@ -0,0 +1,104 @@
+//! Keys to look up in a big list file (`--oui-file`, `--pci-ids`).
+//!
+//! Those files run to megabytes, far more than we keep on the stack, so
+//! we note the keys at hand first (the OUIs of the interfaces, the IDs of
+//! the PCI devices) and stream the file once, keeping only what's said
+//! about them.
+
+#![allow(dead_code)]
+
+use crate::print;
+
+/// Up to N keys, each with what the list file said about it so far.
+pub struct WantedList<K, V, const N: usize> {
+    entries: [(K, V); N],
+    count: usize,
+    /// What gets looked up, for the warning when the list is full
+    what: &'static str,
+    warned: bool,
+}
+
+impl<K: Copy + PartialEq + Default, V: Default, const N: usize> WantedList<K, V, N> {
+    /// An empty list; `what` names the lookups, e.g. "MAC vendors".
+    pub fn new(what: &'static str) -> Self {
+        Self {
+            entries: core::array::from_fn(|_| (K::default(), V::default())),
+            count: 0,
+            what,
+            warned: false,
+        }
+    }
+
+    /// Look up `key` when the file is read. Past N keys the rest go
+    /// without, with one warning.
+    pub fn want(&mut self, key: K) {
+        if self.entries[..self.count].iter().any(|(k, _)| *k == key) {
+            return;
+        }
+        if self.count < N {
+            self.entries[self.count] = (key, V::default());
+            self.count += 1;
+        } else if !self.warned {
+            let mut buf = itoa::Buffer::new();
+            print::eprint("Warning: only the first ");
+            print::eprint(buf.format(N));
+            print::eprint(" ");
+            print::eprint(self.what);
+            print::eprintln(" are looked up");
+            self.warned = true;
+        }
+    }
+
+    /// Hand one line of the file to `add` along with the wanted keys.
+    /// False once `found` holds for every key, so reading can stop.
+    pub fn add_line(&mut self, line: &str, add: impl FnOnce(&mut [(K, V)], &str), found: impl Fn(&V) -> bool) -> bool {
+        let entries = &mut self.entries[..self.count];
+        add(entries, line);
+        !entries.iter().all(|(_, v)| found(v))
+    }
+
+    /// What the file said about `key`, if it was wanted.
+    pub fn get(&self, key: K) -> Option<&V> {
+        self.entries[..self.count].iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// True once a key had to be left out.
+    pub fn is_full(&self) -> bool {
+        self.warned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_want_dedups_and_caps() {
+        let mut list: WantedList<u8, Option<u8>, 2> = WantedList::new("numbers");
+        list.want(1);
+        list.want(1);
+        list.want(2);
+        assert!(!list.is_full());
+        list.want(3);
+        assert!(list.is_full());
+        assert!(list.get(2).is_some());
+        assert!(list.get(3).is_none());
+    }
+
+    #[test]
+    fn test_add_line_stops_when_all_found() {
+        let mut list: WantedList<u8, Option<u8>, 4> = WantedList::new("numbers");
+        list.want(1);
+        list.want(2);
+        let add = |entries: &mut [(u8, Option<u8>)], line: &str| {
+            let n: u8 = line.parse().unwrap();
+            for (k, v) in entries.iter_mut().filter(|(k, _)| *k == n) {
+                *v = Some(*k * 10);
+            }
+        };
+        assert!(list.add_line("1", add, Option::is_some));
+        assert!(list.add_line("7", add, Option::is_some));
+        assert!(!list.add_line("2", add, Option::is_some));
+        assert_eq!(list.get(2), Some(&Some(20)));
+    }
+}
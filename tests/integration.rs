@@ -4,7 +4,8 @@
 //! They verify output format and exit codes, not specific values
 //! (since those vary by system).
 
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 fn kv() -> Command {
     Command::new(env!("CARGO_BIN_EXE_kv"))
@@ -26,6 +27,28 @@ fn version_flag() {
     assert!(stdout.contains(env!("CARGO_PKG_VERSION")));
 }
 
+#[test]
+fn version_json() {
+    for args in [&["--version", "-j"][..], &["version", "--json"][..]] {
+        let (ok, stdout, _) = run_kv(args);
+        assert!(ok);
+        assert!(stdout.contains(concat!("\"version\":\"", env!("CARGO_PKG_VERSION"), "\"")));
+        assert!(stdout.contains("\"features\":["));
+        assert!(stdout.contains("\"subcommand\":\"version\""));
+        assert!(stdout.trim_end().ends_with(",\"error\":null}"));
+    }
+}
+
+#[test]
+fn has_feature_flag() {
+    let (ok, stdout, _) = run_kv(&["--has-feature", "mem"]);
+    assert!(ok);
+    assert!(stdout.is_empty());
+    let (ok, stdout, _) = run_kv(&["--has-feature", "mem,no-such-feature"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+}
+
 #[test]
 fn help_flag() {
     let (ok, stdout, _) = run_kv(&["--help"]);
@@ -34,6 +57,41 @@ fn help_flag() {
     assert!(stdout.contains("SUBCOMMANDS:"));
 }
 
+#[test]
+fn help_all_flag() {
+    let (ok, stdout, _) = run_kv(&["--help-all"]);
+    assert!(ok);
+    assert!(stdout.contains("kv mem - Show memory information"));
+    assert!(stdout.contains("kv net - Show network interfaces"));
+}
+
+#[test]
+fn completions_bash() {
+    let (ok, stdout, _) = run_kv(&["--completions", "bash"]);
+    assert!(ok);
+    assert!(stdout.contains("complete -F _kv kv"));
+    assert!(stdout.contains("--json"));
+    assert!(stdout.contains(" mem"));
+}
+
+#[test]
+fn completions_zsh_and_fish() {
+    let (ok, stdout, _) = run_kv(&["--completions", "zsh"]);
+    assert!(ok);
+    assert!(stdout.contains("compdef _kv kv"));
+    let (ok, stdout, _) = run_kv(&["--completions", "fish"]);
+    assert!(ok);
+    assert!(stdout.contains("complete -c kv -s j -l json"));
+}
+
+#[test]
+fn completions_unknown_shell() {
+    let (ok, stdout, stderr) = run_kv(&["--completions", "tcsh"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("bash, zsh, fish"));
+}
+
 #[test]
 fn no_args_shows_error() {
     let (ok, _, stderr) = run_kv(&[]);
@@ -57,6 +115,40 @@ fn mem_text_output() {
     assert!(stdout.contains("MEM_TOTAL_KB="));
 }
 
+#[test]
+fn mem_text_key_case_and_sep() {
+    let (ok, stdout, _) = run_kv(&["mem", "--text-key-case", "lower", "--text-sep", ":"]);
+    assert!(ok);
+    assert!(stdout.contains("mem_total_kb:"));
+    assert!(!stdout.contains("MEM_TOTAL_KB="));
+}
+
+#[test]
+fn text_key_case_bad_value() {
+    let (ok, stdout, stderr) = run_kv(&["mem", "--text-key-case", "camel"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --text-key-case expects"));
+}
+
+#[test]
+fn text_sep_bad_value() {
+    for sep in ["::", " ", ""] {
+        let (ok, stdout, stderr) = run_kv(&["mem", "--text-sep", sep]);
+        assert!(!ok, "{sep:?}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains("Error: --text-sep expects a single non-space character"), "{stderr}");
+    }
+}
+
+#[test]
+fn filter_mode_bad_value() {
+    let (ok, stdout, stderr) = run_kv(&["net", "-f", "lo", "--filter-mode", "xor"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --filter-mode expects 'and' or 'or'"));
+}
+
 #[test]
 fn mem_json_output() {
     let (ok, stdout, _) = run_kv(&["mem", "-j"]);
@@ -64,6 +156,230 @@ fn mem_json_output() {
     assert!(stdout.contains("\"kv_version\""));
     assert!(stdout.contains("\"subcommand\":\"mem\""));
     assert!(stdout.contains("\"mem_total_kb\""));
+    assert!(stdout.trim_end().ends_with(",\"error\":null}"));
+}
+
+#[test]
+fn watch_rejects_bad_interval() {
+    for value in ["abc", "0"] {
+        let (ok, stdout, stderr) = run_kv(&["mem", "--watch", value]);
+        assert!(!ok, "{value}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains("Error: --watch expects a number of seconds"));
+    }
+}
+
+#[test]
+fn watch_streams_each_sample() {
+    use std::io::{BufRead, BufReader};
+    use std::time::{Duration, Instant};
+
+    // Through a pipe, each sample has to arrive while kv is still running,
+    // not when a buffer fills up or the process exits. `kv net` has no
+    // --watch; mem's is the one streaming mode, and it writes through the
+    // same unbuffered print module every subcommand uses.
+    let mut child = kv()
+        .args(["mem", "--watch", "1", "-j"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to execute kv");
+    let start = Instant::now();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let first = lines.next().unwrap().unwrap();
+    let second = lines.next().unwrap().unwrap();
+    let elapsed = start.elapsed();
+    let still_running = child.try_wait().unwrap().is_none();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(still_running);
+    assert!(elapsed < Duration::from_secs(5));
+    assert!(first.contains("\"subcommand\":\"mem\"") && !first.contains("_delta_kb"));
+    assert!(second.contains("\"mem_free_delta_kb\":"));
+}
+
+#[test]
+fn mem_flatten_json() {
+    let (ok, stdout, _) = run_kv(&["mem", "--flatten"]);
+    assert!(ok);
+    assert!(stdout.contains("\"subcommand\":\"mem\""));
+    assert!(stdout.contains("\"data.mem_total_kb\":"));
+    // Single level: only the outer braces
+    assert_eq!(stdout.matches('{').count(), 1);
+}
+
+#[test]
+fn net_addresses_json() {
+    let (ok, stdout, _) = run_kv(&["net", "--addresses", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"subcommand\":\"net\""));
+    // Loopback is nearly always configured
+    if stdout.contains("127.0.0.1") {
+        assert!(stdout.contains("\"prefix\":8"));
+    }
+}
+
+#[test]
+fn net_list_paths() {
+    let (ok, stdout, _) = run_kv(&["net", "--list-paths"]);
+    assert!(ok);
+    // Only paths, no KEY=value output
+    assert!(stdout.lines().all(|l| l.starts_with('/')));
+    if std::path::Path::new("/sys/class/net").exists() {
+        assert!(stdout.lines().any(|l| l == "/sys/class/net"));
+    }
+    let lines: Vec<&str> = stdout.lines().collect();
+    let mut sorted = lines.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(lines, sorted);
+}
+
+#[test]
+fn net_baseline_delta() {
+    let (ok, saved, _) = run_kv(&["net", "-jv"]);
+    assert!(ok);
+    let path = std::env::temp_dir().join(format!("kv-baseline-{}.json", std::process::id()));
+    std::fs::write(&path, &saved).unwrap();
+
+    let (ok, stdout, _) = run_kv(&["net", "-j", "--baseline", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).ok();
+    assert!(ok);
+    if saved.contains("\"name\":\"lo\"") && saved.contains("\"rx_bytes\"") {
+        assert!(stdout.contains("\"rx_bytes_delta\":"));
+    }
+}
+
+#[test]
+fn net_baseline_missing_file() {
+    let (ok, _, stderr) = run_kv(&["net", "--baseline", "/nonexistent/kv-baseline.json"]);
+    assert!(!ok);
+    assert!(stderr.contains("baseline"));
+}
+
+#[test]
+fn mem_output_file() {
+    let path = std::env::temp_dir().join(format!("kv-output-{}.txt", std::process::id()));
+    let path_str = path.to_str().unwrap();
+
+    let (ok, stdout, _) = run_kv(&["mem", "--output", path_str]);
+    assert!(ok);
+    assert!(stdout.is_empty());
+    let first = std::fs::read_to_string(&path).unwrap();
+    assert!(first.contains("MEM_TOTAL_KB="));
+
+    // --append adds a second run, plain --output starts over
+    let (ok, _, _) = run_kv(&["mem", "--output", path_str, "--append"]);
+    assert!(ok);
+    let appended = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(appended.lines().count(), first.lines().count() * 2);
+    let (ok, _, _) = run_kv(&["mem", "--output", path_str]);
+    assert!(ok);
+    let truncated = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(truncated.lines().count(), first.lines().count());
+}
+
+#[test]
+fn output_file_unwritable() {
+    let (ok, stdout, stderr) = run_kv(&["mem", "--output", "/nonexistent-dir/out.txt"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("cannot open output file"));
+}
+
+#[test]
+fn output_path_too_long() {
+    let path = format!("/tmp/{}", "x".repeat(300));
+    let (ok, stdout, stderr) = run_kv(&["mem", "--output", &path]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --output is longer than 256 bytes"));
+    assert!(!std::path::Path::new(&path[..256]).exists());
+}
+
+#[test]
+fn output_path_empty() {
+    let (ok, stdout, stderr) = run_kv(&["mem", "--output", ""]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --output expects a file path"));
+}
+
+#[test]
+fn mem_strict() {
+    // /proc/meminfo is always there on Linux, so strict mode passes
+    let (ok, stdout, stderr) = run_kv(&["mem", "--strict"]);
+    assert!(ok);
+    assert!(stdout.contains("MEM_TOTAL_KB="));
+    assert!(!stderr.contains("required files"));
+}
+
+#[test]
+fn mem_probe() {
+    // Memory stats always exist, in text and JSON alike
+    for args in [&["mem", "--probe"][..], &["mem", "--probe", "-j"][..]] {
+        let (ok, _, _) = run_kv(args);
+        assert!(ok);
+    }
+}
+
+#[test]
+fn probe_exits_4_when_nothing_matches() {
+    for args in [
+        &["pci", "--probe", "-f", "no-such-device-xyz"][..],
+        &["net", "--probe", "-j", "-f", "no-such-iface-xyz"][..],
+        // A summary or total line over no records isn't data
+        &["power", "--probe", "-f", "no-such-supply-xyz"][..],
+        &["power", "--probe", "-j", "-f", "no-such-supply-xyz"][..],
+        &["block", "--summary", "--probe", "-f", "no-such-disk-xyz"][..],
+        &["block", "--summary", "--probe", "-j", "-f", "no-such-disk-xyz"][..],
+        &["--merge", "pci", "usb", "-j", "--probe", "-f", "no-such-device-xyz"][..],
+    ] {
+        let status = kv().args(args).output().expect("failed to execute kv").status;
+        assert_eq!(status.code(), Some(4), "kv {}", args.join(" "));
+    }
+}
+
+#[test]
+fn merge_text_and_json() {
+    let (ok, stdout, _) = run_kv(&["--merge", "cpu", "mem"]);
+    assert!(ok);
+    assert!(stdout.starts_with("== cpu ==\n"));
+    assert!(stdout.contains("\n== mem ==\nMEM_TOTAL_KB="));
+
+    // One envelope, with each subcommand's own envelope under data
+    let (ok, stdout, _) = run_kv(&["cpu", "mem", "-j", "--merge"]);
+    assert!(ok);
+    assert!(stdout.starts_with("{\"kv_version\":"));
+    assert!(stdout.contains("\"subcommand\":\"merge\""));
+    assert!(stdout.contains("\"data\":{\"cpu\":{\"kv_version\":"));
+    assert!(stdout.contains("},\"mem\":{\"kv_version\":"));
+    assert!(stdout.trim_end().ends_with("}},\"error\":null}"));
+    assert_eq!(stdout.lines().count(), 1);
+
+    // Sections indent like any other nested object
+    let (ok, stdout, _) = run_kv(&["--merge", "cpu", "mem", "-jp"]);
+    assert!(ok);
+    assert!(stdout.contains("\n  \"data\": {\n    \"cpu\": {\n      \"kv_version\": "));
+    assert!(stdout.contains("\n    },\n    \"mem\": {\n"));
+}
+
+#[test]
+fn merge_rejects_single_run_options() {
+    // mem --watch would never return, so cpu would never run
+    let (ok, stdout, stderr) = run_kv(&["--merge", "mem", "cpu", "--watch", "1"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("--watch can't be used with --merge"));
+
+    // read's argument is a path, even one that looks like a subcommand
+    for args in [&["--merge", "read", "mem"][..], &["--merge", "mem", "read"][..]] {
+        let (ok, stdout, stderr) = run_kv(args);
+        assert!(!ok);
+        assert!(stdout.is_empty());
+        assert!(stderr.contains("read can't be used with --merge"));
+    }
 }
 
 #[test]
@@ -88,6 +404,8 @@ fn cpu_json() {
     let (ok, stdout, _) = run_kv(&["cpu", "-j"]);
     assert!(ok);
     assert!(stdout.contains("\"subcommand\":\"cpu\""));
+    assert!(stdout.contains("\"timestamp_unix\":"));
+    assert!(stdout.contains("\"data\":{") && stdout.contains("\"error\":null"));
 }
 
 #[test]
@@ -105,6 +423,84 @@ fn block_json() {
     assert!(stdout.contains("\"subcommand\":\"block\""));
 }
 
+#[test]
+fn raw_flag() {
+    for cmd in ["block", "mounts", "power"] {
+        let (ok, _, stderr) = run_kv(&[cmd, "-v", "--raw"]);
+        assert!(ok, "{cmd}");
+        assert!(stderr.is_empty(), "{cmd}: {stderr}");
+    }
+}
+
+#[test]
+fn explain_flag() {
+    let (ok, stdout, _) = run_kv(&["net", "--explain", "-f", "lo"]);
+    assert!(ok);
+    // lo is "unknown" on most kernels, "up" on some
+    assert!(stdout.is_empty() || stdout.contains("STATE=unknown (") || stdout.contains("STATE=up ("));
+
+    // JSON is left alone
+    let (ok, stdout, _) = run_kv(&["net", "-j", "--explain"]);
+    assert!(ok);
+    assert!(!stdout.contains("driver doesn't report state"));
+}
+
+#[test]
+fn block_summary() {
+    let (ok, stdout, _) = run_kv(&["block", "--summary"]);
+    assert!(ok);
+    assert!(stdout.lines().last().unwrap_or("").starts_with("TOTAL_DISKS="));
+
+    let (ok, stdout, _) = run_kv(&["block", "-j", "--summary"]);
+    assert!(ok);
+    assert!(stdout.contains("\"summary\":{\"total_disks\":"));
+}
+
+#[test]
+fn block_topology() {
+    let (ok, stdout, _) = run_kv(&["block", "--topology", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"data\":["));
+    if stdout.contains("\"kind\":\"block_node\"") {
+        assert!(stdout.contains("\"children\":["));
+    }
+    // The tree follows -f too
+    let (ok, stdout, _) = run_kv(&["block", "--topology", "-f", "name=no-such-device"]);
+    assert!(ok);
+    assert!(stdout == "block: no matching devices\n" || stdout == "block: no block devices found\n");
+}
+
+#[test]
+fn block_topology_types() {
+    // Only disks: partitions and what's built on them are left out
+    let (ok, stdout, _) = run_kv(&["block", "--topology", "-j", "--only-types", "disk"]);
+    assert!(ok);
+    assert!(!stdout.contains("\"type\":\"part\""));
+    let (ok, stdout, _) = run_kv(&["block", "--topology", "-j", "--exclude-types", "part,loop"]);
+    assert!(ok);
+    assert!(!stdout.contains("\"type\":\"part\"") && !stdout.contains("\"type\":\"loop\""));
+}
+
+#[test]
+fn block_topology_rejects_list_options() {
+    let cases: [&[&str]; 6] = [
+        &["--sum", "size_bytes"],
+        &["--template", "{name}"],
+        &["--limit", "1"],
+        &["--offset", "1"],
+        &["--summary"],
+        &["--baseline", "/nonexistent"],
+    ];
+    for extra in cases {
+        let mut args = vec!["block", "--topology"];
+        args.extend_from_slice(extra);
+        let (ok, stdout, stderr) = run_kv(&args);
+        assert!(!ok, "{extra:?}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains(&format!("Error: {} can't be used with --topology", extra[0])), "{stderr}");
+    }
+}
+
 #[test]
 fn net_runs() {
     let (ok, stdout, _) = run_kv(&["net"]);
@@ -120,6 +516,305 @@ fn net_json() {
     assert!(stdout.contains("\"subcommand\":\"net\""));
 }
 
+#[test]
+fn json_records_have_kind() {
+    // There's always at least the root filesystem
+    let (ok, stdout, _) = run_kv(&["mounts", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"data\":[{\"kind\":\"mount\","));
+}
+
+#[test]
+fn filter_multiple_and() {
+    // Both patterns must match, and nothing contains the second one
+    let (ok, stdout, _) = run_kv(&["net", "-f", "lo", "-f", "definitely-not-there"]);
+    assert!(ok);
+    assert!(!stdout.contains("NAME=lo"));
+}
+
+#[test]
+fn filter_multiple_or() {
+    let (ok, stdout, _) = run_kv(&[
+        "net", "-f", "lo", "-f", "definitely-not-there", "--filter-mode", "or",
+    ]);
+    assert!(ok);
+    assert!(stdout.contains("NAME=lo") || stdout.contains("no matching"));
+}
+
+#[test]
+fn filter_keyed_exact() {
+    // name=l must not match "lo" - keyed patterns are exact
+    let (ok, stdout, _) = run_kv(&["net", "-f", "name=l"]);
+    assert!(ok);
+    assert!(!stdout.contains("NAME=lo"));
+}
+
+#[test]
+fn filter_from_stdin() {
+    let mut child = kv()
+        .args(["net", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to execute kv");
+    child.stdin.take().unwrap().write_all(b"definitely-not-there\nlo\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.is_empty() || stdout.contains("NAME=lo"));
+    assert!(!stdout.contains("NAME=eth"));
+}
+
+#[test]
+fn kv_opts_env() {
+    let output = kv().env("KV_OPTS", "-j -p").arg("mem").output().expect("failed to execute kv");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"subcommand\": \"mem\""));
+
+    // The command line wins
+    let output = kv().env("KV_OPTS", "-j").args(["mem", "--no-json"]).output().expect("failed to execute kv");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("MEM_TOTAL"));
+}
+
+#[test]
+fn summary_one_line() {
+    let (ok, stdout, _) = run_kv(&["summary"]);
+    assert!(ok);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("UPTIME_S="));
+
+    // -1 is the same thing
+    let (ok, short, _) = run_kv(&["-1", "-h"]);
+    assert!(ok);
+    assert!(short.contains("UPTIME="));
+}
+
+#[test]
+fn summary_json_flat() {
+    let (ok, stdout, _) = run_kv(&["summary", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"uptime_s\":"));
+    assert!(!stdout.contains('['));
+}
+
+#[test]
+fn cpu_usage_json() {
+    let (ok, stdout, _) = run_kv(&["cpu", "--usage", "-n", "0.1", "-jv"]);
+    assert!(ok);
+    assert!(stdout.contains("\"cpu_usage\":"));
+    assert!(stdout.contains("\"per_cpu\":[{\"cpu\":"));
+}
+
+#[test]
+fn cpu_usage_rejects_bad_interval() {
+    for value in ["x", "0", "1.2345"] {
+        let (ok, stdout, stderr) = run_kv(&["cpu", "--usage", "-n", value]);
+        assert!(!ok, "{value}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains("Error: -n expects seconds"));
+    }
+}
+
+#[test]
+fn read_attribute() {
+    let (ok, stdout, _) = run_kv(&["read", "-j", "/proc/sys/kernel/ostype"]);
+    assert!(ok);
+    assert!(stdout.contains("\"value\":\"Linux\""));
+    // --raw keeps the kernel's trailing newline
+    let (ok, stdout, _) = run_kv(&["read", "-j", "--raw", "/proc/sys/kernel/ostype"]);
+    assert!(ok);
+    assert!(stdout.contains("\"value\":\"Linux\\n\""));
+
+    // Anything outside /sys and /proc is refused
+    let (ok, stdout, _) = run_kv(&["read", "/etc/passwd"]);
+    assert!(!ok);
+    assert!(!stdout.contains("root:"));
+}
+
+#[test]
+fn read_missing_attribute() {
+    // The error goes to stderr, so $(kv read ...) never captures it as a value
+    let (ok, stdout, stderr) = run_kv(&["read", "/sys/class/net/lo/mtux"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("cannot read"));
+}
+
+#[test]
+fn read_whole_proc_file() {
+    // Longer than one formatting buffer: the last line must still be there
+    let (ok, stdout, _) = run_kv(&["read", "/proc/self/status"]);
+    assert!(ok);
+    assert!(stdout.len() > 512);
+    assert!(stdout.contains("nonvoluntary_ctxt_switches:"));
+}
+
+#[test]
+fn kernel_json() {
+    let (ok, stdout, _) = run_kv(&["kernel", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"release\":"));
+    assert!(stdout.contains("\"version_raw\":\"Linux version "));
+    assert!(stdout.contains("\"cmdline\":["));
+}
+
+#[test]
+fn tasks_counts() {
+    let (ok, stdout, _) = run_kv(&["tasks"]);
+    assert!(ok);
+    assert!(stdout.starts_with("PROCESSES="));
+    let (ok, stdout, _) = run_kv(&["tasks", "-jv"]);
+    assert!(ok);
+    assert!(stdout.contains("\"processes\":"));
+    assert!(stdout.contains("\"threads\":"));
+}
+
+#[test]
+fn net_sum() {
+    // lo always exists, so there is at least one set of counters
+    let (ok, stdout, _) = run_kv(&["net", "--sum", "rx_bytes,tx_bytes"]);
+    assert!(ok);
+    assert!(stdout.starts_with("RX_BYTES="));
+    assert_eq!(stdout.lines().count(), 1);
+    let (ok, stdout, stderr) = run_kv(&["net", "-j", "--sum", "name,rx_packets"]);
+    assert!(ok);
+    assert!(stdout.contains("\"rx_packets\":"));
+    assert!(!stdout.contains("\"name\""));
+    assert!(stderr.contains("isn't a numeric field"));
+}
+
+#[test]
+fn sum_rejected_where_ignored() {
+    for sub in ["pci", "usb", "thermal", "mounts"] {
+        let (ok, stdout, stderr) = run_kv(&[sub, "--sum", "size_bytes"]);
+        assert!(!ok, "{sub}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains("doesn't take --sum; it works with kv block, net and power"));
+    }
+}
+
+#[test]
+fn block_sum_counts_disks_once() {
+    // Partitions are inside their disks' bytes, so listing them changes nothing
+    let (ok, sum, _) = run_kv(&["block", "--sum", "size_bytes", "--only-types", "disk"]);
+    assert!(ok);
+    let (ok, all, _) = run_kv(&["block", "--sum", "size_bytes", "--only-types", "disk,part"]);
+    assert!(ok);
+    assert_eq!(sum, all);
+}
+
+#[test]
+fn sum_bad_value() {
+    let (ok, stdout, stderr) = run_kv(&["net", "--sum", ","]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --sum expects field names"));
+}
+
+#[test]
+fn block_sum_rejects_summary() {
+    let (ok, stdout, stderr) = run_kv(&["block", "--sum", "size_bytes", "--summary"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --summary can't be used with --sum"));
+}
+
+#[test]
+fn net_template() {
+    let (ok, stdout, stderr) = run_kv(&["net", "-f", "name=lo", "--template", r"[{name}]\t{mtu}{nope}{speed}"]);
+    assert!(ok);
+    assert!(stdout.starts_with("[lo]\t"));
+    assert!(!stdout.contains("NAME="));
+    assert!(stderr.contains("unknown template field {nope}"));
+    // Loopback has no link speed, but speed is still a net field
+    assert!(!stderr.contains("{speed}"));
+}
+
+#[test]
+fn template_rejected_where_ignored() {
+    for sub in ["cpu", "mem", "thermal"] {
+        let (ok, stdout, stderr) = run_kv(&[sub, "--template", "{name}"]);
+        assert!(!ok, "{sub}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains("doesn't take --template; it works with kv block, mounts, net, pci, power and usb"));
+    }
+}
+
+#[test]
+fn template_too_long() {
+    let template = "{name} ".repeat(40);
+    let (ok, stdout, stderr) = run_kv(&["net", "--template", &template]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --template is longer than 256 bytes"));
+}
+
+#[test]
+fn mounts_limit_offset() {
+    let (ok, all, _) = run_kv(&["mounts"]);
+    assert!(ok);
+    let (ok, stdout, _) = run_kv(&["mounts", "--limit", "2"]);
+    assert!(ok);
+    assert_eq!(stdout.lines().count(), 2);
+    let (ok, stdout, _) = run_kv(&["mounts", "--offset", "1", "--first", "1"]);
+    assert!(ok);
+    assert_eq!(stdout.lines().next(), all.lines().nth(1));
+    let (ok, stdout, _) = run_kv(&["mounts", "-j", "--limit", "1"]);
+    assert!(ok);
+    assert!(stdout.contains("\"shown\":1"));
+    assert!(stdout.contains("\"total\":"));
+    // Unpaged JSON doesn't change
+    let (ok, stdout, _) = run_kv(&["mounts", "-j"]);
+    assert!(ok);
+    assert!(!stdout.contains("\"shown\""));
+}
+
+#[test]
+fn limit_offset_bad_value() {
+    for option in ["--limit", "--first", "--offset"] {
+        let (ok, stdout, stderr) = run_kv(&["mounts", option, "two"]);
+        assert!(!ok, "{option}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains("expects a number of items"));
+    }
+}
+
+#[test]
+fn net_missing_oui_file() {
+    // A missing list warns but still lists interfaces
+    let (ok, stdout, stderr) = run_kv(&["net", "--oui-file", "/nonexistent/oui.txt"]);
+    assert!(ok);
+    assert!(stderr.contains("cannot read OUI file"));
+    assert!(!stdout.contains("MAC_VENDOR="));
+}
+
+#[test]
+fn dmi_runs() {
+    // No DMI on ARM boards and in some containers, but never an error
+    let (ok, stdout, _) = run_kv(&["dmi", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"subcommand\":\"dmi\""));
+}
+
+#[test]
+fn mem_indent_tab() {
+    let (ok, stdout, _) = run_kv(&["mem", "-jp", "--indent", "tab"]);
+    assert!(ok);
+    assert!(stdout.contains("\n\t\"kv_version\""));
+    let (ok, stdout, _) = run_kv(&["mem", "-j", "--indent", "4"]);
+    assert!(ok);
+    assert!(!stdout.contains("\n    "));
+}
+
+#[test]
+fn indent_bad_value() {
+    let (ok, stdout, stderr) = run_kv(&["mem", "-jp", "--indent", "9"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --indent expects"));
+}
+
 #[test]
 fn mounts_runs() {
     let (ok, stdout, _) = run_kv(&["mounts"]);
@@ -142,6 +837,106 @@ fn pci_runs() {
     assert!(ok);
 }
 
+#[test]
+fn pci_tree_json() {
+    let (ok, stdout, _) = run_kv(&["pci", "--tree", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"subcommand\":\"pci\""));
+}
+
+#[test]
+fn pci_tree_rejects_list_options() {
+    for (option, value) in [("--template", "{slot}"), ("--limit", "1"), ("--offset", "1")] {
+        let (ok, stdout, stderr) = run_kv(&["pci", "--tree", option, value]);
+        assert!(!ok, "{option}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains(&format!("Error: {option} can't be used with --tree")));
+    }
+}
+
+#[test]
+fn net_addresses_rejects_list_options() {
+    let cases: [&[&str]; 5] = [
+        &["--sum", "rx_bytes"],
+        &["--template", "{name}"],
+        &["--limit", "1"],
+        &["--offset", "1"],
+        &["--baseline", "/nonexistent"],
+    ];
+    for extra in cases {
+        let mut args = vec!["net", "--addresses"];
+        args.extend_from_slice(extra);
+        let (ok, stdout, stderr) = run_kv(&args);
+        assert!(!ok, "{extra:?}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains(&format!("Error: {} can't be used with --addresses", extra[0])), "{stderr}");
+    }
+}
+
+#[test]
+fn net_stats_only_rejects_fixed_column_options() {
+    let cases: [&[&str]; 3] = [&["--sum", "rx_bytes"], &["--template", "{name}"], &["--baseline", "/nonexistent"]];
+    for extra in cases {
+        let mut args = vec!["net", "--stats-only"];
+        args.extend_from_slice(extra);
+        let (ok, stdout, stderr) = run_kv(&args);
+        assert!(!ok, "{extra:?}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains(&format!("Error: {} can't be used with --stats-only", extra[0])), "{stderr}");
+    }
+}
+
+#[test]
+fn usb_interfaces_rejects_short_forms() {
+    for extra in [&["-n"][..], &["--template", "{name}"]] {
+        let mut args = vec!["usb", "--interfaces"];
+        args.extend_from_slice(extra);
+        let (ok, stdout, stderr) = run_kv(&args);
+        assert!(!ok, "{extra:?}");
+        assert!(stdout.is_empty());
+        assert!(stderr.contains(&format!("Error: {} can't be used with --interfaces", extra[0])), "{stderr}");
+    }
+}
+
+#[test]
+fn pci_numeric() {
+    let (ok, stdout, _) = run_kv(&["pci", "-n"]);
+    assert!(ok);
+    assert!(!stdout.contains("VENDOR_ID="));
+    assert!(!stdout.contains("0x"));
+    let (ok, stdout, _) = run_kv(&["pci", "-j", "--numeric"]);
+    assert!(ok);
+    assert!(!stdout.contains("\"class\""));
+    // The tree draws the same short lines
+    let (ok, stdout, _) = run_kv(&["pci", "--tree", "-n"]);
+    assert!(ok);
+    assert!(!stdout.contains("VENDOR_ID="));
+    let (ok, stdout, _) = run_kv(&["pci", "--tree", "-j", "-n"]);
+    assert!(ok);
+    assert!(!stdout.contains("\"class\""));
+}
+
+#[test]
+fn pci_missing_ids_file() {
+    // A missing list warns but still lists devices
+    let (ok, stdout, stderr) = run_kv(&["pci", "--pci-ids", "/nonexistent/pci.ids"]);
+    assert!(ok);
+    if !stdout.contains("no PCI bus") {
+        assert!(stderr.contains("cannot read PCI ID file"));
+    }
+    assert!(!stdout.contains("VENDOR_NAME="));
+}
+
+#[test]
+fn pci_bars_json() {
+    let (ok, stdout, _) = run_kv(&["pci", "-jv"]);
+    assert!(ok);
+    // Most devices have a BAR, but there may be no PCI bus at all
+    if stdout.contains("\"bars\":[") {
+        assert!(stdout.contains("\"size_bytes\":"));
+    }
+}
+
 #[test]
 fn usb_runs() {
     let (ok, _, _) = run_kv(&["usb"]);
@@ -149,6 +944,16 @@ fn usb_runs() {
     assert!(ok);
 }
 
+#[test]
+fn usb_interfaces() {
+    let (ok, stdout, _) = run_kv(&["usb", "-j", "--interfaces"]);
+    assert!(ok);
+    // Every device carries the array, even where there's no USB at all
+    if stdout.contains("\"usb_device\"") {
+        assert!(stdout.contains("\"interfaces\":["));
+    }
+}
+
 #[test]
 fn snapshot_json() {
     let (ok, stdout, _) = run_kv(&["snapshot"]);
@@ -158,6 +963,94 @@ fn snapshot_json() {
     assert!(stdout.contains("\"subcommand\":\"snapshot\""));
 }
 
+#[test]
+fn snapshot_profile() {
+    let (ok, stdout, _) = run_kv(&["snapshot", "--profile", "health"]);
+    assert!(ok);
+    assert!(stdout.contains("\"profile\":\"health\""));
+    assert!(!stdout.contains("\"pci\":"));
+    assert!(!stdout.contains("\"mounts\":"));
+}
+
+#[test]
+fn snapshot_include_empty() {
+    // Every section is present, even on a machine without batteries
+    let (ok, stdout, _) = run_kv(&["snapshot", "--include-empty"]);
+    assert!(ok);
+    assert!(stdout.contains("\"power\":["));
+    assert!(stdout.contains("\"thermal\":["));
+    assert!(stdout.contains("\"usb\":["));
+    // cpu and mem are objects either way, never null
+    assert!(stdout.contains("\"cpu\":{"));
+    assert!(stdout.contains("\"mem\":{"));
+}
+
+#[test]
+fn snapshot_unknown_profile() {
+    let (ok, _, stderr) = run_kv(&["snapshot", "--profile", "bogus"]);
+    assert!(!ok);
+    assert!(stderr.contains("inventory"));
+    assert!(stderr.contains("health"));
+}
+
+#[test]
+fn snapshot_envelope_has_timestamp_and_host() {
+    let (ok, stdout, _) = run_kv(&["snapshot"]);
+    assert!(ok);
+    assert!(stdout.contains("\"timestamp_unix\":"));
+    // Envelope only - the timestamp must come before the data section
+    let ts = stdout.find("\"timestamp_unix\"").unwrap();
+    assert!(ts < stdout.find("\"data\"").unwrap());
+    if std::path::Path::new("/proc/sys/kernel/hostname").exists() {
+        assert!(stdout.contains("\"hostname\":"));
+    }
+}
+
+#[test]
+fn snapshot_block_has_mountpoints() {
+    // The snapshot hands block its copy of /proc/self/mounts; every
+    // mountpoint `kv block` finds must be in the snapshot too
+    let (ok, block, _) = run_kv(&["block", "-j"]);
+    assert!(ok);
+    let (ok, snapshot, _) = run_kv(&["snapshot"]);
+    assert!(ok);
+    for (start, _) in block.match_indices("\"mountpoint\":\"") {
+        let rest = &block[start..];
+        let end = rest.find("\",").or_else(|| rest.find("\"}")).unwrap() + 1;
+        assert!(snapshot.contains(&rest[..end]), "{}", &rest[..end]);
+    }
+}
+
+/// A pretty-printed document with every number value replaced by "#".
+/// Names and other strings are kept as they are, digits and all.
+fn mask_numbers(doc: &str) -> String {
+    let mut out = String::new();
+    for line in doc.lines() {
+        match line.split_once(": ") {
+            Some((key, value)) if value.trim_end_matches(',').parse::<f64>().is_ok() => {
+                out.push_str(key);
+                out.push_str(": #");
+            }
+            _ => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[test]
+fn snapshot_is_reproducible() {
+    // Counters, temperatures, free memory and the timestamp tick between
+    // runs, and they're all numbers. Everything else - names, and the order
+    // of sections and records (eth2 before eth10) - must not move.
+    assert_eq!(mask_numbers("  \"name\": \"eth10\",\n  \"mtu\": 1500,\n"), "  \"name\": \"eth10\",\n  \"mtu\": #\n");
+    let (ok, first, _) = run_kv(&["snapshot", "-p"]);
+    assert!(ok);
+    let (ok, second, _) = run_kv(&["snapshot", "-p"]);
+    assert!(ok);
+    assert_eq!(mask_numbers(&first), mask_numbers(&second));
+}
+
 #[test]
 fn combined_flags() {
     // Test that -jpv works (combined short flags)
@@ -167,6 +1060,28 @@ fn combined_flags() {
     assert!(stdout.contains("\"mem_total_kb\"")); // json
 }
 
+#[test]
+fn combined_flags_with_subcommand_flag() {
+    // pci's -n can't ride along with -j, and the error says how to write it
+    let (ok, _, stderr) = run_kv(&["pci", "-jn"]);
+    assert!(!ok);
+    assert!(stderr.contains("unknown flag: -n in -jn\n"));
+    assert!(stderr.contains("use -j -n"));
+}
+
+#[test]
+fn combined_flags_unknown_letter() {
+    let (ok, stdout, stderr) = run_kv(&["mem", "-jpx"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.starts_with("unknown flag: -x in -jpx\n"));
+
+    let (ok, stdout, stderr) = run_kv(&["mem", "-jp"]);
+    assert!(ok);
+    assert!(stdout.contains("\n  \""));
+    assert!(stderr.is_empty());
+}
+
 #[test]
 fn verbose_adds_fields() {
     let (ok_normal, stdout_normal, _) = run_kv(&["mem"]);
@@ -207,6 +1122,16 @@ fn help_short_flag() {
     assert!(stdout.contains("SUBCOMMANDS:"));
 }
 
+#[test]
+fn cpu_topology() {
+    let (ok, stdout, stderr) = run_kv(&["cpu", "--topology"]);
+    assert!(ok);
+    assert!(stdout.starts_with("Package ") || stderr.starts_with("cpu: no topology"));
+    let (ok, stdout, _) = run_kv(&["cpu", "--topology", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"packages\":["));
+}
+
 #[test]
 fn thermal_runs() {
     let (ok, _, _) = run_kv(&["thermal"]);
@@ -219,6 +1144,25 @@ fn thermal_json() {
     let (ok, stdout, _) = run_kv(&["thermal", "-j"]);
     assert!(ok);
     assert!(stdout.contains("\"subcommand\":\"thermal\""));
+    // Every sensor has its raw name, with or without -v
+    if stdout.contains("\"sensor\"") {
+        assert!(stdout.contains("\"name\":"));
+    }
+}
+
+#[test]
+fn thermal_hot_json() {
+    let (ok, stdout, _) = run_kv(&["thermal", "-j", "--hot"]);
+    assert!(ok);
+    assert!(stdout.contains("\"data\":["));
+}
+
+#[test]
+fn thermal_precision_bad_value() {
+    let (ok, stdout, stderr) = run_kv(&["thermal", "--precision", "x"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("Error: --precision expects"));
 }
 
 #[test]
@@ -268,6 +1212,35 @@ fn filter_net() {
     assert!(stdout.contains("lo") || stdout.is_empty());
 }
 
+#[test]
+fn net_stats_only() {
+    let (ok, stdout, _) = run_kv(&["net", "--stats-only", "--header", "-f", "lo"]);
+    assert!(ok);
+    let mut lines = stdout.lines();
+    assert!(lines.next().unwrap().starts_with("iface rx_bytes tx_bytes"));
+    for line in lines {
+        assert!(line.starts_with("lo "));
+        assert_eq!(line.split(' ').count(), 9);
+    }
+
+    let (ok, stdout, _) = run_kv(&["net", "--stats-only", "-j"]);
+    assert!(ok);
+    assert!(stdout.contains("\"data\":[]") || stdout.contains("\"kind\":\"net_counters\""));
+}
+
+#[test]
+fn filter_numeric() {
+    let (ok, stdout, _) = run_kv(&["net", "-f", "lo", "-f", "mtu>1000"]);
+    assert!(ok);
+    // lo has a 64K MTU
+    assert!(stdout.contains("NAME=lo") || stdout.is_empty());
+
+    let (ok, stdout, stderr) = run_kv(&["net", "-f", "name>1"]);
+    assert!(ok);
+    assert!(!stdout.contains("NAME="));
+    assert!(stderr.contains("isn't a numeric field") || stdout.contains("no "));
+}
+
 #[test]
 fn filter_json() {
     // Note: -f must be separate because it takes an argument
@@ -275,3 +1248,14 @@ fn filter_json() {
     assert!(ok);
     assert!(stdout.contains("\"subcommand\":\"net\""));
 }
+
+#[test]
+fn profile_timings_flag() {
+    let (ok, stdout, stderr) = run_kv(&["mem", "-j", "--profile-timings"]);
+    assert!(ok);
+    assert!(stdout.starts_with('{'));
+    assert!(!stdout.contains("profile:"));
+    assert!(stderr.contains("profile:"));
+    assert!(stderr.contains("total"));
+    assert!(stderr.contains("file reads"));
+}